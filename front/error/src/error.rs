@@ -1,35 +1,111 @@
+use crate::fixes::{apply_fixes_enabled, apply_suggestions, Suggestion};
+use crate::tr;
+
 #[derive(Debug)]
 pub enum WaveErrorKind {
     UnexpectedToken(String),
     ExpectedToken(String),
     UnexpectedChar(char),
     SyntaxError(String),
+    UnsupportedConstruct(String),
+    SemanticError(String),
+    UnusedFunction(String),
+    ConfusableIdentifier(String),
+    ImplicitConversion(String),
+    UnreachableCode(String),
+    ConstantCondition(String),
+    TypeMismatch(String),
+    Recursion(String),
+    /// Reported by a lint pass registered from outside the compiler, named by the lint.
+    Lint(String),
+    MissingReturn(String),
+    ImmutableAssignment(String),
+    UnresolvedImport(String),
+    InvalidMain(String),
+    UninitializedVariable(String),
+}
+
+impl WaveErrorKind {
+    /// Stable identifier printed with the diagnostic; the same in every locale.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WaveErrorKind::UnexpectedToken(_) => "E0001",
+            WaveErrorKind::ExpectedToken(_) => "E0002",
+            WaveErrorKind::UnexpectedChar(_) => "E0003",
+            WaveErrorKind::SyntaxError(_) => "E0004",
+            WaveErrorKind::UnsupportedConstruct(_) => "E0005",
+            WaveErrorKind::SemanticError(_) => "E0006",
+            WaveErrorKind::UnusedFunction(_) => "W0001",
+            WaveErrorKind::ConfusableIdentifier(_) => "W0002",
+            WaveErrorKind::ImplicitConversion(_) => "W0003",
+            WaveErrorKind::UnreachableCode(_) => "W0004",
+            WaveErrorKind::ConstantCondition(_) => "W0005",
+            WaveErrorKind::TypeMismatch(_) => "E0007",
+            WaveErrorKind::Recursion(_) => "W0006",
+            WaveErrorKind::Lint(_) => "W0007",
+            WaveErrorKind::MissingReturn(_) => "E0008",
+            WaveErrorKind::ImmutableAssignment(_) => "E0009",
+            WaveErrorKind::UnresolvedImport(_) => "E0010",
+            WaveErrorKind::InvalidMain(_) => "E0011",
+            WaveErrorKind::UninitializedVariable(_) => "E0012",
+        }
+    }
+
+    /// The lint `--allow=`, `--warn=` and `--deny=` name for warnings of this kind, if any.
+    pub fn lint_name(&self) -> Option<&str> {
+        match self {
+            WaveErrorKind::ImplicitConversion(_) => Some("implicit-conversion"),
+            WaveErrorKind::Recursion(_) => Some("recursion"),
+            WaveErrorKind::Lint(lint) => Some(lint),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
 }
 
 #[derive(Debug)]
 pub struct WaveError {
     pub kind: WaveErrorKind,
+    pub severity: Severity,
     pub message: String,
     pub file: String,
     pub line: usize,
     pub column: usize,
     pub source: Option<String>,
     pub label: Option<String>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
 }
 
 impl WaveError {
     pub fn new(kind: WaveErrorKind, message: impl Into<String>, file: impl Into<String>, line: usize, column: usize) -> Self {
         Self {
             kind,
+            severity: Severity::Error,
             message: message.into(),
             file: file.into(),
             line,
             column,
             source: None,
             label: None,
+            help: None,
+            notes: vec![],
+            suggestions: vec![],
         }
     }
 
+    /// Reports this diagnostic as a warning; compilation continues.
+    pub fn as_warning(mut self) -> Self {
+        self.severity = Severity::Warning;
+        self
+    }
+
     pub fn with_source(mut self, source: impl Into<String>) -> Self {
         self.source = Some(source.into());
         self
@@ -40,21 +116,78 @@ impl WaveError {
         self
     }
 
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Prints the diagnostic and, under `--apply-fixes`, writes its suggestions into the file.
+    pub fn report(&self) {
+        self.display();
+        if apply_fixes_enabled() {
+            apply_suggestions(&[self]);
+        }
+    }
+
     pub fn display(&self) {
-        eprintln!("error: {}", self.message);
+        let severity = match self.severity {
+            Severity::Error => tr!("error"),
+            Severity::Warning => tr!("warning"),
+        };
+        eprintln!("{}[{}]: {}", severity, self.kind.code(), self.message);
         eprintln!("  --> {}:{}:{}", self.file, self.line, self.column);
-        eprintln!("   |");
+        eprintln!("    |");
 
         if let Some(source_line) = &self.source {
             eprintln!("{:>3} | {}", self.line, source_line);
-            let arrow_line = format!("{:>3} | {:>width$}^", "", "", width = self.column - 1);
+            let arrow_line = format!("{:>3} | {:>width$}^", "", "", width = self.column.saturating_sub(1));
             if let Some(label) = &self.label {
-                eprintln!("   | {} {}", &arrow_line[6..], label);
+                eprintln!("    | {} {}", &arrow_line[6..], label);
             } else {
-                eprintln!("   | {}", &arrow_line[6..]);
+                eprintln!("    | {}", &arrow_line[6..]);
             }
         } else {
-            eprintln!("   | (source unavailable)");
+            eprintln!("    | {}", tr!("(source unavailable)"));
+        }
+
+        for note in &self.notes {
+            eprintln!("    = {}: {}", tr!("note"), note);
+        }
+
+        if let Some(help) = &self.help {
+            eprintln!("    = {}: {}", tr!("help"), help);
+        }
+
+        for suggestion in &self.suggestions {
+            eprintln!("    = {}: {}", tr!("suggestion"), suggestion.message);
+            let original = if suggestion.line == self.line {
+                self.source.clone()
+            } else {
+                std::fs::read_to_string(&self.file)
+                    .ok()
+                    .and_then(|code| code.lines().nth(suggestion.line.wrapping_sub(1)).map(str::to_string))
+            };
+            if let Some(patched) = original.and_then(|line| suggestion.apply_to_line(&line)) {
+                let marker = if suggestion.len == 0 { "+" } else { "~" };
+                eprintln!("    |");
+                eprintln!("{:>3} | {}", suggestion.line, patched);
+                eprintln!(
+                    "    | {:>width$}{}",
+                    "",
+                    marker.repeat(suggestion.replacement.chars().count().max(1)),
+                    width = suggestion.column.saturating_sub(1)
+                );
+            }
         }
     }
-}
\ No newline at end of file
+}
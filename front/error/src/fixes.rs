@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::error::WaveError;
+use crate::tr;
+
+/// A machine-applicable edit: replace `len` characters starting at 1-based `line`/`column`
+/// with `replacement`. A `len` of 0 inserts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+    pub replacement: String,
+}
+
+impl Suggestion {
+    pub fn new(message: impl Into<String>, line: usize, column: usize, len: usize, replacement: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            line,
+            column,
+            len,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// `source_line` with this suggestion applied; `None` if the span falls outside it.
+    pub fn apply_to_line(&self, source_line: &str) -> Option<String> {
+        let chars: Vec<char> = source_line.chars().collect();
+        let start = self.column.checked_sub(1)?;
+        if start + self.len > chars.len() {
+            return None;
+        }
+
+        let mut patched: String = chars[..start].iter().collect();
+        patched.push_str(&self.replacement);
+        patched.extend(&chars[start + self.len..]);
+        Some(patched)
+    }
+}
+
+static APPLY_FIXES: AtomicBool = AtomicBool::new(false);
+
+/// `--apply-fixes`: reported diagnostics rewrite their files with their suggestions.
+pub fn set_apply_fixes(enabled: bool) {
+    APPLY_FIXES.store(enabled, Ordering::Relaxed);
+}
+
+pub fn apply_fixes_enabled() -> bool {
+    APPLY_FIXES.load(Ordering::Relaxed)
+}
+
+/// Writes the suggestions of `errors` into their source files and returns how many were applied.
+/// Edits on the same line are applied right to left so earlier columns stay valid; overlapping
+/// edits after the first are skipped.
+pub fn apply_suggestions(errors: &[&WaveError]) -> usize {
+    let mut by_file: BTreeMap<&str, Vec<&Suggestion>> = BTreeMap::new();
+    for error in errors {
+        by_file.entry(error.file.as_str()).or_default().extend(&error.suggestions);
+    }
+
+    let mut applied = 0;
+    for (file, mut suggestions) in by_file {
+        if suggestions.is_empty() {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(file) else {
+            eprintln!("❌ Cannot apply fixes: failed to read '{}'", file);
+            continue;
+        };
+
+        let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+        suggestions.sort_by_key(|s| std::cmp::Reverse((s.line, s.column)));
+
+        let mut in_file = 0;
+        let mut last: Option<(usize, usize)> = None;
+        for suggestion in suggestions {
+            let overlaps = last.is_some_and(|(line, column)| {
+                line == suggestion.line && suggestion.column + suggestion.len > column
+            });
+            let Some(line) = lines.get_mut(suggestion.line.wrapping_sub(1)) else {
+                continue;
+            };
+            if overlaps {
+                continue;
+            }
+            if let Some(patched) = suggestion.apply_to_line(line) {
+                *line = patched;
+                last = Some((suggestion.line, suggestion.column));
+                in_file += 1;
+            }
+        }
+
+        let mut patched = lines.join("\n");
+        if source.ends_with('\n') {
+            patched.push('\n');
+        }
+        if let Err(e) = fs::write(file, patched) {
+            eprintln!("❌ Cannot apply fixes: failed to write '{}': {}", file, e);
+            continue;
+        }
+
+        eprintln!("{}", tr!("applied {0} fix(es) to {1}", in_file, file));
+        applied += in_file;
+    }
+
+    applied
+}
+
+/// The candidate closest to `name` by edit distance, if it is close enough to be a likely typo.
+pub fn similar_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,456 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language diagnostics are printed in. Error codes, file positions and source excerpts
+/// are the same in every locale, so tools can parse the output regardless of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    English,
+    Korean,
+}
+
+/// Environment variable that picks the locale when `--locale` is not given.
+/// After it, the usual `LC_ALL`, `LC_MESSAGES` and `LANG` are consulted.
+pub const LOCALE_ENV: &str = "WAVE_LOCALE";
+
+static LOCALE: AtomicU8 = AtomicU8::new(0);
+
+impl Locale {
+    /// Parses a language tag such as `ko`, `ko-KR` or `ko_KR.UTF-8`.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::English),
+            "ko" => Some(Locale::Korean),
+            _ => None,
+        }
+    }
+
+    /// The first recognized locale among `WAVE_LOCALE`, `LC_ALL`, `LC_MESSAGES` and `LANG`.
+    pub fn from_env() -> Locale {
+        [LOCALE_ENV, "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find_map(|value| Locale::from_tag(&value))
+            .unwrap_or(Locale::English)
+    }
+}
+
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::Korean,
+        _ => Locale::English,
+    }
+}
+
+/// Looks `template` up in the catalog of the current locale, falling back to the English
+/// text itself, then replaces `{0}`, `{1}`, ... with `args`.
+pub fn translate(template: &str, args: &[&dyn Display]) -> String {
+    let catalog = match locale() {
+        Locale::English => &[][..],
+        Locale::Korean => KOREAN,
+    };
+    let text = catalog
+        .iter()
+        .find(|(english, _)| *english == template)
+        .map_or(template, |(_, translated)| translated);
+
+    // Single pass, so an argument that itself contains `{1}` is left as is
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let digits = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+        match (after[..digits].parse::<usize>().ok().and_then(|i| args.get(i)), after[digits..].starts_with('}')) {
+            (Some(arg), true) => {
+                result.push_str(&arg.to_string());
+                rest = &after[digits + 1..];
+            }
+            _ => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Translates an English message template; arguments fill `{0}`, `{1}`, ... in order.
+/// Templates are catalog keys, so keep the English text and the catalog entry in sync.
+#[macro_export]
+macro_rules! tr {
+    ($template:expr $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::translate($template, &[$(&$arg as &dyn ::std::fmt::Display),*])
+    };
+}
+
+const KOREAN: &[(&str, &str)] = &[
+    // Diagnostic framing
+    ("error", "오류"),
+    ("warning", "경고"),
+    ("note", "참고"),
+    ("help", "도움말"),
+    ("suggestion", "제안"),
+    ("applied {0} fix(es) to {1}", "{1}에 수정 사항 {0}개를 적용했습니다"),
+    ("(source unavailable)", "(소스를 표시할 수 없음)"),
+
+    // Lexer
+    ("unexpected character {0}", "예상하지 못한 문자 {0}"),
+    ("unterminated string literal", "문자열 리터럴이 닫히지 않았습니다"),
+    ("unterminated character literal", "문자 리터럴이 닫히지 않았습니다"),
+    ("character literal may only contain one character", "문자 리터럴에는 문자 하나만 들어갈 수 있습니다"),
+    ("empty character literal", "빈 문자 리터럴"),
+    ("unknown escape `\\{0}` in character literal", "문자 리터럴에 알 수 없는 이스케이프 `\\{0}`"),
+    ("character literal {0} does not fit in a byte", "문자 리터럴 {0}은(는) 1바이트에 들어가지 않습니다"),
+    ("unterminated block comment", "블록 주석이 닫히지 않았습니다"),
+    ("integer literal `{0}` out of range", "정수 리터럴 `{0}`이(가) 범위를 벗어났습니다"),
+    ("too large", "너무 큽니다"),
+    ("an integer literal can be at most 9223372036854775807, the largest `i64`", "정수 리터럴은 가장 큰 `i64`인 9223372036854775807까지 쓸 수 있습니다"),
+    ("backslash escapes only work inside literals", "백슬래시 이스케이프는 리터럴 안에서만 쓸 수 있습니다"),
+    ("not valid in Wave source", "Wave 소스에서 쓸 수 없는 문자입니다"),
+    ("remove it, or put it inside a string literal", "지우거나 문자열 리터럴 안에 넣으세요"),
+    ("string starts here", "문자열이 여기서 시작됩니다"),
+    ("add a closing `\"`", "닫는 `\"`를 추가하세요"),
+    ("character literal starts here", "문자 리터럴이 여기서 시작됩니다"),
+    ("a character literal holds exactly one character, e.g. `'a'`", "문자 리터럴에는 정확히 문자 하나가 들어갑니다. 예: `'a'`"),
+    ("more than one character", "문자가 둘 이상입니다"),
+    ("use a string literal, e.g. `\"ab\"`", "문자열 리터럴을 쓰세요. 예: `\"ab\"`"),
+    ("no character between the quotes", "따옴표 사이에 문자가 없습니다"),
+    ("use `'\\0'` for the zero byte", "0 바이트는 `'\\0'`으로 쓰세요"),
+    ("unknown escape", "알 수 없는 이스케이프"),
+    ("supported escapes are \\n, \\t, \\r, \\0, \\\\ and \\'", "지원되는 이스케이프는 \\n, \\t, \\r, \\0, \\\\, \\' 입니다"),
+    ("not a single byte", "1바이트가 아닙니다"),
+    ("use a string literal for non-ASCII text", "ASCII가 아닌 텍스트에는 문자열 리터럴을 쓰세요"),
+    ("comment starts here", "주석이 여기서 시작됩니다"),
+    ("use a string literal", "문자열 리터럴을 쓰세요"),
+    ("add a closing `*/`", "닫는 `*/`를 추가하세요"),
+
+    // Identifiers
+    ("identifier `{0}` looks like `{1}` but is a different name", "식별자 `{0}`은(는) `{1}`와(과) 비슷해 보이지만 다른 이름입니다"),
+    ("confusable identifier", "혼동하기 쉬운 식별자"),
+    ("`{0}` is first used at {1}", "`{0}`은(는) {1}에서 처음 쓰였습니다"),
+    ("code points: {0}", "코드 포인트: {0}"),
+    ("rename one of them, or retype it so both use the same characters", "둘 중 하나의 이름을 바꾸거나, 같은 문자를 쓰도록 다시 입력하세요"),
+
+    // Entry point and benchmarks
+    ("`main` function not found in `{0}`", "`{0}`에서 `main` 함수를 찾을 수 없습니다"),
+    ("the program needs an entry point", "프로그램에는 진입점이 필요합니다"),
+    ("add `fun main() { ... }` to define where execution starts", "실행이 시작될 곳을 정하려면 `fun main() { ... }`을 추가하세요"),
+    ("`main` parameter `{0}` has no default value", "`main`의 매개변수 `{0}`에 기본값이 없습니다"),
+    ("nothing is passed to `main` at startup; give it a default, e.g. `{0}: {1} = ...`", "시작할 때 `main`에는 아무 값도 전달되지 않습니다. 기본값을 주세요. 예: `{0}: {1} = ...`"),
+    ("`main` must return nothing or `i32`, found `{0}`", "`main`은 아무것도 반환하지 않거나 `i32`를 반환해야 하는데, `{0}`입니다"),
+    ("declare it as `fun main()` or `fun main() -> i32`", "`fun main()` 또는 `fun main() -> i32`로 선언하세요"),
+    ("`main` declared here", "`main`이 여기서 선언되었습니다"),
+    ("benchmark `{0}` takes parameters", "벤치마크 `{0}`에 매개변수가 있습니다"),
+    ("`{0}` would be called without a value", "`{0}`에는 값이 전달되지 않습니다"),
+    ("build the inputs inside the benchmark body instead", "입력값은 벤치마크 본문 안에서 만드세요"),
+    ("`#[{0}]` function `{1}` takes parameters", "`#[{0}]` 함수 `{1}`은(는) 매개변수를 받습니다"),
+    ("set up the values inside the body of `{0}` instead", "값은 `{0}` 본문 안에서 준비하세요"),
+    ("`#[{0}]` function `{1}` returns `{2}`", "`#[{0}]` 함수 `{1}`은(는) `{2}`을(를) 반환합니다"),
+    ("nothing would receive the value", "이 값을 받을 곳이 없습니다"),
+    ("declare it as `fun {0}()`", "`fun {0}()`로 선언하세요"),
+    ("no `#[bench]` functions found in `{0}`", "`{0}`에 `#[bench]` 함수가 없습니다"),
+    ("mark a function with `#[bench]` to measure it", "측정할 함수에 `#[bench]`를 붙이세요"),
+
+    // Definitions
+    ("function", "함수"),
+    ("parameter", "매개변수"),
+    ("struct", "구조체"),
+    ("enum", "열거형"),
+    ("{0} `{1}` is defined more than once", "{0} `{1}`이(가) 두 번 이상 정의되었습니다"),
+    ("`{0}` redefined here", "`{0}`이(가) 여기서 다시 정의되었습니다"),
+    ("first defined at {0}", "처음 정의된 위치: {0}"),
+    ("rename or remove one of the definitions of `{0}`", "`{0}`의 정의 중 하나의 이름을 바꾸거나 지우세요"),
+    ("functions that share a name must differ in their parameter types", "이름이 같은 함수는 매개변수 타입이 달라야 합니다"),
+    ("an `extern` function is called by its name, so no other function can share it", "`extern` 함수는 이름으로 호출되므로 다른 함수가 그 이름을 함께 쓸 수 없습니다"),
+    ("`{0}` cannot be passed to or from C, in `extern` function `{1}`", "`{0}`은(는) C와 주고받을 수 없습니다 (`extern` 함수 `{1}`)"),
+    ("pass a pointer instead, `ptr<{0}>`", "대신 포인터 `ptr<{0}>`를 넘기세요"),
+    ("function `{0}` takes at least {1} argument(s), found {2}", "함수 `{0}`은(는) 인자를 {1}개 이상 받지만 {2}개가 있습니다"),
+    ("argument {0} has type `{1}`", "인자 {0}의 타입은 `{1}`입니다"),
+    ("`{0}` takes variable arguments, so no function type holds its address", "`{0}`은(는) 가변 인자를 받으므로 그 주소를 담을 함수 타입이 없습니다"),
+    ("take a C string, `ptr<byte>`; a string literal converts to it", "C 문자열 `ptr<byte>`를 받으세요. 문자열 리터럴은 그것으로 변환됩니다"),
+    ("`{0}` in a format spec applies to integers, found `{1}`", "형식 지정자의 `{0}`은(는) 정수에 쓰이지만 `{1}`이(가) 있습니다"),
+    ("a precision in a format spec applies to floats and strings, found `{0}`", "형식 지정자의 정밀도는 실수와 문자열에 쓰이지만 `{0}`이(가) 있습니다"),
+    ("placeholder {0} of the format string", "형식 문자열의 {0}번째 자리 표시자"),
+    ("function `{0}` is never used", "함수 `{0}`은(는) 사용되지 않습니다"),
+    ("not reachable from `main`", "`main`에서 도달할 수 없습니다"),
+    ("no code is generated for it", "이 함수의 코드는 생성되지 않습니다"),
+    ("function `{0}` calls itself without a decreasing argument", "함수 `{0}`이(가) 줄어드는 인자 없이 자기 자신을 호출합니다"),
+    ("function `{0}` calls itself through `{1}` without a decreasing argument", "함수 `{0}`이(가) `{1}`을(를) 거쳐 줄어드는 인자 없이 자기 자신을 호출합니다"),
+    ("nothing shows this recursion ends", "이 재귀가 끝난다는 근거가 없습니다"),
+    ("`#[allow({0})]` on the function silences this lint", "함수에 `#[allow({0})]`을 붙이면 이 린트가 꺼집니다"),
+    ("expected an integer as the bound of a range, found `{0}`", "범위의 경계로 정수가 와야 하는데 `{0}`이(가) 왔습니다"),
+    ("bound of this `for`", "이 `for`의 경계"),
+    ("the range counts in `{0}`", "범위는 `{0}`로 셉니다"),
+    ("the `for` loop counts with `{0}`; copy it into a `var` to change it", "`for` 루프가 `{0}`로 셉니다. 바꾸려면 `var`에 복사하세요"),
+    ("the calls go {0}", "호출 순서: {0}"),
+    ("each call takes another stack frame; without an end it overflows the stack", "호출마다 스택 프레임이 하나씩 늘어나므로, 끝나지 않으면 스택이 넘칩니다"),
+    ("pass a smaller value on the recursive call, e.g. `n - 1`, or mark `{0}` with `#[allow(recursion)]`", "재귀 호출에 더 작은 값을 넘기거나(예: `n - 1`), `{0}`에 `#[allow(recursion)]`을 붙이세요"),
+
+    // Control flow
+    ("function `{0}` can reach its end without returning a value", "함수 `{0}`이(가) 값을 반환하지 않고 끝에 도달할 수 있습니다"),
+    ("declared to return `{0}`", "`{0}`을(를) 반환하도록 선언됨"),
+    ("end every path through `{0}` with a `return`", "`{0}`의 모든 경로를 `return`으로 끝내세요"),
+    ("unreachable statement", "도달할 수 없는 문장"),
+    ("unreachable", "도달할 수 없음"),
+    ("every path before it leaves through `return`, `break` or `continue`", "앞선 모든 경로가 `return`, `break` 또는 `continue`로 빠져나갑니다"),
+    ("variable `{0}` is read before it is assigned", "변수 `{0}`이(가) 값이 대입되기 전에 읽힙니다"),
+    ("`{0}` may be uninitialized here", "여기서 `{0}`이(가) 초기화되지 않았을 수 있습니다"),
+    ("give `{0}` an initial value where it is declared", "`{0}`을(를) 선언할 때 초기값을 주세요"),
+    ("loop body never runs", "반복문 본문이 한 번도 실행되지 않습니다"),
+    ("condition is always `false`", "조건이 항상 `false`입니다"),
+    ("condition is always `true`", "조건이 항상 `true`입니다"),
+    ("always taken", "항상 선택됨"),
+    ("never taken", "한 번도 선택되지 않음"),
+    ("the branches after it never run", "뒤따르는 분기는 실행되지 않습니다"),
+    ("the body of this `if` never runs", "이 `if`의 본문은 실행되지 않습니다"),
+    ("loop never exits, so `{0}` never returns a value", "반복문이 끝나지 않으므로 `{0}`은(는) 값을 반환하지 않습니다"),
+    ("nothing breaks out of this loop", "이 반복문을 빠져나가는 곳이 없습니다"),
+    ("add a `break` or a `return` inside the loop", "반복문 안에 `break`나 `return`을 추가하세요"),
+
+    // Type checking
+    ("mismatched types: expected `{0}`, found {1}", "타입이 맞지 않습니다: `{0}`이(가) 필요하지만 {1}이(가) 있습니다"),
+    ("`{0}` does not convert to the narrower `{1}` implicitly; use `as {1}` if the value fits", "`{0}`은(는) 더 좁은 `{1}`(으)로 암묵적으로 변환되지 않습니다; 값이 들어간다면 `as {1}`을(를) 쓰세요"),
+    ("integer division by zero in `{0}`", "`{0}`에서 정수를 0으로 나눕니다"),
+    ("the divisor is always zero", "나누는 값이 항상 0입니다"),
+    ("only float division by zero is defined, as infinity or NaN", "0으로 나누기는 부동소수점에서만 무한대나 NaN으로 정의됩니다"),
+    ("an integer literal", "정수 리터럴"),
+    ("a float literal", "실수 리터럴"),
+    ("`{0}` is declared as `{1}`", "`{0}`은(는) `{1}`(으)로 선언되었습니다"),
+    ("`{0}` is declared to return `{1}`", "`{0}`은(는) `{1}`을(를) 반환하도록 선언되었습니다"),
+    ("`{0}` has type `{1}`", "`{0}`의 타입은 `{1}`입니다"),
+    ("the target has type `{0}`", "대입 대상의 타입은 `{0}`입니다"),
+    ("parameter `{0}` of `{1}` has type `{2}`", "`{1}`의 매개변수 `{0}`의 타입은 `{2}`입니다"),
+    ("parameter {0} of `{1}` has type `{2}`", "`{1}`의 매개변수 {0}의 타입은 `{2}`입니다"),
+    ("the array has type `{0}`", "배열의 타입은 `{0}`입니다"),
+    ("the elements have type `{0}`", "원소의 타입은 `{0}`입니다"),
+    ("expected a number or `bool` as the condition, found `{0}`", "조건에는 숫자나 `bool`이 필요하지만 `{0}`이(가) 있습니다"),
+    ("condition of this `{0}`", "이 `{0}`의 조건"),
+    ("compare the value explicitly, e.g. `{0} (x != 0)`", "값을 명시적으로 비교하세요. 예: `{0} (x != 0)`"),
+    ("operator `{0}` cannot be applied to `{1}` and `{2}`", "연산자 `{0}`은(는) `{1}`과(와) `{2}`에 쓸 수 없습니다"),
+    ("invalid operands", "잘못된 피연산자"),
+    ("not a pointer", "포인터가 아닙니다"),
+    ("not an array or a pointer", "배열이나 포인터가 아닙니다"),
+    ("only numbers and pointers can be cast", "숫자와 포인터만 변환할 수 있습니다"),
+    ("index of this access", "이 접근의 인덱스"),
+    ("index {0} is negative", "인덱스 {0}이(가) 음수입니다"),
+    ("an index is a `usize`, counted from 0", "인덱스는 0부터 세는 `usize`입니다"),
+    ("function `{0}` takes {1} argument(s), found {2}", "함수 `{0}`은(는) 인자를 {1}개 받지만 {2}개가 있습니다"),
+    ("wrong number of arguments", "인자 개수가 맞지 않습니다"),
+    ("the signature is `{0}`", "시그니처는 `{0}`입니다"),
+    ("shift amount {0} is not less than the width of `{1}`", "시프트 양 {0}이(가) `{1}`의 너비보다 작지 않습니다"),
+    ("`{0}` shifts by 0 to {1} bits", "`{0}`은(는) 0에서 {1}비트까지 시프트합니다"),
+    ("an amount out of range is taken modulo the width, or traps with `--shift-amount=trap`", "범위를 벗어난 양은 너비로 나눈 나머지가 되며, `--shift-amount=trap`에서는 트랩합니다"),
+    ("no function `{0}` takes arguments of types ({1})", "인자 타입이 ({1})인 함수 `{0}`이(가) 없습니다"),
+    ("no overload matches", "맞는 오버로드가 없습니다"),
+    ("call to `{0}` is ambiguous", "`{0}` 호출이 모호합니다"),
+    ("more than one overload matches", "둘 이상의 오버로드가 맞습니다"),
+    ("candidate: `{0}`", "후보: `{0}`"),
+    ("store an argument in a `var` of the parameter type to pick an overload", "인자를 매개변수 타입의 `var`에 담아 오버로드를 고르세요"),
+    ("the loop's value has type `{0}`", "반복문 값의 타입은 `{0}`입니다"),
+    ("`break` with a value outside a loop used as a value", "값으로 쓰인 반복문 밖에서 `break`에 값이 있습니다"),
+    ("this `break` carries a value", "이 `break`에 값이 있습니다"),
+    ("assign the value to a variable before the `break`", "`break` 전에 값을 변수에 대입하세요"),
+    ("`break` without a value in a loop used as a value", "값으로 쓰인 반복문 안의 `break`에 값이 없습니다"),
+    ("the loop's value is missing", "반복문의 값이 빠졌습니다"),
+    ("pass the value out, e.g. `break result;`", "값을 넘기세요. 예: `break result;`"),
+    ("this `while` can end without a value", "이 `while`은(는) 값 없이 끝날 수 있습니다"),
+    ("the loop ends without a value when its condition is false", "조건이 거짓이면 반복문이 값 없이 끝납니다"),
+    ("use `loop { ... }` and leave it with `break value;`", "`loop { ... }`를 쓰고 `break value;`로 빠져나오세요"),
+    ("this `{0}` is used as a value but no `break` gives it one", "이 `{0}`은(는) 값으로 쓰였지만 값을 주는 `break`가 없습니다"),
+    ("value of this loop", "이 반복문의 값"),
+    ("leave the loop with `break value;`", "`break value;`로 반복문을 빠져나오세요"),
+    ("use of undeclared label `'{0}`", "선언되지 않은 레이블 `'{0}`을(를) 사용했습니다"),
+    ("no enclosing loop has this label", "이 레이블을 가진 바깥 반복문이 없습니다"),
+    ("label the loop first, e.g. `'{0}: while (...) { ... }`", "먼저 반복문에 레이블을 붙이세요. 예: `'{0}: while (...) { ... }`"),
+    ("cannot jump to `'{0}` from inside a loop used as a value", "값으로 쓰인 반복문 안에서 `'{0}`(으)로 이동할 수 없습니다"),
+    ("the loop is outside this value", "그 반복문은 이 값의 바깥에 있습니다"),
+    ("leave the inner loop with `break value;` first", "먼저 `break value;`로 안쪽 반복문을 빠져나오세요"),
+    ("no field `{0}` on type `{1}`", "`{1}` 타입에는 필드 `{0}`이(가) 없습니다"),
+    ("not a struct", "구조체가 아닙니다"),
+    ("unknown field", "알 수 없는 필드"),
+    ("`{0}` has the fields {1}", "`{0}`의 필드는 {1}입니다"),
+    ("field `{0}` is given twice", "필드 `{0}`이(가) 두 번 주어졌습니다"),
+    ("already given earlier in this literal", "이 리터럴에서 이미 앞서 주어졌습니다"),
+    ("struct `{0}` has no field `{1}`", "구조체 `{0}`에는 필드 `{1}`이(가) 없습니다"),
+    ("missing field(s) {0} in this `{1}` literal", "이 `{1}` 리터럴에 필드 {0}이(가) 빠졌습니다"),
+    ("every field needs a value", "모든 필드에 값이 필요합니다"),
+    ("field `{0}` of `{1}` has type `{2}`", "`{1}`의 필드 `{0}`의 타입은 `{2}`입니다"),
+    ("no variant `{0}` on enum `{1}`", "열거형 `{1}`에는 변형 `{0}`이(가) 없습니다"),
+    ("unknown variant", "알 수 없는 변형"),
+    ("`{0}` has the variants {1}", "`{0}`의 변형은 {1}입니다"),
+    ("`{0}::{1}` has {2} field(s), found {3}", "`{0}::{1}`의 필드는 {2}개이지만 {3}개가 있습니다"),
+    ("wrong number of fields", "필드 개수가 맞지 않습니다"),
+    ("field {0} of `{1}::{2}` has type `{3}`", "`{1}::{2}`의 필드 {0}의 타입은 `{3}`입니다"),
+    ("an integer pattern cannot match a value of type `{0}`", "정수 패턴은 `{0}` 타입의 값과 맞지 않습니다"),
+    ("a `{0}` pattern cannot match a value of type `{1}`", "`{0}` 패턴은 `{1}` 타입의 값과 맞지 않습니다"),
+    ("pattern of this `match`", "이 `match`의 패턴"),
+    ("unreachable pattern", "도달할 수 없는 패턴"),
+    ("an earlier arm already matches these values", "앞선 갈래가 이미 이 값들에 맞습니다"),
+    ("non-exhaustive `match`: {0} not covered", "`match`가 모든 경우를 다루지 않습니다: {0}이(가) 빠졌습니다"),
+    ("not every variant has an arm", "갈래가 없는 변형이 있습니다"),
+    ("add an arm for each missing variant, or a `_ => { ... }` arm", "빠진 변형마다 갈래를 추가하거나 `_ => { ... }` 갈래를 추가하세요"),
+
+    // Code generation
+    ("cannot find variable `{0}` in this scope", "이 범위에서 변수 `{0}`을(를) 찾을 수 없습니다"),
+    ("not found in this scope", "이 범위에 없습니다"),
+    ("a variable with a similar name exists: `{0}`", "이름이 비슷한 변수가 있습니다: `{0}`"),
+    ("cannot find function `{0}` in this scope", "이 범위에서 함수 `{0}`을(를) 찾을 수 없습니다"),
+    ("a function with a similar name exists: `{0}`", "이름이 비슷한 함수가 있습니다: `{0}`"),
+    ("cannot find struct `{0}` in this scope", "이 범위에서 구조체 `{0}`을(를) 찾을 수 없습니다"),
+    ("a struct with a similar name exists: `{0}`", "이름이 비슷한 구조체가 있습니다: `{0}`"),
+    ("struct `{0}`", "구조체 `{0}`"),
+    ("a struct cannot contain itself; use `ptr<{0}>` for field `{1}`", "구조체는 자기 자신을 담을 수 없습니다. 필드 `{1}`에는 `ptr<{0}>`를 쓰세요"),
+    ("cannot find enum `{0}` in this scope", "이 범위에서 열거형 `{0}`을(를) 찾을 수 없습니다"),
+    ("an enum with a similar name exists: `{0}`", "이름이 비슷한 열거형이 있습니다: `{0}`"),
+    ("enum `{0}`", "열거형 `{0}`"),
+    ("an enum cannot contain itself; use `ptr<{0}>` in variant `{1}`", "열거형은 자기 자신을 담을 수 없습니다. 변형 `{1}`에는 `ptr<{0}>`를 쓰세요"),
+    ("accessing a field of {0}", "{0}의 필드에 접근하는 것"),
+    ("store the struct in a variable first, then access the field of that variable", "먼저 구조체를 변수에 저장한 뒤 그 변수의 필드에 접근하세요"),
+    ("cannot assign to immutable variable `{0}`", "불변 변수 `{0}`에 대입할 수 없습니다"),
+    ("cannot assign", "대입할 수 없습니다"),
+    ("`{0}` is declared at {1}", "`{0}`은(는) {1}에서 선언되었습니다"),
+    ("make `{0}` mutable", "`{0}`을(를) 가변으로 만드세요"),
+    ("parameters cannot be assigned to; copy `{0}` into a `var` first", "매개변수에는 대입할 수 없습니다. 먼저 `{0}`을(를) `var`에 복사하세요"),
+    ("the bindings of a `match` arm cannot be assigned to; copy `{0}` into a `var` first", "`match` 갈래의 바인딩에는 대입할 수 없습니다. 먼저 `{0}`을(를) `var`에 복사하세요"),
+    ("literal `{0}` does not fit in {1}", "리터럴 `{0}`은(는) {1}에 들어가지 않습니다"),
+    ("constant value `{0}` does not fit in {1}", "상수 값 `{0}`은(는) {1}에 들어가지 않습니다"),
+    ("a {0}-bit integer", "{0}비트 정수"),
+    ("out of range", "범위를 벗어났습니다"),
+    ("the range of {0} is {1}..={2}", "{0}의 범위는 {1}..={2}입니다"),
+    ("declare the value as `f64`", "값을 `f64`로 선언하세요"),
+    ("implicit conversion from `{0}` to `{1}` {2}", "`{0}`에서 `{1}`(으)로의 암시적 변환은 {2}"),
+    ("may truncate the value", "값을 잘라낼 수 있습니다"),
+    ("drops the fractional part", "소수 부분을 버립니다"),
+    ("may lose precision", "정밀도를 잃을 수 있습니다"),
+    ("widens the value", "값을 넓힙니다"),
+    ("converts the integer to a float", "정수를 실수로 바꿉니다"),
+    ("changes the sign of negative values", "음수 값의 부호를 바꿉니다"),
+    ("may turn large values negative", "큰 값을 음수로 바꿀 수 있습니다"),
+    ("converted implicitly", "암시적으로 변환됨"),
+    ("silence this with `--allow=implicit-conversion`", "`--allow=implicit-conversion`으로 이 경고를 끌 수 있습니다"),
+    ("{0} is not supported", "{0}은(는) 지원되지 않습니다"),
+    ("in this {0}", "이 {0}에서"),
+    ("taking the address of {0}", "{0}의 주소를 얻는 것"),
+    ("`deref` of {0} in an initializer", "초기화식에서 {0}에 `deref`를 쓰는 것"),
+    ("initializing a `{0}` variable with {1}", "`{0}` 변수를 {1}(으)로 초기화하는 것"),
+    ("a `while` condition that is not a number", "숫자가 아닌 `while` 조건"),
+    ("assigning through `deref` of {0}", "{0}의 `deref`를 통해 대입하는 것"),
+    ("assigning to {0}", "{0}에 대입하는 것"),
+    ("indexing into {0} that yields an array", "배열을 만드는 {0}을(를) 인덱싱하는 것"),
+    ("indexing into {0}", "{0}을(를) 인덱싱하는 것"),
+    ("`&` only applies to a variable or an array literal; store the value in a variable first", "`&`는 변수나 배열 리터럴에만 쓸 수 있습니다. 먼저 값을 변수에 저장하세요"),
+    ("bind the pointer to a variable first, then initialize with `deref` of that variable", "먼저 포인터를 변수에 담은 뒤, 그 변수의 `deref`로 초기화하세요"),
+    ("compute the value in a separate statement, or declare the variable with a matching type", "값을 별도의 문장에서 계산하거나, 맞는 타입으로 변수를 선언하세요"),
+    ("compare the value explicitly, e.g. `while (x != 0)`", "값을 명시적으로 비교하세요. 예: `while (x != 0)`"),
+    ("load the pointer into a variable first, then assign through `deref` of that variable", "먼저 포인터를 변수에 담은 뒤, 그 변수의 `deref`를 통해 대입하세요"),
+    ("only a variable or `deref <variable>` can be assigned to; store the value in a variable first", "변수나 `deref <변수>`에만 대입할 수 있습니다. 먼저 값을 변수에 저장하세요"),
+    ("store the array in a variable first and index the variable", "먼저 배열을 변수에 저장한 뒤 그 변수를 인덱싱하세요"),
+    ("only pointers and arrays can be indexed; store the value in a pointer variable first", "포인터와 배열만 인덱싱할 수 있습니다. 먼저 값을 포인터 변수에 저장하세요"),
+    ("a function call", "함수 호출"),
+    ("a literal", "리터럴"),
+    ("a variable", "변수"),
+    ("a `deref` expression", "`deref` 식"),
+    ("an `&` expression", "`&` 식"),
+    ("a binary expression", "이항 식"),
+    ("an index expression", "인덱스 식"),
+    ("an array literal", "배열 리터럴"),
+    ("an assignment", "대입"),
+    ("an asm block", "asm 블록"),
+    ("a `while` loop", "`while` 반복문"),
+    ("a `loop`", "`loop` 반복문"),
+    ("a struct literal", "구조체 리터럴"),
+    ("an enum variant", "열거형 변형"),
+    ("a field access", "필드 접근"),
+    ("an `alloc` expression", "`alloc` 식"),
+    ("an `as` cast", "`as` 변환"),
+    ("print statement", "print 문"),
+    ("println statement", "println 문"),
+    ("if statement", "if 문"),
+    ("while loop", "while 반복문"),
+    ("for loop", "for 반복문"),
+    ("loop", "loop 반복문"),
+    ("match statement", "match 문"),
+    ("asm block", "asm 블록"),
+    ("return statement", "return 문"),
+    ("break statement", "break 문"),
+    ("continue statement", "continue 문"),
+    ("expression statement", "식 문"),
+    ("signature of `{0}`", "`{0}`의 시그니처"),
+    ("type `{0}` is not supported", "타입 `{0}`은(는) 지원되지 않습니다"),
+    ("functions cannot return `{0}` by value; return a pointer to it instead", "함수는 `{0}`을(를) 값으로 반환할 수 없습니다. 대신 포인터를 반환하세요"),
+    ("only `f32` and `f64` are supported", "`f32`와 `f64`만 지원됩니다"),
+    ("cannot `deref` a value of type `{0}`", "`{0}` 타입의 값에는 `deref`를 쓸 수 없습니다"),
+    ("cannot cast `{0}` as `{1}`", "`{0}`을(를) `{1}`(으)로 변환할 수 없습니다"),
+    ("cannot index into a value of type `{0}`", "`{0}` 타입의 값은 인덱싱할 수 없습니다"),
+    ("cannot infer the type of an empty array literal", "빈 배열 리터럴의 타입을 추론할 수 없습니다"),
+    ("cannot infer the type of a call to `{0}`", "`{0}` 호출의 타입을 추론할 수 없습니다"),
+    ("cannot infer the type of this `{0}` from its `break` values", "`break` 값으로 이 `{0}`의 타입을 추론할 수 없습니다"),
+    ("write the type of the binding explicitly", "바인딩의 타입을 명시적으로 적으세요"),
+    ("expected an array of {0} elements, found {1}", "원소가 {0}개인 배열이 필요하지만 {1}개가 있습니다"),
+    ("`&` of an array literal needs a pointer-to-array type, found `{0}`", "배열 리터럴의 `&`에는 배열 포인터 타입이 필요하지만 `{0}`이(가) 있습니다"),
+    ("`&` is only allowed where a pointer is expected", "`&`는 포인터가 필요한 곳에서만 쓸 수 있습니다"),
+    ("register `{0}` is used as both an input and an output", "레지스터 `{0}`이(가) 입력과 출력에 모두 쓰였습니다"),
+    ("register `{0}` is listed more than once", "레지스터 `{0}`이(가) 여러 번 나열되었습니다"),
+    ("the `asm` block could not be lowered to a call", "`asm` 블록을 호출로 변환할 수 없습니다"),
+    ("the `asm` block returned no value for `{0}`", "`asm` 블록이 `{0}`에 줄 값을 반환하지 않았습니다"),
+    ("`break` outside of a loop", "반복문 밖의 `break`"),
+    ("`continue` outside of a loop", "반복문 밖의 `continue`"),
+    ("`{0}` has no return type, so `return` cannot carry a value", "`{0}`에는 반환 타입이 없으므로 `return`에 값을 쓸 수 없습니다"),
+    ("`{0}` does not return a value", "`{0}`은(는) 값을 반환하지 않습니다"),
+    ("`{0}` is overloaded, so its address needs a variable of function type to pick one", "`{0}`은(는) 오버로드되어 있으므로 주소를 쓰려면 함수 타입의 변수로 하나를 골라야 합니다"),
+    ("no single function `{0}` takes the arguments of this call", "이 호출의 인자를 받는 함수 `{0}`이(가) 하나로 정해지지 않습니다"),
+    ("expected `{0}`, found an integer literal", "`{0}`이(가) 필요하지만 정수 리터럴이 있습니다"),
+    ("expected `{0}`, found a float literal", "`{0}`이(가) 필요하지만 실수 리터럴이 있습니다"),
+    ("cannot assign `{0}` to `{1}`", "`{0}`을(를) `{1}`에 대입할 수 없습니다"),
+    ("assigning a whole array", "배열 전체를 대입하는 것"),
+    ("assign the elements one by one", "원소를 하나씩 대입하세요"),
+    ("an array literal outside an array declaration", "배열 선언 밖의 배열 리터럴"),
+    ("declare an array variable with it and use the variable", "그 리터럴로 배열 변수를 선언하고 변수를 쓰세요"),
+    ("an item nested in a function body", "함수 본문 안에 중첩된 항목"),
+    ("move it to the top level of the file", "파일의 최상위로 옮기세요"),
+    ("`{0}` of type `{1}` cannot be assigned to", "`{1}` 타입의 `{0}`에는 대입할 수 없습니다"),
+    ("printing this value on WebAssembly", "WebAssembly에서 이 값을 출력하는 것"),
+    ("print its fields one at a time", "필드를 하나씩 출력하세요"),
+    ("concatenating strings on WebAssembly", "WebAssembly에서 문자열을 잇는 것"),
+    ("print the strings one after the other", "문자열을 차례로 출력하세요"),
+    ("heap allocation on WebAssembly", "WebAssembly에서의 힙 할당"),
+    ("use a local variable or an array", "지역 변수나 배열을 사용하세요"),
+    ("the count of `alloc` must be an integer, found `{0}`", "`alloc`의 개수는 정수여야 하지만 `{0}`이(가) 있습니다"),
+    ("number of values to allocate", "할당할 값의 개수"),
+    ("cannot allocate a value of type `{0}`", "`{0}` 타입의 값은 할당할 수 없습니다"),
+    ("mismatched operand types `{0}` and `{1}`", "피연산자 타입 `{0}`과(와) `{1}`이(가) 맞지 않습니다"),
+    ("an index must be an integer, found `{0}`", "인덱스는 정수여야 하지만 `{0}`이(가) 있습니다"),
+    ("the `{0}` operator on {1}", "{1}에 대한 `{0}` 연산자"),
+    ("convert the operands to a type the operator applies to", "피연산자를 연산자가 적용되는 타입으로 변환하세요"),
+    ("integers", "정수"),
+    ("floats", "실수"),
+    ("an integer and a float", "정수와 실수"),
+    ("a float and an integer", "실수와 정수"),
+    ("strings", "문자열"),
+    ("values that are not numbers", "숫자가 아닌 값"),
+    ("builtin `{0}` takes {1} argument(s), found {2}", "내장 함수 `{0}`은(는) 인자를 {1}개 받지만 {2}개가 있습니다"),
+    ("builtin `{0}` expects integer arguments, found `{1}`", "내장 함수 `{0}`은(는) 정수 인자가 필요하지만 `{1}`이(가) 있습니다"),
+    ("builtin `{0}` expects a `u8` array, found `{1}`", "내장 함수 `{0}`은(는) `u8` 배열이 필요하지만 `{1}`이(가) 있습니다"),
+    ("builtin `{0}` expects a `str`, found `{1}`", "내장 함수 `{0}`은(는) `str`이 필요하지만 `{1}`이(가) 있습니다"),
+    ("builtin `{0}` expects a pointer, found `{1}`", "내장 함수 `{0}`은(는) 포인터가 필요하지만 `{1}`이(가) 있습니다"),
+    ("swapping bytes needs an integer width that is a multiple of 16, found `i{0}`", "바이트 교환에는 16의 배수 너비의 정수가 필요하지만 `i{0}`이(가) 있습니다"),
+    ("builtin `{0}` expects a string literal path", "내장 함수 `{0}`에는 문자열 리터럴 경로가 필요합니다"),
+    ("cannot read `{0}`: {1}", "`{0}`을(를) 읽을 수 없습니다: {1}"),
+    ("`{0}` is not valid UTF-8", "`{0}`은(는) 올바른 UTF-8이 아닙니다"),
+    ("`{0}` is {1} bytes, but the target array holds {2}", "`{0}`은(는) {1}바이트이지만 대상 배열은 {2}바이트입니다"),
+    ("declaration of `{0}`", "`{0}` 선언"),
+    ("assignment to `{0}`", "`{0}`에 대한 대입"),
+    ("call to `{0}`", "`{0}` 호출"),
+    ("cannot find module `{0}`", "모듈 `{0}`을(를) 찾을 수 없습니다"),
+    ("no `{0}.wave` in `{1}` or below", "`{1}`와(과) 그 하위 디렉터리에 `{0}.wave`가 없습니다"),
+    ("module `{0}` has errors", "모듈 `{0}`에 오류가 있습니다"),
+    ("`{0}` is imported here", "`{0}`을(를) 여기서 가져옵니다"),
+    ("parsing stopped here", "여기서 구문 분석이 멈췄습니다"),
+];
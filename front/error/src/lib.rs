@@ -1,3 +1,5 @@
 pub mod error;
+pub mod fixes;
+pub mod i18n;
 
 use error::*;
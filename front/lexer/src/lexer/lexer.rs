@@ -1,4 +1,5 @@
-use std::str::FromStr;
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::UnicodeNormalization;
 use crate::*;
 
 #[derive(Debug, Clone)]
@@ -6,14 +7,16 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize,
+    pub column: usize,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, line: usize, column: usize) -> Self {
         Token {
             token_type,
             lexeme,
             line,
+            column,
         }
     }
 }
@@ -24,6 +27,7 @@ impl Default for Token {
             token_type: TokenType::Eof, // Set default token type to EOF
             lexeme: String::new(),      // The default lexeme is an empty string
             line: 0,                    // Default line number is 0
+            column: 0,
         }
     }
 }
@@ -33,6 +37,9 @@ pub struct Lexer<'a> {
     pub source: &'a str,
     pub current: usize,
     pub line: usize,
+    line_start: usize,
+    token_start: usize,
+    token_column: usize,
 }
 
 impl<'a> Lexer<'a> {
@@ -41,6 +48,9 @@ impl<'a> Lexer<'a> {
             source,
             current: 0,
             line: 1,
+            line_start: 0,
+            token_start: 0,
+            token_column: 1,
         }
     }
 
@@ -80,6 +90,7 @@ impl<'a> Lexer<'a> {
                 '\n' => {
                     self.line += 1;
                     self.advance();
+                    self.line_start = self.current;
                 }
                 _ => break,
             }
@@ -121,6 +132,7 @@ impl<'a> Lexer<'a> {
             }
             tokens.push(token);
         }
+        tracing::debug!(tokens = tokens.len(), "tokenized");
         tokens
     }
 
@@ -130,32 +142,41 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn skip_multiline_comment(&mut self) {
+    // Returns false when the file ends before the closing `*/`
+    fn skip_multiline_comment(&mut self) -> bool {
         while !self.is_at_end() {
             if self.peek() == '*' && self.peek_next() == '/' {
                 self.advance();
                 self.advance();
-                break;
+                return true;
             }
 
             if self.peek() == '\n' {
                 self.line += 1;
+                self.advance();
+                self.line_start = self.current;
+                continue;
             }
 
             self.advance();
         }
 
-        if self.is_at_end() {
-            panic!("Unterminated block comment");
+        false
+    }
+
+    // The offending source text becomes the lexeme, so diagnostics can quote it
+    fn error_token(&self, error: LexError) -> Token {
+        Token {
+            token_type: TokenType::Error(error),
+            lexeme: self.source[self.token_start..self.current].to_string(),
+            line: self.line,
+            column: self.token_column,
         }
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap_or('\0')
-        }
+        // `current` counts bytes, so the characters are taken from there on rather than from the start.
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     /*
@@ -179,12 +200,15 @@ impl<'a> Lexer<'a> {
 
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        self.token_start = self.current;
+        self.token_column = self.source[self.line_start..self.current].chars().count() + 1;
 
         if self.is_at_end() {
             return Token {
                 token_type: TokenType::Eof,
                 lexeme: String::new(),
                 line: self.line,
+                column: self.token_column,
             };
         }
 
@@ -197,18 +221,21 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::Increment,
                         lexeme: "++".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else if self.match_next('=') {
                     Token {
                         token_type: TokenType::PlusEq,
                         lexeme: "+=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Plus,
                         lexeme: "+".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -218,24 +245,28 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::Decrement,
                         lexeme: "--".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else if self.match_next('>') {
                     Token {
                         token_type: TokenType::Arrow,
                         lexeme: "->".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else if self.match_next('=') {
                     Token {
                         token_type: TokenType::MinusEq,
                         lexeme: "-=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Minus,
                         lexeme: "-".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -245,20 +276,49 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::StarEq,
                         lexeme: "*=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Star,
                         lexeme: "*".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             } ,
             '.' => {
+                if self.match_next('.') {
+                    if self.match_next('.') {
+                        Token {
+                            token_type: TokenType::DotDotDot,
+                            lexeme: "...".to_string(),
+                            line: self.line,
+                            column: self.token_column,
+                        }
+                    } else {
+                        Token {
+                            token_type: TokenType::DotDot,
+                            lexeme: "..".to_string(),
+                            line: self.line,
+                            column: self.token_column,
+                        }
+                    }
+                } else {
+                    Token {
+                        token_type: TokenType::Dot,
+                        lexeme: ".".to_string(),
+                        line: self.line,
+                        column: self.token_column,
+                    }
+                }
+            },
+            '#' => {
                 Token {
-                    token_type: TokenType::Dot,
-                    lexeme: ".".to_string(),
+                    token_type: TokenType::Hash,
+                    lexeme: "#".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '/' => {
@@ -266,19 +326,23 @@ impl<'a> Lexer<'a> {
                     self.skip_comment();
                     self.next_token()
                 } else if self.match_next('*') {
-                    self.skip_multiline_comment();
+                    if !self.skip_multiline_comment() {
+                        return self.error_token(LexError::UnterminatedComment);
+                    }
                     self.next_token()
                 } else if self.match_next('=') {
                     Token {
                         token_type: TokenType::DivEq,
                         lexeme: "/=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Div,
                         lexeme: "/".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -288,12 +352,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::RemainderEq,
                         lexeme: "%=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Remainder,
                         lexeme: "%".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             }
@@ -302,13 +368,24 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::SemiColon,
                     lexeme: ";".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             ':' => {
-                Token {
-                    token_type: TokenType::Colon,
-                    lexeme: ":".to_string(),
-                    line: self.line,
+                if self.match_next(':') {
+                    Token {
+                        token_type: TokenType::ColonColon,
+                        lexeme: "::".to_string(),
+                        line: self.line,
+                        column: self.token_column,
+                    }
+                } else {
+                    Token {
+                        token_type: TokenType::Colon,
+                        lexeme: ":".to_string(),
+                        line: self.line,
+                        column: self.token_column,
+                    }
                 }
             },
             '<' => {
@@ -317,12 +394,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::LchevrEq,
                         lexeme: "<=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Lchevr,
                         lexeme: "<".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
 
@@ -333,12 +412,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::RchevrEq,
                         lexeme: ">=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Rchevr,
                         lexeme: ">".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
 
@@ -348,6 +429,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Lparen,
                     lexeme: "(".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             ')' => {
@@ -355,6 +437,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Rparen,
                     lexeme: ")".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '{' => {
@@ -362,6 +445,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Lbrace,
                     lexeme: "{".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '}' => {
@@ -369,6 +453,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Rbrace,
                     lexeme: "}".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '[' => {
@@ -376,6 +461,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Lbrack,
                     lexeme: "[".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             ']' => {
@@ -383,6 +469,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Rbrack,
                     lexeme: "]".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '=' => {
@@ -391,12 +478,21 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::EqualTwo,
                         lexeme: "==".to_string(),
                         line: self.line,
+                        column: self.token_column,
+                    }
+                } else if self.match_next('>') {
+                    Token {
+                        token_type: TokenType::FatArrow,
+                        lexeme: "=>".to_string(),
+                        line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Equal,
                         lexeme: "=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -406,12 +502,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::LogicalAnd,
                         lexeme: "&&".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::AddressOf,
                         lexeme: "&".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -421,12 +519,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::LogicalOr,
                         lexeme: "||".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::BitwiseOr,
                         lexeme: "|".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -436,24 +536,28 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::NotEqual,
                         lexeme: "!=".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else if self.match_next('&') {
                     Token {
                         token_type: TokenType::Nand,
                         lexeme: "!&".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else if self.match_next('|') {
                     Token {
                         token_type: TokenType::Nor,
                         lexeme: "!|".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::Not,
                         lexeme: "!".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -462,6 +566,7 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Xor,
                     lexeme: "^".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '~' => {
@@ -470,12 +575,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::Xnor,
                         lexeme: "~^".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                     Token {
                         token_type: TokenType::BitwiseNot,
                         lexeme: "~".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -485,12 +592,14 @@ impl<'a> Lexer<'a> {
                         token_type: TokenType::NullCoalesce,
                         lexeme: "??".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 } else {
                      Token {
                         token_type: TokenType::Condition,
                         lexeme: "?".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     }
                 }
             },
@@ -499,17 +608,45 @@ impl<'a> Lexer<'a> {
                     token_type: TokenType::Comma,
                     lexeme: ",".to_string(),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
             '"' => {
-                let string_value = self.string();
+                let string_value = match self.string() {
+                    Ok(value) => value,
+                    Err(error) => return self.error_token(error),
+                };
                 Token {
                     token_type: TokenType::String(string_value.clone()),
                     lexeme: format!("\"{}\"", string_value),
                     line: self.line,
+                    column: self.token_column,
                 }
             },
-            'a'..='z' | 'A'..='Z' => {
+            // `'outer` names a loop; a quote after the first character makes a char literal instead.
+            '\'' if self.at_label() => {
+                self.advance();
+                let label = self.identifier()[1..].to_string();
+                Token {
+                    token_type: TokenType::Label(label.clone()),
+                    lexeme: format!("'{}", label),
+                    line: self.line,
+                    column: self.token_column,
+                }
+            },
+            '\'' => {
+                let value = match self.char_literal() {
+                    Ok(value) => value,
+                    Err(error) => return self.error_token(error),
+                };
+                Token {
+                    token_type: TokenType::Char(value),
+                    lexeme: format!("'{}'", value),
+                    line: self.line,
+                    column: self.token_column,
+                }
+            },
+            c if c == '_' || c.is_ascii_alphabetic() || (!c.is_ascii() && is_xid_start(c)) => {
                 let identifier = self.identifier();
                 match identifier.as_str() {
                     "fun" => {
@@ -517,6 +654,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Fun,
                             lexeme: "fun".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "var" => {
@@ -524,6 +662,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Var,
                             lexeme: "var".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "deref" => {
@@ -531,6 +670,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Deref,
                             lexeme: "deref".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "let" => {
@@ -538,6 +678,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Let,
                             lexeme: "let".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     }
                     "mut" => {
@@ -545,6 +686,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Mut,
                             lexeme: "mut".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     }
                     "const" => {
@@ -552,6 +694,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Const,
                             lexeme: "const".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "if" => {
@@ -559,6 +702,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::If,
                             lexeme: "if".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "else" => {
@@ -566,6 +710,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Else,
                             lexeme: "else".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "while" => {
@@ -573,6 +718,15 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::While,
                             lexeme: "while".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "loop" => {
+                        Token {
+                            token_type: TokenType::Loop,
+                            lexeme: "loop".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "for" => {
@@ -580,6 +734,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::For,
                             lexeme: "for".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "module" => {
@@ -587,6 +742,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Module,
                             lexeme: "module".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "class" => {
@@ -594,6 +750,23 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Class,
                             lexeme: "class".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "struct" => {
+                        Token {
+                            token_type: TokenType::Struct,
+                            lexeme: "struct".to_string(),
+                            line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "enum" => {
+                        Token {
+                            token_type: TokenType::Enum,
+                            lexeme: "enum".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "in" => {
@@ -601,6 +774,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::In,
                             lexeme: "in".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "out" => {
@@ -608,6 +782,15 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Out,
                             lexeme: "out".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "as" => {
+                        Token {
+                            token_type: TokenType::As,
+                            lexeme: "as".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "is" => {
@@ -615,6 +798,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Is,
                             lexeme: "is".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "asm" => {
@@ -622,6 +806,23 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Asm,
                             lexeme: "asm".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "true" => {
+                        Token {
+                            token_type: TokenType::True,
+                            lexeme: "true".to_string(),
+                            line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "false" => {
+                        Token {
+                            token_type: TokenType::False,
+                            lexeme: "false".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "rol" => {
@@ -629,6 +830,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Rol,
                             lexeme: "rol".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "ror" => {
@@ -636,6 +838,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Ror,
                             lexeme: "ror".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "xnand" => {
@@ -643,6 +846,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Xnand,
                             lexeme: "xnand".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "import" => {
@@ -650,6 +854,15 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Import,
                             lexeme: "import".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "extern" => {
+                        Token {
+                            token_type: TokenType::Extern,
+                            lexeme: "extern".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "return" => {
@@ -657,6 +870,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Return,
                             lexeme: "return".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "continue" => {
@@ -664,6 +878,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Continue,
                             lexeme: "continue".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "print" => {
@@ -671,6 +886,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Print,
                             lexeme: "print".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "input" => {
@@ -678,6 +894,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Input,
                             lexeme: "input".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "println" => {
@@ -685,6 +902,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Println,
                             lexeme: "println".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "match" => {
@@ -692,6 +910,15 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Match,
                             lexeme: "match".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "macro" => {
+                        Token {
+                            token_type: TokenType::Macro,
+                            lexeme: "macro".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "char" => {
@@ -699,6 +926,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TypeChar,
                             lexeme: "char".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "byte" => {
@@ -706,6 +934,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TypeByte,
                             lexeme: "byte".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "ptr" => {
@@ -713,6 +942,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Identifier("ptr".to_string()),
                             lexeme: "ptr".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "array" => {
@@ -720,13 +950,15 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Identifier("array".to_string()),
                             lexeme: "array".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
-                    "isz" => {
+                    "isize" | "isz" => {
                         Token {
                             token_type: TokenType::TokenTypeInt(IntegerType::ISZ),
-                            lexeme: "isz".to_string(),
+                            lexeme: identifier.clone(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i8" => {
@@ -734,6 +966,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I8),
                             lexeme: "i8".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i16" => {
@@ -741,6 +974,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I16),
                             lexeme: "i16".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i32" => {
@@ -748,6 +982,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I32),
                             lexeme: "i32".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i64" => {
@@ -755,6 +990,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I64),
                             lexeme: "i64".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i128" => {
@@ -762,6 +998,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I128),
                             lexeme: "i128".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i256" => {
@@ -769,6 +1006,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I256),
                             lexeme: "i256".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i512" => {
@@ -776,6 +1014,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I512),
                             lexeme: "i512".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "i1024" => {
@@ -783,88 +1022,113 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::TokenTypeInt(IntegerType::I1024),
                             lexeme: "i1024".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
-                    "usz" => Token {
+                    "usize" | "usz" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::USZ),
-                        lexeme: "usz".to_string(),
+                        lexeme: identifier.clone(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u8" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U8),
                         lexeme: "u8".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u16" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U16),
                         lexeme: "u16".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u32" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U32),
                         lexeme: "u32".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u64" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U64),
                         lexeme: "u64".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u128" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U128),
                         lexeme: "u128".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u256" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U256),
                         lexeme: "u256".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u512" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U512),
                         lexeme: "u512".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "u1024" => Token {
                         token_type: TokenType::TokenTypeUint(UnsignedIntegerType::U1024),
                         lexeme: "u1024".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f32" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F32),
                         lexeme: "f32".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f64" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F64),
                         lexeme: "f64".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f128" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F128),
                         lexeme: "f128".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f256" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F256),
                         lexeme: "f256".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f512" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F512),
                         lexeme: "f512".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "f1024" => Token {
                         token_type: TokenType::TokenTypeFloat(FloatType::F1024),
                         lexeme: "f1024".to_string(),
                         line: self.line,
+                        column: self.token_column,
                     },
                     "str" => {
                         Token {
                             token_type: TokenType::TypeString,
                             lexeme: "str".to_string(),
                             line: self.line,
+                            column: self.token_column,
+                        }
+                    },
+                    "bool" => {
+                        Token {
+                            token_type: TokenType::TypeBool,
+                            lexeme: "bool".to_string(),
+                            line: self.line,
+                            column: self.token_column,
                         }
                     },
                     "break" => {
@@ -872,6 +1136,7 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Break,
                             lexeme: "break".to_string(),
                             line: self.line,
+                            column: self.token_column,
                         }
                     },
                     _ => {
@@ -879,14 +1144,16 @@ impl<'a> Lexer<'a> {
                             token_type: TokenType::Identifier(identifier.clone()),
                             lexeme: identifier,
                             line: self.line,
+                            column: self.token_column,
                         }
                     }
                 }
             },
             '0'..='9' => {
-                let mut num_str = self.number().to_string(); // Converting Numbers to Strings
+                let mut num_str = self.number(); // The digits, parsed once the literal is complete
                 
-                let is_float = if self.peek() == '.' {
+                // `0..n` is a range, not the float `0.` followed by `.n`
+                let is_float = if self.peek() == '.' && self.peek_next() != '.' {
                     num_str.push('.');
                     self.advance();
                     
@@ -905,9 +1172,10 @@ impl<'a> Lexer<'a> {
                         Err(_) => TokenType::Float(0.0),
                     }
                 } else {
+                    // Only digits reach here, so the parse fails only for a value past `i64::MAX`.
                     match num_str.parse::<i64>() {
                         Ok(n) => TokenType::Number(n),
-                        Err(_) => TokenType::Number(0),
+                        Err(_) => return self.error_token(LexError::IntegerOutOfRange),
                     }
                 };
                 
@@ -915,20 +1183,10 @@ impl<'a> Lexer<'a> {
                     token_type,
                     lexeme: num_str, // Save real string to lexeme
                     line: self.line,
+                    column: self.token_column,
                 }
             },
-            _ => {
-                if c == '\0' {
-                    eprintln!("[eprintln] Null character encountered — likely unintended");
-                    panic!("[panic] Null character (`\\0`) is not allowed in source");
-                } else if c == '\\' {
-                    eprintln!("[eprintln] Unexpected backslash outside of string");
-                    panic!("[panic] Unexpected character: '\\' outside of string");
-                } else {
-                    eprintln!("[eprintln] Unexpected character: {:?} (code: {})", c, c as u32);
-                    panic!("[panic] Unexpected character: {:?}", c);
-                }
-            }
+            _ => self.error_token(LexError::UnexpectedChar(c)),
         }
     }
 
@@ -938,6 +1196,7 @@ impl<'a> Lexer<'a> {
             token_type: TokenType::TokenTypeInt(int_type),
             lexeme,
             line: self.line,
+            column: self.token_column,
         }
     }
 
@@ -946,6 +1205,7 @@ impl<'a> Lexer<'a> {
             token_type: TokenType::TokenTypeFloat(float_type),
             lexeme,
             line: self.line,
+            column: self.token_column,
         }
     }
 
@@ -954,15 +1214,12 @@ impl<'a> Lexer<'a> {
             token_type: TokenType::Identifier(identifier.clone()),
             lexeme: identifier,
             line: self.line,
+            column: self.token_column,
         }
     }
     
-    // Add string literal processing function
-    fn string(&mut self) -> String {
-        if self.peek() == '"' {
-            self.advance();
-        }
-
+    // The opening quote is already consumed, so `""` ends at once.
+    fn string(&mut self) -> Result<String, LexError> {
         let mut string_literal = String::new();
 
         while !self.is_at_end() && self.peek() != '"' {
@@ -987,40 +1244,83 @@ impl<'a> Lexer<'a> {
         }
 
         if self.is_at_end() {
-            panic!("Unterminated string.");
+            return Err(LexError::UnterminatedString);
         }
 
         self.advance(); // closing quote
 
-        string_literal
+        Ok(string_literal)
     }
 
-    fn identifier(&mut self) -> String {
-        let start = if self.current > 0 {
-            self.current - 1
-        } else {
-            0
+    // After a quote: an identifier that no closing quote follows, as in `'outer` but not `'a'`
+    fn at_label(&self) -> bool {
+        let mut rest = self.source[self.current..].chars();
+        matches!(rest.next(), Some(c) if c == '_' || is_xid_start(c)) && rest.next() != Some('\'')
+    }
+
+    // Character literal: a single byte-sized character or escape between single quotes
+    fn char_literal(&mut self) -> Result<char, LexError> {
+        if self.is_at_end() || self.peek() == '\n' {
+            return Err(LexError::UnterminatedChar);
+        }
+
+        let c = match self.advance() {
+            '\\' => match self.advance() {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                '\\' => '\\',
+                '\'' => '\'',
+                other => {
+                    self.match_next('\'');
+                    return Err(LexError::UnknownEscape(other));
+                }
+            },
+            '\'' => return Err(LexError::EmptyChar),
+            c => c,
         };
 
+        if !self.match_next('\'') {
+            // 'ab': skip to the closing quote on this line so it isn't read as another literal
+            let rest = &self.source[self.current..];
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            return match rest[..line_end].find('\'') {
+                Some(quote) => {
+                    self.current += quote + 1;
+                    Err(LexError::MultiCharLiteral)
+                }
+                None => Err(LexError::UnterminatedChar),
+            };
+        }
+
+        if !c.is_ascii() {
+            return Err(LexError::NonAsciiChar(c));
+        }
+
+        Ok(c)
+    }
+
+    // UAX #31 identifiers, NFC-normalized so that differently encoded spellings name the same thing
+    fn identifier(&mut self) -> String {
         while !self.is_at_end() {
             let c = self.peek();
-            if c.is_alphabetic() || c.is_numeric() || c == '_' {
+            if c == '_' || is_xid_continue(c) {
                 self.advance();
             } else {
                 break;
             }
         }
 
-        self.source[start..self.current].to_string()
+        self.source[self.token_start..self.current].nfc().collect()
     }
 
-    fn number(&mut self) -> i64 {
+    fn number(&mut self) -> String {
         let start = self.current - 1;
         while !self.is_at_end() && self.peek().is_numeric() {
             self.advance();
         }
 
-        let number_str = &self.source[start..self.current];
-        i64::from_str(number_str).unwrap_or_else(|_| 0)
+        self.source[start..self.current].to_string()
     }
 }
@@ -47,7 +47,7 @@ impl fmt::Display for IntegerType {
             IntegerType::I256 => "i256",
             IntegerType::I512 => "i512",
             IntegerType::I1024 => "i1024",
-            IntegerType::ISZ => "isz",
+            IntegerType::ISZ => "isize",
         };
         write!(f, "{}", name)
     }
@@ -64,12 +64,43 @@ impl fmt::Display for UnsignedIntegerType {
             UnsignedIntegerType::U256 => "u256",
             UnsignedIntegerType::U512 => "u512",
             UnsignedIntegerType::U1024 => "u1024",
-            UnsignedIntegerType::USZ => "usz",
+            UnsignedIntegerType::USZ => "usize",
         };
         write!(f, "{}", name)
     }
 }
 
+/// What went wrong in an `Error` token. The lexer records these and keeps going,
+/// so every lexical problem in a file can be reported at once.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexError {
+    UnexpectedChar(char),
+    UnterminatedString,
+    UnterminatedChar,
+    MultiCharLiteral,
+    EmptyChar,
+    UnknownEscape(char),
+    NonAsciiChar(char),
+    UnterminatedComment,
+    IntegerOutOfRange,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            LexError::UnterminatedString => write!(f, "unterminated string literal"),
+            LexError::UnterminatedChar => write!(f, "unterminated character literal"),
+            LexError::MultiCharLiteral => write!(f, "character literal may only contain one character"),
+            LexError::EmptyChar => write!(f, "empty character literal"),
+            LexError::UnknownEscape(c) => write!(f, "unknown escape `\\{}` in character literal", c),
+            LexError::NonAsciiChar(c) => write!(f, "character literal {:?} does not fit in a byte", c),
+            LexError::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexError::IntegerOutOfRange => write!(f, "integer literal out of range"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     Fun,
@@ -81,8 +112,10 @@ pub enum TokenType {
     If,
     Else,
     While,
+    Loop,
     For,
     Import,
+    Extern,
     Return,
     Continue,
     Input,
@@ -90,7 +123,12 @@ pub enum TokenType {
     Println,
     Module,
     Class,
+    Struct,
+    Enum,
     Match,
+    Macro,
+    True,
+    False,
     LogicalAnd,            // &&
     AddressOf,            // &
     LogicalOr,             // ||
@@ -108,6 +146,7 @@ pub enum TokenType {
     In,                     // in
     Out,                    // out
     Is,                     // is
+    As,                     // as
     Asm,
     Rol,
     Ror,
@@ -125,10 +164,13 @@ pub enum TokenType {
     TypeString,
     TypePointer(Box<TokenType>),
     TypeArray(Box<TokenType>, u32),
+    TypeFunction(Vec<TokenType>, Option<Box<TokenType>>),
     Identifier(String),
+    Label(String),          // 'outer, naming a loop
     String(String),
     Number(i64),
     Float(f64),
+    Char(char),
     Plus,                   // +
     Increment,              // ++
     PlusEq,                 // +=
@@ -145,8 +187,12 @@ pub enum TokenType {
     EqualTwo,              // ==
     Comma,                  // ,
     Dot,                    // .
+    DotDot,                 // ..
+    DotDotDot,              // ...
+    Hash,                   // #
     SemiColon,              // ;
     Colon,                  // :
+    ColonColon,             // ::
     Lchevr,                 // <
     LchevrEq,              // <=
     Rchevr,                 // >
@@ -158,9 +204,10 @@ pub enum TokenType {
     Lbrack,                 // [
     Rbrack,                 // ]
     Eof,                    // End of file
-    Error,
+    Error(LexError),        // Malformed input; lexing continues after it
     Whitespace,
     Break,
     Arrow,                  // ->
+    FatArrow,               // =>
     Array,
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ pub enum Value {
     Int(i64),
     Float(f64),
     Text(String),
+    Bool(bool),
 }
 
 #[derive(Debug, Clone)]
@@ -16,15 +17,76 @@ pub enum WaveType {
     String,
     Pointer(Box<WaveType>),
     Array(Box<WaveType>, u32),
+    /// A `struct` or `enum` declared in the program, by name.
+    Struct(String),
+    /// `fun(i32, i32) -> i32`: the address of a function taking and returning these types.
+    Function(Vec<WaveType>, Option<Box<WaveType>>),
+    TypeOf(Box<Expression>),
+    /// Omitted annotation of `let x = expr;`, taken from the initializer.
+    Inferred,
+}
+
+impl WaveType {
+    /// The width of `isize` and `usize`, `Int(POINTER_SIZED)` and `Uint(POINTER_SIZED)`: that of
+    /// a pointer on the target, which code generation reads from its data layout.
+    pub const POINTER_SIZED: u16 = 0;
+}
+
+impl std::fmt::Display for WaveType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaveType::Int(WaveType::POINTER_SIZED) => write!(f, "isize"),
+            WaveType::Uint(WaveType::POINTER_SIZED) => write!(f, "usize"),
+            WaveType::Int(bits) => write!(f, "i{}", bits),
+            WaveType::Uint(bits) => write!(f, "u{}", bits),
+            WaveType::Float(bits) => write!(f, "f{}", bits),
+            WaveType::Bool => write!(f, "bool"),
+            WaveType::Char => write!(f, "char"),
+            WaveType::Byte => write!(f, "byte"),
+            WaveType::String => write!(f, "str"),
+            WaveType::Pointer(inner) => write!(f, "ptr<{}>", inner),
+            WaveType::Array(inner, size) => write!(f, "array<{}, {}>", inner, size),
+            WaveType::Struct(name) => write!(f, "{}", name),
+            WaveType::Function(params, return_type) => {
+                let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+                write!(f, "fun({})", params.join(", "))?;
+                match return_type {
+                    Some(return_type) => write!(f, " -> {}", return_type),
+                    None => Ok(()),
+                }
+            }
+            WaveType::TypeOf(_) => write!(f, "typeof(..)"),
+            WaveType::Inferred => write!(f, "_"),
+        }
+    }
+}
+
+/// Source position of a node: 1-based line and column of its first token.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Span { line, column }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ASTNode {
     Function(FunctionNode),
     Program(ParameterNode),
-    Statement(StatementNode),
+    Statement(StatementNode, Span),
     Variable(VariableNode),
     Expression(Expression),
+    Macro(MacroNode),
+    Struct(StructNode),
+    Enum(EnumNode),
+    /// `extern "C" fun strlen(s: ptr<byte>) -> i64;`: a function defined outside the program, whose
+    /// body is empty. Called like any other, under its own name in the object file.
+    Extern(FunctionNode),
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +95,102 @@ pub struct FunctionNode {
     pub parameters: Vec<ParameterNode>,
     pub return_type: Option<WaveType>,
     pub body: Vec<ASTNode>,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
+    /// Takes any number of arguments after its parameters, as C's `...`; only an `extern` does.
+    pub variadic: bool,
+}
+
+impl FunctionNode {
+    /// The type of the function's address, `fun(..) -> ..`.
+    pub fn function_type(&self) -> WaveType {
+        WaveType::Function(
+            self.parameters.iter().map(|param| param.param_type.clone()).collect(),
+            self.return_type.clone().map(Box::new),
+        )
+    }
+
+    /// Marked `#[bench]`: run and timed by `wave bench` instead of called from `main`.
+    pub fn is_bench(&self) -> bool {
+        self.attributes.iter().any(|attribute| attribute.name == "bench")
+    }
+
+    /// Marked `#[ctor]`: listed in `llvm.global_ctors`, so it runs before `main`.
+    pub fn is_constructor(&self) -> bool {
+        self.attributes.iter().any(|attribute| attribute.name == "ctor")
+    }
+
+    /// Marked `#[dtor]`: listed in `llvm.global_dtors`, so it runs when the program exits.
+    pub fn is_destructor(&self) -> bool {
+        self.attributes.iter().any(|attribute| attribute.name == "dtor")
+    }
+
+    /// Whether `#[allow(..)]` on the function silences `lint`.
+    pub fn allows(&self, lint: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attribute| attribute.name == "allow" && attribute.args.iter().any(|arg| arg == lint))
+    }
+}
+
+/// `struct Name { field: type; ... }`. Fields are laid out in declaration order.
+#[derive(Debug, Clone)]
+pub struct StructNode {
+    pub name: String,
+    pub fields: Vec<StructField>,
+    pub span: Span,
+}
+
+impl StructNode {
+    /// The position and type of `name` among the fields.
+    pub fn field(&self, name: &str) -> Option<(usize, &WaveType)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+            .map(|(index, field)| (index, &field.field_type))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructField {
+    pub name: String,
+    pub field_type: WaveType,
+}
+
+/// `enum Name { Variant(type, ...), ... }`. A variant without fields leaves off the parentheses.
+/// Variants are numbered in declaration order.
+#[derive(Debug, Clone)]
+pub struct EnumNode {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+    pub span: Span,
+}
+
+impl EnumNode {
+    /// The number and declaration of variant `name`.
+    pub fn variant(&self, name: &str) -> Option<(usize, &EnumVariant)> {
+        self.variants.iter().enumerate().find(|(_, variant)| variant.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<WaveType>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroNode {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: MacroBody,
+}
+
+#[derive(Debug, Clone)]
+pub enum MacroBody {
+    Expression(Expression),
+    Block(Vec<ASTNode>),
 }
 
 #[derive(Debug, Clone)]
@@ -40,12 +198,29 @@ pub struct ParameterNode {
     pub name: String,
     pub param_type: WaveType,
     pub initial_value: Option<Value>,
+    pub span: Span,
 }
 
+/// A piece of the format string of `print`/`println`.
 #[derive(Debug, Clone)]
 pub enum FormatPart {
+    /// Text printed as written, with `{{` and `}}` already turned into braces.
     Literal(String),
-    Placeholder,
+    /// `{}`, `{name}` or either with a spec after a `:`, e.g. `{:.2}` or `{count:08x}`.
+    /// The parser gives a named placeholder the variable as its argument, in order among the others.
+    Placeholder(Option<String>, FormatSpec),
+}
+
+/// The `:` spec of a placeholder: an alignment in the width, `<`, `^` or `>`, `0` to pad with
+/// zeros, a minimum width, `.` and a precision, then a radix for integers, `x`, `X`, `o` or `b`.
+/// Without an alignment, values are aligned right.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatSpec {
+    pub align: Option<char>,
+    pub zero_pad: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub radix: Option<char>,
 }
 
 #[derive(Debug, Clone)]
@@ -82,7 +257,46 @@ pub enum Expression {
         instructions: Vec<String>,
         inputs: Vec<(String, String)>,
         outputs: Vec<(String, String)>,
-    }
+    },
+    /// `Name { field: value, ... }`, listing every field of the struct once, in any order.
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Expression)>,
+    },
+    /// `Name::Variant(value, ...)`, giving every field of the variant in order.
+    EnumVariant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Expression>,
+    },
+    /// `alloc<T>(count)`: room on the heap for `count` values of type `T`, or for one without a
+    /// count, as a `ptr<T>`. The memory is not initialized and stays until `dealloc` is called on it.
+    Alloc {
+        ty: WaveType,
+        count: Option<Box<Expression>>,
+    },
+    /// `target.field`; a pointer to a struct is followed to the struct.
+    FieldAccess {
+        target: Box<Expression>,
+        field: String,
+    },
+    /// A `while` loop used as a value: the value of the `break` that leaves it.
+    While {
+        condition: Box<Expression>,
+        body: Vec<ASTNode>,
+        label: Option<String>,
+    },
+    /// A `loop` used as a value, likewise given by its `break`.
+    Loop {
+        body: Vec<ASTNode>,
+        label: Option<String>,
+    },
+    /// `value as T`: `value` converted to the number or pointer type `T`, truncated, extended or
+    /// rounded toward zero as the two types call for.
+    Cast {
+        value: Box<Expression>,
+        ty: WaveType,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -90,6 +304,8 @@ pub enum Literal {
     Number(i64),
     Float(f64),
     String(String),
+    Bool(bool),
+    Char(char),
 }
 
 #[derive(Debug, Clone)]
@@ -141,15 +357,33 @@ pub enum StatementNode {
         else_if_blocks: Option<Box<Vec<ASTNode>>>,
         else_block: Option<Box<Vec<ASTNode>>>,
     },
+    /// The loops carry the name of their `'label: ` prefix, which `break` and `continue`
+    /// can use to leave or restart a loop other than the innermost one.
     For {
         initialization: Expression,
         condition: Expression,
         increment: Expression,
         body: Vec<ASTNode>,
+        label: Option<String>,
+    },
+    /// `for i in start..end { body }`: `i` counts up from `start` and stops before `end`.
+    /// The bounds are evaluated once, and `i` is immutable in the body.
+    ForRange {
+        variable: String,
+        start: Expression,
+        end: Expression,
+        body: Vec<ASTNode>,
+        label: Option<String>,
     },
     While {
         condition: Expression,
         body: Vec<ASTNode>,
+        label: Option<String>,
+    },
+    /// `loop { body }`: runs until a `break` or `return` leaves it.
+    Loop {
+        body: Vec<ASTNode>,
+        label: Option<String>,
     },
     Import(String),
     Assign {
@@ -161,10 +395,47 @@ pub enum StatementNode {
         inputs: Vec<(String, String)>,
         outputs: Vec<(String, String)>,
     },
-    Break,
-    Continue,
+    /// `break 'label value;`: both parts are optional; without a label it leaves the innermost loop.
+    Break {
+        label: Option<String>,
+        value: Option<Expression>,
+    },
+    Continue {
+        label: Option<String>,
+    },
     Return(Option<Expression>),
     Expression(Expression),
+    /// `match (value) { pattern => { body } ... }`: runs the first arm whose pattern matches.
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<ASTNode>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `Name::Variant(a, b)`: binds the fields to new variables in the arm, except those named `_`.
+    Variant {
+        enum_name: String,
+        variant: String,
+        bindings: Vec<String>,
+    },
+    /// An integer, matched against an integer value.
+    Number(i64),
+    /// `_`, matching anything.
+    Wildcard,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -180,4 +451,6 @@ pub struct VariableNode {
     pub type_name: WaveType,
     pub initial_value: Option<Expression>,
     pub mutability: Mutability,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
 }
\ No newline at end of file
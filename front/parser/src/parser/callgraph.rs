@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::ast::*;
+use crate::overload::symbol_name;
+use crate::typeck::TypeChecker;
+use crate::visit::walk_expressions;
+
+/// Which functions each function calls directly, keyed by the name code generation gives the
+/// function (`symbol_name`), so each overload is a node of its own. Calls to names that are not
+/// functions in the program (builtins, macros) are left out. A call to an overloaded name goes
+/// to the overload the type checker selects for it, or to them all where it selects none.
+/// Taking the address of a function counts as calling it, since the address may be called.
+pub struct CallGraph {
+    pub calls: HashMap<String, Vec<String>>,
+}
+
+impl CallGraph {
+    pub fn build(nodes: &[ASTNode]) -> Self {
+        let mut defined: HashMap<&str, Vec<String>> = HashMap::new();
+        for node in nodes {
+            if let ASTNode::Function(function) = node {
+                defined.entry(function.name.as_str()).or_default().push(symbol_name(function, nodes));
+            }
+        }
+
+        let type_checker = TypeChecker::new(nodes);
+        let mut calls: HashMap<String, Vec<String>> = HashMap::new();
+        for node in nodes {
+            let ASTNode::Function(function) = node else {
+                continue;
+            };
+
+            let mut callees: Vec<String> = vec![];
+            let add = |callees: &mut Vec<String>, symbol: &String| {
+                if !callees.contains(symbol) {
+                    callees.push(symbol.clone());
+                }
+            };
+            // The uses of each overloaded name, to count against those the type checker resolves.
+            let mut overloaded_uses: Vec<(String, usize)> = vec![];
+            walk_expressions(&function.body, &mut |expr| {
+                if let Expression::FunctionCall { name, .. } | Expression::Variable(name) = expr {
+                    match defined.get(name.as_str()).map(Vec::as_slice) {
+                        Some([symbol]) => add(&mut callees, symbol),
+                        Some(_) => match overloaded_uses.iter_mut().find(|(used, _)| used == name) {
+                            Some((_, uses)) => *uses += 1,
+                            None => overloaded_uses.push((name.clone(), 1)),
+                        },
+                        None => {}
+                    }
+                }
+            });
+
+            let mut resolved: HashMap<&str, usize> = HashMap::new();
+            for (name, chosen) in type_checker.overload_choices(function) {
+                *resolved.entry(name).or_default() += 1;
+                match chosen {
+                    Some(chosen) => add(&mut callees, &symbol_name(chosen, nodes)),
+                    None => defined[name].iter().for_each(|symbol| add(&mut callees, symbol)),
+                }
+            }
+            // A use the type checker did not reach, such as a local variable named like the
+            // functions, may go to any of them.
+            for (name, uses) in overloaded_uses {
+                if resolved.get(name.as_str()).copied().unwrap_or(0) < uses {
+                    defined[name.as_str()].iter().for_each(|symbol| add(&mut callees, symbol));
+                }
+            }
+
+            calls.insert(symbol_name(function, nodes), callees);
+        }
+
+        CallGraph { calls }
+    }
+
+    /// The graph with the functions `renamed` maps under their new names, as when pruning the
+    /// other overloads of a name leaves one function to take the plain name.
+    pub fn renamed(self, renamed: &HashMap<String, String>) -> Self {
+        let rename = |symbol: String| renamed.get(&symbol).cloned().unwrap_or(symbol);
+        let calls = self
+            .calls
+            .into_iter()
+            .map(|(caller, callees)| (rename(caller), callees.into_iter().map(rename).collect()))
+            .collect();
+        CallGraph { calls }
+    }
+
+    /// Every function transitively called from `root`, including `root` itself.
+    pub fn reachable_from(&self, root: &str) -> HashSet<String> {
+        let mut reachable = HashSet::new();
+        let mut pending = vec![root.to_string()];
+
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+            if let Some(callees) = self.calls.get(&name) {
+                pending.extend(callees.iter().filter(|callee| !reachable.contains(*callee)).cloned());
+            }
+        }
+
+        reachable
+    }
+
+    /// The shortest chain of calls that leads from `name` back to itself, starting and ending
+    /// with `name`: `[f, f]` for a direct call, `[f, g, f]` when `f` calls `g` and `g` calls `f`.
+    pub fn cycle_through(&self, name: &str) -> Option<Vec<String>> {
+        let mut parents: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([name]);
+
+        while let Some(caller) = queue.pop_front() {
+            for callee in self.calls.get(caller).into_iter().flatten() {
+                if callee == name {
+                    let mut cycle = vec![name.to_string(), caller.to_string()];
+                    let mut current = caller;
+                    while current != name {
+                        current = parents[current];
+                        cycle.push(current.to_string());
+                    }
+                    cycle.reverse();
+                    return Some(cycle);
+                }
+                if !parents.contains_key(callee.as_str()) {
+                    parents.insert(callee, caller);
+                    queue.push_back(callee);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The graph in DOT: an edge from each function to every function it calls. Functions not
+    /// reachable from any of `roots` are dashed.
+    pub fn to_dot(&self, roots: &[String]) -> String {
+        let reachable: HashSet<String> = roots.iter().flat_map(|root| self.reachable_from(root)).collect();
+        let mut callers: Vec<(&String, &Vec<String>)> = self.calls.iter().collect();
+        callers.sort();
+
+        let mut dot = String::from("digraph calls {\n");
+        for (caller, _) in &callers {
+            let style = if reachable.contains(*caller) { "" } else { " [style=dashed]" };
+            dot.push_str(&format!("  \"{}\"{};\n", caller, style));
+        }
+        for (caller, callees) in &callers {
+            for callee in *callees {
+                dot.push_str(&format!("  \"{}\" -> \"{}\";\n", caller, callee));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Whether `name` can end up calling itself, directly or through other functions.
+    pub fn is_recursive(&self, name: &str) -> bool {
+        self.calls
+            .get(name)
+            .is_some_and(|callees| callees.iter().any(|callee| self.reachable_from(callee).contains(name)))
+    }
+}
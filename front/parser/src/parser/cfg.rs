@@ -0,0 +1,615 @@
+use std::collections::HashSet;
+use crate::ast::*;
+use crate::const_eval::condition_value;
+use crate::validate::node_span;
+
+pub type BlockId = usize;
+
+/// One step of a basic block: a statement, or an expression of a compound statement evaluated
+/// on its own (a branch condition, a `for` clause) together with that statement.
+#[derive(Debug, Clone, Copy)]
+pub enum CfgItem<'a> {
+    Node(&'a ASTNode),
+    Expression(&'a Expression, &'a ASTNode),
+}
+
+impl<'a> CfgItem<'a> {
+    /// The statement this item belongs to.
+    pub fn node(&self) -> &'a ASTNode {
+        match self {
+            CfgItem::Node(node) | CfgItem::Expression(_, node) => node,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BasicBlock<'a> {
+    pub items: Vec<CfgItem<'a>>,
+    pub successors: Vec<BlockId>,
+}
+
+/// A `while`, `for` or `loop`: control enters through `header` and leaves to `after`, or from
+/// one of the `exits` to an enclosing loop named by a `break` or `continue` label.
+#[derive(Debug, Clone)]
+pub struct LoopInfo<'a> {
+    pub node: &'a ASTNode,
+    pub header: BlockId,
+    pub after: BlockId,
+    pub exits: Vec<BlockId>,
+}
+
+/// The control-flow graph of one function body. Block `ENTRY` starts the body and `EXIT` is
+/// reached by every `return`; `fallthrough` is where control ends up after the last statement.
+/// Code after a `return`, `break` or `continue` lands in blocks without predecessors.
+///
+/// Conditions that fold to a constant only get the edge they take. The branches left out start
+/// at the blocks in `pruned`, and the conditions are listed in `constant_conditions`.
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub fallthrough: BlockId,
+    pub loops: Vec<LoopInfo<'a>>,
+    pub constant_conditions: Vec<(&'a ASTNode, bool)>,
+    pub pruned: Vec<BlockId>,
+}
+
+impl<'a> Cfg<'a> {
+    pub const ENTRY: BlockId = 0;
+    pub const EXIT: BlockId = 1;
+
+    pub fn build(function: &'a FunctionNode) -> Self {
+        let mut builder = CfgBuilder {
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            loops: vec![],
+            loop_info: vec![],
+            constant_conditions: vec![],
+            pruned: vec![],
+        };
+        let fallthrough = builder.lower_body(&function.body, Cfg::ENTRY);
+        Cfg {
+            blocks: builder.blocks,
+            fallthrough,
+            loops: builder.loop_info,
+            constant_conditions: builder.constant_conditions,
+            pruned: builder.pruned,
+        }
+    }
+
+    /// Blocks reachable from the entry.
+    pub fn reachable(&self) -> HashSet<BlockId> {
+        let mut reachable = HashSet::new();
+        let mut pending = vec![Cfg::ENTRY];
+        while let Some(block) = pending.pop() {
+            if reachable.insert(block) {
+                pending.extend(&self.blocks[block].successors);
+            }
+        }
+        reachable
+    }
+
+    /// Whether control can reach the end of the body without a `return`.
+    pub fn falls_through(&self) -> bool {
+        self.reachable().contains(&self.fallthrough)
+    }
+
+    /// The predecessors of every block, indexed by block.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![vec![]; self.blocks.len()];
+        for (block, data) in self.blocks.iter().enumerate() {
+            for &successor in &data.successors {
+                predecessors[successor].push(block);
+            }
+        }
+        predecessors
+    }
+
+    /// The graph as a DOT `subgraph`, so one `digraph` can hold every function: a box per block
+    /// listing the lines of its statements, quoted from `source`. Dashed blocks are ones no path
+    /// from the entry reaches; empty ones, left behind by a `return` or `break`, are left out.
+    pub fn to_dot(&self, function: &str, source: &str) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let reachable = self.reachable();
+        let shown = |id: BlockId| id == Cfg::EXIT || reachable.contains(&id) || !self.blocks[id].items.is_empty();
+        let mut dot = format!("  subgraph \"cluster_{0}\" {{\n    label=\"{0}\";\n", function);
+
+        for (id, block) in self.blocks.iter().enumerate().filter(|&(id, _)| shown(id)) {
+            let mut label = match id {
+                Cfg::ENTRY => "entry\\l".to_string(),
+                Cfg::EXIT => "exit\\l".to_string(),
+                _ => format!("b{}\\l", id),
+            };
+            let mut last_line = None;
+            for item in &block.items {
+                let Some(span) = node_span(item.node()) else {
+                    continue;
+                };
+                if last_line.replace(span.line) != Some(span.line) {
+                    let text = lines.get(span.line - 1).map_or("", |line| line.trim());
+                    label.push_str(&format!("{}: {}\\l", span.line, dot_escape(text)));
+                }
+            }
+            let style = if reachable.contains(&id) { "" } else { ", style=dashed" };
+            dot.push_str(&format!("    \"{}.{}\" [shape=box, label=\"{}\"{}];\n", function, id, label, style));
+        }
+        for (id, block) in self.blocks.iter().enumerate().filter(|&(id, _)| shown(id)) {
+            for successor in &block.successors {
+                dot.push_str(&format!("    \"{0}.{1}\" -> \"{0}.{2}\";\n", function, id, successor));
+            }
+        }
+
+        dot.push_str("  }\n");
+        dot
+    }
+
+    /// The first statement of each stretch of code no path from the entry reaches. Branches
+    /// pruned by a constant condition are left out; that condition is reported instead.
+    pub fn unreachable_statements(&self) -> Vec<&'a ASTNode> {
+        let reachable = self.reachable();
+        let mut covered = HashSet::new();
+        for &block in &self.pruned {
+            self.cover_dead(block, &reachable, &mut covered);
+        }
+        let mut statements = vec![];
+
+        // Blocks are created in source order, so the first of a dead stretch comes first.
+        for block in 0..self.blocks.len() {
+            if reachable.contains(&block) || covered.contains(&block) {
+                continue;
+            }
+            let Some(item) = self.blocks[block].items.first() else {
+                continue;
+            };
+            statements.push(item.node());
+            self.cover_dead(block, &reachable, &mut covered);
+        }
+
+        statements
+    }
+
+    /// Loops control can enter but never leave, neither through their condition nor a `break`.
+    pub fn endless_loops(&self) -> Vec<&'a ASTNode> {
+        let reachable = self.reachable();
+        self.loops
+            .iter()
+            .filter(|info| reachable.contains(&info.header) && !reachable.contains(&info.after))
+            .filter(|info| !info.exits.iter().any(|exit| reachable.contains(exit)))
+            .map(|info| info.node)
+            .collect()
+    }
+
+    // Marks `block` and the dead blocks after it as already accounted for.
+    fn cover_dead(&self, block: BlockId, reachable: &HashSet<BlockId>, covered: &mut HashSet<BlockId>) {
+        let mut pending = vec![block];
+        while let Some(dead) = pending.pop() {
+            if !reachable.contains(&dead) && covered.insert(dead) {
+                pending.extend(&self.blocks[dead].successors);
+            }
+        }
+    }
+
+    /// Variables declared without an initializer that some path reads before assigning,
+    /// with the first statement reading each.
+    pub fn uninitialized_reads(&self) -> Vec<(&'a ASTNode, String)> {
+        let reachable = self.reachable();
+        let predecessors = self.predecessors();
+
+        // Forward may-analysis: the variables possibly still uninitialized on entry to each block.
+        let mut entry_state: Vec<HashSet<String>> = vec![HashSet::new(); self.blocks.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in 0..self.blocks.len() {
+                if !reachable.contains(&block) {
+                    continue;
+                }
+                let mut state: HashSet<String> = predecessors[block]
+                    .iter()
+                    .filter(|predecessor| reachable.contains(predecessor))
+                    .flat_map(|&predecessor| self.exit_state(predecessor, &entry_state[predecessor]))
+                    .collect();
+                state.extend(entry_state[block].iter().cloned());
+                if state.len() != entry_state[block].len() {
+                    entry_state[block] = state;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut reported = HashSet::new();
+        let mut reads = vec![];
+        for (block, entry) in entry_state.iter().enumerate() {
+            if !reachable.contains(&block) {
+                continue;
+            }
+            let mut state = entry.clone();
+            for item in &self.blocks[block].items {
+                let mut read = vec![];
+                transfer(item, &mut state, &mut read);
+                for name in read {
+                    if reported.insert(name.clone()) {
+                        reads.push((item.node(), name));
+                    }
+                }
+            }
+        }
+        reads
+    }
+
+    fn exit_state(&self, block: BlockId, entry: &HashSet<String>) -> HashSet<String> {
+        let mut state = entry.clone();
+        for item in &self.blocks[block].items {
+            transfer(item, &mut state, &mut vec![]);
+        }
+        state
+    }
+}
+
+// Applies `item` to the set of possibly uninitialized variables, collecting the ones it reads.
+fn transfer(item: &CfgItem, uninit: &mut HashSet<String>, read: &mut Vec<String>) {
+    let node = match item {
+        CfgItem::Expression(expr, _) => return visit_expression(expr, uninit, read),
+        CfgItem::Node(node) => node,
+    };
+
+    match node {
+        ASTNode::Variable(variable) => {
+            if let Some(value) = &variable.initial_value {
+                visit_expression(value, uninit, read);
+                uninit.remove(&variable.name);
+            } else {
+                uninit.insert(variable.name.clone());
+            }
+        }
+        ASTNode::Expression(expr) => visit_expression(expr, uninit, read),
+        ASTNode::Statement(statement, _) => match statement {
+            StatementNode::Assign { variable, value } => {
+                visit_expression(value, uninit, read);
+                uninit.remove(variable);
+            }
+            StatementNode::PrintFormat { args, .. } | StatementNode::PrintlnFormat { args, .. } => {
+                for arg in args {
+                    visit_expression(arg, uninit, read);
+                }
+            }
+            StatementNode::Return(Some(value)) | StatementNode::Break { value: Some(value), .. } | StatementNode::Expression(value) => {
+                visit_expression(value, uninit, read)
+            }
+            StatementNode::AsmBlock { inputs, outputs, .. } => visit_asm(inputs, outputs, uninit, read),
+            StatementNode::ForRange { variable, .. } => {
+                uninit.remove(variable);
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+fn visit_expression(expr: &Expression, uninit: &mut HashSet<String>, read: &mut Vec<String>) {
+    match expr {
+        Expression::Variable(name) => {
+            if uninit.contains(name) {
+                read.push(name.clone());
+            }
+        }
+        Expression::AssignOperation { target, operator, value } => {
+            visit_expression(value, uninit, read);
+            if !matches!(operator, AssignOperator::Assign) {
+                visit_expression(target, uninit, read);
+            }
+            visit_assign_target(target, uninit, read);
+        }
+        Expression::Assignment { target, value } => {
+            visit_expression(value, uninit, read);
+            visit_assign_target(target, uninit, read);
+        }
+        // Taking the address hands the variable to code that may initialize it.
+        Expression::AddressOf(inner) => match &**inner {
+            Expression::Variable(name) => {
+                uninit.remove(name);
+            }
+            other => visit_expression(other, uninit, read),
+        },
+        Expression::FunctionCall { args, .. } | Expression::EnumVariant { args, .. } | Expression::ArrayLiteral(args) => {
+            for arg in args {
+                visit_expression(arg, uninit, read);
+            }
+        }
+        Expression::Deref(inner)
+        | Expression::Grouped(inner)
+        | Expression::FieldAccess { target: inner, .. }
+        | Expression::Cast { value: inner, .. } => visit_expression(inner, uninit, read),
+        Expression::Alloc { count: Some(count), .. } => visit_expression(count, uninit, read),
+        Expression::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                visit_expression(value, uninit, read);
+            }
+        }
+        Expression::BinaryExpression { left, right, .. } => {
+            visit_expression(left, uninit, read);
+            visit_expression(right, uninit, read);
+        }
+        Expression::IndexAccess { target, index } => {
+            visit_expression(target, uninit, read);
+            visit_expression(index, uninit, read);
+        }
+        Expression::AsmBlock { inputs, outputs, .. } => visit_asm(inputs, outputs, uninit, read),
+        // The body may leave at any `break`, so what it initializes is not counted afterwards.
+        Expression::While { condition, body, .. } => {
+            visit_expression(condition, uninit, read);
+            let mut inner = uninit.clone();
+            for node in body {
+                transfer(&CfgItem::Node(node), &mut inner, read);
+            }
+        }
+        Expression::Loop { body, .. } => {
+            let mut inner = uninit.clone();
+            for node in body {
+                transfer(&CfgItem::Node(node), &mut inner, read);
+            }
+        }
+        Expression::Literal(_) | Expression::Alloc { count: None, .. } => {}
+    }
+}
+
+// A write to `target`: a variable, an element or field of one, or through a pointer.
+fn visit_assign_target(target: &Expression, uninit: &mut HashSet<String>, read: &mut Vec<String>) {
+    match target {
+        Expression::Variable(name) => {
+            uninit.remove(name);
+        }
+        Expression::IndexAccess { target, index } => {
+            visit_expression(index, uninit, read);
+            match &**target {
+                Expression::Variable(name) => {
+                    uninit.remove(name);
+                }
+                other => visit_expression(other, uninit, read),
+            }
+        }
+        Expression::FieldAccess { target, .. } => visit_assign_target(target, uninit, read),
+        Expression::Deref(pointer) => visit_expression(pointer, uninit, read),
+        other => visit_expression(other, uninit, read),
+    }
+}
+
+fn visit_asm(inputs: &[(String, String)], outputs: &[(String, String)], uninit: &mut HashSet<String>, read: &mut Vec<String>) {
+    for (_, name) in inputs {
+        if uninit.contains(name) {
+            read.push(name.clone());
+        }
+    }
+    for (_, name) in outputs {
+        uninit.remove(name);
+    }
+}
+
+struct CfgBuilder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    loops: Vec<EnclosingLoop<'a>>,
+    loop_info: Vec<LoopInfo<'a>>,
+    constant_conditions: Vec<(&'a ASTNode, bool)>,
+    pruned: Vec<BlockId>,
+}
+
+// A loop whose body is being lowered, with the `LoopInfo` it was recorded as.
+struct EnclosingLoop<'a> {
+    continue_target: BlockId,
+    break_target: BlockId,
+    label: Option<&'a str>,
+    info: usize,
+}
+
+impl<'a> CfgBuilder<'a> {
+    // The loop last recorded in `loop_info`, entered for lowering its body.
+    fn enclosing_loop(&self, continue_target: BlockId, break_target: BlockId, label: &'a Option<String>) -> EnclosingLoop<'a> {
+        EnclosingLoop { continue_target, break_target, label: label.as_deref(), info: self.loop_info.len() - 1 }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId) {
+        self.blocks[from].successors.push(to);
+    }
+
+    // Branches from `from` on `condition` of `node`, dropping the edge a constant never takes.
+    fn branch(&mut self, node: &'a ASTNode, condition: &Expression, from: BlockId, taken: BlockId, not_taken: BlockId) {
+        let value = condition_value(condition);
+        if let Some(value) = value {
+            self.constant_conditions.push((node, value));
+        }
+        for (target, followed) in [(taken, value != Some(false)), (not_taken, value != Some(true))] {
+            if followed {
+                self.edge(from, target);
+            } else {
+                self.pruned.push(target);
+            }
+        }
+    }
+
+    // Lowers `body` starting in `current` and returns the block control falls out of.
+    fn lower_body(&mut self, body: &'a [ASTNode], mut current: BlockId) -> BlockId {
+        for node in body {
+            current = self.lower_node(node, current);
+        }
+        current
+    }
+
+    fn lower_node(&mut self, node: &'a ASTNode, current: BlockId) -> BlockId {
+        let ASTNode::Statement(statement, _) = node else {
+            self.blocks[current].items.push(CfgItem::Node(node));
+            return current;
+        };
+
+        match statement {
+            StatementNode::If { condition, body, else_if_blocks, else_block } => {
+                let else_ifs = else_if_blocks.as_deref().map(Vec::as_slice).unwrap_or(&[]);
+                self.lower_if(node, condition, body, else_ifs, else_block.as_deref(), current)
+            }
+            StatementNode::While { condition, body, label } => {
+                let header = self.new_block();
+                let after = self.new_block();
+                self.edge(current, header);
+                self.blocks[header].items.push(CfgItem::Expression(condition, node));
+
+                let body_entry = self.new_block();
+                self.branch(node, condition, header, body_entry, after);
+                self.loop_info.push(LoopInfo { node, header, after, exits: vec![] });
+
+                self.loops.push(self.enclosing_loop(header, after, label));
+                let body_end = self.lower_body(body, body_entry);
+                self.loops.pop();
+                self.edge(body_end, header);
+                after
+            }
+            // Without a condition, only a `break` reaches the block after the loop.
+            StatementNode::Loop { body, label } => {
+                let header = self.new_block();
+                let after = self.new_block();
+                self.edge(current, header);
+                self.loop_info.push(LoopInfo { node, header, after, exits: vec![] });
+
+                self.loops.push(self.enclosing_loop(header, after, label));
+                let body_end = self.lower_body(body, header);
+                self.loops.pop();
+                self.edge(body_end, header);
+                after
+            }
+            StatementNode::For { initialization, condition, increment, body, label } => {
+                self.blocks[current].items.push(CfgItem::Expression(initialization, node));
+                let header = self.new_block();
+                let latch = self.new_block();
+                let after = self.new_block();
+                self.edge(current, header);
+                self.blocks[header].items.push(CfgItem::Expression(condition, node));
+                self.blocks[latch].items.push(CfgItem::Expression(increment, node));
+                self.edge(latch, header);
+
+                let body_entry = self.new_block();
+                self.branch(node, condition, header, body_entry, after);
+                self.loop_info.push(LoopInfo { node, header, after, exits: vec![] });
+
+                self.loops.push(self.enclosing_loop(latch, after, label));
+                let body_end = self.lower_body(body, body_entry);
+                self.loops.pop();
+                self.edge(body_end, latch);
+                after
+            }
+            // The bounds are evaluated once; the header assigns the variable and tests it.
+            StatementNode::ForRange { start, end, body, label, .. } => {
+                self.blocks[current].items.push(CfgItem::Expression(start, node));
+                self.blocks[current].items.push(CfgItem::Expression(end, node));
+                let header = self.new_block();
+                let after = self.new_block();
+                self.edge(current, header);
+                self.blocks[header].items.push(CfgItem::Node(node));
+
+                let body_entry = self.new_block();
+                self.edge(header, body_entry);
+                self.edge(header, after);
+                self.loop_info.push(LoopInfo { node, header, after, exits: vec![] });
+
+                self.loops.push(self.enclosing_loop(header, after, label));
+                let body_end = self.lower_body(body, body_entry);
+                self.loops.pop();
+                self.edge(body_end, header);
+                after
+            }
+            StatementNode::Match { value, arms } => {
+                self.blocks[current].items.push(CfgItem::Expression(value, node));
+                let join = self.new_block();
+                for arm in arms {
+                    let arm_entry = self.new_block();
+                    self.edge(current, arm_entry);
+                    let arm_end = self.lower_body(&arm.body, arm_entry);
+                    self.edge(arm_end, join);
+                }
+                // An integer no arm lists skips the match. An enum match lists every variant,
+                // which type checking enforces, unless it has a `_` arm.
+                if !arms.iter().any(|arm| matches!(arm.pattern, Pattern::Wildcard | Pattern::Variant { .. })) {
+                    self.edge(current, join);
+                }
+                join
+            }
+            StatementNode::Return(_) => {
+                self.blocks[current].items.push(CfgItem::Node(node));
+                self.edge(current, Cfg::EXIT);
+                self.new_block()
+            }
+            StatementNode::Break { label, .. } | StatementNode::Continue { label } => {
+                self.blocks[current].items.push(CfgItem::Node(node));
+                // A label names an enclosing loop; type checking reports one that does not.
+                let target = match label {
+                    Some(label) => self.loops.iter().rposition(|enclosing| enclosing.label == Some(label.as_str())),
+                    None => self.loops.len().checked_sub(1),
+                };
+                if let Some(index) = target {
+                    let enclosing = &self.loops[index];
+                    let block = if matches!(statement, StatementNode::Break { .. }) { enclosing.break_target } else { enclosing.continue_target };
+                    self.edge(current, block);
+                    // Jumping to an outer loop leaves the ones in between as well.
+                    for inner in &self.loops[index + 1..] {
+                        self.loop_info[inner.info].exits.push(current);
+                    }
+                }
+                self.new_block()
+            }
+            _ => {
+                self.blocks[current].items.push(CfgItem::Node(node));
+                current
+            }
+        }
+    }
+
+    // `if` with its chain of `else if`s: each `else if` is the else branch of the one before.
+    fn lower_if(
+        &mut self,
+        node: &'a ASTNode,
+        condition: &'a Expression,
+        body: &'a [ASTNode],
+        else_ifs: &'a [ASTNode],
+        else_block: Option<&'a Vec<ASTNode>>,
+        current: BlockId,
+    ) -> BlockId {
+        self.blocks[current].items.push(CfgItem::Expression(condition, node));
+        let join = self.new_block();
+
+        let then_entry = self.new_block();
+        let else_entry = self.new_block();
+        self.branch(node, condition, current, then_entry, else_entry);
+
+        let then_end = self.lower_body(body, then_entry);
+        self.edge(then_end, join);
+
+        let else_end = match else_ifs.split_first() {
+            // `parse_if` hangs a trailing `else` on the innermost `else if`, so prefer its own chain
+            Some((
+                else_if @ ASTNode::Statement(StatementNode::If { condition, body, else_if_blocks, else_block: own_else }, _),
+                rest,
+            )) => {
+                let (else_ifs, else_block) = match (else_if_blocks, own_else) {
+                    (None, None) => (rest, else_block),
+                    (own_ifs, own_else) => (own_ifs.as_deref().map_or(&[][..], |v| &v[..]), own_else.as_deref()),
+                };
+                self.lower_if(else_if, condition, body, else_ifs, else_block, else_entry)
+            }
+            Some((other, rest)) => {
+                let next = self.lower_node(other, else_entry);
+                self.lower_body(rest, next)
+            }
+            None => match else_block {
+                Some(else_body) => self.lower_body(else_body, else_entry),
+                None => else_entry,
+            },
+        };
+        self.edge(else_end, join);
+        join
+    }
+}
+
+// `text` inside a double-quoted DOT string.
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
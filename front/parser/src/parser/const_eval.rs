@@ -0,0 +1,130 @@
+use crate::ast::*;
+use crate::visit::walk_expressions_mut;
+
+/// The value of an expression that can be computed without running the program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl ConstValue {
+    /// How the value acts as a condition: integers and floats are true unless zero.
+    pub fn is_truthy(self) -> bool {
+        match self {
+            ConstValue::Int(value) => value != 0,
+            ConstValue::Float(value) => value != 0.0,
+            ConstValue::Bool(value) => value,
+        }
+    }
+
+    fn as_float(self) -> Option<f64> {
+        match self {
+            ConstValue::Int(value) => Some(value as f64),
+            ConstValue::Float(value) => Some(value),
+            ConstValue::Bool(_) => None,
+        }
+    }
+}
+
+/// Folds `expr` built from literals and operators. Anything that reads a variable, calls a
+/// function or would overflow or divide by zero at run time is left unevaluated; type checking
+/// reports an integer division by a constant zero.
+pub fn evaluate(expr: &Expression) -> Option<ConstValue> {
+    match expr {
+        Expression::Literal(Literal::Number(value)) => Some(ConstValue::Int(*value)),
+        Expression::Literal(Literal::Float(value)) => Some(ConstValue::Float(*value)),
+        Expression::Literal(Literal::Bool(value)) => Some(ConstValue::Bool(*value)),
+        Expression::Grouped(inner) => evaluate(inner),
+        Expression::BinaryExpression { left, operator, right } => {
+            let left = evaluate(left)?;
+            // `&&` and `||` short-circuit, so a constant left side decides them alone.
+            match (operator, left.is_truthy()) {
+                (Operator::LogicalAnd, false) => return Some(ConstValue::Bool(false)),
+                (Operator::LogicalOr, true) => return Some(ConstValue::Bool(true)),
+                _ => {}
+            }
+            binary(left, operator, evaluate(right)?)
+        }
+        _ => None,
+    }
+}
+
+/// Replaces every operator expression of `nodes` that `evaluate` can compute with a literal of
+/// its value, so `2 + 3 * 4` reaches code generation as `14`. Folding is done in 64 bits, so a
+/// result its context's type cannot hold is reported like a literal that big.
+pub fn fold_constants(nodes: &mut [ASTNode]) {
+    walk_expressions_mut(nodes, &mut |expr| {
+        if !matches!(expr, Expression::BinaryExpression { .. } | Expression::Grouped(_)) {
+            return;
+        }
+        if let Some(value) = evaluate(expr) {
+            *expr = Expression::Literal(match value {
+                ConstValue::Int(value) => Literal::Number(value),
+                ConstValue::Float(value) => Literal::Float(value),
+                ConstValue::Bool(value) => Literal::Bool(value),
+            });
+        }
+    });
+}
+
+/// The value of a branch or loop condition, when it is the same on every evaluation.
+pub fn condition_value(condition: &Expression) -> Option<bool> {
+    evaluate(condition).map(ConstValue::is_truthy)
+}
+
+fn binary(left: ConstValue, operator: &Operator, right: ConstValue) -> Option<ConstValue> {
+    use ConstValue::*;
+
+    if let (Int(l), Int(r)) = (left, right) {
+        let value = match operator {
+            Operator::Add => Int(l.checked_add(r)?),
+            Operator::Subtract => Int(l.checked_sub(r)?),
+            Operator::Multiply => Int(l.checked_mul(r)?),
+            Operator::Divide => Int(l.checked_div(r)?),
+            Operator::Remainder => Int(l.checked_rem(r)?),
+            Operator::BitwiseAnd => Int(l & r),
+            Operator::BitwiseOr => Int(l | r),
+            Operator::Greater => Bool(l > r),
+            Operator::GreaterEqual => Bool(l >= r),
+            Operator::Less => Bool(l < r),
+            Operator::LessEqual => Bool(l <= r),
+            Operator::Equal => Bool(l == r),
+            Operator::NotEqual => Bool(l != r),
+            Operator::LogicalAnd | Operator::LogicalOr => Bool(right.is_truthy()),
+            Operator::Assign => return None,
+        };
+        return Some(value);
+    }
+
+    if let (Bool(l), Bool(r)) = (left, right) {
+        return match operator {
+            Operator::Equal => Some(Bool(l == r)),
+            Operator::NotEqual => Some(Bool(l != r)),
+            Operator::LogicalAnd | Operator::LogicalOr => Some(Bool(r)),
+            _ => None,
+        };
+    }
+
+    if matches!(operator, Operator::LogicalAnd | Operator::LogicalOr) {
+        return Some(Bool(right.is_truthy()));
+    }
+
+    let (l, r) = (left.as_float()?, right.as_float()?);
+    let value = match operator {
+        Operator::Add => Float(l + r),
+        Operator::Subtract => Float(l - r),
+        Operator::Multiply => Float(l * r),
+        Operator::Divide => Float(l / r),
+        Operator::Remainder => Float(l % r),
+        Operator::Greater => Bool(l > r),
+        Operator::GreaterEqual => Bool(l >= r),
+        Operator::Less => Bool(l < r),
+        Operator::LessEqual => Bool(l <= r),
+        Operator::Equal => Bool(l == r),
+        Operator::NotEqual => Bool(l != r),
+        _ => return None,
+    };
+    Some(value)
+}
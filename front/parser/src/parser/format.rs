@@ -1,27 +1,48 @@
 use std::iter::Peekable;
 use std::slice::Iter;
 use lexer::{Token, TokenType};
-use crate::ast::{Operator, Expression, FormatPart, Literal, AssignOperator};
+use crate::ast::{Operator, Expression, FormatPart, FormatSpec, Literal, AssignOperator, WaveType};
+use crate::parser::parser::{parse_error, parse_type, token_type_to_wave_type};
 
-pub fn parse_format_string(s: &str) -> Vec<FormatPart> {
+/// Splits the format string of `print`/`println` into text and placeholders, or says what is
+/// wrong with it: a `{` with no `}`, a lone `}`, or a spec it cannot read.
+pub fn parse_format_string(s: &str) -> Result<Vec<FormatPart>, String> {
     let mut parts = Vec::new();
     let mut buffer = String::new();
     let mut chars = s.chars().peekable();
 
     while let Some(c) = chars.next() {
-        if c == '{' {
-            if let Some('}') = chars.peek() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
                 chars.next();
+                buffer.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                buffer.push('}');
+            }
+            '{' => {
+                let mut inside = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inside.push(c),
+                        None => return Err(format!("unclosed '{{' in format string \"{}\"; write '{{{{' for a brace", s)),
+                    }
+                }
                 if !buffer.is_empty() {
-                    parts.push(FormatPart::Literal(buffer.clone()));
-                    buffer.clear();
+                    parts.push(FormatPart::Literal(std::mem::take(&mut buffer)));
                 }
-                parts.push(FormatPart::Placeholder);
-            } else {
-                buffer.push(c);
+                let (name, spec) = inside.split_once(':').unwrap_or((&inside, ""));
+                let name = name.trim();
+                if !name.is_empty() && !is_name(name) {
+                    return Err(format!("'{}' in '{{{}}}' is not a variable name", name, inside));
+                }
+                let spec = parse_format_spec(spec).ok_or_else(|| format!("unknown format spec ':{}' in '{{{}}}'", spec, inside))?;
+                parts.push(FormatPart::Placeholder((!name.is_empty()).then(|| name.to_string()), spec));
             }
-        } else {
-            buffer.push(c);
+            '}' => return Err(format!("unmatched '}}' in format string \"{}\"; write '}}}}' for a brace", s)),
+            c => buffer.push(c),
         }
     }
 
@@ -29,7 +50,50 @@ pub fn parse_format_string(s: &str) -> Vec<FormatPart> {
         parts.push(FormatPart::Literal(buffer));
     }
 
-    parts
+    Ok(parts)
+}
+
+// `[<|^|>][0][width][.precision][x|X|o|b]`, every part optional.
+fn parse_format_spec(spec: &str) -> Option<FormatSpec> {
+    let mut rest = spec;
+    let align = rest.chars().next().filter(|c| matches!(c, '<' | '^' | '>'));
+    if align.is_some() {
+        rest = &rest[1..];
+    }
+    let zero_pad = rest.starts_with('0');
+    if zero_pad {
+        rest = &rest[1..];
+    }
+    let digits = |text: &str| text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+
+    let width_len = digits(rest);
+    let width = rest[..width_len].parse().ok();
+    rest = &rest[width_len..];
+
+    let mut precision = None;
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let precision_len = digits(after_dot);
+        precision = Some(after_dot[..precision_len].parse().ok()?);
+        rest = &after_dot[precision_len..];
+    }
+
+    let mut radix = None;
+    let mut rest = rest.chars();
+    if let Some(c) = rest.next() {
+        if !matches!(c, 'x' | 'X' | 'o' | 'b') {
+            return None;
+        }
+        radix = Some(c);
+    }
+    if rest.next().is_some() || (zero_pad && width.is_none()) {
+        return None;
+    }
+    Some(FormatSpec { align, zero_pad, width, precision, radix })
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
 }
 
 pub fn parse_expression<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
@@ -181,7 +245,7 @@ pub fn parse_multiplicative_expression<'a, T>(tokens: &mut Peekable<T>) -> Optio
 where
     T: Iterator<Item = &'a Token>,
 {
-    let mut left = parse_primary_expression(tokens)?;
+    let mut left = parse_cast_expression(tokens)?;
 
     while let Some(token) = tokens.peek() {
         match token.token_type {
@@ -194,7 +258,7 @@ where
                 };
                 tokens.next();
 
-                let right = parse_primary_expression(tokens)?;
+                let right = parse_cast_expression(tokens)?;
                 left = Expression::BinaryExpression {
                     left: Box::new(left),
                     operator: op,
@@ -207,6 +271,35 @@ where
     Some(left)
 }
 
+// `value as T`, binding tighter than `*`: `a * b as i64` converts `b` alone.
+pub fn parse_cast_expression<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    let mut value = parse_primary_expression(tokens)?;
+
+    while tokens.peek().is_some_and(|token| token.token_type == TokenType::As) {
+        tokens.next(); // consume 'as'
+        let Some(type_token) = tokens.next() else {
+            parse_error!("Error: Expected a type after 'as'");
+            return None;
+        };
+        let type_str = match &type_token.token_type {
+            TokenType::Identifier(name) if tokens.peek().is_some_and(|token| token.token_type == TokenType::Lchevr) => {
+                tokens.next(); // consume '<'
+                format!("{}<{}>", name, type_argument_text(tokens))
+            }
+            _ => type_token.lexeme.clone(),
+        };
+        let Some(ty) = parse_type(&type_str).and_then(|tt| token_type_to_wave_type(&tt)) else {
+            parse_error!("Error: Unknown type '{}' after 'as'", type_str);
+            return None;
+        };
+        value = Expression::Cast { value: Box::new(value), ty };
+    }
+    Some(value)
+}
+
 pub fn parse_primary_expression<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
 where
     T: Iterator<Item = &'a Token>,
@@ -222,11 +315,29 @@ where
             tokens.next();
             Some(Expression::Literal(Literal::Float(*value)))
         }
+        TokenType::Char(value) => {
+            tokens.next();
+            Some(Expression::Literal(Literal::Char(*value)))
+        }
+        TokenType::True | TokenType::False => {
+            tokens.next();
+            Some(Expression::Literal(Literal::Bool(token.token_type == TokenType::True)))
+        }
+        TokenType::Identifier(name) if name == "type_name" => {
+            tokens.next(); // consume 'type_name'
+            parse_type_name(tokens)
+        }
+        TokenType::Identifier(name) if name == "alloc" => {
+            tokens.next(); // consume 'alloc'
+            parse_alloc(tokens)
+        }
         TokenType::Identifier(name) => {
             let name = name.clone();
             tokens.next(); // consume identifier
 
-            let mut expr = if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
+            let mut expr = if let Some(Token { token_type: TokenType::ColonColon, .. }) = tokens.peek() {
+                parse_enum_variant(name, tokens)?
+            } else if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
                 tokens.next(); // consume '('
 
                 let mut args = vec![];
@@ -245,25 +356,44 @@ where
                 }
 
                 Expression::FunctionCall { name, args }
+            } else if let Some(Token { token_type: TokenType::Lbrace, .. }) = tokens.peek() {
+                parse_struct_literal(name, tokens)?
             } else {
                 Expression::Variable(name)
             };
 
-            while let Some(Token { token_type: TokenType::Lbrack, .. }) = tokens.peek() {
-                tokens.next(); // consume '['
+            loop {
+                match tokens.peek().map(|t| &t.token_type) {
+                    Some(TokenType::Lbrack) => {
+                        tokens.next(); // consume '['
 
-                let index_expr = parse_expression(tokens)?;
+                        let index_expr = parse_expression(tokens)?;
 
-                if tokens.peek()?.token_type != TokenType::Rbrack {
-                    println!("Error: Expected ']' after index");
-                    return None;
-                }
-                tokens.next(); // consume ']'
+                        if tokens.peek()?.token_type != TokenType::Rbrack {
+                            parse_error!("Error: Expected ']' after index");
+                            return None;
+                        }
+                        tokens.next(); // consume ']'
 
-                expr = Expression::IndexAccess {
-                    target: Box::new(expr),
-                    index: Box::new(index_expr),
-                };
+                        expr = Expression::IndexAccess {
+                            target: Box::new(expr),
+                            index: Box::new(index_expr),
+                        };
+                    }
+                    Some(TokenType::Dot) => {
+                        tokens.next(); // consume '.'
+
+                        let field = match tokens.next() {
+                            Some(Token { token_type: TokenType::Identifier(field), .. }) => field.clone(),
+                            _ => {
+                                parse_error!("Error: Expected a field name after '.'");
+                                return None;
+                            }
+                        };
+                        expr = Expression::FieldAccess { target: Box::new(expr), field };
+                    }
+                    _ => break,
+                }
             }
 
             Some(expr)
@@ -295,7 +425,7 @@ where
                     }
                     Some(TokenType::Rbrack) => continue,
                     _ => {
-                        println!("Error: Expected ',' or ']' in array literal");
+                        parse_error!("Error: Expected ',' or ']' in array literal");
                         return None;
                     }
                 }
@@ -306,7 +436,7 @@ where
         TokenType::Asm => {
             tokens.next(); // consume 'asm'
             if tokens.peek()?.token_type != TokenType::Lbrace {
-                println!("Expected '{{' after 'asm'");
+                parse_error!("Expected '{{' after 'asm'");
                 return None;
             }
             tokens.next(); // consume '{'
@@ -327,7 +457,7 @@ where
                         tokens.next();
 
                         if tokens.peek().map(|t| t.token_type.clone()) != Some(TokenType::Lparen) {
-                            println!("Expected '(' after in/out");
+                            parse_error!("Expected '(' after in/out");
                             return None;
                         }
                         tokens.next();
@@ -337,17 +467,17 @@ where
                             Some(Token { token_type: TokenType::String(s), .. }) => s.clone(),
                             Some(Token { token_type: TokenType::Identifier(s), .. }) => s.clone(),
                             Some(other) => {
-                                println!("Expected register string or identifier, got {:?}", other.token_type);
+                                parse_error!("Expected register string or identifier, got {:?}", other.token_type);
                                 return None;
                             }
                             None => {
-                                println!("Expected register in in/out(...)");
+                                parse_error!("Expected register in in/out(...)");
                                 return None;
                             }
                         };
 
                         if tokens.peek().map(|t| t.token_type.clone()) != Some(TokenType::Rparen) {
-                            println!("Expected ')' after in/out");
+                            parse_error!("Expected ')' after in/out");
                             return None;
                         }
                         tokens.next();
@@ -358,11 +488,11 @@ where
                             Some(Token { token_type: TokenType::Number(n), .. }) => n.to_string(),
                             Some(Token { token_type: TokenType::String(n), .. }) => n.to_string(),
                             Some(other) => {
-                                println!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
+                                parse_error!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
                                 return None;
                             }
                             None => {
-                                println!("Expected value after in/out(...)");
+                                parse_error!("Expected value after in/out(...)");
                                 return None;
                             }
                         };
@@ -380,7 +510,7 @@ where
                         tokens.next();
 
                         if tokens.peek().map(|t| t.token_type.clone()) != Some(TokenType::Lparen) {
-                            println!("Expected '(' after in/out");
+                            parse_error!("Expected '(' after in/out");
                             return None;
                         }
                         tokens.next();
@@ -390,17 +520,17 @@ where
                             Some(Token { token_type: TokenType::String(s), .. })    => s.clone(),
                             Some(Token { token_type: TokenType::Identifier(s), .. })=> s.clone(),
                             Some(other) => {
-                                println!("Expected register string or identifier, got {:?}", other.token_type);
+                                parse_error!("Expected register string or identifier, got {:?}", other.token_type);
                                 return None;
                             }
                             None => {
-                                println!("Expected register in in/out(...)");
+                                parse_error!("Expected register in in/out(...)");
                                 return None;
                             }
                         };
 
                         if tokens.peek().map(|t| t.token_type.clone()) != Some(TokenType::Rparen) {
-                            println!("Expected ')' after in/out(...)");
+                            parse_error!("Expected ')' after in/out(...)");
                             return None;
                         }
                         tokens.next();
@@ -410,11 +540,11 @@ where
                             Some(Token { token_type: TokenType::Identifier(s), .. }) => s.clone(),
                             Some(Token { token_type: TokenType::Number(n), .. })     => n.to_string(),
                             Some(other) => {
-                                println!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
+                                parse_error!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
                                 return None;
                             }
                             None => {
-                                println!("Expected value after in/out(...)");
+                                parse_error!("Expected value after in/out(...)");
                                 return None;
                             }
                         };
@@ -432,7 +562,7 @@ where
                     }
 
                     other => {
-                        println!("Unexpected token in asm expression: {:?}", other);
+                        parse_error!("Unexpected token in asm expression: {:?}", other);
                         tokens.next();
                     }
                 }
@@ -446,20 +576,186 @@ where
         }
         _ => {
             if let TokenType::SemiColon = token.token_type { } else {
-                println!("Error: Expected primary expression, found {:?}", token.token_type);
+                parse_error!("Error: Expected primary expression, found {:?}", token.token_type);
+            }
+            None
+        }
+    }
+}
+
+// type_name<T>() folds to a string literal holding the canonical spelling of T
+// Name { field: value, ... }, after the struct name
+fn parse_struct_literal<'a, T>(name: String, tokens: &mut Peekable<T>) -> Option<Expression>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    tokens.next(); // consume '{'
+
+    let mut fields = vec![];
+    loop {
+        let field = match tokens.next().map(|t| &t.token_type) {
+            Some(TokenType::Rbrace) => break,
+            Some(TokenType::Identifier(field)) => field.clone(),
+            _ => {
+                parse_error!("Error: Expected a field name in '{}' literal", name);
+                return None;
+            }
+        };
+
+        if tokens.next()?.token_type != TokenType::Colon {
+            parse_error!("Error: Expected ':' after field '{}' in '{}' literal", field, name);
+            return None;
+        }
+        fields.push((field, parse_expression(tokens)?));
+
+        match tokens.peek().map(|t| &t.token_type) {
+            Some(TokenType::Comma) => {
+                tokens.next(); // consume ','
+            }
+            Some(TokenType::Rbrace) => continue,
+            _ => {
+                parse_error!("Error: Expected ',' or '}}' in '{}' literal", name);
+                return None;
+            }
+        }
+    }
+
+    Some(Expression::StructLiteral { name, fields })
+}
+
+// Name::Variant(value, ...), after the enum name
+fn parse_enum_variant<'a, T>(enum_name: String, tokens: &mut Peekable<T>) -> Option<Expression>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    tokens.next(); // consume '::'
+
+    let variant = match tokens.next().map(|t| &t.token_type) {
+        Some(TokenType::Identifier(variant)) => variant.clone(),
+        _ => {
+            parse_error!("Error: Expected a variant after '{}::'", enum_name);
+            return None;
+        }
+    };
+
+    let mut args = vec![];
+    if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
+        tokens.next(); // consume '('
+        loop {
+            if let Some(Token { token_type: TokenType::Rparen, .. }) = tokens.peek() {
+                tokens.next(); // consume ')'
+                break;
             }
+            args.push(parse_expression(tokens)?);
+            match tokens.peek().map(|t| &t.token_type) {
+                Some(TokenType::Comma) => {
+                    tokens.next(); // consume ','
+                }
+                Some(TokenType::Rparen) => continue,
+                _ => {
+                    parse_error!("Error: Expected ',' or ')' in '{}::{}'", enum_name, variant);
+                    return None;
+                }
+            }
+        }
+    }
+
+    Some(Expression::EnumVariant { enum_name, variant, args })
+}
+
+fn parse_type_name<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    let wave_type = parse_type_argument(tokens, "type_name")?;
+
+    if tokens.next()?.token_type != TokenType::Lparen || tokens.next()?.token_type != TokenType::Rparen {
+        parse_error!("Error: Expected '()' after type_name<{}>", wave_type);
+        return None;
+    }
+
+    Some(Expression::Literal(Literal::String(wave_type.to_string())))
+}
+
+// alloc<T>() or alloc<T>(count), a heap allocation lowered to `malloc`
+fn parse_alloc<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    let ty = parse_type_argument(tokens, "alloc")?;
+
+    if tokens.next()?.token_type != TokenType::Lparen {
+        parse_error!("Error: Expected '(' after alloc<{}>", ty);
+        return None;
+    }
+    let count = if let Some(Token { token_type: TokenType::Rparen, .. }) = tokens.peek() {
+        None
+    } else {
+        Some(Box::new(parse_expression(tokens)?))
+    };
+    if tokens.next()?.token_type != TokenType::Rparen {
+        parse_error!("Error: Expected ')' after the count of alloc<{}>", ty);
+        return None;
+    }
+
+    Some(Expression::Alloc { ty, count })
+}
+
+// The `<T>` after `keyword`
+fn parse_type_argument<'a, T>(tokens: &mut Peekable<T>, keyword: &str) -> Option<WaveType>
+where
+    T: Iterator<Item = &'a Token>,
+{
+    if tokens.peek()?.token_type != TokenType::Lchevr {
+        parse_error!("Error: Expected '<' after '{}'", keyword);
+        return None;
+    }
+    tokens.next(); // consume '<'
+
+    let type_str = type_argument_text(tokens);
+    match parse_type(&type_str).and_then(|tt| token_type_to_wave_type(&tt)) {
+        Some(wt) => Some(wt),
+        None => {
+            parse_error!("Error: Unknown type in {}<{}>", keyword, type_str);
             None
         }
     }
 }
 
+// The lexemes up to the `>` closing a `<` already consumed, joined, and the `>` consumed.
+fn type_argument_text<'a, T>(tokens: &mut Peekable<T>) -> String
+where
+    T: Iterator<Item = &'a Token>,
+{
+    let mut type_str = String::new();
+    let mut depth = 1;
+
+    for t in tokens.by_ref() {
+        match &t.token_type {
+            TokenType::Lchevr => {
+                depth += 1;
+                type_str.push('<');
+            }
+            TokenType::Rchevr => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                type_str.push('>');
+            }
+            _ => type_str.push_str(&t.lexeme),
+        }
+    }
+    type_str
+}
+
 pub fn parse_parenthesized_expression<'a, T>(tokens: &mut Peekable<T>) -> Option<Expression>
 where
     T: Iterator<Item = &'a Token>,
 {
     // Ensure the next token is '('
     if tokens.next()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '('");
+        parse_error!("Error: Expected '('");
         return None;
     }
 
@@ -468,7 +764,7 @@ where
 
     // Ensure the next token is ')'
     if tokens.next()?.token_type != TokenType::Rparen {
-        println!("Error: Expected ')'");
+        parse_error!("Error: Expected ')'");
         return None;
     }
 
@@ -1,8 +1,54 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use crate::ast::ASTNode;
+use crate::ast::{ASTNode, StatementNode};
 use crate::parse;
+use error::error::{WaveError, WaveErrorKind};
+use error::tr;
 use lexer::Lexer;
+use crate::validate::{diagnostic_at, lexical_errors};
+
+/// Every file the program in `main_file`, parsed as `ast`, imports, directly or through the
+/// imports of another file, with its nodes. A file comes once, after the file that first
+/// imports it, however many files import it and even when imports go in a cycle. `std::`
+/// modules come with the compiler and are left out.
+pub fn resolve_imports(main_file: &Path, ast: &[ASTNode], base_dir: &Path) -> Result<Vec<(PathBuf, Vec<ASTNode>)>, Box<WaveError>> {
+    let mut already_imported = HashSet::new();
+    let mut units = vec![];
+    import_all(main_file, ast, base_dir, &mut already_imported, &mut units)?;
+    Ok(units)
+}
+
+fn import_all(
+    importer: &Path,
+    ast: &[ASTNode],
+    base_dir: &Path,
+    already_imported: &mut HashSet<String>,
+    units: &mut Vec<(PathBuf, Vec<ASTNode>)>,
+) -> Result<(), Box<WaveError>> {
+    for node in ast {
+        let ASTNode::Statement(StatementNode::Import(path), span) = node else {
+            continue;
+        };
+        if path.starts_with("std::") || already_imported.contains(path) {
+            continue;
+        }
+        let unresolved = |message: String, label: String| {
+            Box::new(diagnostic_at(WaveErrorKind::UnresolvedImport(path.clone()), message, importer, *span).with_label(label))
+        };
+        let Some(found) = find_import_file(path, base_dir) else {
+            return Err(unresolved(
+                tr!("cannot find module `{0}`", path),
+                tr!("no `{0}.wave` in `{1}` or below", path, base_dir.display()),
+            ));
+        };
+        let Some(nodes) = local_import(path, already_imported, base_dir) else {
+            return Err(unresolved(tr!("module `{0}` has errors", path), tr!("`{0}` is imported here", found.display())));
+        };
+        units.push((found.clone(), nodes.clone()));
+        import_all(&found, &nodes, base_dir, already_imported, units)?;
+    }
+    Ok(())
+}
 
 pub fn local_import(path: &str, already_imported: &mut HashSet<String>, base_dir: &Path) -> Option<Vec<ASTNode>> {
     if already_imported.contains(path) {
@@ -11,18 +57,29 @@ pub fn local_import(path: &str, already_imported: &mut HashSet<String>, base_dir
 
     already_imported.insert(path.to_string());
 
-    let target_file_name = format!("{}.wave", path);
-
-    let found_path = find_wave_file_recursive(base_dir, &target_file_name)?;
+    let found_path = find_import_file(path, base_dir)?;
+    tracing::debug!(import = path, file = %found_path.display(), "importing");
 
     let content = std::fs::read_to_string(&found_path).ok()?;
     let mut lexer = Lexer::new(&content);
     let tokens = lexer.tokenize();
+
+    let errors = lexical_errors(&tokens, &found_path, &content);
+    if !errors.is_empty() {
+        errors.iter().for_each(WaveError::display);
+        return None;
+    }
+
     let ast = parse(&tokens)?;
 
     Some(ast)
 }
 
+pub fn find_import_file(path: &str, base_dir: &Path) -> Option<PathBuf> {
+    let target_file_name = format!("{}.wave", path);
+    find_wave_file_recursive(base_dir, &target_file_name)
+}
+
 fn find_wave_file_recursive(dir: &Path, target_file_name: &str) -> Option<PathBuf> {
     for entry in std::fs::read_dir(dir).ok()? {
         let entry = entry.ok()?;
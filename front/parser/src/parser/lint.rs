@@ -0,0 +1,149 @@
+use std::path::Path;
+use std::sync::Mutex;
+use error::error::{WaveError, WaveErrorKind};
+use error::tr;
+use crate::ast::*;
+use crate::visit::walk_expression;
+
+/// A lint that a crate outside the compiler adds to the checks every program goes through.
+/// Each hook sees one part of a function that gets compiled and reports what it finds
+/// through the `LintContext`; the diagnostics go out with the compiler's own.
+pub trait LintPass: Send {
+    /// The name `#[allow(..)]` takes to silence the lint, e.g. `long_function`, and that
+    /// `CodegenOptions::lint_levels` sets its level by.
+    fn name(&self) -> &'static str;
+
+    /// Called once per function, before any node of its body.
+    fn check_function(&self, _cx: &mut LintContext, _function: &FunctionNode) {}
+
+    /// Called for every statement and declaration of a body, nested ones included, in source order.
+    fn check_node(&self, _cx: &mut LintContext, _node: &ASTNode) {}
+
+    /// Called for every expression of a statement or declaration, parents before children.
+    fn check_expression(&self, _cx: &mut LintContext, _expr: &Expression) {}
+}
+
+/// Where a `LintPass` is in the program, and the diagnostics it reported.
+pub struct LintContext<'a> {
+    /// Every node of the program being compiled.
+    pub ast: &'a [ASTNode],
+    /// The function being checked.
+    pub function: &'a FunctionNode,
+    file: &'a Path,
+    source: Option<&'a str>,
+    lint: &'static str,
+    span: Span,
+    diagnostics: Vec<WaveError>,
+}
+
+impl LintContext<'_> {
+    /// The position of the statement being checked, or of the function before its body.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// A warning of this lint at `span()`, to be finished with a label or help and passed to `report`.
+    pub fn warning(&self, message: impl Into<String>) -> WaveError {
+        let mut warning = WaveError::new(
+            WaveErrorKind::Lint(self.lint.to_string()),
+            message,
+            self.file.display().to_string(),
+            self.span.line,
+            self.span.column,
+        )
+        .as_warning()
+        .with_note(tr!("`#[allow({0})]` on the function silences this lint", self.lint));
+        if let Some(line) = self.source.and_then(|source| source.lines().nth(self.span.line.checked_sub(1)?)) {
+            warning = warning.with_source(line);
+        }
+        warning
+    }
+
+    /// Adds `diagnostic` to what the compilation reports.
+    pub fn report(&mut self, diagnostic: WaveError) {
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+static LINT_PASSES: Mutex<Vec<Box<dyn LintPass>>> = Mutex::new(Vec::new());
+
+/// Adds `pass` to the lints every later compilation in the process runs.
+pub fn register_lint_pass(pass: Box<dyn LintPass>) {
+    LINT_PASSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(pass);
+}
+
+/// Whether `#[allow(..)]` knows `lint`: one of the compiler's, or that of a registered pass.
+pub fn is_known_lint(lint: &str) -> bool {
+    lint == "recursion" || LINT_PASSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter().any(|pass| pass.name() == lint)
+}
+
+/// Runs the registered passes over `function`, defined in `file`. `source` is the text of
+/// `file`, for the line each diagnostic quotes, when there is one.
+pub fn run_lint_passes(function: &FunctionNode, ast: &[ASTNode], file: &Path, source: Option<&str>) -> Vec<WaveError> {
+    let passes = LINT_PASSES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut diagnostics = vec![];
+
+    for pass in passes.iter().filter(|pass| !function.allows(pass.name())) {
+        let mut cx = LintContext {
+            ast,
+            function,
+            file,
+            source,
+            lint: pass.name(),
+            span: function.span,
+            diagnostics: vec![],
+        };
+        pass.check_function(&mut cx, function);
+        check_body(&**pass, &mut cx, &function.body);
+        diagnostics.append(&mut cx.diagnostics);
+    }
+    diagnostics
+}
+
+fn check_body(pass: &dyn LintPass, cx: &mut LintContext, body: &[ASTNode]) {
+    for node in body {
+        if let ASTNode::Statement(_, span) | ASTNode::Variable(VariableNode { span, .. }) = node {
+            cx.span = *span;
+        }
+        pass.check_node(cx, node);
+
+        let (expressions, bodies) = parts(node);
+        for expr in expressions {
+            walk_expression(expr, &mut |expr| pass.check_expression(cx, expr));
+        }
+        for body in bodies {
+            check_body(pass, cx, body);
+        }
+    }
+}
+
+// The expressions of `node` itself and the bodies nested in it.
+fn parts(node: &ASTNode) -> (Vec<&Expression>, Vec<&[ASTNode]>) {
+    let ASTNode::Statement(statement, _) = node else {
+        return match node {
+            ASTNode::Variable(VariableNode { initial_value: Some(value), .. }) => (vec![value], vec![]),
+            _ => (vec![], vec![]),
+        };
+    };
+    match statement {
+        StatementNode::PrintFormat { args, .. } | StatementNode::PrintlnFormat { args, .. } => (args.iter().collect(), vec![]),
+        StatementNode::If { condition, body, else_if_blocks, else_block } => {
+            let mut bodies = vec![body.as_slice()];
+            bodies.extend(else_if_blocks.as_deref().map(Vec::as_slice));
+            bodies.extend(else_block.as_deref().map(Vec::as_slice));
+            (vec![condition], bodies)
+        }
+        StatementNode::For { initialization, condition, increment, body, .. } => {
+            (vec![initialization, condition, increment], vec![body])
+        }
+        StatementNode::ForRange { start, end, body, .. } => (vec![start, end], vec![body]),
+        StatementNode::While { condition, body, .. } => (vec![condition], vec![body]),
+        StatementNode::Loop { body, .. } => (vec![], vec![body]),
+        StatementNode::Assign { value, .. }
+        | StatementNode::Return(Some(value))
+        | StatementNode::Break { value: Some(value), .. }
+        | StatementNode::Expression(value) => (vec![value], vec![]),
+        StatementNode::Match { value, arms } => (vec![value], arms.iter().map(|arm| arm.body.as_slice()).collect()),
+        _ => (vec![], vec![]),
+    }
+}
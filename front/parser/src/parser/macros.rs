@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use crate::ast::*;
+use crate::visit::walk_expressions;
+
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// Removes every `macro` definition from `nodes` and expands its call sites.
+///
+/// Arguments are substituted as whole expressions, so precedence is never affected.
+/// Variables declared inside a block macro are renamed per expansion, and a macro body
+/// may only refer to its own parameters and declarations, which keeps expansions hygienic.
+pub fn expand_macros(nodes: Vec<ASTNode>) -> Result<Vec<ASTNode>, String> {
+    let mut macros = HashMap::new();
+    let mut rest = vec![];
+
+    for node in nodes {
+        match node {
+            ASTNode::Macro(mac) => {
+                if macros.contains_key(&mac.name) {
+                    return Err(format!("macro '{}' is defined more than once", mac.name));
+                }
+                macros.insert(mac.name.clone(), mac);
+            }
+            other => rest.push(other),
+        }
+    }
+
+    if macros.is_empty() {
+        return Ok(rest);
+    }
+
+    let mut expander = MacroExpander { macros, expansion_count: 0 };
+    let expanded = expander.expand_nodes(rest, 0)?;
+    tracing::debug!(macros = expander.macros.len(), expansions = expander.expansion_count, "expanded macros");
+    Ok(expanded)
+}
+
+struct MacroExpander {
+    macros: HashMap<String, MacroNode>,
+    expansion_count: usize,
+}
+
+impl MacroExpander {
+    fn expand_nodes(&mut self, nodes: Vec<ASTNode>, depth: usize) -> Result<Vec<ASTNode>, String> {
+        let mut expanded = vec![];
+
+        for node in nodes {
+            if let ASTNode::Statement(StatementNode::Expression(Expression::FunctionCall { name, args }), _) = &node {
+                if let Some(MacroNode { body: MacroBody::Block(_), .. }) = self.macros.get(name) {
+                    let body = self.instantiate_block(name, args, depth)?;
+                    expanded.extend(self.expand_nodes(body, depth + 1)?);
+                    continue;
+                }
+            }
+
+            expanded.push(self.expand_node(node, depth)?);
+        }
+
+        Ok(expanded)
+    }
+
+    fn expand_node(&mut self, node: ASTNode, depth: usize) -> Result<ASTNode, String> {
+        Ok(match node {
+            ASTNode::Function(mut function) => {
+                function.body = self.expand_nodes(function.body, depth)?;
+                ASTNode::Function(function)
+            }
+            ASTNode::Variable(mut var) => {
+                var.initial_value = var.initial_value.map(|e| self.expand_expr(e, depth)).transpose()?;
+                var.type_name = self.expand_type(var.type_name, depth)?;
+                ASTNode::Variable(var)
+            }
+            ASTNode::Expression(expr) => ASTNode::Expression(self.expand_expr(expr, depth)?),
+            ASTNode::Statement(stmt, span) => ASTNode::Statement(self.expand_statement(stmt, depth)?, span),
+            other => other,
+        })
+    }
+
+    fn expand_statement(&mut self, stmt: StatementNode, depth: usize) -> Result<StatementNode, String> {
+        Ok(match stmt {
+            StatementNode::PrintFormat { format, args } => StatementNode::PrintFormat {
+                format,
+                args: self.expand_exprs(args, depth)?,
+            },
+            StatementNode::PrintlnFormat { format, args } => StatementNode::PrintlnFormat {
+                format,
+                args: self.expand_exprs(args, depth)?,
+            },
+            StatementNode::If { condition, body, else_if_blocks, else_block } => StatementNode::If {
+                condition: self.expand_expr(condition, depth)?,
+                body: self.expand_nodes(body, depth)?,
+                else_if_blocks: else_if_blocks.map(|b| self.expand_nodes(*b, depth).map(Box::new)).transpose()?,
+                else_block: else_block.map(|b| self.expand_nodes(*b, depth).map(Box::new)).transpose()?,
+            },
+            StatementNode::For { initialization, condition, increment, body, label } => StatementNode::For {
+                initialization: self.expand_expr(initialization, depth)?,
+                condition: self.expand_expr(condition, depth)?,
+                increment: self.expand_expr(increment, depth)?,
+                body: self.expand_nodes(body, depth)?,
+                label,
+            },
+            StatementNode::ForRange { variable, start, end, body, label } => StatementNode::ForRange {
+                variable,
+                start: self.expand_expr(start, depth)?,
+                end: self.expand_expr(end, depth)?,
+                body: self.expand_nodes(body, depth)?,
+                label,
+            },
+            StatementNode::While { condition, body, label } => StatementNode::While {
+                condition: self.expand_expr(condition, depth)?,
+                body: self.expand_nodes(body, depth)?,
+                label,
+            },
+            StatementNode::Loop { body, label } => StatementNode::Loop { body: self.expand_nodes(body, depth)?, label },
+            StatementNode::Assign { variable, value } => StatementNode::Assign {
+                variable,
+                value: self.expand_expr(value, depth)?,
+            },
+            StatementNode::Return(value) => StatementNode::Return(value.map(|e| self.expand_expr(e, depth)).transpose()?),
+            StatementNode::Break { label, value } => StatementNode::Break {
+                label,
+                value: value.map(|e| self.expand_expr(e, depth)).transpose()?,
+            },
+            StatementNode::Expression(expr) => StatementNode::Expression(self.expand_expr(expr, depth)?),
+            StatementNode::Match { value, arms } => StatementNode::Match {
+                value: self.expand_expr(value, depth)?,
+                arms: arms
+                    .into_iter()
+                    .map(|arm| Ok(MatchArm { pattern: arm.pattern, body: self.expand_nodes(arm.body, depth)? }))
+                    .collect::<Result<_, String>>()?,
+            },
+            other => other,
+        })
+    }
+
+    fn expand_exprs(&mut self, exprs: Vec<Expression>, depth: usize) -> Result<Vec<Expression>, String> {
+        exprs.into_iter().map(|e| self.expand_expr(e, depth)).collect()
+    }
+
+    fn expand_type(&mut self, wave_type: WaveType, depth: usize) -> Result<WaveType, String> {
+        Ok(match wave_type {
+            WaveType::TypeOf(expr) => WaveType::TypeOf(Box::new(self.expand_expr(*expr, depth)?)),
+            WaveType::Pointer(inner) => WaveType::Pointer(Box::new(self.expand_type(*inner, depth)?)),
+            WaveType::Array(inner, size) => WaveType::Array(Box::new(self.expand_type(*inner, depth)?), size),
+            other => other,
+        })
+    }
+
+    fn expand_expr(&mut self, expr: Expression, depth: usize) -> Result<Expression, String> {
+        if let Expression::While { condition, body, label } = expr {
+            return Ok(Expression::While {
+                condition: Box::new(self.expand_expr(*condition, depth)?),
+                body: self.expand_nodes(body, depth)?,
+                label,
+            });
+        }
+        if let Expression::Loop { body, label } = expr {
+            return Ok(Expression::Loop { body: self.expand_nodes(body, depth)?, label });
+        }
+        let expr = map_subexpressions(expr, &mut |e| self.expand_expr(e, depth))?;
+
+        if let Expression::FunctionCall { name, args } = &expr {
+            if let Some(mac) = self.macros.get(name) {
+                let body = match &mac.body {
+                    MacroBody::Expression(body) => body.clone(),
+                    MacroBody::Block(_) => {
+                        return Err(format!(
+                            "macro '{}' expands to statements and cannot be used inside an expression",
+                            name
+                        ));
+                    }
+                };
+                check_depth(name, depth)?;
+                let bindings = bind_arguments(mac, args)?;
+                let renames = self.renames(name, &[ASTNode::Expression(body.clone())]);
+                let substituted = substitute_expr(body, name, &bindings, &renames)?;
+                return self.expand_expr(Expression::Grouped(Box::new(substituted)), depth + 1);
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn instantiate_block(&mut self, name: &str, args: &[Expression], depth: usize) -> Result<Vec<ASTNode>, String> {
+        check_depth(name, depth)?;
+
+        let mac = &self.macros[name];
+        let bindings = bind_arguments(mac, args)?;
+        let body = match &mac.body {
+            MacroBody::Block(body) => body.clone(),
+            MacroBody::Expression(_) => unreachable!(),
+        };
+
+        let renames = self.renames(name, &body);
+
+        body.into_iter()
+            .map(|node| substitute_node(node, name, &bindings, &renames))
+            .collect()
+    }
+
+    // Fresh names for the variables declared in one expansion of macro `name`.
+    fn renames(&mut self, name: &str, body: &[ASTNode]) -> HashMap<String, String> {
+        self.expansion_count += 1;
+        let mut renames = HashMap::new();
+        collect_declarations(body, &mut |decl| {
+            renames
+                .entry(decl.to_string())
+                .or_insert_with(|| format!("{}__{}_{}", decl, name, self.expansion_count));
+        });
+        renames
+    }
+}
+
+fn check_depth(name: &str, depth: usize) -> Result<(), String> {
+    if depth >= MAX_EXPANSION_DEPTH {
+        return Err(format!("macro '{}' exceeded the expansion limit of {} (recursive macro?)", name, MAX_EXPANSION_DEPTH));
+    }
+    Ok(())
+}
+
+fn bind_arguments(mac: &MacroNode, args: &[Expression]) -> Result<HashMap<String, Expression>, String> {
+    if mac.parameters.len() != args.len() {
+        return Err(format!(
+            "macro '{}' expects {} argument(s), got {}",
+            mac.name,
+            mac.parameters.len(),
+            args.len()
+        ));
+    }
+
+    Ok(mac.parameters.iter().cloned().zip(args.iter().cloned()).collect())
+}
+
+fn collect_declarations(nodes: &[ASTNode], f: &mut dyn FnMut(&str)) {
+    collect_statement_declarations(nodes, f);
+    // A `while` or `loop` used as a value has a block of its own.
+    walk_expressions(nodes, &mut |expr| {
+        if let Expression::While { body, .. } | Expression::Loop { body, .. } = expr {
+            collect_statement_declarations(body, f);
+        }
+    });
+}
+
+fn collect_statement_declarations(nodes: &[ASTNode], f: &mut dyn FnMut(&str)) {
+    for node in nodes {
+        match node {
+            ASTNode::Variable(var) => f(&var.name),
+            ASTNode::Statement(StatementNode::If { body, else_if_blocks, else_block, .. }, _) => {
+                collect_statement_declarations(body, f);
+                if let Some(blocks) = else_if_blocks {
+                    collect_statement_declarations(blocks, f);
+                }
+                if let Some(block) = else_block {
+                    collect_statement_declarations(block, f);
+                }
+            }
+            ASTNode::Statement(StatementNode::ForRange { variable, body, .. }, _) => {
+                f(variable);
+                collect_statement_declarations(body, f);
+            }
+            ASTNode::Statement(StatementNode::For { body, .. }, _)
+            | ASTNode::Statement(StatementNode::While { body, .. }, _)
+            | ASTNode::Statement(StatementNode::Loop { body, .. }, _) => collect_statement_declarations(body, f),
+            ASTNode::Statement(StatementNode::Match { arms, .. }, _) => {
+                for arm in arms {
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        bindings.iter().filter(|binding| *binding != "_").for_each(|binding| f(binding));
+                    }
+                    collect_statement_declarations(&arm.body, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn resolve_name(
+    ident: &str,
+    macro_name: &str,
+    bindings: &HashMap<String, Expression>,
+    renames: &HashMap<String, String>,
+) -> Result<String, String> {
+    if let Some(renamed) = renames.get(ident) {
+        return Ok(renamed.clone());
+    }
+    match bindings.get(ident) {
+        Some(Expression::Variable(arg)) => Ok(arg.clone()),
+        Some(_) => Err(format!(
+            "macro '{}' needs a variable name for parameter '{}' here, not an arbitrary expression",
+            macro_name, ident
+        )),
+        None => Err(unbound_identifier(macro_name, ident)),
+    }
+}
+
+fn unbound_identifier(macro_name: &str, ident: &str) -> String {
+    format!(
+        "macro '{}' refers to '{}', which is neither a parameter nor declared inside the macro",
+        macro_name, ident
+    )
+}
+
+fn substitute_nodes(
+    nodes: Vec<ASTNode>,
+    macro_name: &str,
+    bindings: &HashMap<String, Expression>,
+    renames: &HashMap<String, String>,
+) -> Result<Vec<ASTNode>, String> {
+    nodes.into_iter().map(|n| substitute_node(n, macro_name, bindings, renames)).collect()
+}
+
+fn substitute_node(
+    node: ASTNode,
+    macro_name: &str,
+    bindings: &HashMap<String, Expression>,
+    renames: &HashMap<String, String>,
+) -> Result<ASTNode, String> {
+    let expr = |e: Expression| substitute_expr(e, macro_name, bindings, renames);
+    let nodes = |n: Vec<ASTNode>| substitute_nodes(n, macro_name, bindings, renames);
+
+    Ok(match node {
+        ASTNode::Variable(mut var) => {
+            var.name = renames[&var.name].clone();
+            var.initial_value = var.initial_value.map(expr).transpose()?;
+            var.type_name = substitute_type(var.type_name, macro_name, bindings, renames)?;
+            ASTNode::Variable(var)
+        }
+        ASTNode::Expression(e) => ASTNode::Expression(expr(e)?),
+        ASTNode::Statement(stmt, span) => ASTNode::Statement(match stmt {
+            StatementNode::PrintFormat { format, args } => StatementNode::PrintFormat {
+                format,
+                args: args.into_iter().map(expr).collect::<Result<_, _>>()?,
+            },
+            StatementNode::PrintlnFormat { format, args } => StatementNode::PrintlnFormat {
+                format,
+                args: args.into_iter().map(expr).collect::<Result<_, _>>()?,
+            },
+            StatementNode::If { condition, body, else_if_blocks, else_block } => StatementNode::If {
+                condition: expr(condition)?,
+                body: nodes(body)?,
+                else_if_blocks: else_if_blocks.map(|b| nodes(*b).map(Box::new)).transpose()?,
+                else_block: else_block.map(|b| nodes(*b).map(Box::new)).transpose()?,
+            },
+            StatementNode::For { initialization, condition, increment, body, label } => StatementNode::For {
+                initialization: expr(initialization)?,
+                condition: expr(condition)?,
+                increment: expr(increment)?,
+                body: nodes(body)?,
+                label,
+            },
+            StatementNode::ForRange { variable, start, end, body, label } => StatementNode::ForRange {
+                variable: renames.get(&variable).cloned().unwrap_or(variable),
+                start: expr(start)?,
+                end: expr(end)?,
+                body: nodes(body)?,
+                label,
+            },
+            StatementNode::While { condition, body, label } => StatementNode::While {
+                condition: expr(condition)?,
+                body: nodes(body)?,
+                label,
+            },
+            StatementNode::Loop { body, label } => StatementNode::Loop { body: nodes(body)?, label },
+            StatementNode::Assign { variable, value } => StatementNode::Assign {
+                variable: resolve_name(&variable, macro_name, bindings, renames)?,
+                value: expr(value)?,
+            },
+            StatementNode::AsmBlock { instructions, inputs, outputs } => {
+                let rename_operands = |operands: Vec<(String, String)>| {
+                    operands
+                        .into_iter()
+                        .map(|(reg, var)| Ok((reg, resolve_name(&var, macro_name, bindings, renames)?)))
+                        .collect::<Result<Vec<_>, String>>()
+                };
+                StatementNode::AsmBlock {
+                    instructions,
+                    inputs: rename_operands(inputs)?,
+                    outputs: rename_operands(outputs)?,
+                }
+            }
+            StatementNode::Return(value) => StatementNode::Return(value.map(expr).transpose()?),
+            StatementNode::Break { label, value } => StatementNode::Break { label, value: value.map(expr).transpose()? },
+            StatementNode::Expression(e) => StatementNode::Expression(expr(e)?),
+            StatementNode::Match { value, arms } => StatementNode::Match {
+                value: expr(value)?,
+                arms: arms
+                    .into_iter()
+                    .map(|arm| {
+                        let pattern = match arm.pattern {
+                            Pattern::Variant { enum_name, variant, bindings } => Pattern::Variant {
+                                enum_name,
+                                variant,
+                                bindings: bindings.into_iter().map(|binding| renames.get(&binding).cloned().unwrap_or(binding)).collect(),
+                            },
+                            other => other,
+                        };
+                        Ok(MatchArm { pattern, body: nodes(arm.body)? })
+                    })
+                    .collect::<Result<_, String>>()?,
+            },
+            other => other,
+        }, span),
+        other => other,
+    })
+}
+
+fn substitute_type(
+    wave_type: WaveType,
+    macro_name: &str,
+    bindings: &HashMap<String, Expression>,
+    renames: &HashMap<String, String>,
+) -> Result<WaveType, String> {
+    Ok(match wave_type {
+        WaveType::TypeOf(e) => WaveType::TypeOf(Box::new(substitute_expr(*e, macro_name, bindings, renames)?)),
+        WaveType::Pointer(inner) => WaveType::Pointer(Box::new(substitute_type(*inner, macro_name, bindings, renames)?)),
+        WaveType::Array(inner, size) => {
+            WaveType::Array(Box::new(substitute_type(*inner, macro_name, bindings, renames)?), size)
+        }
+        other => other,
+    })
+}
+
+fn substitute_expr(
+    expr: Expression,
+    macro_name: &str,
+    bindings: &HashMap<String, Expression>,
+    renames: &HashMap<String, String>,
+) -> Result<Expression, String> {
+    match expr {
+        Expression::Variable(ident) => {
+            if let Some(renamed) = renames.get(&ident) {
+                Ok(Expression::Variable(renamed.clone()))
+            } else if let Some(arg) = bindings.get(&ident) {
+                Ok(arg.clone())
+            } else {
+                Err(unbound_identifier(macro_name, &ident))
+            }
+        }
+        Expression::AsmBlock { instructions, inputs, outputs } => {
+            let rename_operands = |operands: Vec<(String, String)>| {
+                operands
+                    .into_iter()
+                    .map(|(reg, var)| Ok((reg, resolve_name(&var, macro_name, bindings, renames)?)))
+                    .collect::<Result<Vec<_>, String>>()
+            };
+            Ok(Expression::AsmBlock {
+                instructions,
+                inputs: rename_operands(inputs)?,
+                outputs: rename_operands(outputs)?,
+            })
+        }
+        Expression::While { condition, body, label } => Ok(Expression::While {
+            condition: Box::new(substitute_expr(*condition, macro_name, bindings, renames)?),
+            body: substitute_nodes(body, macro_name, bindings, renames)?,
+            label,
+        }),
+        Expression::Loop { body, label } => Ok(Expression::Loop {
+            body: substitute_nodes(body, macro_name, bindings, renames)?,
+            label,
+        }),
+        other => map_subexpressions(other, &mut |e| substitute_expr(e, macro_name, bindings, renames)),
+    }
+}
+
+/// Rebuilds `expr` with `f` applied to each of its direct subexpressions.
+fn map_subexpressions(
+    expr: Expression,
+    f: &mut dyn FnMut(Expression) -> Result<Expression, String>,
+) -> Result<Expression, String> {
+    Ok(match expr {
+        Expression::FunctionCall { name, args } => Expression::FunctionCall {
+            name,
+            args: args.into_iter().map(&mut *f).collect::<Result<_, _>>()?,
+        },
+        Expression::Deref(inner) => Expression::Deref(Box::new(f(*inner)?)),
+        Expression::AddressOf(inner) => Expression::AddressOf(Box::new(f(*inner)?)),
+        Expression::Grouped(inner) => Expression::Grouped(Box::new(f(*inner)?)),
+        Expression::BinaryExpression { left, operator, right } => Expression::BinaryExpression {
+            left: Box::new(f(*left)?),
+            operator,
+            right: Box::new(f(*right)?),
+        },
+        Expression::IndexAccess { target, index } => Expression::IndexAccess {
+            target: Box::new(f(*target)?),
+            index: Box::new(f(*index)?),
+        },
+        Expression::ArrayLiteral(elements) => {
+            Expression::ArrayLiteral(elements.into_iter().map(&mut *f).collect::<Result<_, _>>()?)
+        }
+        Expression::AssignOperation { target, operator, value } => Expression::AssignOperation {
+            target: Box::new(f(*target)?),
+            operator,
+            value: Box::new(f(*value)?),
+        },
+        Expression::Assignment { target, value } => Expression::Assignment {
+            target: Box::new(f(*target)?),
+            value: Box::new(f(*value)?),
+        },
+        Expression::StructLiteral { name, fields } => Expression::StructLiteral {
+            name,
+            fields: fields.into_iter().map(|(field, value)| Ok((field, f(value)?))).collect::<Result<_, String>>()?,
+        },
+        Expression::FieldAccess { target, field } => Expression::FieldAccess {
+            target: Box::new(f(*target)?),
+            field,
+        },
+        Expression::Alloc { ty, count } => Expression::Alloc {
+            ty,
+            count: count.map(|count| f(*count).map(Box::new)).transpose()?,
+        },
+        Expression::EnumVariant { enum_name, variant, args } => Expression::EnumVariant {
+            enum_name,
+            variant,
+            args: args.into_iter().map(&mut *f).collect::<Result<_, _>>()?,
+        },
+        other => other,
+    })
+}
@@ -1,6 +1,17 @@
 mod parser;
 pub mod ast;
+pub mod callgraph;
+pub mod cfg;
+pub mod const_eval;
 mod format;
 pub mod import;
+pub mod lint;
+pub mod macros;
+pub mod overload;
+pub mod symbols;
+pub mod typeck;
+pub mod validate;
+pub mod visit;
 
-pub use parser::*;
\ No newline at end of file
+pub use parser::*;
+pub use format::parse_format_string;
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::ast::{ASTNode, FunctionNode, WaveType};
+use crate::typeck::fits;
+
+/// Which of the functions sharing a name a call goes to.
+#[derive(Debug, PartialEq)]
+pub enum Overload {
+    /// The index of the function among the candidates.
+    Selected(usize),
+    /// No candidate takes arguments of these types.
+    NoMatch,
+    /// The candidates, by index, that take them equally well.
+    Ambiguous(Vec<usize>),
+}
+
+/// Whether more than one function of `ast` is named `name`.
+pub fn is_overloaded(name: &str, ast: &[ASTNode]) -> bool {
+    ast.iter()
+        .filter(|node| matches!(node, ASTNode::Function(function) if function.name == name))
+        .nth(1)
+        .is_some()
+}
+
+/// The name code generation gives `function` in the program `ast`: its own, or when other
+/// functions share it, the name followed by the words of its parameter types, e.g. `area.i32.f64`
+/// and `area.ptr.i32` for `area(p: ptr<i32>)`. Wave names cannot contain a `.`, so these never
+/// collide with a name of the program, and LLVM takes them without quotes.
+pub fn symbol_name(function: &FunctionNode, ast: &[ASTNode]) -> String {
+    if !is_overloaded(&function.name, ast) {
+        return function.name.clone();
+    }
+    let mut symbol = function.name.clone();
+    for param in &function.parameters {
+        push_type_words(&mut symbol, &param.param_type);
+    }
+    symbol
+}
+
+// A function type is `fun` with its number of parameters, then those and its return type or
+// `void`, so that the words of two different parameter lists never run together alike.
+fn push_type_words(symbol: &mut String, ty: &WaveType) {
+    if let WaveType::Function(params, return_type) = ty {
+        symbol.push_str(&format!(".fun{}", params.len()));
+        for param in params {
+            push_type_words(symbol, param);
+        }
+        match return_type {
+            Some(return_type) => push_type_words(symbol, return_type),
+            None => symbol.push_str(".void"),
+        }
+        return;
+    }
+    let written = ty.to_string();
+    for word in written.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|word| !word.is_empty()) {
+        symbol.push('.');
+        symbol.push_str(word);
+    }
+}
+
+/// Picks the function a call with arguments of types `args` goes to among `candidates`, the
+/// parameter types of the functions that share its name. A function whose parameters have
+/// exactly the argument types wins; otherwise the one function the arguments convert to. An
+/// argument of unknown type converts to any parameter, and an unsuffixed literal has the type
+/// `var` would give it, `i32` or `f64`.
+pub fn select_overload(candidates: &[Vec<WaveType>], args: &[Option<WaveType>]) -> Overload {
+    let by_arity: Vec<usize> = (0..candidates.len()).filter(|&i| candidates[i].len() == args.len()).collect();
+    if let [only] = by_arity[..] {
+        return Overload::Selected(only);
+    }
+
+    let exact: Vec<usize> = by_arity
+        .iter()
+        .copied()
+        .filter(|&i| candidates[i].iter().zip(args).all(|(param, arg)| arg.as_ref().is_some_and(|arg| same_type(param, arg))))
+        .collect();
+    if let [only] = exact[..] {
+        return Overload::Selected(only);
+    }
+
+    let converting: Vec<usize> = by_arity
+        .into_iter()
+        .filter(|&i| candidates[i].iter().zip(args).all(|(param, arg)| arg.as_ref().is_none_or(|arg| fits(param, arg))))
+        .collect();
+    match converting[..] {
+        [] => Overload::NoMatch,
+        [only] => Overload::Selected(only),
+        _ => Overload::Ambiguous(converting),
+    }
+}
+
+/// Whether two functions named alike take the same parameter types, and so cannot both be defined.
+pub fn same_parameters(a: &FunctionNode, b: &FunctionNode) -> bool {
+    a.parameters.len() == b.parameters.len()
+        && a.parameters.iter().zip(&b.parameters).all(|(a, b)| same_type(&a.param_type, &b.param_type))
+}
+
+// `WaveType` holds expressions, so types compare as written.
+fn same_type(a: &WaveType, b: &WaveType) -> bool {
+    a.to_string() == b.to_string()
+}
@@ -1,42 +1,155 @@
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell};
 use std::iter::Peekable;
 use std::slice::Iter;
-use regex::Regex;
 use ::lexer::*;
-use parser::ast::*;
-use crate::*;
+use crate::ast::*;
 use crate::parser::format::*;
+use crate::const_eval::{evaluate, ConstValue};
+use crate::lint::is_known_lint;
+
+thread_local! {
+    // What the parser reported about the program being parsed, in order.
+    static PARSE_ERRORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    // Set while `parse_program` parses: the messages are only recorded, not printed.
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
 
-pub fn parse(tokens: &Vec<Token>) -> Option<Vec<ASTNode>> {
+/// Says why the program does not parse: on stderr, and to `parse_program`.
+macro_rules! parse_error {
+    ($($arg:tt)*) => {
+        $crate::parser::parser::record_parse_error(format!($($arg)*))
+    };
+}
+pub(crate) use parse_error;
+
+pub(crate) fn record_parse_error(message: String) {
+    if !QUIET.get() {
+        eprintln!("{}", message);
+    }
+    PARSE_ERRORS.with(|errors| errors.borrow_mut().push(message));
+}
+
+/// Why a program does not parse: the first thing the parser found wrong, and the token it
+/// stopped at.
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parses the program of `tokens` like `parse`, but answers why it does not parse instead of
+/// printing it, for diagnostics that carry a position.
+pub fn parse_program(tokens: &[Token]) -> Result<Vec<ASTNode>, ParseError> {
+    PARSE_ERRORS.with(|errors| errors.borrow_mut().clear());
+    let quiet = QUIET.replace(true);
     let mut iter = tokens.iter().peekable();
+    let parsed = parse_items(&mut iter);
+    QUIET.set(quiet);
+
+    parsed.ok_or_else(|| {
+        let stopped_at = iter.peek().copied().or(tokens.last());
+        // The last message before ones like "Failed to parse function", which only say where an
+        // inner failure was reached; earlier ones can come from statements that parsed in the end.
+        let specific = PARSE_ERRORS.with(|errors| {
+            errors
+                .borrow()
+                .iter()
+                .map(|message| message.trim_start_matches("❌ ").trim_start_matches("Error: ").to_string())
+                .rfind(|message| !message.starts_with("Failed to parse"))
+        });
+        let message = specific.unwrap_or_else(|| match stopped_at {
+            Some(token) if token.token_type != TokenType::Eof => format!("Unexpected `{}`", token.lexeme),
+            _ => "Unexpected end of file".to_string(),
+        });
+        ParseError {
+            message,
+            line: stopped_at.map_or(1, |token| token.line),
+            column: stopped_at.map_or(1, |token| token.column),
+        }
+    })
+}
+
+pub fn parse(tokens: &Vec<Token>) -> Option<Vec<ASTNode>> {
+    parse_items(&mut tokens.iter().peekable())
+}
+
+fn parse_items(iter: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>> {
     let mut nodes = vec![];
 
     while let Some(token) = iter.peek() {
         match token.token_type {
             TokenType::Import => {
+                let span = Span::new(token.line, token.column);
                 iter.next();
-                if let Some(path) = parse_import(&mut iter) {
+                if let Some(path) = parse_import(iter, span) {
                     nodes.push(path);
                 } else {
                     return None;
                 }
             }
             TokenType::Fun => {
-                if let Some(func) = parse_function(&mut iter) {
+                if let Some(func) = parse_function(iter) {
+                    if let ASTNode::Function(function) = &func {
+                        tracing::trace!(function = %function.name, line = function.span.line, "parsed function");
+                    }
+                    nodes.push(func);
+                } else {
+                    parse_error!("❌ Failed to parse function");
+                    return None;
+                }
+            }
+            TokenType::Extern => {
+                if let Some(declaration) = parse_extern(iter) {
+                    nodes.push(declaration);
+                } else {
+                    parse_error!("❌ Failed to parse extern function");
+                    return None;
+                }
+            }
+            TokenType::Hash => {
+                iter.next(); // consume '#'
+                if let Some(func) = parse_attributed_function(iter) {
                     nodes.push(func);
                 } else {
-                    println!("❌ Failed to parse function");
+                    parse_error!("❌ Failed to parse function");
+                    return None;
+                }
+            }
+            TokenType::Macro => {
+                iter.next(); // consume 'macro'
+                if let Some(mac) = parse_macro(iter) {
+                    nodes.push(mac);
+                } else {
+                    parse_error!("❌ Failed to parse macro");
+                    return None;
+                }
+            }
+            TokenType::Struct => {
+                if let Some(declaration) = parse_struct(iter) {
+                    nodes.push(declaration);
+                } else {
+                    parse_error!("❌ Failed to parse struct");
+                    return None;
+                }
+            }
+            TokenType::Enum => {
+                if let Some(declaration) = parse_enum(iter) {
+                    nodes.push(declaration);
+                } else {
+                    parse_error!("❌ Failed to parse enum");
                     return None;
                 }
             }
             TokenType::Eof => break,
             _ => {
-                println!("❌ Unexpected token at top level: {:?}", token);
+                parse_error!("❌ Unexpected token at top level: {:?}", token);
                 return None;
             }
         }
     }
 
+    tracing::debug!(nodes = nodes.len(), "parsed");
     Some(nodes)
 }
 
@@ -45,6 +158,7 @@ pub fn param(parameter: String, param_type: WaveType, initial_value: Option<Valu
         name: parameter,
         param_type,
         initial_value,
+        span: Span::default(),
     }
 }
 
@@ -59,27 +173,18 @@ pub fn parse_parameters(tokens: &mut Peekable<Iter<Token>>) -> Vec<ParameterNode
         match &token.token_type {
             TokenType::Identifier(name) => {
                 let name = name.clone();
+                let span = Span::new(token.line, token.column);
                 tokens.next(); // consume identifier
 
                 if !matches!(tokens.peek().map(|t| &t.token_type), Some(TokenType::Colon)) {
-                    println!("Error: Expected ':' after parameter name '{}'", name);
+                    parse_error!("Error: Expected ':' after parameter name '{}'", name);
                     break;
                 }
                 tokens.next(); // consume ':'
 
-                let token = tokens.next();
-                let param_type = match &token {
-                    Some(Token { token_type, .. }) => match token_type_to_wave_type(token_type) {
-                        Some(wt) => wt,
-                        None => {
-                            println!("Error: Unsupported or unknown type token: {:?}", token_type);
-                            break;
-                        }
-                    },
-                    None => {
-                        println!("Expected type after ':' for parameter '{}'", name);
-                        break;
-                    }
+                let Some(param_type) = parse_type_annotation(tokens) else {
+                    parse_error!("Error: Expected a type for parameter '{}'", name);
+                    break;
                 };
 
                 let initial_value = if matches!(tokens.peek().map(|t| &t.token_type), Some(TokenType::Equal)) {
@@ -88,6 +193,8 @@ pub fn parse_parameters(tokens: &mut Peekable<Iter<Token>>) -> Vec<ParameterNode
                         Some(Token { token_type: TokenType::Number(n), .. }) => Some(Value::Int(*n)),
                         Some(Token { token_type: TokenType::Float(f), .. }) => Some(Value::Float(*f)),
                         Some(Token { token_type: TokenType::String(s), .. }) => Some(Value::Text(s.clone())),
+                        Some(Token { token_type: TokenType::True, .. }) => Some(Value::Bool(true)),
+                        Some(Token { token_type: TokenType::False, .. }) => Some(Value::Bool(false)),
                         _ => None,
                     }
                 } else {
@@ -98,6 +205,7 @@ pub fn parse_parameters(tokens: &mut Peekable<Iter<Token>>) -> Vec<ParameterNode
                     name,
                     param_type,
                     initial_value,
+                    span,
                 });
 
                 match tokens.peek().map(|t| &t.token_type) {
@@ -110,7 +218,7 @@ pub fn parse_parameters(tokens: &mut Peekable<Iter<Token>>) -> Vec<ParameterNode
                         break;
                     }
                     Some(TokenType::Comma) => {
-                        println!("Error: use `;` instead of `,` to separate parameters");
+                        parse_error!("Error: use `;` instead of `,` to separate parameters");
                         break;
                     }
                     _ => break,
@@ -129,7 +237,7 @@ pub fn parse_parameters(tokens: &mut Peekable<Iter<Token>>) -> Vec<ParameterNode
     params
 }
 
-fn token_type_to_wave_type(token_type: &TokenType) -> Option<WaveType> {
+pub(crate) fn token_type_to_wave_type(token_type: &TokenType) -> Option<WaveType> {
     match token_type {
         TokenType::TypeInt(bits) => Some(WaveType::Int(*bits)),
         TokenType::TokenTypeInt(int_type) => match int_type {
@@ -141,7 +249,7 @@ fn token_type_to_wave_type(token_type: &TokenType) -> Option<WaveType> {
             IntegerType::I256 => Some(WaveType::Int(256)),
             IntegerType::I512 => Some(WaveType::Int(512)),
             IntegerType::I1024 => Some(WaveType::Int(1024)),
-            _ => panic!("Unhandled integer type: {:?}", int_type),
+            IntegerType::ISZ => Some(WaveType::Int(WaveType::POINTER_SIZED)),
         },
         TokenType::TypeUint(bits) => Some(WaveType::Uint(*bits)),
         TokenType::TokenTypeUint(uint_type) => match uint_type {
@@ -153,7 +261,7 @@ fn token_type_to_wave_type(token_type: &TokenType) -> Option<WaveType> {
             UnsignedIntegerType::U256 => Some(WaveType::Uint(256)),
             UnsignedIntegerType::U512 => Some(WaveType::Uint(512)),
             UnsignedIntegerType::U1024 => Some(WaveType::Uint(1024)),
-            _ => panic!("Unhandled uint type: {:?}", uint_type),
+            UnsignedIntegerType::USZ => Some(WaveType::Uint(WaveType::POINTER_SIZED)),
         },
         TokenType::TokenTypeFloat(float_type) => match float_type {
             FloatType::F32 => Some(WaveType::Float(32)),
@@ -175,6 +283,15 @@ fn token_type_to_wave_type(token_type: &TokenType) -> Option<WaveType> {
         TokenType::TypeArray(inner, size) => {
             token_type_to_wave_type(inner).map(|t| WaveType::Array(Box::new(t), *size))
         }
+        TokenType::TypeFunction(params, return_type) => {
+            let params = params.iter().map(token_type_to_wave_type).collect::<Option<Vec<_>>>()?;
+            let return_type = match return_type {
+                Some(return_type) => Some(Box::new(token_type_to_wave_type(return_type)?)),
+                None => None,
+            };
+            Some(WaveType::Function(params, return_type))
+        }
+        TokenType::Identifier(name) => Some(WaveType::Struct(name.clone())),
         _ => None,
     }
 }
@@ -183,12 +300,13 @@ pub fn extract_body(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>>
     let mut body = vec![];
 
     if tokens.peek()?.token_type != TokenType::Lbrace {
-        println!("❌ Expected '{{' at the beginning of function body");
+        parse_error!("❌ Expected '{{' at the beginning of function body");
         return None;
     }
     tokens.next(); // consume '{'
 
     while let Some(token) = tokens.peek() {
+        let span = Span::new(token.line, token.column);
         match &token.token_type {
             TokenType::Whitespace => {
                 tokens.next(); // ignore
@@ -198,65 +316,81 @@ pub fn extract_body(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>>
                 break;
             }
             TokenType::Eof => {
-                println!("❌ Unexpected EOF inside function body");
+                parse_error!("❌ Unexpected EOF inside function body");
                 return None;
             }
             TokenType::Asm => {
                 tokens.next();
-                body.push(parse_asm_block(tokens)?);
+                body.push(with_span(parse_asm_block(tokens)?, span));
+            }
+            TokenType::Hash => {
+                tokens.next(); // consume '#'
+                body.push(with_span(parse_attributed_declaration(tokens)?, span));
             }
             TokenType::Var => {
                 tokens.next(); // consume 'var'
-                body.push(parse_var(tokens)?);
+                body.push(with_span(parse_var(tokens)?, span));
             }
             TokenType::Let => {
                 tokens.next(); // consume 'let'
-                body.push(parse_let(tokens)?);
+                body.push(with_span(parse_let(tokens)?, span));
             }
             TokenType::Println => {
                 tokens.next(); // consume 'println'
-                body.push(parse_println(tokens)?);
+                body.push(with_span(parse_println(tokens)?, span));
             }
             TokenType::Print => {
                 tokens.next();
-                body.push(parse_print(tokens)?);
+                body.push(with_span(parse_print(tokens)?, span));
             }
             TokenType::If => {
                 tokens.next();
-                body.push(parse_if(tokens)?);
+                body.push(with_span(parse_if(tokens)?, span));
             }
             TokenType::For => {
                 tokens.next();
-                body.push(parse_for(tokens)?);
+                body.push(with_span(parse_for(tokens)?, span));
             }
             TokenType::While => {
                 tokens.next();
-                body.push(parse_while(tokens)?);
+                body.push(with_span(parse_while(tokens)?, span));
+            }
+            TokenType::Loop => {
+                tokens.next();
+                body.push(with_span(parse_loop(tokens)?, span));
+            }
+            TokenType::Label(label) => {
+                tokens.next();
+                body.push(with_span(parse_labeled_loop(tokens, label)?, span));
+            }
+            TokenType::Match => {
+                tokens.next();
+                body.push(with_span(parse_match(tokens)?, span));
             }
             TokenType::Identifier(_) => {
                 if let Some(expr) = parse_expression(tokens) {
                     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                         tokens.next(); // consume ';'
                     }
-                    body.push(ASTNode::Statement(StatementNode::Expression(expr)));
+                    body.push(ASTNode::Statement(StatementNode::Expression(expr), span));
                 } else {
-                    println!("❌ Failed to parse expression starting with identifier");
+                    parse_error!("❌ Failed to parse expression starting with identifier");
                     return None;
                 }
             }
             TokenType::Break => {
                 tokens.next(); // consume 'break'
-                if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
-                    tokens.next(); // consume ;
-                }
-                body.push(ASTNode::Statement(StatementNode::Break));
+                let label = parse_label(tokens);
+                let value = parse_break_value(tokens)?;
+                body.push(ASTNode::Statement(StatementNode::Break { label, value }, span));
             }
             TokenType::Continue => {
-                tokens.next(); // consume 'break'
+                tokens.next(); // consume 'continue'
+                let label = parse_label(tokens);
                 if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                     tokens.next(); // consume ;
                 }
-                body.push(ASTNode::Statement(StatementNode::Continue));
+                body.push(ASTNode::Statement(StatementNode::Continue { label }, span));
             }
             TokenType::Return => {
                 tokens.next(); // consume 'return'
@@ -265,26 +399,26 @@ pub fn extract_body(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>>
                     tokens.next(); // return;
                     None
                 } else {
-                    let value = parse_expression(tokens)?;
+                    let value = parse_value(tokens)?;
                     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                         tokens.next();
                     }
                     Some(value)
                 };
 
-                body.push(ASTNode::Statement(StatementNode::Return(expr)));
+                body.push(ASTNode::Statement(StatementNode::Return(expr), span));
             }
             TokenType::Deref => {
                 let token = token.clone();
                 tokens.next();
-                body.push(parse_assignment(tokens, &token)?);
+                body.push(with_span(parse_assignment(tokens, &token)?, span));
             }
             _ => {
                 if let Some(expr) = parse_expression(tokens) {
                     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                         tokens.next(); // consume ;
                     }
-                    body.push(ASTNode::Statement(StatementNode::Expression(expr)));
+                    body.push(ASTNode::Statement(StatementNode::Expression(expr), span));
                 } else {
                     tokens.next(); // fallback skip
                 }
@@ -299,7 +433,7 @@ pub fn parse_function_call(name: Option<String>, tokens: &mut Peekable<Iter<Toke
     let name = name?;
 
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("❌ Expected '(' after function name '{}'", name);
+        parse_error!("❌ Expected '(' after function name '{}'", name);
         return None;
     }
     tokens.next(); // consume '('
@@ -321,7 +455,7 @@ pub fn parse_function_call(name: Option<String>, tokens: &mut Peekable<Iter<Toke
             }
             Some(TokenType::Rparen) => continue,
             _ => {
-                println!("❌ Unexpected token in function arguments: {:?}", tokens.peek());
+                parse_error!("❌ Unexpected token in function arguments: {:?}", tokens.peek());
                 return None;
             }
         }
@@ -355,7 +489,8 @@ fn parse_parentheses(tokens: &mut Peekable<Iter<Token>>) -> Vec<Token> {
 
 // FUN parsing
 fn parse_function(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
-    tokens.next();
+    let fun_token = tokens.next()?;
+    let span = Span::new(fun_token.line, fun_token.column);
 
     let name = match tokens.next() {
         Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
@@ -368,27 +503,14 @@ fn parse_function(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
 
     tokens.next(); // consume '('
     let parameters = parse_parameters(tokens);
-
-    let mut param_names = HashSet::new();
-    for param in &parameters {
-        if !param_names.insert(param.name.clone()) {
-            println!("Error: Parameter '{}' is declared multiple times", param.name);
-            return None;
-        }
+    if tokens.peek()?.token_type == TokenType::DotDotDot {
+        parse_error!("Error: Only extern functions can take variable arguments, as '{}' does", name);
+        return None;
     }
 
     let return_type = if let Some(Token { token_type: TokenType::Arrow, .. }) = tokens.peek() {
         tokens.next(); // consume '->'
-
-        match tokens.next() {
-            Some(Token { token_type, .. }) => {
-                token_type_to_wave_type(token_type)
-            }
-            None => {
-                println!("Error: Expected type after '->'");
-                None
-            }
-        }
+        Some(parse_type_annotation(tokens)?)
     } else {
         None
     };
@@ -399,9 +521,242 @@ fn parse_function(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
         parameters,
         body,
         return_type,
+        attributes: vec![],
+        span,
+        variadic: false,
+    }))
+}
+
+// extern "C" fun name(params; ...) -> type;  The ABI string may be left out; C is the only one.
+// A `...` after the parameters takes any number of arguments more.
+fn parse_extern(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    tokens.next(); // consume 'extern'
+    if let Some(Token { token_type: TokenType::String(abi), .. }) = tokens.peek() {
+        if abi != "C" {
+            parse_error!("Error: Unsupported ABI \"{}\", expected \"C\"", abi);
+            return None;
+        }
+        tokens.next();
+    }
+
+    let fun_token = tokens.next().filter(|token| token.token_type == TokenType::Fun)?;
+    let span = Span::new(fun_token.line, fun_token.column);
+    let name = match tokens.next() {
+        Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
+        _ => return None,
+    };
+
+    if tokens.next()?.token_type != TokenType::Lparen {
+        return None;
+    }
+    let parameters = parse_parameters(tokens);
+    // `...` ends the parameters, which leave it and the `)` after it.
+    let variadic = tokens.peek()?.token_type == TokenType::DotDotDot;
+    if variadic {
+        tokens.next();
+        if tokens.next()?.token_type != TokenType::Rparen {
+            parse_error!("Error: Expected ')' after '...' in the declaration of extern function '{}'", name);
+            return None;
+        }
+    }
+
+    let return_type = if let Some(Token { token_type: TokenType::Arrow, .. }) = tokens.peek() {
+        tokens.next(); // consume '->'
+        Some(parse_type_annotation(tokens)?)
+    } else {
+        None
+    };
+
+    if tokens.next()?.token_type != TokenType::SemiColon {
+        parse_error!("Error: Expected ';' after the declaration of extern function '{}'", name);
+        return None;
+    }
+    Some(ASTNode::Extern(FunctionNode {
+        name,
+        parameters,
+        body: vec![],
+        return_type,
+        attributes: vec![],
+        span,
+        variadic,
     }))
 }
 
+// struct Name { field: type; ... }
+fn parse_struct(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let struct_token = tokens.next()?;
+    let span = Span::new(struct_token.line, struct_token.column);
+
+    let name = match tokens.next() {
+        Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
+        _ => {
+            parse_error!("Error: Expected a name after 'struct'");
+            return None;
+        }
+    };
+
+    if tokens.next()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after 'struct {}'", name);
+        return None;
+    }
+
+    let mut fields: Vec<StructField> = vec![];
+    loop {
+        match tokens.next().map(|t| &t.token_type) {
+            Some(TokenType::Rbrace) => break,
+            Some(TokenType::Identifier(field)) => {
+                let field = field.clone();
+                if !matches!(tokens.next().map(|t| &t.token_type), Some(TokenType::Colon)) {
+                    parse_error!("Error: Expected ':' after field '{}' of struct '{}'", field, name);
+                    return None;
+                }
+                let field_type = parse_type_annotation(tokens)?;
+
+                if fields.iter().any(|existing| existing.name == field) {
+                    parse_error!("Error: Field '{}' is declared twice in struct '{}'", field, name);
+                    return None;
+                }
+                fields.push(StructField { name: field, field_type });
+
+                if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
+                    tokens.next(); // consume ';'
+                }
+            }
+            other => {
+                parse_error!("Error: Expected a field or '}}' in struct '{}', found {:?}", name, other);
+                return None;
+            }
+        }
+    }
+
+    Some(ASTNode::Struct(StructNode { name, fields, span }))
+}
+
+// enum Name { Variant(type, ...), Variant, ... }
+fn parse_enum(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let enum_token = tokens.next()?;
+    let span = Span::new(enum_token.line, enum_token.column);
+
+    let name = match tokens.next() {
+        Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
+        _ => {
+            parse_error!("Error: Expected a name after 'enum'");
+            return None;
+        }
+    };
+
+    if tokens.next()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after 'enum {}'", name);
+        return None;
+    }
+
+    let mut variants: Vec<EnumVariant> = vec![];
+    loop {
+        match tokens.next().map(|t| &t.token_type) {
+            Some(TokenType::Rbrace) => break,
+            Some(TokenType::Identifier(variant)) => {
+                let variant = variant.clone();
+                let mut fields = vec![];
+                if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
+                    tokens.next(); // consume '('
+                    loop {
+                        if let Some(Token { token_type: TokenType::Rparen, .. }) = tokens.peek() {
+                            tokens.next(); // consume ')'
+                            break;
+                        }
+                        fields.push(parse_type_annotation(tokens)?);
+                        match tokens.peek().map(|t| &t.token_type) {
+                            Some(TokenType::Comma) => {
+                                tokens.next(); // consume ','
+                            }
+                            Some(TokenType::Rparen) => continue,
+                            _ => {
+                                parse_error!("Error: Expected ',' or ')' in variant '{}' of enum '{}'", variant, name);
+                                return None;
+                            }
+                        }
+                    }
+                }
+
+                if variants.iter().any(|existing| existing.name == variant) {
+                    parse_error!("Error: Variant '{}' is declared twice in enum '{}'", variant, name);
+                    return None;
+                }
+                variants.push(EnumVariant { name: variant, fields });
+
+                if let Some(Token { token_type: TokenType::Comma, .. }) = tokens.peek() {
+                    tokens.next(); // consume ','
+                }
+            }
+            other => {
+                parse_error!("Error: Expected a variant or '}}' in enum '{}', found {:?}", name, other);
+                return None;
+            }
+        }
+    }
+
+    Some(ASTNode::Enum(EnumNode { name, variants, span }))
+}
+
+// macro name(a, b) => expr;  or  macro name(a, b) => { statements }
+fn parse_macro(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let name = match tokens.next() {
+        Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
+        _ => {
+            parse_error!("Error: Expected macro name after 'macro'");
+            return None;
+        }
+    };
+
+    if tokens.next()?.token_type != TokenType::Lparen {
+        parse_error!("Error: Expected '(' after macro name '{}'", name);
+        return None;
+    }
+
+    let mut parameters = vec![];
+    loop {
+        match tokens.next().map(|t| &t.token_type) {
+            Some(TokenType::Rparen) => break,
+            Some(TokenType::Identifier(param)) => {
+                if parameters.contains(param) {
+                    parse_error!("Error: Duplicate parameter '{}' in macro '{}'", param, name);
+                    return None;
+                }
+                parameters.push(param.clone());
+                match tokens.next().map(|t| &t.token_type) {
+                    Some(TokenType::Comma) => {}
+                    Some(TokenType::Rparen) => break,
+                    _ => {
+                        parse_error!("Error: Expected ',' or ')' in parameters of macro '{}'", name);
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                parse_error!("Error: Expected parameter name in macro '{}'", name);
+                return None;
+            }
+        }
+    }
+
+    if tokens.next()?.token_type != TokenType::FatArrow {
+        parse_error!("Error: Expected '=>' after parameters of macro '{}'", name);
+        return None;
+    }
+
+    let body = if tokens.peek()?.token_type == TokenType::Lbrace {
+        MacroBody::Block(extract_body(tokens)?)
+    } else {
+        let expr = parse_value(tokens)?;
+        if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
+            tokens.next(); // consume ';'
+        }
+        MacroBody::Expression(expr)
+    };
+
+    Some(ASTNode::Macro(MacroNode { name, parameters, body }))
+}
+
 // VAR parsing
 fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
     let mutability = Mutability::Var;
@@ -409,26 +764,30 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
     let name = match tokens.next() {
         Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
         _ => {
-            println!("Expected identifier");
+            parse_error!("Expected identifier");
             return None;
         }
     };
 
     if !matches!(tokens.next().map(|t| &t.token_type), Some(TokenType::Colon)) {
-        println!("Expected ':' after identifier");
+        parse_error!("Expected ':' after identifier");
         return None;
     }
 
     let type_token = match tokens.next() {
         Some(token) => token.clone(),
         _ => {
-            println!("Expected type after ':'");
+            parse_error!("Expected type after ':'");
             return None;
         }
     };
 
-    let wave_type = if let TokenType::Identifier(ref name) = type_token.token_type {
-        if let Some(Token { token_type: TokenType::Lchevr, .. }) = tokens.peek() {
+    let wave_type = if type_token.token_type == TokenType::Fun {
+        parse_function_type(tokens)?
+    } else if let TokenType::Identifier(ref name) = type_token.token_type {
+        if name == "typeof" {
+            parse_typeof(tokens)?
+        } else if let Some(Token { token_type: TokenType::Lchevr, .. }) = tokens.peek() {
             tokens.next(); // consume '<'
 
             let mut inner = String::new();
@@ -456,14 +815,14 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
             let parsed_type = parse_type(&full_type_str);
 
             if parsed_type.is_none() {
-                println!("Unknown generic type: {}", full_type_str);
+                parse_error!("Unknown generic type: {}", full_type_str);
                 return None;
             }
 
             match token_type_to_wave_type(&parsed_type.unwrap()) {
                 Some(wt) => wt,
                 None => {
-                    println!("Failed to convert to WaveType: {}", full_type_str);
+                    parse_error!("Failed to convert to WaveType: {}", full_type_str);
                     return None;
                 }
             }
@@ -471,7 +830,7 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
             match parse_type(&name).and_then(|tt| token_type_to_wave_type(&tt)) {
                 Some(wt) => wt,
                 None => {
-                    println!("Unknown type: {}", name);
+                    parse_error!("Unknown type: {}", name);
                     return None;
                 }
             }
@@ -480,7 +839,7 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
         match token_type_to_wave_type(&type_token.token_type) {
             Some(t) => t,
             None => {
-                println!("Unknown or unsupported type: {}", type_token.lexeme);
+                parse_error!("Unknown or unsupported type: {}", type_token.lexeme);
                 return None;
             }
         }
@@ -488,7 +847,7 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
 
     let initial_value = if let Some(Token { token_type: TokenType::Equal, .. }) = tokens.peek() {
         tokens.next(); // consume '='
-        let expr = parse_expression(tokens)?;
+        let expr = parse_value(tokens)?;
         Some(expr)
     } else {
         None
@@ -500,7 +859,7 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
 
     if let (WaveType::Array(_, expected_len), Some(Expression::ArrayLiteral(elements))) = (&wave_type, &initial_value) {
         if *expected_len != elements.len() as u32 {
-            println!(
+            parse_error!(
                 "❌ Error: Array length mismatch. Expected {}, but got {} elements",
                 expected_len,
                 elements.len()
@@ -514,6 +873,8 @@ fn parse_var(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
         type_name: wave_type,
         initial_value,
         mutability,
+        attributes: vec![],
+        span: Span::default(),
     }))
 }
 
@@ -528,26 +889,72 @@ fn parse_let(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
     let name = match tokens.next() {
         Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
         _ => {
-            println!("Expected identifier after `let`");
+            parse_error!("Expected identifier after `let`");
             return None;
         }
     };
 
-    if !matches!(tokens.next().map(|t| &t.token_type), Some(TokenType::Colon)) {
-        println!("Expected ':' after identifier");
-        return None;
+    // `let x = expr;` leaves the type to be inferred from the initializer
+    let wave_type = match tokens.next().map(|t| &t.token_type) {
+        Some(TokenType::Colon) => parse_type_annotation(tokens)?,
+        Some(TokenType::Equal) => WaveType::Inferred,
+        _ => {
+            parse_error!("Expected ':' or '=' after identifier");
+            return None;
+        }
+    };
+
+    let initial_value = if matches!(wave_type, WaveType::Inferred) {
+        Some(parse_value(tokens)?)
+    } else if let Some(Token { token_type: TokenType::Equal, .. }) = tokens.peek() {
+        tokens.next(); // consume '='
+        let expr = parse_value(tokens)?; // 반드시 expression 파서 있어야 함
+        Some(expr)
+    } else {
+        None
+    };
+
+    if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
+        tokens.next(); // Consume ';'
     }
 
+    if let (WaveType::Array(_, expected_len), Some(Expression::ArrayLiteral(elements))) = (&wave_type, &initial_value) {
+        if *expected_len != elements.len() as u32 {
+            parse_error!(
+                "❌ Error: Array length mismatch. Expected {}, but got {} elements",
+                expected_len,
+                elements.len()
+            );
+            return None;
+        }
+    }
+
+    Some(ASTNode::Variable(VariableNode {
+        name,
+        type_name: wave_type,
+        initial_value,
+        mutability,
+        attributes: vec![],
+        span: Span::default(),
+    }))
+}
+
+// A type after the ':' of a `let` binding, parameter or struct field, or the '->' of a function
+fn parse_type_annotation(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<WaveType> {
     let type_token = match tokens.next() {
         Some(token) => token.clone(),
         _ => {
-            println!("Expected type after ':'");
+            parse_error!("Expected type after ':'");
             return None;
         }
     };
 
-    let wave_type = if let TokenType::Identifier(ref name) = type_token.token_type {
-        if let Some(Token { token_type: TokenType::Lchevr, .. }) = tokens.peek() {
+    let wave_type = if type_token.token_type == TokenType::Fun {
+        parse_function_type(tokens)?
+    } else if let TokenType::Identifier(ref name) = type_token.token_type {
+        if name == "typeof" {
+            parse_typeof(tokens)?
+        } else if let Some(Token { token_type: TokenType::Lchevr, .. }) = tokens.peek() {
             tokens.next(); // consume '<'
 
             let mut inner = String::new();
@@ -575,14 +982,14 @@ fn parse_let(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
             let parsed_type = parse_type(&full_type_str);
 
             if parsed_type.is_none() {
-                println!("Unknown generic type: {}", full_type_str);
+                parse_error!("Unknown generic type: {}", full_type_str);
                 return None;
             }
 
             match token_type_to_wave_type(&parsed_type.unwrap()) {
                 Some(wt) => wt,
                 None => {
-                    println!("Failed to convert to WaveType: {}", full_type_str);
+                    parse_error!("Failed to convert to WaveType: {}", full_type_str);
                     return None;
                 }
             }
@@ -590,7 +997,7 @@ fn parse_let(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
             match parse_type(&name).and_then(|tt| token_type_to_wave_type(&tt)) {
                 Some(wt) => wt,
                 None => {
-                    println!("Unknown type: {}", name);
+                    parse_error!("Unknown type: {}", name);
                     return None;
                 }
             }
@@ -599,112 +1006,234 @@ fn parse_let(tokens: &mut Peekable<Iter<'_, Token>>) -> Option<ASTNode> {
         match token_type_to_wave_type(&type_token.token_type) {
             Some(t) => t,
             None => {
-                println!("Unknown or unsupported type: {}", type_token.lexeme);
+                parse_error!("Unknown or unsupported type: {}", type_token.lexeme);
                 return None;
             }
         }
     };
 
-    let initial_value = if let Some(Token { token_type: TokenType::Equal, .. }) = tokens.peek() {
-        tokens.next(); // consume '='
-        let expr = parse_expression(tokens)?; // 반드시 expression 파서 있어야 함
-        Some(expr)
-    } else {
-        None
-    };
+    Some(wave_type)
+}
 
-    if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
-        tokens.next(); // Consume ';'
+// fun(type, ...) -> type in a type position, the `fun` already consumed; without `->` the
+// function returns nothing
+fn parse_function_type(tokens: &mut Peekable<Iter<Token>>) -> Option<WaveType> {
+    if tokens.next()?.token_type != TokenType::Lparen {
+        parse_error!("Error: Expected '(' after 'fun' in a function type");
+        return None;
     }
 
-    if let (WaveType::Array(_, expected_len), Some(Expression::ArrayLiteral(elements))) = (&wave_type, &initial_value) {
-        if *expected_len != elements.len() as u32 {
-            println!(
-                "❌ Error: Array length mismatch. Expected {}, but got {} elements",
-                expected_len,
-                elements.len()
-            );
-            return None;
+    let mut params = vec![];
+    if tokens.peek()?.token_type == TokenType::Rparen {
+        tokens.next(); // consume ')'
+    } else {
+        loop {
+            params.push(parse_type_annotation(tokens)?);
+            match tokens.next().map(|t| &t.token_type) {
+                Some(TokenType::Comma) => continue,
+                Some(TokenType::Rparen) => break,
+                _ => {
+                    parse_error!("Error: Expected ',' or ')' in the parameters of a function type");
+                    return None;
+                }
+            }
         }
     }
 
-    Some(ASTNode::Variable(VariableNode {
-        name,
-        type_name: wave_type,
-        initial_value,
-        mutability,
-    }))
-}
-
+    let return_type = if tokens.peek()?.token_type == TokenType::Arrow {
+        tokens.next(); // consume '->'
+        Some(Box::new(parse_type_annotation(tokens)?))
+    } else {
+        None
+    };
+    Some(WaveType::Function(params, return_type))
+}
 
-// PRINTLN parsing
-fn parse_println(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+// ATTRIBUTE parsing: #[name] or #[name(arg, ...)], the leading '#' is already consumed
+// typeof(expr) in a type position; resolved to a concrete type during codegen
+fn parse_typeof(tokens: &mut Peekable<Iter<Token>>) -> Option<WaveType> {
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'println'");
+        parse_error!("Error: Expected '(' after 'typeof'");
         return None;
     }
-    tokens.next(); // Consume '('
+    tokens.next(); // consume '('
 
-    let content = if let Some(Token { token_type: TokenType::String(content), .. }) = tokens.next() {
-        content.clone()
-    } else {
-        println!("Error: Expected string literal in 'println'");
+    let expr = parse_expression(tokens)?;
+
+    if tokens.peek()?.token_type != TokenType::Rparen {
+        parse_error!("Error: Expected ')' after typeof expression");
         return None;
-    };
+    }
+    tokens.next(); // consume ')'
 
-    let placeholder_count = Regex::new(r"\{[^}]*\}")
-        .unwrap()
-        .find_iter(&content)
-        .count();
+    Some(WaveType::TypeOf(Box::new(expr)))
+}
 
-    if placeholder_count == 0 {
-        if tokens.peek()?.token_type != TokenType::Rparen {
-            println!("Error: Expected closing ')'");
+fn parse_attributes(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<Attribute>> {
+    let mut attributes = vec![];
+
+    loop {
+        if tokens.next()?.token_type != TokenType::Lbrack {
+            parse_error!("Error: Expected '[' after '#'");
             return None;
         }
-        tokens.next(); // Consume ')'
 
-        return Some(ASTNode::Statement(StatementNode::Println(
-            format!("{}\n", content),
-        )));
-    }
+        let name = match tokens.next() {
+            Some(Token { token_type: TokenType::Identifier(name), .. }) => name.clone(),
+            other => {
+                parse_error!("Error: Expected attribute name, found {:?}", other.map(|t| &t.token_type));
+                return None;
+            }
+        };
 
-    let mut args = Vec::new();
-    while let Some(Token { token_type: TokenType::Comma, .. }) = tokens.peek() {
-        tokens.next(); // Consume ','
-        if let Some(expr) = parse_expression(tokens) {
-            args.push(expr);
+        let mut args = vec![];
+        if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
+            tokens.next(); // consume '('
+            loop {
+                match tokens.next() {
+                    Some(Token { token_type: TokenType::Rparen, .. }) => break,
+                    Some(Token { token_type: TokenType::Comma, .. }) => continue,
+                    Some(Token { token_type: TokenType::Eof, .. }) | None => {
+                        parse_error!("Error: Unterminated argument list in attribute '{}'", name);
+                        return None;
+                    }
+                    Some(token) => args.push(token.lexeme.clone()),
+                }
+            }
+        }
+
+        if tokens.next()?.token_type != TokenType::Rbrack {
+            parse_error!("Error: Expected ']' to close attribute '{}'", name);
+            return None;
+        }
+
+        attributes.push(Attribute { name, args });
+
+        if let Some(Token { token_type: TokenType::Hash, .. }) = tokens.peek() {
+            tokens.next(); // consume '#'
         } else {
-            println!("Error: Failed to parse expression in 'println'");
+            break;
+        }
+    }
+
+    Some(attributes)
+}
+
+fn parse_attributed_declaration(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let attributes = parse_attributes(tokens)?;
+
+    let node = match tokens.next().map(|t| &t.token_type) {
+        Some(TokenType::Var) => parse_var(tokens)?,
+        Some(TokenType::Let) => parse_let(tokens)?,
+        other => {
+            parse_error!("Error: Expected variable declaration after attributes, found {:?}", other);
             return None;
         }
+    };
+
+    let ASTNode::Variable(mut variable) = node else {
+        return None;
+    };
+
+    for attribute in &attributes {
+        match attribute.name.as_str() {
+            "align" => {
+                let is_valid = attribute.args.len() == 1
+                    && attribute.args[0].parse::<u32>().is_ok_and(|n| n.is_power_of_two());
+                if !is_valid {
+                    parse_error!("Error: #[align(n)] on '{}' expects a single power-of-two byte count", variable.name);
+                    return None;
+                }
+            }
+            _ => {
+                parse_error!("Error: Unknown variable attribute '{}'", attribute.name);
+                return None;
+            }
+        }
     }
 
-    if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected closing ')'");
+    variable.attributes = attributes;
+    Some(ASTNode::Variable(variable))
+}
+
+fn parse_attributed_function(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let attributes = parse_attributes(tokens)?;
+
+    if tokens.peek()?.token_type != TokenType::Fun {
+        parse_error!("Error: Expected function after attributes, found {:?}", tokens.peek().map(|t| &t.token_type));
         return None;
     }
-    tokens.next(); // Consume ')'
 
-    if placeholder_count != args.len() {
-        println!(
-            "Error: Expected {} arguments, found {}",
-            placeholder_count,
-            args.len()
-        );
+    let ASTNode::Function(mut function) = parse_function(tokens)? else {
         return None;
+    };
+
+    for attribute in &attributes {
+        match attribute.name.as_str() {
+            "bench" | "ctor" | "dtor" => {
+                if !attribute.args.is_empty() {
+                    parse_error!("Error: #[{}] on '{}' takes no arguments", attribute.name, function.name);
+                    return None;
+                }
+            }
+            "allow" => {
+                if attribute.args.is_empty() {
+                    parse_error!("Error: #[allow] on '{}' expects a lint name, e.g. #[allow(recursion)]", function.name);
+                    return None;
+                }
+                if let Some(lint) = attribute.args.iter().find(|lint| !is_known_lint(lint)) {
+                    parse_error!("Error: Unknown lint '{}' in #[allow] on '{}'", lint, function.name);
+                    return None;
+                }
+            }
+            _ => {
+                parse_error!("Error: Unknown function attribute '{}'", attribute.name);
+                return None;
+            }
+        }
+    }
+
+    function.attributes = attributes;
+    Some(ASTNode::Function(function))
+}
+
+// PRINTLN parsing
+fn parse_println(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let (content, parts, args) = parse_print_arguments("println", tokens)?;
+    if args.is_empty() {
+        return Some(ASTNode::Statement(StatementNode::Println(
+            format!("{}\n", literal_text(&parts)),
+        ), Span::default()));
     }
 
     Some(ASTNode::Statement(StatementNode::PrintlnFormat {
         format: format!("{}\n", content),
         args,
-    }))
+    }, Span::default()))
 }
 
 // PRINT parsing
 fn parse_print(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let (content, parts, args) = parse_print_arguments("print", tokens)?;
+    if args.is_empty() {
+        // No format → Print just a string
+        return Some(ASTNode::Statement(StatementNode::Print(
+            literal_text(&parts),
+        ), Span::default()));
+    }
+
+    Some(ASTNode::Statement(StatementNode::PrintFormat {
+        format: content,
+        args,
+    }, Span::default()))
+}
+
+// `("format", args...)` after `print` or `println`: the format as written, its parts, and one
+// argument for each placeholder, in order. A named placeholder, `{count}`, takes the variable
+// of that name; the others take the arguments given.
+fn parse_print_arguments(keyword: &str, tokens: &mut Peekable<Iter<Token>>) -> Option<(String, Vec<FormatPart>, Vec<Expression>)> {
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'println'");
+        parse_error!("Error: Expected '(' after '{}'", keyword);
         return None;
     }
     tokens.next(); // Consume '('
@@ -712,59 +1241,68 @@ fn parse_print(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     let content = if let Some(Token { token_type: TokenType::String(content), .. }) = tokens.next() {
         content.clone() // Need clone() because it is String
     } else {
-        println!("Error: Expected string literal in 'println'");
+        parse_error!("Error: Expected string literal in '{}'", keyword);
         return None;
     };
-
-    let placeholder_count = Regex::new(r"\{[^}]*\}")
-        .unwrap()
-        .find_iter(&content)
-        .count();
-
-    if placeholder_count == 0 {
-        // No format → Print just a string
-        if tokens.peek()?.token_type != TokenType::Rparen {
-            println!("Error: Expected closing ')'");
+    let parts = match parse_format_string(&content) {
+        Ok(parts) => parts,
+        Err(message) => {
+            parse_error!("Error: {}", message);
             return None;
         }
-        tokens.next(); // Consume ')'
-
-        return Some(ASTNode::Statement(StatementNode::Print(
-            format!("{}", content),
-        )));
-    }
+    };
 
-    let mut args = Vec::new();
+    let mut given = Vec::new();
     while let Some(Token { token_type: TokenType::Comma, .. }) = tokens.peek() {
         tokens.next(); // Consume ','
         if let Some(expr) = parse_expression(tokens) {
-            args.push(expr);
+            given.push(expr);
         } else {
-            println!("Error: Failed to parse expression in 'println'");
+            parse_error!("Error: Failed to parse expression in '{}'", keyword);
             return None;
         }
     }
-    tokens.next();
 
     if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected closing ')'");
+        parse_error!("Error: Expected closing ')'");
         return None;
     }
     tokens.next(); // Consume ')'
 
-    if placeholder_count != args.len() {
-        println!(
+    let positional = parts
+        .iter()
+        .filter(|part| matches!(part, FormatPart::Placeholder(None, _)))
+        .count();
+    if positional != given.len() {
+        parse_error!(
             "Error: Expected {} arguments, found {}",
-            placeholder_count,
-            args.len()
+            positional,
+            given.len()
         );
         return None;
     }
 
-    Some(ASTNode::Statement(StatementNode::PrintFormat {
-        format: content,
-        args,
-    }))
+    let mut given = given.into_iter();
+    let args = parts
+        .iter()
+        .filter_map(|part| match part {
+            FormatPart::Placeholder(Some(name), _) => Some(Expression::Variable(name.clone())),
+            FormatPart::Placeholder(None, _) => given.next(),
+            FormatPart::Literal(_) => None,
+        })
+        .collect();
+    Some((content, parts, args))
+}
+
+// The text of a format string without placeholders.
+fn literal_text(parts: &[FormatPart]) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            FormatPart::Literal(text) => text.as_str(),
+            FormatPart::Placeholder(..) => "",
+        })
+        .collect()
 }
 
 fn skip_whitespace(tokens: &mut Peekable<Iter<Token>>) {
@@ -780,7 +1318,7 @@ fn skip_whitespace(tokens: &mut Peekable<Iter<Token>>) {
 // IF parsing
 fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'if'");
+        parse_error!("Error: Expected '(' after 'if'");
         return None;
     }
     tokens.next(); // Consume '('
@@ -795,14 +1333,14 @@ fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     };
 
     if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected ')' after 'if' condition");
+        parse_error!("Error: Expected ')' after 'if' condition");
         return None;
     }
     tokens.next(); // Consume ')'
 
     // Expect '{' after condition
     if tokens.peek()?.token_type != TokenType::Lbrace {
-        println!("Error: Expected '{{' after 'if' condition");
+        parse_error!("Error: Expected '{{' after 'if' condition");
         return None;
     }
     tokens.next(); // Consume '{'
@@ -819,13 +1357,14 @@ fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
         tokens.next(); // Consume 'else'
 
         // Check if it comes right after else
-        if let Some(Token { token_type: TokenType::If, .. }) = tokens.peek() {
+        if let Some(Token { token_type: TokenType::If, line, column, .. }) = tokens.peek() {
+            let span = Span::new(*line, *column);
             tokens.next();
             let parsed = parse_if(tokens);
 
             match parsed {
-                Some(ASTNode::Statement(stmt @ StatementNode::If { .. })) => {
-                    else_if_blocks.push(ASTNode::Statement(stmt));
+                Some(ASTNode::Statement(stmt @ StatementNode::If { .. }, _)) => {
+                    else_if_blocks.push(ASTNode::Statement(stmt, span));
                 }
                 Some(other) => {
                     return None;
@@ -840,7 +1379,7 @@ fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
 
         // Handle 'else' case
         if tokens.peek()?.token_type != TokenType::Lbrace {
-            println!("Error: Expected '{{' after 'else'");
+            parse_error!("Error: Expected '{{' after 'else'");
             return None;
         }
         tokens.next(); // Consume '{'
@@ -857,47 +1396,110 @@ fn parse_if(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
             Some(Box::new(else_if_blocks))
         },
         else_block,
-    });
+    }, Span::default());
 
     Some(result)
 }
 
-// FOR parsing
+// FOR parsing: `for (initialization; condition; increment) { body }` or `for i in start..end { body }`
 fn parse_for(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
-    /*
-    // Check 'for' keyword and see if there is '()
+    if let Some(Token { token_type: TokenType::Identifier(_), .. }) = tokens.peek() {
+        return parse_for_range(tokens);
+    }
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'if'");
+        parse_error!("Error: Expected '(' after 'for'");
+        return None;
+    }
+    tokens.next(); // Consume '('
+
+    let initialization = parse_expression(tokens)?;
+    if tokens.next()?.token_type != TokenType::SemiColon {
+        parse_error!("Error: Expected ';' after 'for' initialization");
         return None;
     }
-    tokens.next(); // '(' Consumption
 
-    // Conditional parsing (where condition must be made ASTNode)
-    let initialization = parse_expression(tokens)?; // Parsing conditions with expressions
     let condition = parse_expression(tokens)?;
-    let increment = parse_expression(tokens)?;
-    let body = parse_expression(tokens)?;
+    if tokens.next()?.token_type != TokenType::SemiColon {
+        parse_error!("Error: Expected ';' after 'for' condition");
+        return None;
+    }
 
+    let increment = parse_expression(tokens)?;
     if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected ')' after condition");
+        parse_error!("Error: Expected ')' after 'for' increment");
         return None;
     }
-    tokens.next(); // ')' Consumption
+    tokens.next(); // Consume ')'
 
-    Some(ASTNode::Statement(StatementNode::For {
-        initialization,
-        condition,
-        increment,
-        body,
-    }))
-     */
-    None
+    if tokens.peek()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after 'for'");
+        return None;
+    }
+    tokens.next(); // Consume '{'
+
+    let body = parse_block(tokens)?;
+
+    Some(ASTNode::Statement(StatementNode::For { initialization, condition, increment, body, label: None }, Span::default()))
+}
+
+fn parse_for_range(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    let Some(Token { token_type: TokenType::Identifier(variable), .. }) = tokens.next() else {
+        return None;
+    };
+    if tokens.next()?.token_type != TokenType::In {
+        parse_error!("Error: Expected 'in' after '{}' in 'for'", variable);
+        return None;
+    }
+
+    let start = parse_range_bound(tokens)?;
+    if tokens.next()?.token_type != TokenType::DotDot {
+        parse_error!("Error: Expected '..' in the range of 'for'");
+        return None;
+    }
+    let end = parse_range_bound(tokens)?;
+
+    if tokens.peek()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after the range of 'for'");
+        return None;
+    }
+    tokens.next(); // Consume '{'
+
+    let body = parse_block(tokens)?;
+
+    Some(ASTNode::Statement(
+        StatementNode::ForRange { variable: variable.clone(), start, end, body, label: None },
+        Span::default(),
+    ))
+}
+
+// A bound ends at the `..` or at the `{` of the body, so `0..n {` is not read as a struct literal.
+fn parse_range_bound(tokens: &mut Peekable<Iter<Token>>) -> Option<Expression> {
+    let mut bound = vec![];
+    let mut depth = 0usize;
+    while let Some(&token) = tokens.peek() {
+        match token.token_type {
+            TokenType::Lparen | TokenType::Lbrack => depth += 1,
+            TokenType::Rparen | TokenType::Rbrack => depth = depth.saturating_sub(1),
+            TokenType::DotDot | TokenType::Lbrace | TokenType::Eof if depth == 0 => break,
+            _ => {}
+        }
+        bound.push(token);
+        tokens.next();
+    }
+
+    let mut bound = bound.into_iter().peekable();
+    let expr = parse_expression(&mut bound)?;
+    if let Some(token) = bound.next() {
+        parse_error!("Error: Unexpected '{}' in the range of 'for'", token.lexeme);
+        return None;
+    }
+    Some(expr)
 }
 
 // WHILE parsing
 fn parse_while(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'while'");
+        parse_error!("Error: Expected '(' after 'while'");
         return None;
     }
     tokens.next(); // Consume '('
@@ -905,25 +1507,68 @@ fn parse_while(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     let condition = parse_expression(tokens)?;
 
     if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected ')' after 'while' condition");
+        parse_error!("Error: Expected ')' after 'while' condition");
         return None;
     }
     tokens.next(); // Consume ')'
 
     if tokens.peek()?.token_type != TokenType::Lbrace {
-        println!("Error: Expected '{{' after 'while'");
+        parse_error!("Error: Expected '{{' after 'while'");
         return None;
     }
     tokens.next(); // Consume '{'
 
     let body = parse_block(tokens)?;
 
-    Some(ASTNode::Statement(StatementNode::While { condition, body }))
+    Some(ASTNode::Statement(StatementNode::While { condition, body, label: None }, Span::default()))
 }
 
-fn parse_import(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+// LOOP parsing: `loop { body }`
+fn parse_loop(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    if tokens.peek()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after 'loop'");
+        return None;
+    }
+    tokens.next(); // Consume '{'
+
+    let body = parse_block(tokens)?;
+
+    Some(ASTNode::Statement(StatementNode::Loop { body, label: None }, Span::default()))
+}
+
+// LABELED LOOP parsing: `'label: ` before a `while`, `for` or `loop`
+fn parse_labeled_loop(tokens: &mut Peekable<Iter<Token>>, label: &str) -> Option<ASTNode> {
+    if tokens.peek()?.token_type != TokenType::Colon {
+        parse_error!("Error: Expected ':' after label '{}", label);
+        return None;
+    }
+    tokens.next(); // Consume ':'
+
+    let mut node = match tokens.next()?.token_type {
+        TokenType::While => parse_while(tokens)?,
+        TokenType::For => parse_for(tokens)?,
+        TokenType::Loop => parse_loop(tokens)?,
+        _ => {
+            parse_error!("Error: Expected 'while', 'for' or 'loop' after label '{}", label);
+            return None;
+        }
+    };
+    if let ASTNode::Statement(
+        StatementNode::While { label: slot, .. }
+        | StatementNode::For { label: slot, .. }
+        | StatementNode::ForRange { label: slot, .. }
+        | StatementNode::Loop { label: slot, .. },
+        _,
+    ) = &mut node
+    {
+        *slot = Some(label.to_string());
+    }
+    Some(node)
+}
+
+fn parse_import(tokens: &mut Peekable<Iter<Token>>, span: Span) -> Option<ASTNode> {
     if tokens.peek()?.token_type != TokenType::Lparen {
-        println!("Error: Expected '(' after 'import'");
+        parse_error!("Error: Expected '(' after 'import'");
         return None;
     }
     tokens.next();
@@ -931,29 +1576,29 @@ fn parse_import(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     let import_path = match tokens.next() {
         Some(Token { token_type: TokenType::String(s), .. }) => s.clone(),
         other => {
-            println!("Error: Expected string literal in import, found {:?}", other);
+            parse_error!("Error: Expected string literal in import, found {:?}", other);
             return None;
         }
     };
 
     if tokens.peek()?.token_type != TokenType::Rparen {
-        println!("Error: Expected ')' after 'import' condition");
+        parse_error!("Error: Expected ')' after 'import' condition");
         return None;
     }
     tokens.next();
 
     if tokens.peek()?.token_type != TokenType::SemiColon {
-        println!("Error: Expected ';' after 'import' condition");
+        parse_error!("Error: Expected ';' after 'import' condition");
         return None;
     }
     tokens.next();
 
-    Some(ASTNode::Statement(StatementNode::Import(import_path)))
+    Some(ASTNode::Statement(StatementNode::Import(import_path), span))
 }
 
 fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
     if tokens.peek()?.token_type != TokenType::Lbrace {
-        println!("Expected '{{' after 'asm'");
+        parse_error!("Expected '{{' after 'asm'");
         return None;
     }
     tokens.next();
@@ -970,7 +1615,7 @@ fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
                 let is_input = matches!(token.token_type, TokenType::In);
 
                 if tokens.next().map(|t| t.token_type.clone()) != Some(TokenType::Lparen) {
-                    println!("Expected '(' after in/out");
+                    parse_error!("Expected '(' after in/out");
                     return None;
                 }
 
@@ -979,17 +1624,17 @@ fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
                     Some(Token { token_type: TokenType::String(s), .. }) => s.clone(),
                     Some(Token { token_type: TokenType::Identifier(s), .. }) => s.clone(),
                     Some(other) => {
-                        println!("Expected register string or identifier, got {:?}", other.token_type);
+                        parse_error!("Expected register string or identifier, got {:?}", other.token_type);
                         return None;
                     }
                     None => {
-                        println!("Expected register in in/out(...)");
+                        parse_error!("Expected register in in/out(...)");
                         return None;
                     }
                 };
 
                 if tokens.next().map(|t| t.token_type.clone()) != Some(TokenType::Rparen) {
-                    println!("Expected ')' after in/out");
+                    parse_error!("Expected ')' after in/out");
                     return None;
                 }
 
@@ -999,11 +1644,11 @@ fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
                     Some(Token { token_type: TokenType::Number(n), .. }) => n.to_string(),
                     Some(Token { token_type: TokenType::String(n), .. }) => n.to_string(),
                     Some(other) => {
-                        println!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
+                        parse_error!("Expected identifier or number after in/out(...), got {:?}", other.token_type);
                         return None;
                     }
                     None => {
-                        println!("Expected value after in/out(...)");
+                        parse_error!("Expected value after in/out(...)");
                         return None;
                     }
                 };
@@ -1020,7 +1665,7 @@ fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
             }
 
             other => {
-                println!("Unexpected token in asm expression {:?}", other);
+                parse_error!("Unexpected token in asm expression {:?}", other);
             }
         }
     }
@@ -1029,18 +1674,31 @@ fn parse_asm_block(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
         instructions,
         inputs,
         outputs,
-    }))
+    }, Span::default()))
 }
 
 fn parse_assignment(tokens: &mut Peekable<Iter<Token>>, first_token: &Token) -> Option<ASTNode> {
-    let left_expr = match parse_expression_from_token(first_token, tokens) {
+    let mut left_expr = match parse_expression_from_token(first_token, tokens) {
         Some(expr) => expr,
         None => {
-            println!("Error: Failed to parse left-hand side of assignment. Token: {:?}", first_token.token_type);
+            parse_error!("Error: Failed to parse left-hand side of assignment. Token: {:?}", first_token.token_type);
             return None;
         }
     };
 
+    while let Some(Token { token_type: TokenType::Dot, .. }) = tokens.peek() {
+        tokens.next(); // consume '.'
+        match tokens.next() {
+            Some(Token { token_type: TokenType::Identifier(field), .. }) => {
+                left_expr = Expression::FieldAccess { target: Box::new(left_expr), field: field.clone() };
+            }
+            _ => {
+                parse_error!("Error: Expected a field name after '.'");
+                return None;
+            }
+        }
+    }
+
     let assign_op = match tokens.peek()?.token_type {
         TokenType::PlusEq => {
             tokens.next();
@@ -1069,7 +1727,7 @@ fn parse_assignment(tokens: &mut Peekable<Iter<Token>>, first_token: &Token) ->
         _ => return None,
     };
 
-    let right_expr = parse_expression(tokens)?;
+    let right_expr = parse_value(tokens)?;
 
     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
         tokens.next();
@@ -1084,7 +1742,7 @@ fn parse_assignment(tokens: &mut Peekable<Iter<Token>>, first_token: &Token) ->
         (None, Expression::Variable(name)) => Some(ASTNode::Statement(StatementNode::Assign {
             variable: name.clone(),
             value: right_expr,
-        })),
+        }, Span::default())),
         (None, Expression::Deref(_)) => Some(ASTNode::Statement(StatementNode::Assign {
             variable: "deref".to_string(),
             value: Expression::BinaryExpression {
@@ -1092,14 +1750,177 @@ fn parse_assignment(tokens: &mut Peekable<Iter<Token>>, first_token: &Token) ->
                 operator: Operator::Assign,
                 right: Box::new(right_expr),
             },
-        })),
+        }, Span::default())),
+        (op, Expression::FieldAccess { .. }) => Some(ASTNode::Statement(StatementNode::Expression(Expression::AssignOperation {
+            target: Box::new(left_expr),
+            operator: op.unwrap_or(AssignOperator::Assign),
+            value: Box::new(right_expr),
+        }), Span::default())),
         (_, _) => {
-            println!("Error: Unsupported assignment left expression: {:?}", left_expr);
+            parse_error!("Error: Unsupported assignment left expression: {:?}", left_expr);
+            None
+        }
+    }
+}
+
+/// The value of an initializer, assignment, `return` or `break`: an expression, or a `while`
+/// or `loop`, possibly labeled, whose value is the one its `break` passes out.
+fn parse_value(tokens: &mut Peekable<Iter<Token>>) -> Option<Expression> {
+    let node = match &tokens.peek()?.token_type {
+        TokenType::While => {
+            tokens.next(); // consume 'while'
+            parse_while(tokens)?
+        }
+        TokenType::Loop => {
+            tokens.next(); // consume 'loop'
+            parse_loop(tokens)?
+        }
+        TokenType::Label(label) => {
+            tokens.next(); // consume the label
+            parse_labeled_loop(tokens, label)?
+        }
+        _ => return parse_expression(tokens),
+    };
+    match node {
+        ASTNode::Statement(StatementNode::While { condition, body, label }, _) => {
+            Some(Expression::While { condition: Box::new(condition), body, label })
+        }
+        ASTNode::Statement(StatementNode::Loop { body, label }, _) => Some(Expression::Loop { body, label }),
+        _ => {
+            parse_error!("Error: A 'for' loop cannot be used as a value");
+            None
+        }
+    }
+}
+
+// The label after `break` or `continue`, if it names one.
+fn parse_label(tokens: &mut Peekable<Iter<Token>>) -> Option<String> {
+    match tokens.peek() {
+        Some(Token { token_type: TokenType::Label(label), .. }) => {
+            tokens.next(); // consume the label
+            Some(label.clone())
+        }
+        _ => None,
+    }
+}
+
+// What follows `break`: nothing, or the value the loop produces.
+fn parse_break_value(tokens: &mut Peekable<Iter<Token>>) -> Option<Option<Expression>> {
+    let value = match tokens.peek().map(|t| &t.token_type) {
+        Some(TokenType::SemiColon | TokenType::Rbrace) => None,
+        _ => Some(parse_value(tokens)?),
+    };
+    if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
+        tokens.next(); // consume ';'
+    }
+    Some(value)
+}
+// MATCH parsing: `match (value) { pattern => { body } ... }`
+fn parse_match(tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode> {
+    if tokens.peek()?.token_type != TokenType::Lparen {
+        parse_error!("Error: Expected '(' after 'match'");
+        return None;
+    }
+    tokens.next(); // Consume '('
+
+    let value = parse_expression(tokens)?;
+
+    if tokens.peek()?.token_type != TokenType::Rparen {
+        parse_error!("Error: Expected ')' after 'match' value");
+        return None;
+    }
+    tokens.next(); // Consume ')'
+
+    if tokens.peek()?.token_type != TokenType::Lbrace {
+        parse_error!("Error: Expected '{{' after 'match'");
+        return None;
+    }
+    tokens.next(); // Consume '{'
+
+    let mut arms = vec![];
+    loop {
+        if let Some(Token { token_type: TokenType::Rbrace, .. }) = tokens.peek() {
+            tokens.next(); // Consume '}'
+            break;
+        }
+
+        let pattern = parse_pattern(tokens)?;
+        if tokens.next()?.token_type != TokenType::FatArrow {
+            parse_error!("Error: Expected '=>' after a 'match' pattern");
+            return None;
+        }
+        if tokens.next()?.token_type != TokenType::Lbrace {
+            parse_error!("Error: Expected '{{' after '=>'");
+            return None;
+        }
+        let body = parse_block(tokens)?;
+        arms.push(MatchArm { pattern, body });
+
+        if let Some(Token { token_type: TokenType::Comma, .. }) = tokens.peek() {
+            tokens.next(); // Consume ','
+        }
+    }
+
+    Some(ASTNode::Statement(StatementNode::Match { value, arms }, Span::default()))
+}
+
+// PATTERN parsing: `Name::Variant(a, b)`, `Name::Variant`, an integer or `_`
+fn parse_pattern(tokens: &mut Peekable<Iter<Token>>) -> Option<Pattern> {
+    match &tokens.next()?.token_type {
+        TokenType::Number(value) => Some(Pattern::Number(*value)),
+        TokenType::Identifier(name) if name == "_" => Some(Pattern::Wildcard),
+        TokenType::Identifier(enum_name) => {
+            if tokens.next()?.token_type != TokenType::ColonColon {
+                parse_error!("Error: Expected '::' after '{}' in a pattern", enum_name);
+                return None;
+            }
+            let variant = match &tokens.next()?.token_type {
+                TokenType::Identifier(variant) => variant.clone(),
+                _ => {
+                    parse_error!("Error: Expected a variant after '{}::'", enum_name);
+                    return None;
+                }
+            };
+
+            let mut bindings = vec![];
+            if let Some(Token { token_type: TokenType::Lparen, .. }) = tokens.peek() {
+                tokens.next(); // Consume '('
+                loop {
+                    match &tokens.next()?.token_type {
+                        TokenType::Rparen => break,
+                        TokenType::Identifier(binding) => bindings.push(binding.clone()),
+                        other => {
+                            parse_error!("Error: Expected a name in the fields of '{}::{}', found {:?}", enum_name, variant, other);
+                            return None;
+                        }
+                    }
+                    if let Some(Token { token_type: TokenType::Comma, .. }) = tokens.peek() {
+                        tokens.next(); // Consume ','
+                    }
+                }
+            }
+
+            Some(Pattern::Variant { enum_name: enum_name.clone(), variant, bindings })
+        }
+        other => {
+            parse_error!("Error: Expected a pattern, found {:?}", other);
             None
         }
     }
 }
 
+/// Records where a statement or declaration starts; nodes without a span are left as-is.
+fn with_span(node: ASTNode, span: Span) -> ASTNode {
+    match node {
+        ASTNode::Statement(stmt, _) => ASTNode::Statement(stmt, span),
+        ASTNode::Variable(mut var) => {
+            var.span = span;
+            ASTNode::Variable(var)
+        }
+        other => other,
+    }
+}
+
 // block parsing
 fn parse_block(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>> {
     let mut body = vec![];
@@ -1109,54 +1930,58 @@ fn parse_block(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>> {
             break;
         }
 
+        let span = Span::new(token.line, token.column);
         let node = match token.token_type {
+            TokenType::Hash => parse_attributed_declaration(tokens),
             TokenType::Var => parse_var(tokens),
             TokenType::Println => parse_println(tokens),
             TokenType::Print => parse_print(tokens),
             TokenType::If => parse_if(tokens),
             TokenType::For => parse_for(tokens),
             TokenType::While => parse_while(tokens),
+            TokenType::Loop => parse_loop(tokens),
+            TokenType::Label(ref label) => parse_labeled_loop(tokens, label),
+            TokenType::Match => parse_match(tokens),
             TokenType::Identifier(_) => parse_assignment(tokens, token),
             TokenType::Break => {
-                if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
-                    tokens.next();
-                }
-                Some(ASTNode::Statement(StatementNode::Break))
+                let label = parse_label(tokens);
+                parse_break_value(tokens).map(|value| ASTNode::Statement(StatementNode::Break { label, value }, span))
             }
             TokenType::Continue => {
+                let label = parse_label(tokens);
                 if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                     tokens.next();
                 }
-                Some(ASTNode::Statement(StatementNode::Continue))
+                Some(ASTNode::Statement(StatementNode::Continue { label }, span))
             }
             TokenType::Return => {
                 let expr = if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                     tokens.next(); // consume ;
                     None
                 } else {
-                    let value = parse_expression(tokens)?;
+                    let value = parse_value(tokens)?;
                     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                         tokens.next(); // consume ;
                     }
                     Some(value)
                 };
-                Some(ASTNode::Statement(StatementNode::Return(expr)))
+                Some(ASTNode::Statement(StatementNode::Return(expr), span))
             }
             _ => {
                 if let Some(expr) = parse_expression(tokens) {
                     if let Some(Token { token_type: TokenType::SemiColon, .. }) = tokens.peek() {
                         tokens.next();
                     }
-                    Some(ASTNode::Statement(StatementNode::Expression(expr)))
+                    Some(ASTNode::Statement(StatementNode::Expression(expr), span))
                 } else {
-                    println!("Error: Expected primary expression, found {:?}", token.token_type);
+                    parse_error!("Error: Expected primary expression, found {:?}", token.token_type);
                     None
                 }
             }
         };
 
         if let Some(ast_node) = node {
-            body.push(ast_node);
+            body.push(with_span(ast_node, span));
         }
     }
 
@@ -1166,6 +1991,20 @@ fn parse_block(tokens: &mut Peekable<Iter<Token>>) -> Option<Vec<ASTNode>> {
 pub fn parse_type(type_str: &str) -> Option<TokenType> {
     let type_str = type_str.trim();
 
+    // `fun(i32,i32)->i32`, as the lexemes of a function type inside `ptr<..>` or `array<..>` join up
+    if let Some(rest) = type_str.strip_prefix("fun(") {
+        let close = matching_paren(rest)?;
+        let params = split_top_level(&rest[..close])
+            .into_iter()
+            .map(parse_type)
+            .collect::<Option<Vec<_>>>()?;
+        let return_type = match rest[close + 1..].trim() {
+            "" => None,
+            ret => Some(Box::new(parse_type(ret.strip_prefix("->")?)?)),
+        };
+        return Some(TokenType::TypeFunction(params, return_type));
+    }
+
     if let Some(lt_index) = type_str.find('<') {
         if !type_str.ends_with('>') {
             return None;
@@ -1175,27 +2014,12 @@ pub fn parse_type(type_str: &str) -> Option<TokenType> {
         let inner = &type_str[lt_index + 1..type_str.len() - 1];
 
         if base == "array" {
-            let mut depth = 0;
-            let mut split_pos = None;
-
-            for (i, c) in inner.char_indices() {
-                match c {
-                    '<' => depth += 1,
-                    '>' => depth -= 1,
-                    ',' if depth == 0 => {
-                        split_pos = Some(i);
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-
-            let split_pos = split_pos?;
-            let elem_type_str = inner[..split_pos].trim();
-            let size_str = inner[split_pos + 1..].trim();
+            let [elem_type_str, size_str] = split_top_level(inner)[..] else {
+                return None;
+            };
 
             let elem_type = parse_type(elem_type_str)?;
-            let size = size_str.parse::<u32>().ok()?;
+            let size = array_size(size_str)?;
 
             return Some(TokenType::TypeArray(Box::new(elem_type), size));
         }
@@ -1208,14 +2032,17 @@ pub fn parse_type(type_str: &str) -> Option<TokenType> {
         return None;
     }
 
-    if type_str.starts_with('i') {
-        let bits = type_str[1..].parse::<u16>().ok()?;
+    let bits = |prefix: char| type_str.strip_prefix(prefix).and_then(|bits| bits.parse::<u16>().ok());
+
+    if type_str == "isize" || type_str == "isz" {
+        return Some(TokenType::TokenTypeInt(IntegerType::ISZ));
+    } else if type_str == "usize" || type_str == "usz" {
+        return Some(TokenType::TokenTypeUint(UnsignedIntegerType::USZ));
+    } else if let Some(bits) = bits('i') {
         return Some(TokenType::TypeInt(bits));
-    } else if type_str.starts_with('u') {
-        let bits = type_str[1..].parse::<u16>().ok()?;
+    } else if let Some(bits) = bits('u') {
         return Some(TokenType::TypeUint(bits));
-    } else if type_str.starts_with('f') {
-        let bits = type_str[1..].parse::<u16>().ok()?;
+    } else if let Some(bits) = bits('f') {
         return Some(TokenType::TypeFloat(bits));
     } else if type_str == "bool" {
         return Some(TokenType::TypeBool);
@@ -1227,9 +2054,69 @@ pub fn parse_type(type_str: &str) -> Option<TokenType> {
         return Some(TokenType::TypeString);
     }
 
+    // Any other name is a struct; code generation reports one that is not declared.
+    let is_name = type_str.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && type_str.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_name {
+        return Some(TokenType::Identifier(type_str.to_string()));
+    }
+
     None
 }
 
+// `text` split at the commas outside any `<..>` or `(..)`; the `>` of a `->` closes nothing.
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' if previous == '-' => {}
+            '>' | ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    if !text[start..].trim().is_empty() || !parts.is_empty() {
+        parts.push(text[start..].trim());
+    }
+    parts
+}
+
+// The index in `text` of the `)` that closes a `(` just before it.
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// The size of `array<T, size>`: a constant expression such as `4 * 8`.
+fn array_size(size: &str) -> Option<u32> {
+    let tokens = Lexer::new(size).tokenize();
+    let mut tokens = tokens.iter().peekable();
+    let expr = parse_expression(&mut tokens)?;
+    if !matches!(tokens.next(), None | Some(Token { token_type: TokenType::Eof, .. })) {
+        return None;
+    }
+    match evaluate(&expr)? {
+        ConstValue::Int(size) => u32::try_from(size).ok(),
+        _ => None,
+    }
+}
+
 fn validate_type(expected: &TokenType, actual: &TokenType) -> bool {
     match (expected, actual) {
         (TokenType::TypeInt(_), TokenType::TypeInt(_)) => true,
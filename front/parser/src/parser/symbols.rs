@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use lexer::{Lexer, Token, TokenType};
+use crate::ast::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Parameter,
+    /// A `var` or `let` in a function body, or a binding of a `match` arm.
+    Variable,
+}
+
+impl SymbolKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Variable => "variable",
+        }
+    }
+}
+
+/// A name the program defines, where it is defined and every place that uses it.
+/// Spans point at the first character of the name.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file: PathBuf,
+    pub span: Span,
+    pub references: Vec<(PathBuf, Span)>,
+}
+
+/// The definitions of a program and their references, for editors: find-references and
+/// semantic highlighting. Wave has no globals, so every name is a function or local to one.
+/// Built from the tokens rather than the AST, whose expressions carry no positions.
+pub struct SymbolIndex {
+    pub symbols: Vec<Symbol>,
+}
+
+// A block of names in scope, and the brace depth at which it closes.
+struct Scope {
+    depth: usize,
+    names: HashMap<String, usize>,
+}
+
+impl SymbolIndex {
+    /// Indexes `files`, each a path with its source, the main file first. Functions are
+    /// visible from every file, as imports make them.
+    pub fn build(files: &[(PathBuf, String)]) -> Self {
+        let tokens: Vec<Vec<Token>> = files
+            .iter()
+            .map(|(_, source)| {
+                Lexer::new(source)
+                    .tokenize()
+                    .into_iter()
+                    .filter(|token| token.token_type != TokenType::Whitespace)
+                    .collect()
+            })
+            .collect();
+
+        let mut symbols = vec![];
+        let mut functions = HashMap::new();
+        for ((path, _), tokens) in files.iter().zip(&tokens) {
+            let mut depth = 0usize;
+            for (i, token) in tokens.iter().enumerate() {
+                match token.token_type {
+                    TokenType::Lbrace => depth += 1,
+                    TokenType::Rbrace => depth = depth.saturating_sub(1),
+                    TokenType::Fun if depth == 0 => {
+                        // Telling overloads apart takes the types of the arguments, so the
+                        // uses of an overloaded name go to its first definition.
+                        if let Some(name) = tokens.get(i + 1).filter(|name| is_identifier(name)) {
+                            symbols.push(definition(name, SymbolKind::Function, path));
+                            functions.entry(name.lexeme.clone()).or_insert(symbols.len() - 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for ((path, _), tokens) in files.iter().zip(&tokens) {
+            index_file(path, tokens, &functions, &mut symbols);
+        }
+        SymbolIndex { symbols }
+    }
+
+    /// `{"symbols": [...]}`, each symbol with `name`, `kind`, `file`, `line`, `column` and
+    /// `references`, the `file`, `line` and `column` of every use.
+    pub fn to_json(&self) -> String {
+        let symbols: Vec<String> = self
+            .symbols
+            .iter()
+            .map(|symbol| {
+                let references: Vec<String> = symbol
+                    .references
+                    .iter()
+                    .map(|(file, span)| format!("{{{}}}", location(file, *span)))
+                    .collect();
+                format!(
+                    "{{\"name\":{},\"kind\":\"{}\",{},\"references\":[{}]}}",
+                    json_string(&symbol.name),
+                    symbol.kind.as_str(),
+                    location(&symbol.file, symbol.span),
+                    references.join(","),
+                )
+            })
+            .collect();
+        format!("{{\"symbols\":[{}]}}\n", symbols.join(",\n"))
+    }
+}
+
+// Walks one file keeping the names in scope. A parameter, the `i` of a `for` header and the
+// bindings of a `match` arm belong to the block that follows them; a `var` or `let` is in
+// scope from the end of its declaration to the end of its block.
+fn index_file(path: &Path, tokens: &[Token], functions: &HashMap<String, usize>, symbols: &mut Vec<Symbol>) {
+    let mut scopes: Vec<Scope> = vec![];
+    let mut depth = 0;
+    let mut pending: Option<(String, usize)> = None;
+    let mut in_parameters = false;
+    let mut in_for_header = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i].token_type);
+        let next = tokens.get(i + 1).map(|token| &token.token_type);
+        match &token.token_type {
+            TokenType::Fun | TokenType::For => {
+                // The parameters of an `extern` declaration have no body to close their scope.
+                if depth == 0 {
+                    scopes.clear();
+                }
+                scopes.push(Scope { depth: depth + 1, names: HashMap::new() });
+                in_parameters = token.token_type == TokenType::Fun;
+                in_for_header = token.token_type == TokenType::For;
+            }
+            TokenType::FatArrow => {
+                scopes.push(Scope { depth: depth + 1, names: HashMap::new() });
+                // The arm's bindings are the names between the `(` and `)` before the `=>`.
+                if previous == Some(&TokenType::Rparen) {
+                    let mut j = i - 1;
+                    while j > 0 && tokens[j].token_type != TokenType::Lparen {
+                        j -= 1;
+                        if is_identifier(&tokens[j]) && tokens[j].lexeme != "_" {
+                            declare(&mut scopes, &tokens[j], SymbolKind::Variable, path, symbols);
+                        }
+                    }
+                }
+            }
+            TokenType::Lbrace => {
+                depth += 1;
+                in_parameters = false;
+                in_for_header = false;
+                scopes.push(Scope { depth, names: HashMap::new() });
+            }
+            TokenType::Rbrace => {
+                while scopes.last().is_some_and(|scope| scope.depth >= depth) {
+                    scopes.pop();
+                }
+                depth = depth.saturating_sub(1);
+            }
+            TokenType::SemiColon => {
+                in_for_header = false;
+                if let Some((name, index)) = pending.take() {
+                    if let Some(scope) = scopes.last_mut() {
+                        scope.names.insert(name, index);
+                    }
+                }
+            }
+            TokenType::Identifier(name) => {
+                let defines_variable = matches!(previous, Some(TokenType::Var | TokenType::Let | TokenType::Mut));
+                if previous == Some(&TokenType::Fun) {
+                    // Defined by the first pass.
+                } else if defines_variable {
+                    symbols.push(definition(token, SymbolKind::Variable, path));
+                    pending = Some((name.clone(), symbols.len() - 1));
+                } else if in_parameters && next == Some(&TokenType::Colon) {
+                    declare(&mut scopes, token, SymbolKind::Parameter, path, symbols);
+                } else if in_for_header && matches!(next, Some(TokenType::Colon | TokenType::In)) {
+                    declare(&mut scopes, token, SymbolKind::Variable, path, symbols);
+                } else if !matches!(previous, Some(TokenType::Dot | TokenType::ColonColon)) && next != Some(&TokenType::Colon) {
+                    let found = scopes
+                        .iter()
+                        .rev()
+                        .find_map(|scope| scope.names.get(name))
+                        .or_else(|| functions.get(name));
+                    if let Some(&index) = found {
+                        symbols[index].references.push((path.to_path_buf(), Span::new(token.line, token.column)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn declare(scopes: &mut [Scope], name: &Token, kind: SymbolKind, path: &Path, symbols: &mut Vec<Symbol>) {
+    symbols.push(definition(name, kind, path));
+    if let Some(scope) = scopes.last_mut() {
+        scope.names.insert(name.lexeme.clone(), symbols.len() - 1);
+    }
+}
+
+fn definition(name: &Token, kind: SymbolKind, path: &Path) -> Symbol {
+    Symbol {
+        name: name.lexeme.clone(),
+        kind,
+        file: path.to_path_buf(),
+        span: Span::new(name.line, name.column),
+        references: vec![],
+    }
+}
+
+fn is_identifier(token: &Token) -> bool {
+    matches!(token.token_type, TokenType::Identifier(_))
+}
+
+// The `file`, `line` and `column` members of a JSON object.
+fn location(file: &Path, span: Span) -> String {
+    format!(
+        "\"file\":{},\"line\":{},\"column\":{}",
+        json_string(&file.display().to_string()),
+        span.line,
+        span.column
+    )
+}
+
+// `text` as a JSON string, quotes included.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,1445 @@
+use std::collections::HashMap;
+use std::path::Path;
+use error::error::{WaveError, WaveErrorKind};
+use error::fixes::Suggestion;
+use error::tr;
+use crate::ast::*;
+use crate::const_eval::{condition_value, evaluate, ConstValue};
+use crate::overload::{select_overload, Overload};
+use crate::parse_format_string;
+use crate::validate::{diagnostic_at, node_span};
+
+/// Resolves every expression of the program's functions to a `WaveType` and reports the
+/// mismatches code generation cannot lower. Numbers, `bool` and `char` convert into each other
+/// implicitly, with a warning, except into a narrower type, which takes an `as` cast; strings,
+/// pointers and arrays only go where a value of the same layout is expected.
+pub struct TypeChecker<'a> {
+    /// Every function by name; more than one where the name is overloaded.
+    functions: HashMap<&'a str, Vec<&'a FunctionNode>>,
+    structs: HashMap<&'a str, &'a StructNode>,
+    enums: HashMap<&'a str, &'a EnumNode>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(nodes: &'a [ASTNode]) -> Self {
+        let mut functions: HashMap<&str, Vec<&FunctionNode>> = HashMap::new();
+        for node in nodes {
+            if let ASTNode::Function(function) | ASTNode::Extern(function) = node {
+                functions.entry(function.name.as_str()).or_default().push(function);
+            }
+        }
+        let structs = nodes
+            .iter()
+            .filter_map(|node| match node {
+                ASTNode::Struct(declaration) => Some((declaration.name.as_str(), declaration)),
+                _ => None,
+            })
+            .collect();
+        let enums = nodes
+            .iter()
+            .filter_map(|node| match node {
+                ASTNode::Enum(declaration) => Some((declaration.name.as_str(), declaration)),
+                _ => None,
+            })
+            .collect();
+        TypeChecker { functions, structs, enums }
+    }
+
+    /// The types of an `extern` declaration that have no C counterpart: structs, arrays and
+    /// `str`, which C passes by value under rules not followed here, and integers of a width C
+    /// lacks. Calls to it are checked against its parameters like calls to any function.
+    pub fn check_extern(&self, function: &FunctionNode, file: &Path) -> Vec<WaveError> {
+        let return_type = function.return_type.iter().map(|ty| (ty, function.span, tr!("`{0}` is declared to return `{1}`", function.name, ty)));
+        let params = function.parameters.iter().map(|param| {
+            (&param.param_type, param.span, tr!("parameter `{0}` of `{1}` has type `{2}`", param.name, function.name, param.param_type))
+        });
+        params
+            .chain(return_type)
+            .filter(|(ty, _, _)| !is_c_type(ty))
+            .map(|(ty, span, label)| {
+                let error = diagnostic_at(
+                    WaveErrorKind::TypeMismatch(function.name.clone()),
+                    tr!("`{0}` cannot be passed to or from C, in `extern` function `{1}`", ty, function.name),
+                    file,
+                    span,
+                )
+                .with_label(label);
+                match ty {
+                    WaveType::Struct(_) | WaveType::Array(..) => error.with_help(tr!("pass a pointer instead, `ptr<{0}>`", ty)),
+                    WaveType::String => error.with_help(tr!("take a C string, `ptr<byte>`; a string literal converts to it")),
+                    _ => error,
+                }
+            })
+            .collect()
+    }
+
+    /// The type errors in the body of `function`, in source order. An expression whose type
+    /// cannot be resolved (an unknown name, a builtin call) is left to code generation, and
+    /// nothing built on top of it is checked.
+    pub fn check_function(&self, function: &FunctionNode, file: &Path) -> Vec<WaveError> {
+        self.run(function, file).errors
+    }
+
+    /// The function each use of an overloaded name in the body of `function` goes to, by name
+    /// and in the order checked: `None` where the arguments or the expected type pick none.
+    pub fn overload_choices(&self, function: &FunctionNode) -> Vec<(&'a str, Option<&'a FunctionNode>)> {
+        self.run(function, Path::new("")).overloads
+    }
+
+    fn run<'c>(&'c self, function: &'c FunctionNode, file: &'c Path) -> FunctionCheck<'c, 'a> {
+        let mut check = FunctionCheck {
+            checker: self,
+            function,
+            file,
+            variables: HashMap::new(),
+            immutable: HashMap::new(),
+            loops: vec![],
+            loop_floor: 0,
+            span: function.span,
+            errors: vec![],
+            overloads: vec![],
+        };
+        for param in &function.parameters {
+            let param_type = check.resolve(&param.param_type);
+            check.variables.insert(param.name.clone(), param_type);
+            check.immutable.insert(param.name.clone(), Immutable::Parameter);
+        }
+        check.block(&function.body);
+        check
+    }
+}
+
+struct FunctionCheck<'c, 'a> {
+    checker: &'c TypeChecker<'a>,
+    function: &'c FunctionNode,
+    file: &'c Path,
+    // As in code generation, a declaration is visible until the end of its block.
+    variables: HashMap<String, Option<WaveType>>,
+    // The names in scope that cannot be assigned to, in the same scopes as `variables`.
+    immutable: HashMap<String, Immutable>,
+    // The enclosing loops, innermost last, with their labels; `Some` for a loop used as a value.
+    loops: Vec<(Option<String>, Option<LoopValue>)>,
+    // Where the innermost loop used as a value starts in `loops`: as in code generation, none of
+    // its `break`s or `continue`s reach a loop outside it.
+    loop_floor: usize,
+    span: Span,
+    errors: Vec<WaveError>,
+    // Where each use of an overloaded name goes, as `TypeChecker::overload_choices` returns it.
+    overloads: Vec<(&'a str, Option<&'a FunctionNode>)>,
+}
+
+// Why a name cannot be assigned to. `var` and `let mut` bindings can be.
+#[derive(Clone, Copy)]
+enum Immutable {
+    // A plain `let`, declared at the span.
+    Let(Span),
+    Parameter,
+    // The counter of a `for` loop.
+    Counter,
+    // A binding of a `match` arm.
+    Binding,
+}
+
+// The type of a loop used as a value: the one its context expects, or that of its first `break`.
+struct LoopValue {
+    ty: Option<WaveType>,
+    breaks: usize,
+}
+
+impl<'a> FunctionCheck<'_, 'a> {
+    fn block(&mut self, nodes: &[ASTNode]) {
+        let outer_scope = (self.variables.clone(), self.immutable.clone());
+        for node in nodes {
+            self.span = node_span(node).unwrap_or(self.function.span);
+            self.node(node);
+        }
+        (self.variables, self.immutable) = outer_scope;
+    }
+
+    fn loop_body(&mut self, body: &[ASTNode], label: Option<&String>, value: Option<LoopValue>) -> Option<LoopValue> {
+        self.loops.push((label.cloned(), value));
+        self.block(body);
+        self.loops.pop().and_then(|(_, value)| value)
+    }
+
+    // The innermost enclosing loop named `label`, reporting it when there is none.
+    fn labeled_loop(&mut self, label: &str) -> Option<usize> {
+        let index = self.loops.iter().rposition(|(name, _)| name.as_deref() == Some(label));
+        let error = match index {
+            Some(index) if index >= self.loop_floor => return Some(index),
+            Some(_) => self
+                .error(tr!("cannot jump to `'{0}` from inside a loop used as a value", label), tr!("the loop is outside this value"))
+                .with_help(tr!("leave the inner loop with `break value;` first")),
+            None => self
+                .error(tr!("use of undeclared label `'{0}`", label), tr!("no enclosing loop has this label"))
+                .with_help(tr!("label the loop first, e.g. `'{0}: while (...) { ... }`", label)),
+        };
+        self.errors.push(error);
+        None
+    }
+
+    fn node(&mut self, node: &ASTNode) {
+        match node {
+            ASTNode::Variable(variable) => self.variable(variable),
+            ASTNode::Statement(statement, _) => self.statement(statement),
+            ASTNode::Expression(expr) => {
+                self.expression(expr, None);
+            }
+            _ => {}
+        }
+    }
+
+    fn variable(&mut self, variable: &VariableNode) {
+        let declared = match (&variable.type_name, &variable.initial_value) {
+            (WaveType::Inferred, Some(init)) => self.expression(init, None),
+            (WaveType::Inferred, None) => None,
+            (declared, init) => {
+                let declared = self.resolve(declared);
+                if let (Some(declared), Some(init)) = (&declared, init) {
+                    self.expect(init, declared, || tr!("`{0}` is declared as `{1}`", variable.name, declared));
+                }
+                declared
+            }
+        };
+        self.variables.insert(variable.name.clone(), declared);
+        match variable.mutability {
+            Mutability::Let => self.immutable.insert(variable.name.clone(), Immutable::Let(variable.span)),
+            Mutability::Var | Mutability::LetMut => self.immutable.remove(&variable.name),
+        };
+    }
+
+    fn statement(&mut self, statement: &StatementNode) {
+        match statement {
+            StatementNode::PrintFormat { format, args } | StatementNode::PrintlnFormat { format, args } => {
+                let specs: Vec<FormatSpec> = parse_format_string(format)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|part| match part {
+                        FormatPart::Placeholder(_, spec) => Some(spec),
+                        FormatPart::Literal(_) => None,
+                    })
+                    .collect();
+                for (i, arg) in args.iter().enumerate() {
+                    let found = self.expression(arg, None);
+                    if let (Some(found), Some(spec)) = (found, specs.get(i)) {
+                        self.format_spec(spec, &found, i + 1);
+                    }
+                }
+            }
+            StatementNode::If { condition, body, else_if_blocks, else_block } => {
+                self.condition(condition, "if");
+                self.block(body);
+                if let Some(blocks) = else_if_blocks {
+                    self.block(blocks);
+                }
+                if let Some(block) = else_block {
+                    self.block(block);
+                }
+            }
+            StatementNode::While { condition, body, label } => {
+                self.condition(condition, "while");
+                self.loop_body(body, label.as_ref(), None);
+            }
+            StatementNode::For { initialization, condition, increment, body, label } => {
+                self.expression(initialization, None);
+                self.condition(condition, "for");
+                self.expression(increment, None);
+                self.loop_body(body, label.as_ref(), None);
+            }
+            StatementNode::ForRange { variable, start, end, body, label, .. } => {
+                let counter_type = self.range_bounds(start, end);
+                let outer_scope = (self.variables.clone(), self.immutable.clone());
+                self.variables.insert(variable.clone(), counter_type);
+                self.immutable.insert(variable.clone(), Immutable::Counter);
+                self.loop_body(body, label.as_ref(), None);
+                (self.variables, self.immutable) = outer_scope;
+            }
+            StatementNode::Loop { body, label } => {
+                self.loop_body(body, label.as_ref(), None);
+            }
+            // `deref p = value` is parsed as an assignment to the pseudo-variable `deref`.
+            StatementNode::Assign { variable, value: Expression::BinaryExpression { left, right, .. } } if variable == "deref" => {
+                self.assignment(left, right);
+            }
+            StatementNode::Assign { variable, value } => {
+                self.assignment(&Expression::Variable(variable.clone()), value);
+            }
+            StatementNode::Return(Some(value)) => {
+                if let Some(return_type) = &self.function.return_type {
+                    let return_type = self.resolve(return_type);
+                    if let Some(return_type) = return_type {
+                        let function = self.function;
+                        self.expect(value, &return_type, || tr!("`{0}` is declared to return `{1}`", function.name, return_type));
+                    }
+                }
+            }
+            StatementNode::Break { label, value } => self.break_value(label.as_deref(), value.as_ref()),
+            StatementNode::Continue { label: Some(label) } => {
+                self.labeled_loop(label);
+            }
+            StatementNode::Expression(expr) => {
+                self.expression(expr, None);
+            }
+            StatementNode::Match { value, arms } => self.match_arms(value, arms),
+            _ => {}
+        }
+    }
+
+    // Each arm sees the fields its pattern binds. An arm that can only match what an earlier
+    // one does is reported, as is an enum match without an arm for some variant.
+    fn match_arms(&mut self, value: &Expression, arms: &[MatchArm]) {
+        let span = self.span;
+        let value_type = self.expression(value, None);
+        let mut variants: Vec<&str> = vec![];
+        let mut numbers: Vec<i64> = vec![];
+        let mut wildcard = false;
+
+        for arm in arms {
+            self.span = span;
+            let outer_scope = (self.variables.clone(), self.immutable.clone());
+            let repeated = match &arm.pattern {
+                Pattern::Wildcard => std::mem::replace(&mut wildcard, true),
+                Pattern::Number(number) => {
+                    if let Some(ty) = value_type.as_ref().filter(|ty| int_width(ty).is_none()) {
+                        let error = self.error(
+                            tr!("an integer pattern cannot match a value of type `{0}`", ty),
+                            tr!("pattern of this `match`"),
+                        );
+                        self.errors.push(error);
+                    }
+                    let repeated = wildcard || numbers.contains(number);
+                    numbers.push(*number);
+                    repeated
+                }
+                Pattern::Variant { enum_name, variant, bindings } => {
+                    let matches_value = match &value_type {
+                        Some(WaveType::Struct(name)) => name == enum_name,
+                        Some(_) => false,
+                        None => true,
+                    };
+                    if !matches_value {
+                        let ty = value_type.as_ref().unwrap();
+                        let error = self.error(
+                            tr!("a `{0}` pattern cannot match a value of type `{1}`", enum_name, ty),
+                            tr!("pattern of this `match`"),
+                        );
+                        self.errors.push(error);
+                    }
+                    let fields = self.variant_fields(enum_name, variant);
+                    if let Some(fields) = &fields {
+                        if fields.len() != bindings.len() {
+                            let error = self.error(
+                                tr!("`{0}::{1}` has {2} field(s), found {3}", enum_name, variant, fields.len(), bindings.len()),
+                                tr!("wrong number of fields"),
+                            );
+                            self.errors.push(error);
+                        }
+                    }
+                    for (i, binding) in bindings.iter().enumerate().filter(|(_, binding)| *binding != "_") {
+                        let field_type = fields.as_ref().and_then(|fields| fields.get(i)).and_then(|ty| self.resolve(ty));
+                        self.variables.insert(binding.clone(), field_type);
+                        self.immutable.insert(binding.clone(), Immutable::Binding);
+                    }
+                    let repeated = wildcard || variants.contains(&variant.as_str());
+                    variants.push(variant);
+                    repeated
+                }
+            };
+            if repeated {
+                let error = self.error(tr!("unreachable pattern"), tr!("an earlier arm already matches these values"));
+                self.errors.push(error);
+            }
+            self.block(&arm.body);
+            (self.variables, self.immutable) = outer_scope;
+        }
+
+        self.span = span;
+        let Some(WaveType::Struct(name)) = &value_type else {
+            return;
+        };
+        let Some(&declaration) = self.checker.enums.get(name.as_str()).filter(|_| !wildcard) else {
+            return;
+        };
+        let missing: Vec<String> = declaration
+            .variants
+            .iter()
+            .filter(|declared| !variants.contains(&declared.name.as_str()))
+            .map(|declared| format!("`{}::{}`", name, declared.name))
+            .collect();
+        if !missing.is_empty() {
+            let error = self
+                .error(tr!("non-exhaustive `match`: {0} not covered", missing.join(", ")), tr!("not every variant has an arm"))
+                .with_help(tr!("add an arm for each missing variant, or a `_ => { ... }` arm"));
+            self.errors.push(error);
+        }
+    }
+
+    fn break_value(&mut self, label: Option<&str>, value: Option<&Expression>) {
+        let target = match label {
+            Some(label) => {
+                let Some(index) = self.labeled_loop(label) else {
+                    if let Some(value) = value {
+                        self.expression(value, None);
+                    }
+                    return;
+                };
+                Some(index)
+            }
+            None => self.loops.len().checked_sub(1),
+        };
+        let target_value = target.map(|index| self.loops[index].1.as_ref().map(|value_loop| value_loop.ty.clone()));
+        match (value, target_value) {
+            (Some(value), Some(Some(ty))) => {
+                let ty = match ty {
+                    Some(ty) => {
+                        self.expect(value, &ty, || tr!("the loop's value has type `{0}`", ty));
+                        Some(ty)
+                    }
+                    None => self.expression(value, None),
+                };
+                if let Some((_, Some(value_loop))) = target.map(|index| &mut self.loops[index]) {
+                    value_loop.ty = value_loop.ty.take().or(ty);
+                    value_loop.breaks += 1;
+                }
+            }
+            (Some(value), _) => {
+                self.expression(value, None);
+                let error = self
+                    .error(
+                        tr!("`break` with a value outside a loop used as a value"),
+                        tr!("this `break` carries a value"),
+                    )
+                    .with_help(tr!("assign the value to a variable before the `break`"));
+                self.errors.push(error);
+            }
+            (None, Some(Some(_))) => {
+                let error = self
+                    .error(
+                        tr!("`break` without a value in a loop used as a value"),
+                        tr!("the loop's value is missing"),
+                    )
+                    .with_help(tr!("pass the value out, e.g. `break result;`"));
+                self.errors.push(error);
+            }
+            (None, _) => {}
+        }
+    }
+
+    // A `while` used as a value: it must not end but through a `break` that passes one out.
+    fn while_value(
+        &mut self,
+        condition: &Expression,
+        body: &[ASTNode],
+        label: Option<&String>,
+        expected: Option<&WaveType>,
+    ) -> Option<WaveType> {
+        self.condition(condition, "while");
+        if condition_value(condition) != Some(true) {
+            let error = self
+                .error(tr!("this `while` can end without a value"), tr!("the loop ends without a value when its condition is false"))
+                .with_help(tr!("use `loop { ... }` and leave it with `break value;`"));
+            self.errors.push(error);
+        }
+        self.loop_value("while", body, label, expected)
+    }
+
+    // The body of a `keyword` loop used as a value, which some `break` must give one.
+    fn loop_value(&mut self, keyword: &str, body: &[ASTNode], label: Option<&String>, expected: Option<&WaveType>) -> Option<WaveType> {
+        let span = self.span;
+        let enclosing_floor = std::mem::replace(&mut self.loop_floor, self.loops.len());
+        let value_loop = self.loop_body(body, label, Some(LoopValue { ty: expected.cloned(), breaks: 0 }));
+        self.loop_floor = enclosing_floor;
+        let value_loop = value_loop?;
+        self.span = span;
+        if value_loop.breaks == 0 {
+            let error = self
+                .error(tr!("this `{0}` is used as a value but no `break` gives it one", keyword), tr!("value of this loop"))
+                .with_help(tr!("leave the loop with `break value;`"));
+            self.errors.push(error);
+        }
+        value_loop.ty
+    }
+
+    // Whether the spec of the `placeholder`th placeholder applies to a value of type `found`. An
+    // array prints each element with the spec.
+    fn format_spec(&mut self, spec: &FormatSpec, found: &WaveType, placeholder: usize) {
+        let mut element = found;
+        while let WaveType::Array(inner, _) = element {
+            element = inner;
+        }
+        let is_integer = int_width(element).is_some() && !matches!(element, WaveType::Bool);
+        let message = if let Some(radix) = spec.radix.filter(|_| !is_integer) {
+            tr!("`{0}` in a format spec applies to integers, found `{1}`", radix, found)
+        } else if spec.precision.is_some() && !matches!(element, WaveType::Float(_) | WaveType::String) {
+            tr!("a precision in a format spec applies to floats and strings, found `{0}`", found)
+        } else {
+            return;
+        };
+        let error = self.error(message, tr!("placeholder {0} of the format string", placeholder));
+        self.errors.push(error);
+    }
+
+    fn condition(&mut self, condition: &Expression, keyword: &str) {
+        let Some(found) = self.expression(condition, None) else {
+            return;
+        };
+        if !is_number(&found) {
+            let error = self
+                .error(
+                    tr!("expected a number or `bool` as the condition, found `{0}`", found),
+                    tr!("condition of this `{0}`", keyword),
+                )
+                .with_help(tr!("compare the value explicitly, e.g. `{0} (x != 0)`", keyword));
+            self.errors.push(error);
+        }
+    }
+
+    // The type the variable of `for i in start..end` counts in: that of a bound other than an
+    // unsuffixed literal, which adopts it, and `i32` for two literals.
+    fn range_bounds(&mut self, start: &Expression, end: &Expression) -> Option<WaveType> {
+        let (typed, adopting) = if is_unsuffixed_literal(start) { (end, start) } else { (start, end) };
+        let Some(counter_type) = self.expression(typed, None) else {
+            self.expression(adopting, None);
+            return None;
+        };
+        if int_width(&counter_type).is_none() || matches!(counter_type, WaveType::Bool) {
+            let error = self
+                .error(
+                    tr!("expected an integer as the bound of a range, found `{0}`", counter_type),
+                    tr!("bound of this `for`"),
+                );
+            self.errors.push(error);
+            self.expression(adopting, None);
+            return None;
+        }
+        self.expect(adopting, &counter_type, || tr!("the range counts in `{0}`", counter_type));
+        Some(counter_type)
+    }
+
+    fn assignment(&mut self, target: &Expression, value: &Expression) -> Option<WaveType> {
+        self.check_mutable(target);
+        let Some(target_type) = self.expression(target, None) else {
+            self.expression(value, None);
+            return None;
+        };
+        let label = match target {
+            Expression::Variable(name) => tr!("`{0}` has type `{1}`", name, target_type),
+            _ => tr!("the target has type `{0}`", target_type),
+        };
+        self.expect(value, &target_type, || label);
+        Some(target_type)
+    }
+
+    // Reports an assignment to `target` that changes an immutable binding: the binding itself, or
+    // a field or element of the struct or array it holds. A struct behind a pointer is not the
+    // pointer's to protect.
+    fn check_mutable(&mut self, target: &Expression) {
+        let Some(name) = self.assigned_variable(target) else { return };
+        let Some(&immutable) = self.immutable.get(name) else { return };
+        let error = diagnostic_at(
+            WaveErrorKind::ImmutableAssignment(name.to_string()),
+            tr!("cannot assign to immutable variable `{0}`", name),
+            self.file,
+            self.span,
+        )
+        .with_label(tr!("cannot assign"));
+        let error = match immutable {
+            Immutable::Let(declared_at) => error
+                .with_note(tr!("`{0}` is declared at {1}", name, format!("{}:{}:{}", self.file.display(), declared_at.line, declared_at.column)))
+                .with_suggestion(Suggestion::new(tr!("make `{0}` mutable", name), declared_at.line, declared_at.column, 3, "let mut")),
+            Immutable::Parameter => error.with_help(tr!("parameters cannot be assigned to; copy `{0}` into a `var` first", name)),
+            Immutable::Counter => error.with_help(tr!("the `for` loop counts with `{0}`; copy it into a `var` to change it", name)),
+            Immutable::Binding => error.with_help(tr!("the bindings of a `match` arm cannot be assigned to; copy `{0}` into a `var` first", name)),
+        };
+        self.errors.push(error);
+    }
+
+    fn assigned_variable<'e>(&self, target: &'e Expression) -> Option<&'e str> {
+        match target {
+            Expression::Variable(name) => Some(name),
+            Expression::Grouped(inner) => self.assigned_variable(inner),
+            Expression::FieldAccess { target, .. } => self
+                .assigned_variable(target)
+                .filter(|name| !matches!(self.variables.get(*name), Some(Some(WaveType::Pointer(_))))),
+            Expression::IndexAccess { target, .. } => self
+                .assigned_variable(target)
+                .filter(|name| matches!(self.variables.get(*name), Some(Some(WaveType::Array(..))))),
+            _ => None,
+        }
+    }
+
+    // Checks `expr` where a value of type `expected` is required; `label` says why.
+    fn expect(&mut self, expr: &Expression, expected: &WaveType, label: impl FnOnce() -> String) {
+        let Some(found) = self.expression(expr, Some(expected)) else {
+            return;
+        };
+        if !fits(expected, &found) {
+            let error = self.error(
+                tr!("mismatched types: expected `{0}`, found {1}", expected, describe_found(expr, &found)),
+                label(),
+            );
+            self.errors.push(error);
+        } else if narrows(expected, &found) {
+            let error = self
+                .error(
+                    tr!("mismatched types: expected `{0}`, found {1}", expected, describe_found(expr, &found)),
+                    label(),
+                )
+                .with_help(tr!("`{0}` does not convert to the narrower `{1}` implicitly; use `as {1}` if the value fits", found, expected));
+            self.errors.push(error);
+        } else {
+            self.implicit_conversion(expr, &found, expected);
+            // Code generation would truncate a constant that does not fit, or fail at the first.
+            let Some((min, max)) = int_range(expected) else { return };
+            let Some(ConstValue::Int(value)) = evaluate(expr) else { return };
+            if !(min..=max).contains(&i128::from(value)) {
+                let message = match expr {
+                    Expression::Literal(_) => tr!("literal `{0}` does not fit in {1}", value, expected),
+                    _ => tr!("constant value `{0}` does not fit in {1}", value, expected),
+                };
+                let mut error = self.error(message, label());
+                if int_width(expected).is_some_and(|bits| bits <= 64) {
+                    error = error.with_note(tr!("the range of {0} is {1}..={2}", expected, min, max));
+                }
+                self.errors.push(error);
+            }
+        }
+    }
+
+    // Warns of `expr`, a `from`, converted to `to` where code generation converts it implicitly.
+    // An unsuffixed literal is written at the type it is used at, so only a fraction it drops
+    // is a conversion. `--allow` and `--deny` apply to the warning once the program is checked.
+    fn implicit_conversion(&mut self, expr: &Expression, from: &WaveType, to: &WaveType) {
+        let from = match is_unsuffixed_literal(expr) {
+            true if has_fraction(expr) && int_width(to).is_some() => WaveType::Float(64),
+            true => return,
+            false => from.clone(),
+        };
+        let Some(effect) = conversion_effect(&from, to) else { return };
+        let mut warning = diagnostic_at(
+            WaveErrorKind::ImplicitConversion("implicit conversion".to_string()),
+            tr!("implicit conversion from `{0}` to `{1}` {2}", from, to, effect),
+            self.file,
+            self.span,
+        )
+        .with_label(tr!("converted implicitly"))
+        .with_note(tr!("silence this with `--allow=implicit-conversion`"))
+        .as_warning();
+        if let Some(column) = warning.source.as_deref().and_then(|line| expression_column(line, expr, warning.column)) {
+            warning.column = column;
+        }
+        self.errors.push(warning);
+    }
+
+    /// The type of `expr`, or `None` when it cannot be resolved or is already reported.
+    /// `expected` is the type the context asks for; unsuffixed literals adopt it.
+    fn expression(&mut self, expr: &Expression, expected: Option<&WaveType>) -> Option<WaveType> {
+        let contextual = expected.filter(|ty| is_number(ty)).cloned();
+        match expr {
+            Expression::Literal(Literal::Number(_)) => Some(contextual.unwrap_or(WaveType::Int(32))),
+            Expression::Literal(Literal::Float(_)) => Some(contextual.unwrap_or(WaveType::Float(64))),
+            Expression::Literal(Literal::Bool(_)) => Some(WaveType::Bool),
+            Expression::Literal(Literal::Char(_)) => Some(WaveType::Char),
+            // Where a byte pointer is expected, a string literal is the address of its first byte.
+            Expression::Literal(Literal::String(_)) => match expected {
+                Some(pointer @ WaveType::Pointer(pointee)) if int_width(pointee) == Some(8) => Some(pointer.clone()),
+                _ => Some(WaveType::String),
+            },
+            Expression::Variable(name) => match self.variables.get(name) {
+                Some(ty) => ty.clone(),
+                None => self.function_value(name, expected),
+            },
+            Expression::Grouped(inner) => self.expression(inner, expected),
+            Expression::AddressOf(inner) => {
+                let pointee = match expected {
+                    Some(WaveType::Pointer(pointee)) => Some(&**pointee),
+                    _ => None,
+                };
+                let inner = self.expression(inner, pointee)?;
+                Some(WaveType::Pointer(Box::new(inner)))
+            }
+            Expression::Deref(inner) => match self.expression(inner, None)? {
+                WaveType::Pointer(pointee) => Some(*pointee),
+                WaveType::String => Some(WaveType::Char),
+                other => {
+                    let error = self.error(tr!("cannot `deref` a value of type `{0}`", other), tr!("not a pointer"));
+                    self.errors.push(error);
+                    None
+                }
+            },
+            // A number converts to any other number but `bool`, which would keep only the lowest
+            // bit; a pointer converts to another pointer, or to and from an integer.
+            Expression::Cast { value, ty } => {
+                let found = self.expression(value, None)?;
+                let target = self.resolve(ty)?;
+                let castable = match (&found, &target) {
+                    (_, WaveType::Bool) => matches!(found, WaveType::Bool),
+                    (WaveType::Pointer(_), WaveType::Pointer(_)) => true,
+                    (WaveType::Pointer(_), other) | (other, WaveType::Pointer(_)) => int_width(other).is_some(),
+                    (found, target) => is_number(found) && is_number(target),
+                };
+                if !castable {
+                    let error = self.error(
+                        tr!("cannot cast `{0}` as `{1}`", found, target),
+                        tr!("only numbers and pointers can be cast"),
+                    );
+                    self.errors.push(error);
+                    return None;
+                }
+                Some(target)
+            }
+            Expression::IndexAccess { target, index } => {
+                let target = self.expression(target, None);
+                let index_type = self.expression(index, Some(&WaveType::Uint(WaveType::POINTER_SIZED)));
+                if let Some(index) = index_type.filter(|index| int_width(index).is_none()) {
+                    let error = self.error(tr!("an index must be an integer, found `{0}`", index), tr!("index of this access"));
+                    self.errors.push(error);
+                    return None;
+                }
+                // An index is a `usize`: a smaller unsigned integer extends to it, and a signed
+                // one must not be negative, which a constant shows before the program runs.
+                if let Some(ConstValue::Int(value @ ..0)) = evaluate(index) {
+                    let error = self
+                        .error(tr!("index {0} is negative", value), tr!("index of this access"))
+                        .with_help(tr!("an index is a `usize`, counted from 0"));
+                    self.errors.push(error);
+                    return None;
+                }
+                match target? {
+                    WaveType::Array(element, _) => Some(*element),
+                    WaveType::Pointer(pointee) => match *pointee {
+                        WaveType::Array(element, _) => Some(*element),
+                        other => Some(other),
+                    },
+                    WaveType::String => Some(WaveType::Char),
+                    other => {
+                        let error = self.error(
+                            tr!("cannot index into a value of type `{0}`", other),
+                            tr!("not an array or a pointer"),
+                        );
+                        self.errors.push(error);
+                        None
+                    }
+                }
+            }
+            Expression::ArrayLiteral(elements) => self.array_literal(elements, expected),
+            Expression::BinaryExpression { left, operator, right } => self.binary(left, operator, right, expected),
+            Expression::Assignment { target, value }
+            | Expression::AssignOperation { target, operator: AssignOperator::Assign, value } => self.assignment(target, value),
+            Expression::AssignOperation { target, operator, value } => {
+                self.check_mutable(target);
+                let target_type = self.expression(target, None)?;
+                let found = self.expression(value, Some(&target_type))?;
+                if !is_number(&target_type) || !is_number(&found) {
+                    let error = self.error(
+                        tr!("operator `{0}` cannot be applied to `{1}` and `{2}`", assign_operator_symbol(operator), target_type, found),
+                        tr!("invalid operands"),
+                    );
+                    self.errors.push(error);
+                    return None;
+                }
+                // The value takes the target's type before the operator is applied.
+                self.implicit_conversion(value, &found, &target_type);
+                if matches!(operator, AssignOperator::DivAssign | AssignOperator::RemAssign) && int_width(&target_type).is_some() {
+                    self.constant_divisor(assign_operator_symbol(operator), value);
+                }
+                Some(target_type)
+            }
+            Expression::FunctionCall { name, args } => self.call(name, args),
+            Expression::AsmBlock { .. } => None,
+            Expression::While { condition, body, label } => self.while_value(condition, body, label.as_ref(), expected),
+            Expression::Loop { body, label } => self.loop_value("loop", body, label.as_ref(), expected),
+            Expression::StructLiteral { name, fields } => self.struct_literal(name, fields),
+            Expression::EnumVariant { enum_name, variant, args } => self.enum_variant(enum_name, variant, args),
+            Expression::FieldAccess { target, field } => {
+                let target_type = self.expression(target, None)?;
+                self.field(&target_type, field)
+            }
+            Expression::Alloc { ty, count } => {
+                if let Some(count) = count.as_deref().and_then(|count| self.expression(count, None)) {
+                    if int_width(&count).is_none() {
+                        let error = self.error(tr!("the count of `alloc` must be an integer, found `{0}`", count), tr!("number of values to allocate"));
+                        self.errors.push(error);
+                    }
+                }
+                Some(WaveType::Pointer(Box::new(self.resolve(ty)?)))
+            }
+        }
+    }
+
+    // The type of `field` of a struct, or of the struct a pointer points to.
+    fn field(&mut self, target_type: &WaveType, field: &str) -> Option<WaveType> {
+        let struct_type = match target_type {
+            WaveType::Pointer(pointee) => pointee,
+            other => other,
+        };
+        let WaveType::Struct(name) = struct_type else {
+            let error = self.error(tr!("no field `{0}` on type `{1}`", field, target_type), tr!("not a struct"));
+            self.errors.push(error);
+            return None;
+        };
+        // An undeclared struct is reported where the type is lowered.
+        let declaration = *self.checker.structs.get(name.as_str())?;
+
+        match declaration.field(field) {
+            Some((_, field_type)) => Some(field_type.clone()),
+            None => {
+                let error = self
+                    .error(tr!("no field `{0}` on type `{1}`", field, name), tr!("unknown field"))
+                    .with_note(tr!("`{0}` has the fields {1}", name, field_names(declaration)));
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    fn struct_literal(&mut self, name: &str, fields: &[(String, Expression)]) -> Option<WaveType> {
+        let Some(&declaration) = self.checker.structs.get(name) else {
+            for (_, value) in fields {
+                self.expression(value, None);
+            }
+            return None;
+        };
+
+        for (i, (field, value)) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|(earlier, _)| earlier == field) {
+                let error = self.error(tr!("field `{0}` is given twice", field), tr!("already given earlier in this literal"));
+                self.errors.push(error);
+            }
+            match declaration.field(field) {
+                Some((_, field_type)) => {
+                    self.expect(value, field_type, || tr!("field `{0}` of `{1}` has type `{2}`", field, name, field_type));
+                }
+                None => {
+                    self.expression(value, None);
+                    let error = self
+                        .error(tr!("struct `{0}` has no field `{1}`", name, field), tr!("unknown field"))
+                        .with_note(tr!("`{0}` has the fields {1}", name, field_names(declaration)));
+                    self.errors.push(error);
+                }
+            }
+        }
+
+        let missing: Vec<String> = declaration
+            .fields
+            .iter()
+            .filter(|declared| !fields.iter().any(|(field, _)| *field == declared.name))
+            .map(|declared| format!("`{}`", declared.name))
+            .collect();
+        if !missing.is_empty() {
+            let error = self
+                .error(tr!("missing field(s) {0} in this `{1}` literal", missing.join(", "), name), tr!("every field needs a value"));
+            self.errors.push(error);
+        }
+        Some(WaveType::Struct(name.to_string()))
+    }
+
+    fn enum_variant(&mut self, enum_name: &str, variant: &str, args: &[Expression]) -> Option<WaveType> {
+        let Some(fields) = self.variant_fields(enum_name, variant) else {
+            for arg in args {
+                self.expression(arg, None);
+            }
+            return None;
+        };
+
+        if args.len() != fields.len() {
+            let error = self.error(
+                tr!("`{0}::{1}` has {2} field(s), found {3}", enum_name, variant, fields.len(), args.len()),
+                tr!("wrong number of fields"),
+            );
+            self.errors.push(error);
+        }
+        for (i, arg) in args.iter().enumerate() {
+            match fields.get(i).and_then(|field_type| self.resolve(field_type)) {
+                Some(field_type) => {
+                    self.expect(arg, &field_type, || tr!("field {0} of `{1}::{2}` has type `{3}`", i, enum_name, variant, field_type));
+                }
+                None => {
+                    self.expression(arg, None);
+                }
+            }
+        }
+        Some(WaveType::Struct(enum_name.to_string()))
+    }
+
+    // The field types of `variant` of enum `enum_name`, reporting a variant it does not declare.
+    // An undeclared enum is reported where it is lowered.
+    fn variant_fields(&mut self, enum_name: &str, variant: &str) -> Option<Vec<WaveType>> {
+        let declaration = *self.checker.enums.get(enum_name)?;
+        match declaration.variant(variant) {
+            Some((_, declared)) => Some(declared.fields.clone()),
+            None => {
+                let error = self
+                    .error(tr!("no variant `{0}` on enum `{1}`", variant, enum_name), tr!("unknown variant"))
+                    .with_note(tr!("`{0}` has the variants {1}", enum_name, variant_names(declaration)));
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    fn array_literal(&mut self, elements: &[Expression], expected: Option<&WaveType>) -> Option<WaveType> {
+        let Some(WaveType::Array(element_type, size)) = expected else {
+            let mut element = None;
+            for (i, value) in elements.iter().enumerate() {
+                let value = self.expression(value, None);
+                if i == 0 {
+                    element = value;
+                }
+            }
+            return Some(WaveType::Array(Box::new(element?), elements.len() as u32));
+        };
+
+        if elements.len() != *size as usize {
+            let error = self.error(
+                tr!("expected an array of {0} elements, found {1}", size, elements.len()),
+                tr!("the array has type `{0}`", WaveType::Array(element_type.clone(), *size)),
+            );
+            self.errors.push(error);
+        }
+        for value in elements {
+            self.expect(value, element_type, || tr!("the elements have type `{0}`", element_type));
+        }
+        expected.cloned()
+    }
+
+    // Integer division by a divisor that is always zero, which would trap or be undefined on
+    // every run, and which constant folding leaves alone.
+    fn constant_divisor(&mut self, symbol: &str, divisor: &Expression) {
+        if !matches!(evaluate(divisor), Some(ConstValue::Int(0))) {
+            return;
+        }
+        let error = self
+            .error(tr!("integer division by zero in `{0}`", symbol), tr!("the divisor is always zero"))
+            .with_help(tr!("only float division by zero is defined, as infinity or NaN"));
+        self.errors.push(error);
+    }
+
+    fn binary(&mut self, left: &Expression, operator: &Operator, right: &Expression, expected: Option<&WaveType>) -> Option<WaveType> {
+        let arithmetic = matches!(
+            operator,
+            Operator::Add | Operator::Subtract | Operator::Multiply | Operator::Divide | Operator::Remainder
+        );
+
+        // As in code generation, an unsuffixed literal takes the type of the other operand.
+        let (left_type, right_type) = match (is_unsuffixed_literal(left), is_unsuffixed_literal(right)) {
+            (true, false) => {
+                let right_type = self.expression(right, None);
+                (self.expression(left, right_type.as_ref()), right_type)
+            }
+            (false, true) => {
+                let left_type = self.expression(left, None);
+                let right_type = self.expression(right, left_type.as_ref());
+                (left_type, right_type)
+            }
+            (true, true) if arithmetic => (self.expression(left, expected), self.expression(right, expected)),
+            _ => (self.expression(left, None), self.expression(right, None)),
+        };
+        let (left_type, right_type) = (left_type?, right_type?);
+
+        let strings = matches!((&left_type, &right_type), (WaveType::String, WaveType::String));
+        let valid = match operator {
+            // `+` also concatenates strings.
+            Operator::Add if strings => true,
+            _ if arithmetic => is_number(&left_type) && is_number(&right_type),
+            Operator::BitwiseAnd | Operator::BitwiseOr | Operator::LogicalAnd | Operator::LogicalOr => {
+                int_width(&left_type).is_some() && int_width(&right_type).is_some()
+            }
+            // Strings compare by content.
+            _ => (is_number(&left_type) && is_number(&right_type)) || strings,
+        };
+        if !valid {
+            let error = self.error(
+                tr!("operator `{0}` cannot be applied to `{1}` and `{2}`", operator_symbol(operator), left_type, right_type),
+                tr!("invalid operands"),
+            );
+            self.errors.push(error);
+            return None;
+        }
+
+        if matches!(operator, Operator::Divide | Operator::Remainder) && int_width(&left_type).is_some() {
+            self.constant_divisor(operator_symbol(operator), right);
+        }
+
+        // Both operands convert to the type of `widen` before the operator is applied; a literal
+        // already has the type of the other operand, or of the context.
+        let logical = matches!(operator, Operator::LogicalAnd | Operator::LogicalOr);
+        if let (false, Some(common)) = (logical, widen(&left_type, &right_type)) {
+            for (operand, ty) in [(left, &left_type), (right, &right_type)] {
+                if !is_unsuffixed_literal(operand) {
+                    self.implicit_conversion(operand, ty, &common);
+                }
+            }
+        }
+
+        match operator {
+            Operator::Greater | Operator::Less | Operator::GreaterEqual | Operator::LessEqual
+            | Operator::Equal | Operator::NotEqual | Operator::LogicalAnd | Operator::LogicalOr => Some(WaveType::Bool),
+            _ if strings => Some(left_type),
+            _ => widen(&left_type, &right_type),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expression]) -> Option<WaveType> {
+        if let Some(Some(WaveType::Function(params, return_type))) = self.variables.get(name) {
+            let (params, return_type) = (params.clone(), return_type.clone());
+            return self.indirect_call(name, &params, return_type.as_deref(), args);
+        }
+        let Some(candidates) = self.checker.functions.get(name) else {
+            // Builtins check their own operands during lowering.
+            let arg_types: Vec<Option<WaveType>> = args.iter().map(|arg| self.expression(arg, None)).collect();
+            if matches!(name, "shift_left" | "shift_right") {
+                self.shift_amount(name, args, &arg_types);
+            }
+            return None;
+        };
+        let callee = match candidates[..] {
+            [callee] => callee,
+            _ => return self.overloaded_call(name, candidates, args),
+        };
+
+        if args.len() != callee.parameters.len() && !(callee.variadic && args.len() > callee.parameters.len()) {
+            let message = match callee.variadic {
+                true => tr!("function `{0}` takes at least {1} argument(s), found {2}", name, callee.parameters.len(), args.len()),
+                false => tr!("function `{0}` takes {1} argument(s), found {2}", name, callee.parameters.len(), args.len()),
+            };
+            let error = self
+                .error(message, tr!("wrong number of arguments"))
+                .with_note(tr!("the signature is `{0}`", signature(callee)));
+            self.errors.push(error);
+            return None;
+        }
+
+        for (arg, param) in args.iter().zip(&callee.parameters) {
+            let Some(param_type) = self.resolve(&param.param_type) else {
+                self.expression(arg, None);
+                continue;
+            };
+            self.expect(arg, &param_type, || tr!("parameter `{0}` of `{1}` has type `{2}`", param.name, name, param_type));
+        }
+        for (i, arg) in args.iter().enumerate().skip(callee.parameters.len()) {
+            self.variadic_argument(name, i, arg);
+        }
+
+        let return_type = callee.return_type.as_ref()?;
+        self.resolve(return_type)
+    }
+
+    // Argument `index` of a call to `name` that goes to its `...`: a value an `extern` parameter
+    // could have, or a string literal, which is passed as the address of its bytes. Code
+    // generation promotes it as C does.
+    fn variadic_argument(&mut self, name: &str, index: usize, arg: &Expression) {
+        let Some(ty) = self.expression(arg, None) else { return };
+        if matches!(arg, Expression::Literal(Literal::String(_))) || is_c_type(&ty) {
+            return;
+        }
+        let error = self.error(
+            tr!("`{0}` cannot be passed to or from C, in `extern` function `{1}`", ty, name),
+            tr!("argument {0} has type `{1}`", index + 1, ty),
+        );
+        self.errors.push(error);
+    }
+
+    // A call through `name`, a variable of type `fun(params) -> return_type`.
+    fn indirect_call(&mut self, name: &str, params: &[WaveType], return_type: Option<&WaveType>, args: &[Expression]) -> Option<WaveType> {
+        if args.len() != params.len() {
+            let function_type = WaveType::Function(params.to_vec(), return_type.cloned().map(Box::new));
+            let error = self
+                .error(
+                    tr!("function `{0}` takes {1} argument(s), found {2}", name, params.len(), args.len()),
+                    tr!("wrong number of arguments"),
+                )
+                .with_note(tr!("`{0}` has type `{1}`", name, function_type));
+            self.errors.push(error);
+            return None;
+        }
+        for (i, (arg, param)) in args.iter().zip(params).enumerate() {
+            self.expect(arg, param, || tr!("parameter {0} of `{1}` has type `{2}`", i + 1, name, param));
+        }
+        return_type.cloned()
+    }
+
+    // The type of function `name` used as a value. Of an overloaded name, that of the overload
+    // with the `expected` type, as code generation picks it; `None` when none has it.
+    fn function_value(&mut self, name: &str, expected: Option<&WaveType>) -> Option<WaveType> {
+        if let Some([function]) = self.checker.functions.get(name).map(Vec::as_slice) {
+            if function.variadic {
+                let error = self.error(
+                    tr!("`{0}` takes variable arguments, so no function type holds its address", name),
+                    tr!("the signature is `{0}`", signature(function)),
+                );
+                self.errors.push(error);
+                return None;
+            }
+        }
+        let candidates: &[&'a FunctionNode] = self.checker.functions.get(name)?;
+        match candidates {
+            [function] => self.resolve(&function.function_type()),
+            _ => {
+                let chosen = expected.and_then(|expected| {
+                    candidates.iter().find_map(|&function| {
+                        self.resolve(&function.function_type()).filter(|ty| same_layout(ty, expected)).map(|ty| (function, ty))
+                    })
+                });
+                self.overloads.push((candidates[0].name.as_str(), chosen.as_ref().map(|&(function, _)| function)));
+                chosen.map(|(_, ty)| ty)
+            }
+        }
+    }
+
+    // A call to one of the functions named `name`, chosen by the types of the arguments as
+    // code generation chooses it. The arguments are checked once, before the choice.
+    fn overloaded_call(&mut self, name: &str, candidates: &[&'a FunctionNode], args: &[Expression]) -> Option<WaveType> {
+        let arg_types: Vec<Option<WaveType>> = args.iter().map(|arg| self.expression(arg, None)).collect();
+        let parameters: Vec<Vec<WaveType>> = candidates
+            .iter()
+            .map(|candidate| candidate.parameters.iter().map(|param| param.param_type.clone()).collect())
+            .collect();
+
+        let overload = select_overload(&parameters, &arg_types);
+        let chosen = match overload {
+            Overload::Selected(index) => Some(candidates[index]),
+            _ => None,
+        };
+        self.overloads.push((candidates[0].name.as_str(), chosen));
+        let (message, label, listed, help) = match overload {
+            Overload::Selected(index) => {
+                let return_type = candidates[index].return_type.as_ref()?;
+                return self.resolve(return_type);
+            }
+            Overload::NoMatch => {
+                let found: Vec<String> = arg_types
+                    .iter()
+                    .map(|ty| ty.as_ref().map_or_else(|| "_".to_string(), ToString::to_string))
+                    .collect();
+                let message = tr!("no function `{0}` takes arguments of types ({1})", name, found.join(", "));
+                (message, tr!("no overload matches"), candidates.to_vec(), None)
+            }
+            Overload::Ambiguous(indices) => {
+                let message = tr!("call to `{0}` is ambiguous", name);
+                let help = tr!("store an argument in a `var` of the parameter type to pick an overload");
+                (message, tr!("more than one overload matches"), indices.iter().map(|&i| candidates[i]).collect(), Some(help))
+            }
+        };
+        let mut error = self.error(message, label);
+        for candidate in listed {
+            error = error.with_note(tr!("candidate: `{0}`", signature(candidate)));
+        }
+        if let Some(help) = help {
+            error = error.with_help(help);
+        }
+        self.errors.push(error);
+        None
+    }
+
+    // Shifting by the width of the value or more is undefined in LLVM, so code generation makes it
+    // defined: the amount is taken modulo the width, as the hardware does (`--shift-amount=mask`,
+    // the default), or such a shift traps (`--shift-amount=trap`, or `--check-ub`). A literal amount
+    // that is out of range is a mistake under either, and is reported here.
+    fn shift_amount(&mut self, name: &str, args: &[Expression], arg_types: &[Option<WaveType>]) {
+        let (Some(Some(value_type)), Some(Expression::Literal(Literal::Number(amount)))) = (arg_types.first(), args.get(1)) else {
+            return;
+        };
+        let Some(width) = int_width(value_type).filter(|&width| width != WaveType::POINTER_SIZED) else { return };
+        if *amount >= i64::from(width) {
+            let error = self
+                .error(
+                    tr!("shift amount {0} is not less than the width of `{1}`", amount, value_type),
+                    tr!("`{0}` shifts by 0 to {1} bits", name, width - 1),
+                )
+                .with_note(tr!("an amount out of range is taken modulo the width, or traps with `--shift-amount=trap`"));
+            self.errors.push(error);
+        }
+    }
+
+    // Replaces `typeof(expr)` inside `ty` with the type of `expr`. A struct or enum that is not declared
+    // is unknown; code generation reports it.
+    fn resolve(&mut self, ty: &WaveType) -> Option<WaveType> {
+        match ty {
+            WaveType::Struct(name)
+                if !self.checker.structs.contains_key(name.as_str()) && !self.checker.enums.contains_key(name.as_str()) =>
+            {
+                None
+            }
+            WaveType::TypeOf(expr) => self.expression(expr, None),
+            WaveType::Pointer(inner) => Some(WaveType::Pointer(Box::new(self.resolve(inner)?))),
+            WaveType::Array(inner, size) => Some(WaveType::Array(Box::new(self.resolve(inner)?), *size)),
+            WaveType::Function(params, return_type) => Some(WaveType::Function(
+                params.iter().map(|param| self.resolve(param)).collect::<Option<_>>()?,
+                match return_type {
+                    Some(return_type) => Some(Box::new(self.resolve(return_type)?)),
+                    None => None,
+                },
+            )),
+            WaveType::Inferred => None,
+            other => Some(other.clone()),
+        }
+    }
+
+    // A type error at the statement being checked.
+    fn error(&self, message: String, label: String) -> WaveError {
+        diagnostic_at(WaveErrorKind::TypeMismatch(self.function.name.clone()), message, self.file, self.span)
+            .with_label(label)
+    }
+}
+
+// Whether a value of type `found` can be used where `expected` is required: any two numbers,
+// or two types that are lowered to the same LLVM type.
+pub(crate) fn fits(expected: &WaveType, found: &WaveType) -> bool {
+    (is_number(expected) && is_number(found)) || same_layout(expected, found)
+}
+
+// Whether storing a `found` where `expected` is required can lose part of the value: an integer
+// into a narrower integer, or a float into a narrower float. Widening follows the lattice of
+// `widen`; the other way takes an `as` cast.
+fn narrows(expected: &WaveType, found: &WaveType) -> bool {
+    match (expected, found) {
+        (WaveType::Float(to), WaveType::Float(from)) => to < from,
+        _ => matches!((int_rank(expected), int_rank(found)), (Some(to), Some(from)) if to < from),
+    }
+}
+
+// What converting a `from` to a `to` does to the value, for the warning of an implicit
+// conversion; `None` unless both are numbers of different types. Types of the same signedness
+// and rank, such as `u8` and `char`, do not convert.
+fn conversion_effect(from: &WaveType, to: &WaveType) -> Option<String> {
+    let mantissa = |bits: u16| if bits == 32 { 24 } else { 53 };
+    let signed = |ty: &WaveType| matches!(ty, WaveType::Int(_));
+    let effect = match (from, to) {
+        (WaveType::Float(from), WaveType::Float(to)) if from == to => return None,
+        (WaveType::Float(from), WaveType::Float(to)) if to < from => tr!("may lose precision"),
+        (WaveType::Float(_), WaveType::Float(_)) => tr!("widens the value"),
+        (WaveType::Float(_), _) if is_number(to) => tr!("drops the fractional part"),
+        (_, WaveType::Float(bits)) if int_rank(from)? > mantissa(*bits) => tr!("may lose precision"),
+        (_, WaveType::Float(_)) if is_number(from) => tr!("converts the integer to a float"),
+        _ => {
+            let (from_rank, to_rank) = (int_rank(from)?, int_rank(to)?);
+            if to_rank < from_rank {
+                tr!("may truncate the value")
+            } else if signed(from) && !signed(to) {
+                tr!("changes the sign of negative values")
+            } else if !signed(from) && signed(to) && to_rank == from_rank {
+                tr!("may turn large values negative")
+            } else if to_rank == from_rank {
+                return None;
+            } else {
+                tr!("widens the value")
+            }
+        }
+    };
+    Some(effect)
+}
+
+fn same_layout(a: &WaveType, b: &WaveType) -> bool {
+    match (a, b) {
+        (WaveType::Array(a, n), WaveType::Array(b, m)) => n == m && same_layout(a, b),
+        (WaveType::Pointer(a), WaveType::Pointer(b)) => same_layout(a, b),
+        (WaveType::Function(a, r), WaveType::Function(b, s)) => {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| same_layout(a, b))
+                && match (r, s) {
+                    (Some(r), Some(s)) => same_layout(r, s),
+                    (r, s) => r.is_none() && s.is_none(),
+                }
+        }
+        (WaveType::String, WaveType::String) => true,
+        (WaveType::Float(a), WaveType::Float(b)) => a == b,
+        (WaveType::Struct(a), WaveType::Struct(b)) => a == b,
+        _ => matches!((int_width(a), int_width(b)), (Some(a), Some(b)) if a == b),
+    }
+}
+
+/// The type the two operands of arithmetic or a comparison convert to before it is applied, or
+/// `None` unless both are numbers. A float takes an integer or a narrower float; of two integers
+/// of the same signedness the narrower extends to the wider, and so does an unsigned one to a
+/// wider signed one. A signed integer meets an unsigned one at least as wide in the unsigned type,
+/// where a negative value changes sign; code generation warns of it. `bool`, `char` and `byte`
+/// are unsigned, and `isize` and `usize` rank as 64 bits wide.
+pub fn widen(a: &WaveType, b: &WaveType) -> Option<WaveType> {
+    if !is_number(a) || !is_number(b) {
+        return None;
+    }
+    let rank = |ty: &WaveType| int_rank(ty).unwrap_or(0);
+    let signed = |ty: &WaveType| matches!(ty, WaveType::Int(_));
+    let takes_a = match (a, b) {
+        (WaveType::Float(x), WaveType::Float(y)) => x >= y,
+        (WaveType::Float(_), _) => true,
+        (_, WaveType::Float(_)) => false,
+        _ if signed(a) && !signed(b) => rank(a) > rank(b),
+        _ => rank(a) >= rank(b),
+    };
+    Some(if takes_a { a } else { b }.clone())
+}
+
+fn is_number(ty: &WaveType) -> bool {
+    matches!(ty, WaveType::Float(_)) || int_width(ty).is_some()
+}
+
+fn int_width(ty: &WaveType) -> Option<u16> {
+    match ty {
+        WaveType::Int(bits) | WaveType::Uint(bits) => Some(*bits),
+        WaveType::Bool => Some(1),
+        WaveType::Char | WaveType::Byte => Some(8),
+        _ => None,
+    }
+}
+
+// The width an integer type ranks by in conversions, with `isize` and `usize` as 64 bits.
+fn int_rank(ty: &WaveType) -> Option<u16> {
+    match int_width(ty) {
+        Some(WaveType::POINTER_SIZED) => Some(64),
+        bits => bits,
+    }
+}
+
+// Whether a value of type `ty` crosses a C call as the C type of the same layout.
+fn is_c_type(ty: &WaveType) -> bool {
+    match ty {
+        WaveType::Int(bits) | WaveType::Uint(bits) => matches!(*bits, WaveType::POINTER_SIZED | 8 | 16 | 32 | 64),
+        WaveType::Float(bits) => matches!(bits, 32 | 64),
+        WaveType::Bool | WaveType::Char | WaveType::Byte | WaveType::Pointer(_) => true,
+        WaveType::Function(params, return_type) => params.iter().chain(return_type.as_deref()).all(is_c_type),
+        _ => false,
+    }
+}
+
+// The values an integer type holds; `char` and `byte` are unsigned. A constant is an `i64`,
+// so the range of a wider type is cut to what a constant can reach. That of `isize` and `usize`
+// depends on the target, so code generation checks their constants.
+fn int_range(ty: &WaveType) -> Option<(i128, i128)> {
+    let (bits, signed) = match ty {
+        WaveType::Int(WaveType::POINTER_SIZED) | WaveType::Uint(WaveType::POINTER_SIZED) => return None,
+        WaveType::Int(bits) => (u32::from(*bits).min(64), true),
+        WaveType::Uint(bits) => (u32::from(*bits).min(64), false),
+        WaveType::Char | WaveType::Byte => (8, false),
+        _ => return None,
+    };
+    match signed {
+        true => Some((-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)),
+        false => Some((0, (1i128 << bits) - 1)),
+    }
+}
+
+fn is_unsuffixed_literal(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Number(_) | Literal::Float(_)) => true,
+        Expression::Grouped(inner) => is_unsuffixed_literal(inner),
+        Expression::BinaryExpression { left, right, .. } => is_unsuffixed_literal(left) && is_unsuffixed_literal(right),
+        _ => false,
+    }
+}
+
+fn has_fraction(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Float(_)) => true,
+        Expression::Grouped(inner) => has_fraction(inner),
+        Expression::BinaryExpression { left, right, .. } => has_fraction(left) || has_fraction(right),
+        _ => false,
+    }
+}
+
+// The 1-based column where `expr` starts on `line`, the source line of a statement at `column`:
+// that of its leftmost name, looked for after the `=` of a declaration or assignment, or what
+// follows that `=` when `expr` starts with a literal. `None` when neither is found.
+fn expression_column(line: &str, expr: &Expression, column: usize) -> Option<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut start = column.saturating_sub(1);
+    let assigns = |i: usize| {
+        chars[i] == '=' && i > 0 && !matches!(chars[i - 1], '=' | '!' | '<' | '>') && chars.get(i + 1) != Some(&'=')
+    };
+    let equals = (start..chars.len()).find(|&i| assigns(i));
+    if let Some(equals) = equals {
+        start = equals + 1;
+    }
+    let Some(name) = leading_name(expr) else {
+        return equals.and_then(|_| (start..chars.len()).find(|&i| !chars[i].is_whitespace())).map(|i| i + 1);
+    };
+    let name: Vec<char> = name.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    (start..(chars.len() + 1).saturating_sub(name.len()))
+        .find(|&i| {
+            chars[i..i + name.len()] == name[..]
+                && (i == 0 || !is_word(&chars[i - 1]))
+                && chars.get(i + name.len()).is_none_or(|c| !is_word(c))
+        })
+        .map(|i| i + 1)
+}
+
+fn leading_name(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Variable(name) | Expression::FunctionCall { name, .. } => Some(name),
+        Expression::Grouped(inner) | Expression::Deref(inner) => leading_name(inner),
+        Expression::BinaryExpression { left: inner, .. }
+        | Expression::IndexAccess { target: inner, .. }
+        | Expression::FieldAccess { target: inner, .. }
+        | Expression::Cast { value: inner, .. } => leading_name(inner),
+        _ => None,
+    }
+}
+
+// An unsuffixed literal is named as such: its type is only a default.
+fn describe_found(expr: &Expression, found: &WaveType) -> String {
+    match expr {
+        Expression::Literal(Literal::Number(_)) => tr!("an integer literal"),
+        Expression::Literal(Literal::Float(_)) => tr!("a float literal"),
+        Expression::Grouped(inner) => describe_found(inner, found),
+        _ => format!("`{}`", found),
+    }
+}
+
+fn field_names(declaration: &StructNode) -> String {
+    let names: Vec<String> = declaration.fields.iter().map(|field| format!("`{}`", field.name)).collect();
+    names.join(", ")
+}
+
+fn variant_names(declaration: &EnumNode) -> String {
+    let names: Vec<String> = declaration.variants.iter().map(|variant| format!("`{}`", variant.name)).collect();
+    names.join(", ")
+}
+
+fn signature(function: &FunctionNode) -> String {
+    let mut parameters: Vec<String> = function
+        .parameters
+        .iter()
+        .map(|param| format!("{}: {}", param.name, param.param_type))
+        .collect();
+    if function.variadic {
+        parameters.push("...".to_string());
+    }
+    match &function.return_type {
+        Some(return_type) => format!("fun {}({}) -> {}", function.name, parameters.join("; "), return_type),
+        None => format!("fun {}({})", function.name, parameters.join("; ")),
+    }
+}
+
+fn operator_symbol(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Add => "+",
+        Operator::Subtract => "-",
+        Operator::Multiply => "*",
+        Operator::Divide => "/",
+        Operator::Remainder => "%",
+        Operator::GreaterEqual => ">=",
+        Operator::LessEqual => "<=",
+        Operator::Greater => ">",
+        Operator::Less => "<",
+        Operator::Equal => "==",
+        Operator::NotEqual => "!=",
+        Operator::LogicalAnd => "&&",
+        Operator::BitwiseAnd => "&",
+        Operator::LogicalOr => "||",
+        Operator::BitwiseOr => "|",
+        Operator::Assign => "=",
+    }
+}
+
+fn assign_operator_symbol(operator: &AssignOperator) -> &'static str {
+    match operator {
+        AssignOperator::Assign => "=",
+        AssignOperator::AddAssign => "+=",
+        AssignOperator::SubAssign => "-=",
+        AssignOperator::MulAssign => "*=",
+        AssignOperator::DivAssign => "/=",
+        AssignOperator::RemAssign => "%=",
+    }
+}
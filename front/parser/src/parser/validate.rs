@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+use unicode_security::skeleton;
+use std::path::{Path, PathBuf};
+use error::error::{WaveError, WaveErrorKind};
+use error::fixes::Suggestion;
+use error::tr;
+use lexer::{LexError, Token, TokenType};
+use crate::ast::*;
+use crate::callgraph::CallGraph;
+use crate::cfg::Cfg;
+use crate::overload::{same_parameters, symbol_name};
+use crate::visit::walk_expressions;
+
+/// One diagnostic per `Error` token the lexer produced for `source`, in source order.
+pub fn lexical_errors(tokens: &[Token], file: &Path, source: &str) -> Vec<WaveError> {
+    tokens
+        .iter()
+        .filter_map(|token| match &token.token_type {
+            TokenType::Error(error) => Some((token, error)),
+            _ => None,
+        })
+        .map(|(token, error)| {
+            let kind = match error {
+                LexError::UnexpectedChar(c) => WaveErrorKind::UnexpectedChar(*c),
+                other => WaveErrorKind::SyntaxError(other.to_string()),
+            };
+            let message = match error {
+                LexError::UnexpectedChar(c) => tr!("unexpected character {0}", format!("{:?}", c)),
+                LexError::UnterminatedString => tr!("unterminated string literal"),
+                LexError::UnterminatedChar => tr!("unterminated character literal"),
+                LexError::MultiCharLiteral => tr!("character literal may only contain one character"),
+                LexError::EmptyChar => tr!("empty character literal"),
+                LexError::UnknownEscape(c) => tr!("unknown escape `\\{0}` in character literal", c),
+                LexError::NonAsciiChar(c) => tr!("character literal {0} does not fit in a byte", format!("{:?}", c)),
+                LexError::UnterminatedComment => tr!("unterminated block comment"),
+                LexError::IntegerOutOfRange => tr!("integer literal `{0}` out of range", token.lexeme),
+            };
+            let (label, help) = match error {
+                LexError::UnexpectedChar('\\') => ("backslash escapes only work inside literals", None),
+                LexError::UnexpectedChar(_) => ("not valid in Wave source", Some("remove it, or put it inside a string literal")),
+                LexError::UnterminatedString => ("string starts here", Some("add a closing `\"`")),
+                LexError::UnterminatedChar => ("character literal starts here", Some("a character literal holds exactly one character, e.g. `'a'`")),
+                LexError::MultiCharLiteral => ("more than one character", Some("use a string literal, e.g. `\"ab\"`")),
+                LexError::EmptyChar => ("no character between the quotes", Some("use `'\\0'` for the zero byte")),
+                LexError::UnknownEscape(_) => ("unknown escape", Some("supported escapes are \\n, \\t, \\r, \\0, \\\\ and \\'")),
+                LexError::NonAsciiChar(_) => ("not a single byte", Some("use a string literal for non-ASCII text")),
+                LexError::UnterminatedComment => ("comment starts here", Some("add a closing `*/`")),
+                LexError::IntegerOutOfRange => ("too large", Some("an integer literal can be at most 9223372036854775807, the largest `i64`")),
+            };
+
+            let mut diagnostic = WaveError::new(
+                kind,
+                message,
+                file.display().to_string(),
+                token.line,
+                token.column,
+            )
+            .with_source(source.lines().nth(token.line.saturating_sub(1)).unwrap_or(""))
+            .with_label(tr!(label));
+            if let Some(help) = help {
+                diagnostic = diagnostic.with_help(tr!(help));
+            }
+
+            // 'ab' -> "ab"
+            let inner = token.lexeme.get(1..token.lexeme.len().saturating_sub(1)).unwrap_or("");
+            if *error == LexError::MultiCharLiteral && !inner.contains('"') {
+                diagnostic = diagnostic.with_suggestion(Suggestion::new(
+                    tr!("use a string literal"),
+                    token.line,
+                    token.column,
+                    token.lexeme.chars().count(),
+                    format!("\"{}\"", inner),
+                ));
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+/// Warns when two different identifiers in `tokens` look alike (same Unicode confusable
+/// skeleton), e.g. Latin `a` and Cyrillic `а`. Each confusable spelling is reported once,
+/// at its first use.
+pub fn confusable_identifier_warnings(tokens: &[Token], file: &Path, source: &str) -> Vec<WaveError> {
+    let mut first_by_skeleton: HashMap<String, &Token> = HashMap::new();
+    let mut reported: Vec<&str> = vec![];
+    let mut warnings = vec![];
+
+    for token in tokens {
+        let TokenType::Identifier(name) = &token.token_type else {
+            continue;
+        };
+
+        let key: String = skeleton(name).collect();
+        let Some(first) = first_by_skeleton.get(&key) else {
+            first_by_skeleton.insert(key, token);
+            continue;
+        };
+
+        // ASCII-only pairs such as `rn`/`m` or `l`/`I` are left alone, as in most editors' fonts they differ
+        let first_name = first.lexeme.as_str();
+        if first_name == name || (first_name.is_ascii() && name.is_ascii()) || reported.contains(&name.as_str()) {
+            continue;
+        }
+        reported.push(name);
+
+        warnings.push(
+            WaveError::new(
+                WaveErrorKind::ConfusableIdentifier(name.clone()),
+                tr!("identifier `{0}` looks like `{1}` but is a different name", name, first_name),
+                file.display().to_string(),
+                token.line,
+                token.column,
+            )
+            .as_warning()
+            .with_source(source.lines().nth(token.line.saturating_sub(1)).unwrap_or(""))
+            .with_label(tr!("confusable identifier"))
+            .with_note(tr!(
+                "`{0}` is first used at {1}",
+                first_name,
+                format!("{}:{}:{}", file.display(), first.line, first.column)
+            ))
+            .with_note(tr!("code points: {0}", describe_code_points(name)))
+            .with_help(tr!("rename one of them, or retype it so both use the same characters")),
+        );
+    }
+
+    warnings
+}
+
+fn describe_code_points(name: &str) -> String {
+    name.chars().map(|c| format!("U+{:04X}", c as u32)).collect::<Vec<_>>().join(" ")
+}
+
+/// Checks that the program has a `main` entry point the runtime can call:
+/// every parameter defaulted, returning nothing or `i32`.
+pub fn validate_main(nodes: &[ASTNode], file: &Path, source: &str) -> Result<(), Box<WaveError>> {
+    let file_name = file.display().to_string();
+
+    let main = nodes.iter().find_map(|node| match node {
+        ASTNode::Function(function) if function.name == "main" => Some(function),
+        _ => None,
+    });
+
+    let Some(main) = main else {
+        let line = source.lines().count().max(1);
+        let last_line = source.lines().last().unwrap_or("");
+        return Err(Box::new(WaveError::new(
+            WaveErrorKind::InvalidMain(file_name.to_string()),
+            tr!("`main` function not found in `{0}`", file_name),
+            file_name,
+            line,
+            last_line.chars().count() + 1,
+        )
+        .with_source(last_line)
+        .with_label(tr!("the program needs an entry point"))
+        .with_help(tr!("add `fun main() { ... }` to define where execution starts"))));
+    };
+
+    let required = main.parameters.iter().find(|param| param.initial_value.is_none());
+
+    let problem = if let Some(param) = required {
+        Some((
+            tr!("`main` parameter `{0}` has no default value", param.name),
+            tr!(
+                "nothing is passed to `main` at startup; give it a default, e.g. `{0}: {1} = ...`",
+                param.name, param.param_type
+            ),
+        ))
+    } else {
+        match &main.return_type {
+            None | Some(WaveType::Int(32)) => None,
+            Some(other) => Some((
+                tr!("`main` must return nothing or `i32`, found `{0}`", other),
+                tr!("declare it as `fun main()` or `fun main() -> i32`"),
+            )),
+        }
+    };
+
+    match problem {
+        Some((message, help)) => Err(Box::new(WaveError::new(
+            WaveErrorKind::InvalidMain(main.name.clone()),
+            message,
+            file_name,
+            main.span.line,
+            main.span.column,
+        )
+        .with_source(source.lines().nth(main.span.line.saturating_sub(1)).unwrap_or(""))
+        .with_label(tr!("`main` declared here"))
+        .with_help(help))),
+        None => Ok(()),
+    }
+}
+
+/// Collects the `#[bench]` functions of the main file for `wave bench`, which calls each one
+/// with no arguments.
+pub fn validate_benches(nodes: &[ASTNode], file: &Path) -> Result<Vec<String>, Box<WaveError>> {
+    let file_name = file.display().to_string();
+    let mut benches = vec![];
+
+    for node in nodes {
+        let ASTNode::Function(function) = node else {
+            continue;
+        };
+        if !function.is_bench() {
+            continue;
+        }
+
+        if let Some(param) = function.parameters.first() {
+            let mut error = WaveError::new(
+                WaveErrorKind::SemanticError("invalid bench".to_string()),
+                tr!("benchmark `{0}` takes parameters", function.name),
+                file_name,
+                param.span.line,
+                param.span.column,
+            )
+            .with_label(tr!("`{0}` would be called without a value", param.name))
+            .with_help(tr!("build the inputs inside the benchmark body instead"));
+            if let Some(line) = source_line(file, param.span.line) {
+                error = error.with_source(line);
+            }
+            return Err(Box::new(error));
+        }
+
+        benches.push(function.name.clone());
+    }
+
+    if benches.is_empty() {
+        return Err(Box::new(
+            WaveError::new(
+                WaveErrorKind::SemanticError("no benches".to_string()),
+                tr!("no `#[bench]` functions found in `{0}`", file_name),
+                file_name,
+                1,
+                1,
+            )
+            .with_help(tr!("mark a function with `#[bench]` to measure it")),
+        ));
+    }
+
+    Ok(benches)
+}
+
+/// Checks that the `#[ctor]` and `#[dtor]` functions of every file can be called as the
+/// program's startup and exit code call them: with no arguments, for no value.
+pub fn validate_constructors(units: &[(PathBuf, Vec<ASTNode>)]) -> Result<(), Box<WaveError>> {
+    for (file, nodes) in units {
+        for node in nodes {
+            let ASTNode::Function(function) = node else {
+                continue;
+            };
+            let attribute = match (function.is_constructor(), function.is_destructor()) {
+                (true, _) => "ctor",
+                (_, true) => "dtor",
+                _ => continue,
+            };
+
+            let (message, span, label, help) = if let Some(param) = function.parameters.first() {
+                (
+                    tr!("`#[{0}]` function `{1}` takes parameters", attribute, function.name),
+                    param.span,
+                    tr!("`{0}` would be called without a value", param.name),
+                    tr!("set up the values inside the body of `{0}` instead", function.name),
+                )
+            } else if let Some(return_type) = &function.return_type {
+                (
+                    tr!("`#[{0}]` function `{1}` returns `{2}`", attribute, function.name, return_type),
+                    function.span,
+                    tr!("nothing would receive the value"),
+                    tr!("declare it as `fun {0}()`", function.name),
+                )
+            } else {
+                continue;
+            };
+            let mut error = WaveError::new(
+                WaveErrorKind::SemanticError(format!("invalid {}", attribute)),
+                message,
+                file.display().to_string(),
+                span.line,
+                span.column,
+            )
+            .with_label(label)
+            .with_help(help);
+            if let Some(line) = source_line(file, span.line) {
+                error = error.with_source(line);
+            }
+            return Err(Box::new(error));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that no function or type is defined twice across the main file and its
+/// imports, and that no function declares the same parameter twice. `units` pairs each
+/// file with the nodes parsed from it. Structs and enums share one namespace of types.
+/// Functions may share a name when their parameter types differ, except `main`, the
+/// `#[bench]` functions and `extern` functions, which are called by name.
+pub fn validate_definitions(units: &[(PathBuf, Vec<ASTNode>)]) -> Result<(), Box<WaveError>> {
+    let mut functions: HashMap<&str, Vec<(&FunctionNode, &Path, bool)>> = HashMap::new();
+    let mut types: HashMap<&str, (&Path, Span)> = HashMap::new();
+
+    for (file, nodes) in units {
+        for node in nodes {
+            let declared_type = match node {
+                ASTNode::Struct(declaration) => Some(("struct", &declaration.name, declaration.span)),
+                ASTNode::Enum(declaration) => Some(("enum", &declaration.name, declaration.span)),
+                _ => None,
+            };
+            if let Some((kind, name, span)) = declared_type {
+                if let Some((first_file, first_span)) = types.get(name.as_str()) {
+                    return Err(duplicate_error(kind, name, file, span, first_file, *first_span));
+                }
+                types.insert(name, (file, span));
+            }
+
+            let (function, is_extern) = match node {
+                ASTNode::Function(function) => (function, false),
+                ASTNode::Extern(function) => (function, true),
+                _ => continue,
+            };
+
+            let overloads = functions.entry(&function.name).or_default();
+            let clash = overloads.iter().find(|(first, _, first_extern)| {
+                same_parameters(first, function)
+                    || function.name == "main"
+                    || first.is_bench()
+                    || function.is_bench()
+                    || is_extern
+                    || *first_extern
+            });
+            if let Some((first, first_file, first_extern)) = clash {
+                let mut error = duplicate_error("function", &function.name, file, function.span, first_file, first.span);
+                if is_extern || *first_extern {
+                    error.notes.push(tr!("an `extern` function is called by its name, so no other function can share it"));
+                } else if same_parameters(first, function) && function.name != "main" {
+                    error.notes.push(tr!("functions that share a name must differ in their parameter types"));
+                }
+                return Err(error);
+            }
+            overloads.push((function, file, is_extern));
+
+            let mut parameters: HashMap<&str, Span> = HashMap::new();
+            for param in &function.parameters {
+                if let Some(first_span) = parameters.get(param.name.as_str()) {
+                    return Err(duplicate_error("parameter", &param.name, file, param.span, file, *first_span));
+                }
+                parameters.insert(&param.name, param.span);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warning for a function that `main` never reaches; such functions are left out of code generation.
+pub fn unused_function_warning(function: &FunctionNode, file: &Path) -> WaveError {
+    let mut warning = WaveError::new(
+        WaveErrorKind::UnusedFunction(function.name.clone()),
+        tr!("function `{0}` is never used", function.name),
+        file.display().to_string(),
+        function.span.line,
+        function.span.column,
+    )
+    .as_warning()
+    .with_label(tr!("not reachable from `main`"))
+    .with_note(tr!("no code is generated for it"));
+
+    if let Some(line) = source_line(file, function.span.line) {
+        warning = warning.with_source(line);
+    }
+
+    warning
+}
+
+/// Warning for a function that can end up calling itself, directly or through other functions
+/// of `nodes`, when no call on the way passes a decreasing argument such as `n - 1` or `n / 2`.
+/// Each cycle is reported once, at its first function in `nodes`; `#[allow(recursion)]` on any
+/// function of the cycle silences it.
+pub fn recursion_warning(function: &FunctionNode, nodes: &[ASTNode], call_graph: &CallGraph, file: &Path) -> Option<WaveError> {
+    let symbol = symbol_name(function, nodes);
+    let cycle = call_graph.cycle_through(&symbol)?;
+    let find = |symbol: &str| {
+        nodes.iter().enumerate().find_map(|(index, node)| match node {
+            ASTNode::Function(function) if symbol_name(function, nodes) == symbol => Some((index, function)),
+            _ => None,
+        })
+    };
+    let members: Vec<(usize, &FunctionNode)> = cycle.iter().filter_map(|symbol| find(symbol)).collect();
+    let first = members.iter().map(|(index, _)| *index).min()?;
+
+    if members.iter().any(|(_, member)| member.allows("recursion")) || find(&symbol)?.0 != first {
+        return None;
+    }
+    let decreases = members.windows(2).any(|pair| every_call_decreases(pair[0].1, &pair[1].1.name));
+    if decreases {
+        return None;
+    }
+
+    let message = if cycle.len() == 2 {
+        tr!("function `{0}` calls itself without a decreasing argument", function.name)
+    } else {
+        tr!("function `{0}` calls itself through `{1}` without a decreasing argument", function.name, members[1].1.name)
+    };
+    let mut warning = WaveError::new(
+        WaveErrorKind::Recursion(function.name.clone()),
+        message,
+        file.display().to_string(),
+        function.span.line,
+        function.span.column,
+    )
+    .as_warning()
+    .with_label(tr!("nothing shows this recursion ends"));
+
+    if cycle.len() > 2 {
+        let chain: Vec<String> = members.iter().map(|(_, member)| format!("`{}`", member.name)).collect();
+        warning = warning.with_note(tr!("the calls go {0}", chain.join(" -> ")));
+    }
+    warning = warning
+        .with_note(tr!("each call takes another stack frame; without an end it overflows the stack"))
+        .with_help(tr!(
+            "pass a smaller value on the recursive call, e.g. `n - 1`, or mark `{0}` with `#[allow(recursion)]`",
+            function.name
+        ));
+    if let Some(line) = source_line(file, function.span.line) {
+        warning = warning.with_source(line);
+    }
+
+    Some(warning)
+}
+
+// Every call from `caller` to `callee` passes at least one argument that shrinks a variable.
+fn every_call_decreases(caller: &FunctionNode, callee: &str) -> bool {
+    let mut decreases = true;
+    walk_expressions(&caller.body, &mut |expr| {
+        if let Expression::FunctionCall { name, args } = expr {
+            if name == callee && !args.iter().any(is_decreasing) {
+                decreases = false;
+            }
+        }
+    });
+    decreases
+}
+
+// `n - k` for a positive `k`, or `n / k` for `k` of at least 2.
+fn is_decreasing(arg: &Expression) -> bool {
+    match arg {
+        Expression::Grouped(inner) => is_decreasing(inner),
+        Expression::BinaryExpression { left, operator, right } => {
+            let Expression::Variable(_) = &**left else {
+                return false;
+            };
+            match (operator, &**right) {
+                (Operator::Subtract, Expression::Literal(Literal::Number(k))) => *k > 0,
+                (Operator::Divide, Expression::Literal(Literal::Number(k))) => *k >= 2,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Diagnostics from the control-flow graph of `function`: a missing `return` in a function
+/// with a return type, unreachable statements, variables read before they are assigned,
+/// constant conditions, and loops that never run or never end.
+pub fn control_flow_diagnostics(function: &FunctionNode, file: &Path) -> Vec<WaveError> {
+    let cfg = Cfg::build(function);
+    let mut diagnostics = vec![];
+
+    for &(statement, value) in &cfg.constant_conditions {
+        let span = node_span(statement).unwrap_or(function.span);
+        let kind = WaveErrorKind::ConstantCondition(function.name.clone());
+        let diagnostic = match (statement, value) {
+            // `while true` is how an endless loop is written; it is checked below instead.
+            (ASTNode::Statement(StatementNode::While { .. } | StatementNode::For { .. }, _), true) => continue,
+            (ASTNode::Statement(StatementNode::While { .. } | StatementNode::For { .. }, _), false) => {
+                diagnostic_at(kind, tr!("loop body never runs"), file, span)
+                    .with_label(tr!("condition is always `false`"))
+            }
+            (ASTNode::Statement(StatementNode::If { else_if_blocks, else_block, .. }, _), true) => {
+                let diagnostic = diagnostic_at(kind, tr!("condition is always `true`"), file, span)
+                    .with_label(tr!("always taken"));
+                if else_if_blocks.is_some() || else_block.is_some() {
+                    diagnostic.with_note(tr!("the branches after it never run"))
+                } else {
+                    diagnostic
+                }
+            }
+            (_, _) => {
+                diagnostic_at(kind, tr!("condition is always `false`"), file, span)
+                    .with_label(tr!("never taken"))
+                    .with_note(tr!("the body of this `if` never runs"))
+            }
+        };
+        diagnostics.push(diagnostic.as_warning());
+    }
+
+    if function.return_type.is_some() && !cfg.reachable().contains(&Cfg::EXIT) {
+        // A `loop` says that it does not end; only `while` and `for` are flagged.
+        for statement in cfg.endless_loops() {
+            if matches!(statement, ASTNode::Statement(StatementNode::Loop { .. }, _)) {
+                continue;
+            }
+            let span = node_span(statement).unwrap_or(function.span);
+            diagnostics.push(
+                diagnostic_at(
+                    WaveErrorKind::ConstantCondition(function.name.clone()),
+                    tr!("loop never exits, so `{0}` never returns a value", function.name),
+                    file,
+                    span,
+                )
+                .as_warning()
+                .with_label(tr!("nothing breaks out of this loop"))
+                .with_help(tr!("add a `break` or a `return` inside the loop")),
+            );
+        }
+    }
+
+    if let Some(return_type) = &function.return_type {
+        if cfg.falls_through() {
+            diagnostics.push(
+                diagnostic_at(
+                    WaveErrorKind::MissingReturn(function.name.clone()),
+                    tr!("function `{0}` can reach its end without returning a value", function.name),
+                    file,
+                    function.span,
+                )
+                .with_label(tr!("declared to return `{0}`", return_type))
+                .with_help(tr!("end every path through `{0}` with a `return`", function.name)),
+            );
+        }
+    }
+
+    for statement in cfg.unreachable_statements() {
+        let span = node_span(statement).unwrap_or(function.span);
+        diagnostics.push(
+            diagnostic_at(WaveErrorKind::UnreachableCode(function.name.clone()), tr!("unreachable statement"), file, span)
+                .as_warning()
+                .with_label(tr!("unreachable"))
+                .with_note(tr!("every path before it leaves through `return`, `break` or `continue`")),
+        );
+    }
+
+    for (statement, name) in cfg.uninitialized_reads() {
+        let span = node_span(statement).unwrap_or(function.span);
+        diagnostics.push(
+            diagnostic_at(
+                WaveErrorKind::UninitializedVariable(name.to_string()),
+                tr!("variable `{0}` is read before it is assigned", name),
+                file,
+                span,
+            )
+            .with_label(tr!("`{0}` may be uninitialized here", name))
+            .with_help(tr!("give `{0}` an initial value where it is declared", name)),
+        );
+    }
+
+    diagnostics
+}
+
+pub(crate) fn node_span(node: &ASTNode) -> Option<Span> {
+    let span = match node {
+        ASTNode::Statement(_, span) => *span,
+        ASTNode::Variable(variable) => variable.span,
+        _ => return None,
+    };
+    (span != Span::default()).then_some(span)
+}
+
+pub(crate) fn diagnostic_at(kind: WaveErrorKind, message: String, file: &Path, span: Span) -> WaveError {
+    let mut error = WaveError::new(kind, message, file.display().to_string(), span.line, span.column);
+    if let Some(line) = source_line(file, span.line) {
+        error = error.with_source(line);
+    }
+    error
+}
+
+fn duplicate_error(kind: &str, name: &str, file: &Path, span: Span, first_file: &Path, first_span: Span) -> Box<WaveError> {
+    let mut error = WaveError::new(
+        WaveErrorKind::SemanticError(format!("duplicate {}", kind)),
+        tr!("{0} `{1}` is defined more than once", tr!(kind), name),
+        file.display().to_string(),
+        span.line,
+        span.column,
+    )
+    .with_label(tr!("`{0}` redefined here", name))
+    .with_note(tr!(
+        "first defined at {0}",
+        format!("{}:{}:{}", first_file.display(), first_span.line, first_span.column)
+    ))
+    .with_help(tr!("rename or remove one of the definitions of `{0}`", name));
+
+    if let Some(line) = source_line(file, span.line) {
+        error = error.with_source(line);
+    }
+
+    Box::new(error)
+}
+
+fn source_line(file: &Path, line: usize) -> Option<String> {
+    let source = std::fs::read_to_string(file).ok()?;
+    source.lines().nth(line.checked_sub(1)?).map(str::to_string)
+}
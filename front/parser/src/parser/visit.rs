@@ -0,0 +1,271 @@
+use crate::ast::*;
+
+/// Calls `f` on every expression reachable from `nodes`, including nested subexpressions,
+/// in source order (parents before children).
+pub fn walk_expressions(nodes: &[ASTNode], f: &mut dyn FnMut(&Expression)) {
+    for node in nodes {
+        walk_node(node, f);
+    }
+}
+
+fn walk_node(node: &ASTNode, f: &mut dyn FnMut(&Expression)) {
+    match node {
+        ASTNode::Function(function) => walk_expressions(&function.body, f),
+        ASTNode::Variable(var) => {
+            walk_type(&var.type_name, f);
+            if let Some(value) = &var.initial_value {
+                walk_expression(value, f);
+            }
+        }
+        ASTNode::Expression(expr) => walk_expression(expr, f),
+        ASTNode::Statement(stmt, _) => walk_statement(stmt, f),
+        ASTNode::Macro(mac) => match &mac.body {
+            MacroBody::Expression(expr) => walk_expression(expr, f),
+            MacroBody::Block(body) => walk_expressions(body, f),
+        },
+        ASTNode::Program(_) | ASTNode::Struct(_) | ASTNode::Enum(_) | ASTNode::Extern(_) => {}
+    }
+}
+
+fn walk_statement(stmt: &StatementNode, f: &mut dyn FnMut(&Expression)) {
+    match stmt {
+        StatementNode::PrintFormat { args, .. } | StatementNode::PrintlnFormat { args, .. } => {
+            for arg in args {
+                walk_expression(arg, f);
+            }
+        }
+        StatementNode::If { condition, body, else_if_blocks, else_block } => {
+            walk_expression(condition, f);
+            walk_expressions(body, f);
+            if let Some(blocks) = else_if_blocks {
+                walk_expressions(blocks, f);
+            }
+            if let Some(block) = else_block {
+                walk_expressions(block, f);
+            }
+        }
+        StatementNode::For { initialization, condition, increment, body, .. } => {
+            walk_expression(initialization, f);
+            walk_expression(condition, f);
+            walk_expression(increment, f);
+            walk_expressions(body, f);
+        }
+        StatementNode::ForRange { start, end, body, .. } => {
+            walk_expression(start, f);
+            walk_expression(end, f);
+            walk_expressions(body, f);
+        }
+        StatementNode::While { condition, body, .. } => {
+            walk_expression(condition, f);
+            walk_expressions(body, f);
+        }
+        StatementNode::Loop { body, .. } => walk_expressions(body, f),
+        StatementNode::Assign { value, .. } => walk_expression(value, f),
+        StatementNode::Return(Some(value)) | StatementNode::Break { value: Some(value), .. } => walk_expression(value, f),
+        StatementNode::Expression(expr) => walk_expression(expr, f),
+        StatementNode::Match { value, arms } => {
+            walk_expression(value, f);
+            for arm in arms {
+                walk_expressions(&arm.body, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_type(wave_type: &WaveType, f: &mut dyn FnMut(&Expression)) {
+    match wave_type {
+        WaveType::TypeOf(expr) => walk_expression(expr, f),
+        WaveType::Pointer(inner) | WaveType::Array(inner, _) => walk_type(inner, f),
+        _ => {}
+    }
+}
+
+pub fn walk_expression(expr: &Expression, f: &mut dyn FnMut(&Expression)) {
+    f(expr);
+
+    match expr {
+        Expression::FunctionCall { args, .. } | Expression::EnumVariant { args, .. } => {
+            for arg in args {
+                walk_expression(arg, f);
+            }
+        }
+        Expression::Deref(inner) | Expression::AddressOf(inner) | Expression::Grouped(inner) => {
+            walk_expression(inner, f)
+        }
+        Expression::FieldAccess { target, .. } => walk_expression(target, f),
+        Expression::Cast { value, ty } => {
+            walk_expression(value, f);
+            walk_type(ty, f);
+        }
+        Expression::Alloc { ty, count } => {
+            walk_type(ty, f);
+            if let Some(count) = count {
+                walk_expression(count, f);
+            }
+        }
+        Expression::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                walk_expression(value, f);
+            }
+        }
+        Expression::BinaryExpression { left, right, .. } => {
+            walk_expression(left, f);
+            walk_expression(right, f);
+        }
+        Expression::IndexAccess { target, index } => {
+            walk_expression(target, f);
+            walk_expression(index, f);
+        }
+        Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                walk_expression(element, f);
+            }
+        }
+        Expression::AssignOperation { target, value, .. } | Expression::Assignment { target, value } => {
+            walk_expression(target, f);
+            walk_expression(value, f);
+        }
+        Expression::While { condition, body, .. } => {
+            walk_expression(condition, f);
+            walk_expressions(body, f);
+        }
+        Expression::Loop { body, .. } => walk_expressions(body, f),
+        Expression::Literal(_) | Expression::Variable(_) | Expression::AsmBlock { .. } => {}
+    }
+}
+
+/// Like `walk_expressions`, with `f` free to rewrite each expression before its
+/// subexpressions (those of the rewritten one) are visited.
+pub fn walk_expressions_mut(nodes: &mut [ASTNode], f: &mut dyn FnMut(&mut Expression)) {
+    for node in nodes {
+        walk_node_mut(node, f);
+    }
+}
+
+fn walk_node_mut(node: &mut ASTNode, f: &mut dyn FnMut(&mut Expression)) {
+    match node {
+        ASTNode::Function(function) => walk_expressions_mut(&mut function.body, f),
+        ASTNode::Variable(var) => {
+            walk_type_mut(&mut var.type_name, f);
+            if let Some(value) = &mut var.initial_value {
+                walk_expression_mut(value, f);
+            }
+        }
+        ASTNode::Expression(expr) => walk_expression_mut(expr, f),
+        ASTNode::Statement(stmt, _) => walk_statement_mut(stmt, f),
+        ASTNode::Macro(mac) => match &mut mac.body {
+            MacroBody::Expression(expr) => walk_expression_mut(expr, f),
+            MacroBody::Block(body) => walk_expressions_mut(body, f),
+        },
+        ASTNode::Program(_) | ASTNode::Struct(_) | ASTNode::Enum(_) | ASTNode::Extern(_) => {}
+    }
+}
+
+fn walk_statement_mut(stmt: &mut StatementNode, f: &mut dyn FnMut(&mut Expression)) {
+    match stmt {
+        StatementNode::PrintFormat { args, .. } | StatementNode::PrintlnFormat { args, .. } => {
+            for arg in args {
+                walk_expression_mut(arg, f);
+            }
+        }
+        StatementNode::If { condition, body, else_if_blocks, else_block } => {
+            walk_expression_mut(condition, f);
+            walk_expressions_mut(body, f);
+            if let Some(blocks) = else_if_blocks {
+                walk_expressions_mut(blocks, f);
+            }
+            if let Some(block) = else_block {
+                walk_expressions_mut(block, f);
+            }
+        }
+        StatementNode::For { initialization, condition, increment, body, .. } => {
+            walk_expression_mut(initialization, f);
+            walk_expression_mut(condition, f);
+            walk_expression_mut(increment, f);
+            walk_expressions_mut(body, f);
+        }
+        StatementNode::ForRange { start, end, body, .. } => {
+            walk_expression_mut(start, f);
+            walk_expression_mut(end, f);
+            walk_expressions_mut(body, f);
+        }
+        StatementNode::While { condition, body, .. } => {
+            walk_expression_mut(condition, f);
+            walk_expressions_mut(body, f);
+        }
+        StatementNode::Loop { body, .. } => walk_expressions_mut(body, f),
+        StatementNode::Assign { value, .. } => walk_expression_mut(value, f),
+        StatementNode::Return(Some(value)) | StatementNode::Break { value: Some(value), .. } => walk_expression_mut(value, f),
+        StatementNode::Expression(expr) => walk_expression_mut(expr, f),
+        StatementNode::Match { value, arms } => {
+            walk_expression_mut(value, f);
+            for arm in arms {
+                walk_expressions_mut(&mut arm.body, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_type_mut(wave_type: &mut WaveType, f: &mut dyn FnMut(&mut Expression)) {
+    match wave_type {
+        WaveType::TypeOf(expr) => walk_expression_mut(expr, f),
+        WaveType::Pointer(inner) | WaveType::Array(inner, _) => walk_type_mut(inner, f),
+        _ => {}
+    }
+}
+
+pub fn walk_expression_mut(expr: &mut Expression, f: &mut dyn FnMut(&mut Expression)) {
+    f(expr);
+
+    match expr {
+        Expression::FunctionCall { args, .. } | Expression::EnumVariant { args, .. } => {
+            for arg in args {
+                walk_expression_mut(arg, f);
+            }
+        }
+        Expression::Deref(inner) | Expression::AddressOf(inner) | Expression::Grouped(inner) => {
+            walk_expression_mut(inner, f)
+        }
+        Expression::FieldAccess { target, .. } => walk_expression_mut(target, f),
+        Expression::Cast { value, ty } => {
+            walk_expression_mut(value, f);
+            walk_type_mut(ty, f);
+        }
+        Expression::Alloc { ty, count } => {
+            walk_type_mut(ty, f);
+            if let Some(count) = count {
+                walk_expression_mut(count, f);
+            }
+        }
+        Expression::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                walk_expression_mut(value, f);
+            }
+        }
+        Expression::BinaryExpression { left, right, .. } => {
+            walk_expression_mut(left, f);
+            walk_expression_mut(right, f);
+        }
+        Expression::IndexAccess { target, index } => {
+            walk_expression_mut(target, f);
+            walk_expression_mut(index, f);
+        }
+        Expression::ArrayLiteral(elements) => {
+            for element in elements {
+                walk_expression_mut(element, f);
+            }
+        }
+        Expression::AssignOperation { target, value, .. } | Expression::Assignment { target, value } => {
+            walk_expression_mut(target, f);
+            walk_expression_mut(value, f);
+        }
+        Expression::While { condition, body, .. } => {
+            walk_expression_mut(condition, f);
+            walk_expressions_mut(body, f);
+        }
+        Expression::Loop { body, .. } => walk_expressions_mut(body, f),
+        Expression::Literal(_) | Expression::Variable(_) | Expression::AsmBlock { .. } => {}
+    }
+}
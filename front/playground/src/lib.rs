@@ -0,0 +1,269 @@
+//! The checks of the compiler for the online playground, built for the browser with
+//! `cargo build -p playground --target wasm32-unknown-unknown --release`. The LLVM backend is
+//! left out, so a program is checked but not compiled.
+//!
+//! JavaScript passes a program by asking `wave_alloc` for `len` bytes of the module's memory,
+//! writing the UTF-8 source there and calling `wave_check` or `wave_ast` with the address and
+//! length. Those return the length of their JSON answer, which starts at `wave_output()` and
+//! stays there until the next call. `wave_dealloc` gives the source's bytes back.
+
+use std::cell::RefCell;
+use std::path::Path;
+use error::error::{Severity, WaveError};
+use lexer::Lexer;
+use parser::ast::{ASTNode, WaveType};
+use parser::parse_program;
+
+thread_local! {
+    static OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+// The name diagnostics give the program, which has no file of its own. Imports are looked up
+// next to it and are never found.
+const FILE_NAME: &str = "playground.wave";
+
+/// `len` bytes for JavaScript to write a program into.
+#[no_mangle]
+pub extern "C" fn wave_alloc(len: usize) -> *mut u8 {
+    let mut bytes = Vec::<u8>::with_capacity(len);
+    let ptr = bytes.as_mut_ptr();
+    std::mem::forget(bytes);
+    ptr
+}
+
+/// Gives back bytes from `wave_alloc`.
+///
+/// # Safety
+///
+/// `ptr` and `len` must be an address `wave_alloc(len)` returned, not given back yet.
+#[no_mangle]
+pub unsafe extern "C" fn wave_dealloc(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// The first byte of the answer of the last `wave_check` or `wave_ast`.
+#[no_mangle]
+pub extern "C" fn wave_output() -> *const u8 {
+    OUTPUT.with(|output| output.borrow().as_ptr())
+}
+
+/// Checks the program of `len` bytes at `ptr` and answers
+/// `{"diagnostics": [...], "summary": ...}`: the errors and warnings, each with `severity`,
+/// `code`, `message`, `line`, `column`, `label`, `help` and `notes`, and what stopped the
+/// checks when it was not a diagnostic, or `null`.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wave_check(ptr: *const u8, len: usize) -> usize {
+    let json = match source(ptr, len) {
+        Ok(source) => check_json(source),
+        Err(summary) => format!("{{\"diagnostics\":[],\"summary\":{}}}", string(summary)),
+    };
+    answer(json)
+}
+
+/// Parses the program of `len` bytes at `ptr` and answers `{"items": [...]}`: its functions,
+/// structs and enums in declaration order, for an outline of the program. When it does not
+/// parse, `{"error": ..., "line": ..., "column": ...}`: why, and where parsing stopped.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wave_ast(ptr: *const u8, len: usize) -> usize {
+    let json = match source(ptr, len) {
+        Ok(source) => ast_json(source),
+        Err(error) => format!("{{\"error\":{}}}", string(error)),
+    };
+    answer(json)
+}
+
+/// The answer `wave_check` gives for `source`.
+pub fn check_json(source: &str) -> String {
+    let (diagnostics, summary) = match wavec::check_source(source, Path::new(FILE_NAME), Path::new("."), false) {
+        Ok(checked) => (checked.warnings, None),
+        Err(failed) => (failed.diagnostics, failed.summary),
+    };
+    let diagnostics: Vec<String> = diagnostics.iter().map(diagnostic).collect();
+    let summary = summary.as_deref().map_or_else(|| "null".to_string(), string);
+    format!("{{\"diagnostics\":[{}],\"summary\":{}}}", diagnostics.join(","), summary)
+}
+
+/// The answer `wave_ast` gives for `source`.
+pub fn ast_json(source: &str) -> String {
+    let tokens = Lexer::new(source).tokenize();
+    let ast = match parse_program(&tokens) {
+        Ok(ast) => ast,
+        Err(error) => return format!("{{\"error\":{},\"line\":{},\"column\":{}}}", string(&error.message), error.line, error.column),
+    };
+    let items: Vec<String> = ast.iter().filter_map(item).collect();
+    format!("{{\"items\":[{}]}}", items.join(","))
+}
+
+unsafe fn source<'a>(ptr: *const u8, len: usize) -> Result<&'a str, &'static str> {
+    let bytes = if len == 0 { &[][..] } else { std::slice::from_raw_parts(ptr, len) };
+    std::str::from_utf8(bytes).map_err(|_| "the program is not valid UTF-8")
+}
+
+fn answer(json: String) -> usize {
+    let len = json.len();
+    OUTPUT.with(|output| *output.borrow_mut() = json);
+    len
+}
+
+fn diagnostic(error: &WaveError) -> String {
+    let severity = match error.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let notes: Vec<String> = error.notes.iter().map(|note| string(note)).collect();
+    format!(
+        "{{\"severity\":\"{}\",\"code\":\"{}\",\"message\":{},\"line\":{},\"column\":{},\"label\":{},\"help\":{},\"notes\":[{}]}}",
+        severity,
+        error.kind.code(),
+        string(&error.message),
+        error.line,
+        error.column,
+        optional(error.label.as_deref()),
+        optional(error.help.as_deref()),
+        notes.join(","),
+    )
+}
+
+fn item(node: &ASTNode) -> Option<String> {
+    match node {
+        ASTNode::Function(function) => {
+            let params: Vec<String> = function
+                .parameters
+                .iter()
+                .map(|param| format!("{{\"name\":{},\"type\":{}}}", string(&param.name), type_name(&param.param_type)))
+                .collect();
+            let return_type = function.return_type.as_ref().map_or_else(|| "null".to_string(), type_name);
+            Some(format!(
+                "{{\"kind\":\"function\",\"name\":{},\"line\":{},\"column\":{},\"params\":[{}],\"return_type\":{}}}",
+                string(&function.name),
+                function.span.line,
+                function.span.column,
+                params.join(","),
+                return_type,
+            ))
+        }
+        ASTNode::Struct(struct_node) => {
+            let fields: Vec<String> = struct_node
+                .fields
+                .iter()
+                .map(|field| format!("{{\"name\":{},\"type\":{}}}", string(&field.name), type_name(&field.field_type)))
+                .collect();
+            Some(format!(
+                "{{\"kind\":\"struct\",\"name\":{},\"line\":{},\"column\":{},\"fields\":[{}]}}",
+                string(&struct_node.name),
+                struct_node.span.line,
+                struct_node.span.column,
+                fields.join(","),
+            ))
+        }
+        ASTNode::Enum(enum_node) => {
+            let variants: Vec<String> = enum_node
+                .variants
+                .iter()
+                .map(|variant| {
+                    let fields: Vec<String> = variant.fields.iter().map(type_name).collect();
+                    format!("{{\"name\":{},\"fields\":[{}]}}", string(&variant.name), fields.join(","))
+                })
+                .collect();
+            Some(format!(
+                "{{\"kind\":\"enum\",\"name\":{},\"line\":{},\"column\":{},\"variants\":[{}]}}",
+                string(&enum_node.name),
+                enum_node.span.line,
+                enum_node.span.column,
+                variants.join(","),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn type_name(ty: &WaveType) -> String {
+    string(&ty.to_string())
+}
+
+fn optional(text: Option<&str>) -> String {
+    text.map_or_else(|| "null".to_string(), string)
+}
+
+// `text` as a JSON string, quotes included.
+fn string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output() -> String {
+        OUTPUT.with(|output| output.borrow().clone())
+    }
+
+    #[test]
+    fn check_reports_a_type_error() {
+        let json = check_json("fun main() {\n    var flag: bool = \"yes\";\n}\n");
+        assert!(json.starts_with("{\"diagnostics\":[{\"severity\":\"error\",\"code\":\"E0007\""), "{}", json);
+        assert!(json.contains("\"line\":2,"), "{}", json);
+        assert!(json.ends_with(",\"summary\":null}"), "{}", json);
+    }
+
+    #[test]
+    fn check_reports_a_parse_error_where_parsing_stopped() {
+        let json = check_json("fun main() {\n    var x: i32 = ;\n}\n");
+        assert!(json.starts_with("{\"diagnostics\":[{\"severity\":\"error\",\"code\":\"E0004\""), "{}", json);
+        assert!(json.contains("\"message\":\"Unexpected `;`\",\"line\":2,\"column\":18,"), "{}", json);
+        assert!(json.ends_with(",\"summary\":\"Failed to parse Wave code\"}"), "{}", json);
+    }
+
+    #[test]
+    fn check_accepts_a_valid_program() {
+        assert_eq!(check_json("fun main() {\n    println(\"hi\");\n}\n"), "{\"diagnostics\":[],\"summary\":null}");
+    }
+
+    #[test]
+    fn ast_lists_the_items() {
+        let json = ast_json("struct Point {\n    x: i32;\n}\n\nfun main() {\n}\n");
+        assert_eq!(
+            json,
+            "{\"items\":[{\"kind\":\"struct\",\"name\":\"Point\",\"line\":1,\"column\":1,\"fields\":[{\"name\":\"x\",\"type\":\"i32\"}]},\
+             {\"kind\":\"function\",\"name\":\"main\",\"line\":5,\"column\":1,\"params\":[],\"return_type\":null}]}"
+        );
+    }
+
+    #[test]
+    fn ast_reports_a_parse_error_where_parsing_stopped() {
+        assert_eq!(ast_json("fun main() {\n    var x: i32 = ;\n}\n"), "{\"error\":\"Unexpected `;`\",\"line\":2,\"column\":18}");
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported() {
+        let bytes = [b'f', 0xff, b'n'];
+        let len = unsafe { wave_check(bytes.as_ptr(), bytes.len()) };
+        assert_eq!(output(), "{\"diagnostics\":[],\"summary\":\"the program is not valid UTF-8\"}");
+        assert_eq!(len, output().len());
+
+        unsafe { wave_ast(bytes.as_ptr(), bytes.len()) };
+        assert_eq!(output(), "{\"error\":\"the program is not valid UTF-8\"}");
+    }
+}
@@ -0,0 +1,176 @@
+use inkwell::context::Context;
+use inkwell::module::Linkage;
+use inkwell::types::StructType;
+use inkwell::values::{FunctionValue, GlobalValue, PointerValue};
+use inkwell::{AddressSpace, IntPredicate};
+use crate::llvm_temporary::llvm_codegen::value_name;
+
+// The innermost frame of the running thread, null outside of Wave functions.
+const FRAME_TOP: &str = "wave.frames";
+const PRINT_BACKTRACE: &str = "wave.print_backtrace";
+
+// A frame record, `{ caller's record, function name, line }`, lives on the stack of its function.
+// The line is that of the statement being run, which for a caller is the one calling.
+fn frame_type<'ctx>(context: &'ctx Context) -> StructType<'ctx> {
+    context.get_struct_type("wave.frame").unwrap_or_else(|| {
+        let frame = context.opaque_struct_type("wave.frame");
+        let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+        frame.set_body(&[i8_ptr.into(), i8_ptr.into(), context.i32_type().into()], false);
+        frame
+    })
+}
+
+fn frame_top<'ctx>(module: &'ctx inkwell::module::Module<'ctx>) -> Option<GlobalValue<'ctx>> {
+    module.get_global(FRAME_TOP)
+}
+
+/// `-g`: makes every Wave function keep a record of itself on a list of frames, so that a
+/// failed runtime check can print the Wave call stack. Called before any function is lowered.
+/// A foreign exception unwinding through Wave frames (`--panic=unwind`) skips their `return`s,
+/// so a later backtrace can list frames that are gone.
+pub fn enable_backtraces<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) {
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let top = module.add_global(i8_ptr, None, FRAME_TOP);
+    top.set_linkage(Linkage::Internal);
+    top.set_thread_local(true);
+    top.set_initializer(&i8_ptr.const_null());
+}
+
+/// At the entry of `function`, called `name` and defined at `line`: pushes its frame record.
+/// Does nothing unless backtraces are enabled.
+pub fn generate_frame_push<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    name: &str,
+    line: usize,
+) {
+    let Some(top) = frame_top(module) else { return };
+    let frame_type = frame_type(context);
+    let frame = builder.build_alloca(frame_type, "wave.frame").unwrap();
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+
+    let caller = builder.build_load(top.as_pointer_value(), &value_name("caller_frame")).unwrap();
+    let name = builder.build_global_string_ptr(name, "wave.frame_name").unwrap();
+    let fields = [caller, name.as_pointer_value().into(), context.i32_type().const_int(line as u64, false).into()];
+    for (i, value) in fields.into_iter().enumerate() {
+        let field = builder.build_struct_gep(frame, i as u32, "").unwrap();
+        builder.build_store(field, value).unwrap();
+    }
+    let frame = builder.build_pointer_cast(frame, i8_ptr, "").unwrap();
+    builder.build_store(top.as_pointer_value(), frame).unwrap();
+}
+
+/// Before a `return`: pops the frame of the function returning, the innermost one.
+pub fn generate_frame_pop<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) {
+    let Some(top) = frame_top(module) else { return };
+    let frame = innermost_frame(context, builder, top);
+    let caller = builder.build_struct_gep(frame, 0, "").unwrap();
+    let caller = builder.build_load(caller, &value_name("caller_frame")).unwrap();
+    builder.build_store(top.as_pointer_value(), caller).unwrap();
+}
+
+/// Records `line` as the one the innermost frame is running.
+pub fn generate_frame_line<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    line: usize,
+) {
+    let Some(top) = frame_top(module) else { return };
+    if line == 0 {
+        return;
+    }
+    let frame = innermost_frame(context, builder, top);
+    let field = builder.build_struct_gep(frame, 2, "").unwrap();
+    builder.build_store(field, context.i32_type().const_int(line as u64, false)).unwrap();
+}
+
+fn innermost_frame<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    top: GlobalValue<'ctx>,
+) -> PointerValue<'ctx> {
+    let frame = builder.build_load(top.as_pointer_value(), &value_name("frame")).unwrap().into_pointer_value();
+    let frame_ptr = frame_type(context).ptr_type(AddressSpace::default());
+    builder.build_pointer_cast(frame, frame_ptr, "").unwrap()
+}
+
+/// In a block that aborts: prints the Wave call stack to stderr, innermost frame first, as
+/// `  0: divide, line 3`. Does nothing unless backtraces are enabled.
+pub fn generate_backtrace_print<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    dprintf: FunctionValue<'ctx>,
+) {
+    if frame_top(module).is_none() {
+        return;
+    }
+    let print = module
+        .get_function(PRINT_BACKTRACE)
+        .unwrap_or_else(|| generate_print_backtrace_function(context, module, dprintf));
+    builder.build_call(print, &[], "").unwrap();
+}
+
+// `wave.print_backtrace()`, which walks the frame records from the innermost one.
+fn generate_print_backtrace_function<'ctx>(
+    context: &'ctx Context,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    dprintf: FunctionValue<'ctx>,
+) -> FunctionValue<'ctx> {
+    let function = module.add_function(PRINT_BACKTRACE, context.void_type().fn_type(&[], false), Some(Linkage::Internal));
+    let builder = context.create_builder();
+    let entry = context.append_basic_block(function, "entry");
+    let walk = context.append_basic_block(function, "walk");
+    let print = context.append_basic_block(function, "print");
+    let done = context.append_basic_block(function, "done");
+
+    let i32_type = context.i32_type();
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let stderr = i32_type.const_int(2, false);
+
+    builder.position_at_end(entry);
+    let header = builder.build_global_string_ptr("stack backtrace:\n", "bt_header").unwrap();
+    let format = builder.build_global_string_ptr("  %d: %s, line %d\n", "bt_frame").unwrap();
+    builder.build_call(dprintf, &[stderr.into(), header.as_pointer_value().into()], "").unwrap();
+    let first = builder.build_load(frame_top(module).unwrap().as_pointer_value(), "first").unwrap();
+    builder.build_unconditional_branch(walk).unwrap();
+
+    builder.position_at_end(walk);
+    let frame = builder.build_phi(i8_ptr, "frame").unwrap();
+    let depth = builder.build_phi(i32_type, "depth").unwrap();
+    frame.add_incoming(&[(&first, entry)]);
+    depth.add_incoming(&[(&i32_type.const_zero(), entry)]);
+    let frame_value = frame.as_basic_value().into_pointer_value();
+    let is_last = builder.build_is_null(frame_value, "is_last").unwrap();
+    builder.build_conditional_branch(is_last, done, print).unwrap();
+
+    builder.position_at_end(print);
+    let record = builder.build_pointer_cast(frame_value, frame_type(context).ptr_type(AddressSpace::default()), "record").unwrap();
+    let caller = builder.build_struct_gep(record, 0, "").unwrap();
+    let caller = builder.build_load(caller, "caller").unwrap();
+    let name = builder.build_struct_gep(record, 1, "").unwrap();
+    let name = builder.build_load(name, "name").unwrap();
+    let line = builder.build_struct_gep(record, 2, "").unwrap();
+    let line = builder.build_load(line, "line").unwrap();
+    let depth_value = depth.as_basic_value().into_int_value();
+    builder
+        .build_call(dprintf, &[stderr.into(), format.as_pointer_value().into(), depth_value.into(), name.into(), line.into()], "")
+        .unwrap();
+    let next_depth = builder.build_int_add(depth_value, i32_type.const_int(1, false), "next_depth").unwrap();
+    // A corrupted list could loop; no real stack is this deep.
+    let too_deep = builder.build_int_compare(IntPredicate::UGE, next_depth, i32_type.const_int(1 << 16, false), "too_deep").unwrap();
+    let next = builder.build_select(too_deep, i8_ptr.const_null(), caller.into_pointer_value(), "next").unwrap();
+    frame.add_incoming(&[(&next, print)]);
+    depth.add_incoming(&[(&next_depth, print)]);
+    builder.build_unconditional_branch(walk).unwrap();
+
+    builder.position_at_end(done);
+    builder.build_return(None).unwrap();
+    function
+}
@@ -0,0 +1,170 @@
+use inkwell::context::Context;
+use inkwell::values::{BasicValue, CallableValue, FunctionValue, IntValue, PointerValue};
+use inkwell::{AddressSpace, IntPredicate};
+use crate::llvm_temporary::llvm_codegen::generate_string_literal;
+
+/// Untimed calls made before measuring, so caches and branch predictors settle.
+const WARMUP_ITERATIONS: u64 = 100;
+/// The iteration count doubles until one timed run takes at least this long.
+const TARGET_NANOS: u64 = 100_000_000;
+const MAX_ITERATIONS: u64 = 1 << 30;
+const CLOCK_MONOTONIC: u64 = 1;
+
+/// Stack slots shared by every benchmark in the generated `main`.
+struct BenchSlots<'ctx> {
+    counter: PointerValue<'ctx>,
+    iterations: PointerValue<'ctx>,
+    timespec: PointerValue<'ctx>,
+    callee: PointerValue<'ctx>,
+}
+
+/// Emits a `main` that times each of `benches` in order and prints one `ns/iter` line per benchmark.
+///
+/// Benchmarks are called through a pointer reloaded with a volatile load on every iteration,
+/// and their results are stored volatile, so the optimizer can neither hoist nor drop the calls.
+pub fn generate_bench_main<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    benches: &[FunctionValue<'ctx>],
+) {
+    let i64_type = context.i64_type();
+    let main = module.add_function("main", context.i32_type().fn_type(&[], false), None);
+    builder.position_at_end(context.append_basic_block(main, "entry"));
+
+    let timespec_type = context.struct_type(&[i64_type.into(), i64_type.into()], false);
+    let slots = BenchSlots {
+        counter: builder.build_alloca(i64_type, "bench_counter").unwrap(),
+        iterations: builder.build_alloca(i64_type, "bench_iterations").unwrap(),
+        timespec: builder.build_alloca(timespec_type, "bench_timespec").unwrap(),
+        callee: builder.build_alloca(context.i8_type().ptr_type(AddressSpace::default()), "bench_callee").unwrap(),
+    };
+    let sinks: Vec<_> = benches
+        .iter()
+        .map(|bench| bench.get_type().get_return_type().map(|ty| builder.build_alloca(ty, "bench_sink").unwrap()))
+        .collect();
+
+    let printf_type = context.i32_type().fn_type(&[context.i8_type().ptr_type(AddressSpace::default()).into()], true);
+    let printf_func = match module.get_function("printf") {
+        Some(f) => f,
+        None => module.add_function("printf", printf_type, None),
+    };
+
+    let width = benches.iter().map(|bench| bench.get_name().to_bytes().len()).max().unwrap_or(0);
+
+    for (bench, sink) in benches.iter().zip(sinks) {
+        let name = bench.get_name().to_string_lossy().into_owned();
+        let callee = bench.as_global_value().as_pointer_value();
+        let callee = builder.build_pointer_cast(callee, context.i8_type().ptr_type(AddressSpace::default()), "callee").unwrap();
+        builder.build_store(slots.callee, callee).unwrap();
+
+        generate_call_loop(context, builder, main, *bench, sink, &slots, i64_type.const_int(WARMUP_ITERATIONS, false));
+        builder.build_store(slots.iterations, i64_type.const_int(1, false)).unwrap();
+
+        let calibrate = context.append_basic_block(main, "bench_calibrate");
+        let grow = context.append_basic_block(main, "bench_grow");
+        let report = context.append_basic_block(main, "bench_report");
+        builder.build_unconditional_branch(calibrate).unwrap();
+
+        builder.position_at_end(calibrate);
+        let count = builder.build_load(slots.iterations, "iterations").unwrap().into_int_value();
+        let start = generate_now_nanos(context, builder, module, &slots);
+        generate_call_loop(context, builder, main, *bench, sink, &slots, count);
+        let end = generate_now_nanos(context, builder, module, &slots);
+        let elapsed = builder.build_int_sub(end, start, "elapsed").unwrap();
+
+        let too_short = builder.build_int_compare(
+            IntPredicate::ULT, elapsed, i64_type.const_int(TARGET_NANOS, false), "too_short",
+        ).unwrap();
+        let below_max = builder.build_int_compare(
+            IntPredicate::ULT, count, i64_type.const_int(MAX_ITERATIONS, false), "below_max",
+        ).unwrap();
+        let again = builder.build_and(too_short, below_max, "again").unwrap();
+        builder.build_conditional_branch(again, grow, report).unwrap();
+
+        builder.position_at_end(grow);
+        let doubled = builder.build_int_mul(count, i64_type.const_int(2, false), "doubled").unwrap();
+        builder.build_store(slots.iterations, doubled).unwrap();
+        builder.build_unconditional_branch(calibrate).unwrap();
+
+        builder.position_at_end(report);
+        let per_iter = builder.build_int_unsigned_div(elapsed, count, "ns_per_iter").unwrap();
+        let format = format!("bench {:<width$} ... %ld ns/iter (%ld iterations)\n", name, width = width);
+        let format = generate_string_literal(context, builder, module, &format, &format!("bench_fmt_{}", name));
+        builder.build_call(printf_func, &[format.into(), per_iter.into(), count.into()], "printf_call").unwrap();
+    }
+
+    builder.build_return(Some(&context.i32_type().const_zero())).unwrap();
+}
+
+// for (counter = 0; counter < count; counter++) (*callee)();
+fn generate_call_loop<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    main: FunctionValue<'ctx>,
+    bench: FunctionValue<'ctx>,
+    sink: Option<PointerValue<'ctx>>,
+    slots: &BenchSlots<'ctx>,
+    count: IntValue<'ctx>,
+) {
+    let i64_type = context.i64_type();
+    let cond = context.append_basic_block(main, "bench_loop_cond");
+    let body = context.append_basic_block(main, "bench_loop_body");
+    let done = context.append_basic_block(main, "bench_loop_done");
+
+    builder.build_store(slots.counter, i64_type.const_zero()).unwrap();
+    builder.build_unconditional_branch(cond).unwrap();
+
+    builder.position_at_end(cond);
+    let index = builder.build_load(slots.counter, "index").unwrap().into_int_value();
+    let more = builder.build_int_compare(IntPredicate::ULT, index, count, "more").unwrap();
+    builder.build_conditional_branch(more, body, done).unwrap();
+
+    builder.position_at_end(body);
+    let callee = builder.build_load(slots.callee, "callee").unwrap();
+    callee.as_instruction_value().unwrap().set_volatile(true).unwrap();
+    let fn_ptr_type = bench.get_type().ptr_type(AddressSpace::default());
+    let callee = builder.build_pointer_cast(callee.into_pointer_value(), fn_ptr_type, "bench_fn").unwrap();
+    let callee = CallableValue::try_from(callee).unwrap();
+    let call = builder.build_call(callee, &[], "bench_call").unwrap();
+    if let (Some(sink), Some(result)) = (sink, call.try_as_basic_value().left()) {
+        builder.build_store(sink, result).unwrap().set_volatile(true).unwrap();
+    }
+    let next = builder.build_int_add(index, i64_type.const_int(1, false), "next").unwrap();
+    builder.build_store(slots.counter, next).unwrap();
+    builder.build_unconditional_branch(cond).unwrap();
+
+    builder.position_at_end(done);
+}
+
+// clock_gettime(CLOCK_MONOTONIC, &ts); ts.tv_sec * 1e9 + ts.tv_nsec
+fn generate_now_nanos<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    slots: &BenchSlots<'ctx>,
+) -> IntValue<'ctx> {
+    let i64_type = context.i64_type();
+    let clock_type = context.i32_type().fn_type(
+        &[context.i32_type().into(), slots.timespec.get_type().into()],
+        false,
+    );
+    let clock_gettime = match module.get_function("clock_gettime") {
+        Some(f) => f,
+        None => module.add_function("clock_gettime", clock_type, None),
+    };
+
+    builder.build_call(
+        clock_gettime,
+        &[context.i32_type().const_int(CLOCK_MONOTONIC, false).into(), slots.timespec.into()],
+        "clock_call",
+    ).unwrap();
+
+    let sec_ptr = builder.build_struct_gep(slots.timespec, 0, "tv_sec").unwrap();
+    let nsec_ptr = builder.build_struct_gep(slots.timespec, 1, "tv_nsec").unwrap();
+    let sec = builder.build_load(sec_ptr, "sec").unwrap().into_int_value();
+    let nsec = builder.build_load(nsec_ptr, "nsec").unwrap().into_int_value();
+
+    let sec_nanos = builder.build_int_mul(sec, i64_type.const_int(1_000_000_000, false), "sec_nanos").unwrap();
+    builder.build_int_add(sec_nanos, nsec, "now").unwrap()
+}
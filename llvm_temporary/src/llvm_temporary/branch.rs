@@ -0,0 +1,75 @@
+use inkwell::basic_block::BasicBlock;
+use inkwell::builder::Builder;
+use inkwell::context::Context;
+use inkwell::values::{BasicValueEnum, IntValue};
+use inkwell::{FloatPredicate, IntPredicate};
+use crate::llvm_temporary::llvm_codegen::value_name;
+use crate::llvm_temporary::statement::block_terminated;
+
+/// A two-way branch that produces a value, such as `a && b`: the caller lowers the then arm,
+/// passes its value to `else_arm`, lowers the else arm and passes that value to `merge`, which
+/// joins the two with a phi. Both values must have the same type.
+pub struct ValueBranch<'ctx> {
+    else_block: BasicBlock<'ctx>,
+    merge_block: BasicBlock<'ctx>,
+    name: String,
+    incoming: Vec<(BasicValueEnum<'ctx>, BasicBlock<'ctx>)>,
+}
+
+impl<'ctx> ValueBranch<'ctx> {
+    /// Branches on `condition` and leaves the builder at the start of the then arm. The blocks
+    /// and the phi are named after `name`.
+    pub fn begin(context: &'ctx Context, builder: &Builder<'ctx>, condition: IntValue<'ctx>, name: &str) -> Self {
+        let function = builder.get_insert_block().unwrap().get_parent().unwrap();
+        let then_block = context.append_basic_block(function, &format!("{}.then", name));
+        let else_block = context.append_basic_block(function, &format!("{}.else", name));
+        let merge_block = context.append_basic_block(function, &format!("{}.end", name));
+        let _ = builder.build_conditional_branch(condition, then_block, else_block);
+        builder.position_at_end(then_block);
+        ValueBranch { else_block, merge_block, name: name.to_string(), incoming: vec![] }
+    }
+
+    /// Ends the then arm with its value and moves the builder to the else arm.
+    pub fn else_arm(&mut self, builder: &Builder<'ctx>, value: BasicValueEnum<'ctx>) {
+        self.finish_arm(builder, value);
+        builder.position_at_end(self.else_block);
+    }
+
+    /// Ends the else arm with its value and leaves the builder in the block after the branch,
+    /// returning the value of whichever arm ran. `None` when neither arm gets there, e.g.
+    /// because both `return`.
+    pub fn merge(mut self, builder: &Builder<'ctx>, value: BasicValueEnum<'ctx>) -> Option<BasicValueEnum<'ctx>> {
+        self.finish_arm(builder, value);
+        builder.position_at_end(self.merge_block);
+        let (first, _) = self.incoming.first()?;
+        let phi = builder.build_phi(first.get_type(), &value_name(&format!("{}.value", self.name))).unwrap();
+        for (value, block) in &self.incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+        Some(phi.as_basic_value())
+    }
+
+    // An arm that already left, through `return` or `break`, adds nothing to the phi.
+    fn finish_arm(&mut self, builder: &Builder<'ctx>, value: BasicValueEnum<'ctx>) {
+        if block_terminated(builder) {
+            return;
+        }
+        self.incoming.push((value, builder.get_insert_block().unwrap()));
+        let _ = builder.build_unconditional_branch(self.merge_block);
+    }
+}
+
+/// `value` as a condition: an `i1` that is true unless the number is zero. `None` for a value
+/// that is not a number.
+pub fn truth_value<'ctx>(builder: &Builder<'ctx>, value: BasicValueEnum<'ctx>, name: &str) -> Option<IntValue<'ctx>> {
+    match value {
+        BasicValueEnum::IntValue(value) if value.get_type().get_bit_width() == 1 => Some(value),
+        BasicValueEnum::IntValue(value) => {
+            Some(builder.build_int_compare(IntPredicate::NE, value, value.get_type().const_zero(), &value_name(name)).unwrap())
+        }
+        BasicValueEnum::FloatValue(value) => {
+            Some(builder.build_float_compare(FloatPredicate::ONE, value, value.get_type().const_float(0.0), &value_name(name)).unwrap())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use inkwell::{AddressSpace, IntPredicate};
+use inkwell::context::Context;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicTypeEnum, IntType};
+use inkwell::values::{BasicValue, BasicValueEnum, IntValue};
+use parser::ast::{Expression, Literal};
+use crate::llvm_temporary::expression::{generate_expression_ir, is_unsigned_integer};
+use crate::llvm_temporary::string::{build_str, generate_str_slice, is_str_type, str_parts};
+use error::tr;
+use crate::llvm_temporary::llvm_codegen::{generate_runtime_trap, invalid, llvm_type_name, value_name, CodegenError, CodegenOptions, ShiftAmount, VariableInfo};
+
+/// Every name `generate_builtin_call` recognizes, offered as spelling suggestions.
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "count_ones", "leading_zeros", "trailing_zeros", "byte_swap",
+    "to_le", "to_be", "from_le", "from_be",
+    "to_le_bytes", "to_be_bytes", "from_le_bytes", "from_be_bytes",
+    "include_bytes", "include_str",
+    "len", "slice",
+    "dealloc", "flush",
+    "rotate_left", "rotate_right",
+    "shift_left", "shift_right",
+    "wrapping_add", "wrapping_sub", "wrapping_mul",
+    "saturating_add", "saturating_sub", "saturating_mul",
+    "checked_add", "checked_sub", "checked_mul",
+];
+
+/// Lowers a call to a compiler builtin. Returns `None` when `name` is not a builtin,
+/// so the caller can fall back to a regular function call.
+pub fn generate_builtin_call<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<Option<BasicValueEnum<'ctx>>, CodegenError> {
+    match name {
+        "count_ones" | "leading_zeros" | "trailing_zeros" | "byte_swap" => {
+            return generate_bit_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        "to_le" | "to_be" | "from_le" | "from_be" => {
+            return generate_endian_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        "to_le_bytes" | "to_be_bytes" | "from_le_bytes" | "from_be_bytes" => {
+            return generate_bytes_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        "include_bytes" | "include_str" => {
+            return generate_include_builtin(context, builder, name, args, module, expected_type, options).map(Some);
+        }
+        "rotate_left" | "rotate_right" => {
+            return generate_rotate_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        "shift_left" | "shift_right" => {
+            return generate_shift_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        "len" | "slice" => {
+            return generate_string_builtin(context, builder, name, args, variables, module, expected_type, options).map(Some);
+        }
+        _ => {}
+    }
+
+    let Some((mode, op)) = name.split_once('_') else {
+        return Ok(None);
+    };
+    if !matches!(mode, "wrapping" | "saturating" | "checked") || !matches!(op, "add" | "sub" | "mul") {
+        return Ok(None);
+    }
+
+    expect_arg_count(name, args, 2)?;
+
+    let signed = !args.iter().any(|arg| is_unsigned_integer(arg, variables));
+    let (lhs, rhs) = generate_int_operands(context, builder, name, args, signed, variables, module, expected_type, options)?;
+
+    let result = match mode {
+        "wrapping" => match op {
+            "add" => builder.build_int_add(lhs, rhs, &value_name("wrapping_add")).unwrap(),
+            "sub" => builder.build_int_sub(lhs, rhs, &value_name("wrapping_sub")).unwrap(),
+            _ => builder.build_int_mul(lhs, rhs, &value_name("wrapping_mul")).unwrap(),
+        },
+        "saturating" if op != "mul" => {
+            let intrinsic_name = format!("llvm.{}{}.sat", if signed { "s" } else { "u" }, op);
+            call_int_intrinsic(builder, module, &intrinsic_name, lhs, rhs)
+                .into_int_value()
+        }
+        "saturating" => {
+            let (product, overflow) = generate_overflow_op(builder, module, op, signed, lhs, rhs);
+            let int_ty = lhs.get_type();
+            let limit = if signed {
+                let negative = builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::SLT,
+                        builder.build_xor(lhs, rhs, "sign").unwrap(),
+                        int_ty.const_zero(),
+                        "neg_result",
+                    )
+                    .unwrap();
+                builder
+                    .build_select(negative, signed_min(int_ty), signed_max(int_ty), &value_name("sat_limit"))
+                    .unwrap()
+                    .into_int_value()
+            } else {
+                int_ty.const_all_ones()
+            };
+            builder
+                .build_select(overflow, limit, product, &value_name("saturating_mul"))
+                .unwrap()
+                .into_int_value()
+        }
+        _ => {
+            let (value, overflow) = generate_overflow_op(builder, module, op, signed, lhs, rhs);
+            generate_runtime_trap(context, builder, module, overflow, &format!("arithmetic overflow in {}", name));
+            value
+        }
+    };
+
+    Ok(Some(result.as_basic_value_enum()))
+}
+
+fn generate_bit_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 1)?;
+
+    // The bits are counted or swapped at the operand's own width, a bare literal's being i32 as
+    // type checking has it; only the result is converted to the expected type.
+    let value = generate_expression_ir(context, builder, &args[0], variables, module, None, options)?;
+    let value = expect_int(value, name)?;
+    let int_ty = value.get_type();
+
+    let result = if name == "byte_swap" {
+        generate_byte_swap(builder, module, value)?
+    } else {
+        let intrinsic_name = match name {
+            "count_ones" => "llvm.ctpop",
+            "leading_zeros" => "llvm.ctlz",
+            _ => "llvm.cttz",
+        };
+        let intrinsic = Intrinsic::find(intrinsic_name)
+            .unwrap_or_else(|| panic!("LLVM intrinsic '{}' not found", intrinsic_name));
+        let function = intrinsic
+            .get_declaration(module, &[int_ty.into()])
+            .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic '{}'", intrinsic_name));
+
+        // ctlz/cttz take an `is_zero_poison` flag; zero input must yield the bit width.
+        let mut call_args = vec![value.into()];
+        if matches!(name, "leading_zeros" | "trailing_zeros") {
+            call_args.push(context.bool_type().const_zero().into());
+        }
+
+        builder
+            .build_call(function, &call_args, &value_name(name))
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value()
+    };
+
+    // A count is never negative; swapped bytes keep the operand's signedness.
+    let signed = name == "byte_swap" && !is_unsigned_integer(&args[0], variables);
+    Ok(fit_expected(builder, result, expected_type, signed).as_basic_value_enum())
+}
+
+fn generate_rotate_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 2)?;
+
+    // Rotated at the value's own width, the amount cast to it.
+    let signed = !is_unsigned_integer(&args[0], variables);
+    let (value, amount) = generate_int_operands(context, builder, name, args, false, variables, module, None, options)?;
+
+    // A funnel shift with both halves set to the same value is a rotate.
+    let intrinsic_name = if name == "rotate_left" { "llvm.fshl" } else { "llvm.fshr" };
+    let intrinsic = Intrinsic::find(intrinsic_name)
+        .unwrap_or_else(|| panic!("LLVM intrinsic '{}' not found", intrinsic_name));
+    let function = intrinsic
+        .get_declaration(module, &[value.get_type().into()])
+        .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic '{}'", intrinsic_name));
+
+    let rotated = builder
+        .build_call(function, &[value.into(), value.into(), amount.into()], &value_name(name))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    Ok(fit_expected(builder, rotated, expected_type, signed).as_basic_value_enum())
+}
+
+// `shift_right` of a signed value copies the sign bit in, of an unsigned one shifts in zeros.
+// LLVM leaves a shift by the width or more undefined, so the amount is first brought in range
+// as `options.shift_amount` says.
+fn generate_shift_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 2)?;
+
+    let signed = !is_unsigned_integer(&args[0], variables);
+    let (value, amount) = generate_int_operands(context, builder, name, args, false, variables, module, expected_type, options)?;
+    let int_ty = value.get_type();
+    let width = int_ty.get_bit_width();
+
+    let bound = int_ty.const_int(width as u64, false);
+    let amount = if options.shift_amount == ShiftAmount::Trap || options.check_ub {
+        let too_far = builder.build_int_compare(IntPredicate::UGE, amount, bound, &value_name("shift_too_far")).unwrap();
+        generate_runtime_trap(context, builder, module, too_far, &format!("shift amount out of range (the width is {})", width));
+        amount
+    } else if width.is_power_of_two() {
+        builder.build_and(amount, int_ty.const_int(width as u64 - 1, false), &value_name("shift_amount")).unwrap()
+    } else {
+        // Unsigned, so a negative amount wraps to a large one as the hardware sees it.
+        builder.build_int_unsigned_rem(amount, bound, &value_name("shift_amount")).unwrap()
+    };
+
+    let result = match name {
+        "shift_left" => builder.build_left_shift(value, amount, &value_name(name)).unwrap(),
+        _ => builder.build_right_shift(value, amount, signed, &value_name(name)).unwrap(),
+    };
+    Ok(result.as_basic_value_enum())
+}
+
+fn generate_endian_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 1)?;
+
+    let value = generate_expression_ir(context, builder, &args[0], variables, module, expected_type, options)?;
+    let value = expect_int(value, name)?;
+    let value = match expected_type {
+        Some(BasicTypeEnum::IntType(int_ty)) => cast_int(builder, value, int_ty, false),
+        _ => value,
+    };
+
+    // Wave only targets little-endian machines, so the `le` variants are the identity.
+    if name.ends_with("_le") {
+        Ok(value.as_basic_value_enum())
+    } else {
+        Ok(generate_byte_swap(builder, module, value)?.as_basic_value_enum())
+    }
+}
+
+fn generate_bytes_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 1)?;
+    let big_endian = name.contains("_be_");
+
+    if name.starts_with("to_") {
+        let operand_type = match (&args[0], expected_type) {
+            (Expression::Literal(_), Some(BasicTypeEnum::ArrayType(array_ty))) => {
+                Some(context.custom_width_int_type(array_ty.len() * 8).into())
+            }
+            _ => None,
+        };
+        let value = generate_expression_ir(context, builder, &args[0], variables, module, operand_type, options)?;
+        let value = expect_int(value, name)?;
+        let value = if big_endian { generate_byte_swap(builder, module, value)? } else { value };
+
+        let byte_count = value.get_type().get_bit_width().div_ceil(8);
+        let array_type = context.i8_type().array_type(byte_count);
+        let bytes = builder.build_alloca(array_type, &value_name("bytes_tmp")).unwrap();
+        let int_ptr = builder
+            .build_pointer_cast(bytes, value.get_type().ptr_type(AddressSpace::default()), &value_name("bytes_as_int"))
+            .unwrap();
+        builder.build_store(int_ptr, value).unwrap();
+
+        Ok(builder.build_load(bytes, &value_name(name)).unwrap())
+    } else {
+        let value = generate_expression_ir(context, builder, &args[0], variables, module, None, options)?;
+        let bytes = match value {
+            BasicValueEnum::ArrayValue(array) => {
+                let tmp = builder.build_alloca(array.get_type(), &value_name("bytes_tmp")).unwrap();
+                builder.build_store(tmp, array).unwrap();
+                tmp
+            }
+            BasicValueEnum::PointerValue(ptr) if ptr.get_type().get_element_type().is_array_type() => ptr,
+            other => return Err(not_a_byte_array(name, other.get_type())),
+        };
+
+        let array_type = bytes.get_type().get_element_type().into_array_type();
+        if array_type.get_element_type() != context.i8_type().into() {
+            return Err(not_a_byte_array(name, array_type.into()));
+        }
+
+        let int_ty = context.custom_width_int_type(array_type.len() * 8);
+        let int_ptr = builder
+            .build_pointer_cast(bytes, int_ty.ptr_type(AddressSpace::default()), &value_name("bytes_as_int"))
+            .unwrap();
+        let value = builder.build_load(int_ptr, &value_name("bytes_load")).unwrap().into_int_value();
+        let value = if big_endian { generate_byte_swap(builder, module, value)? } else { value };
+
+        let value = match expected_type {
+            Some(BasicTypeEnum::IntType(target_ty)) => cast_int(builder, value, target_ty, false),
+            _ => value,
+        };
+        Ok(value.as_basic_value_enum())
+    }
+}
+
+fn generate_byte_swap<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodegenError> {
+    let bit_width = value.get_type().get_bit_width();
+    if bit_width == 8 {
+        return Ok(value);
+    }
+    if !bit_width.is_multiple_of(16) {
+        return Err(invalid(
+            "byte swap width",
+            tr!("swapping bytes needs an integer width that is a multiple of 16, found `i{0}`", bit_width),
+        ));
+    }
+
+    let intrinsic = Intrinsic::find("llvm.bswap").unwrap();
+    let function = intrinsic
+        .get_declaration(module, &[value.get_type().into()])
+        .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic 'llvm.bswap'"));
+
+    Ok(builder
+        .build_call(function, &[value.into()], &value_name("bswap"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value())
+}
+
+fn generate_include_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, 1)?;
+
+    let Expression::Literal(Literal::String(path)) = &args[0] else {
+        return Err(invalid("include path", tr!("builtin `{0}` expects a string literal path", name)));
+    };
+    let full_path = match &options.source_dir {
+        Some(dir) => dir.join(path),
+        None => Path::new(path).to_path_buf(),
+    };
+    let mut bytes = fs::read(&full_path).map_err(|e| {
+        invalid("include read", tr!("cannot read `{0}`: {1}", full_path.display(), e))
+    })?;
+
+    let text_len = bytes.len();
+    if name == "include_str" {
+        if std::str::from_utf8(&bytes).is_err() {
+            return Err(invalid("include utf-8", tr!("`{0}` is not valid UTF-8", full_path.display())));
+        }
+        bytes.push(0);
+    }
+
+    let array_type = context.i8_type().array_type(bytes.len() as u32);
+    let global = module.add_global(array_type, None, &format!("{}_{}", name, path));
+    global.set_initializer(&context.const_string(&bytes, false));
+    global.set_linkage(Linkage::Private);
+    global.set_constant(true);
+
+    match expected_type {
+        Some(BasicTypeEnum::ArrayType(expected)) if name == "include_bytes" => {
+            if expected.len() != array_type.len() {
+                return Err(invalid(
+                    "include size",
+                    tr!("`{0}` is {1} bytes, but the target array holds {2}", full_path.display(), array_type.len(), expected.len()),
+                ));
+            }
+            Ok(builder.build_load(global.as_pointer_value(), &value_name(name)).unwrap())
+        }
+        _ => {
+            let zero = context.i32_type().const_zero();
+            let gep = unsafe { builder.build_gep(global.as_pointer_value(), &[zero, zero], &value_name(name)).unwrap() };
+            match expected_type {
+                Some(BasicTypeEnum::PointerType(_)) => Ok(gep.as_basic_value_enum()),
+                _ if name == "include_str" => {
+                    let len = context.i64_type().const_int(text_len as u64, false);
+                    Ok(build_str(context, builder, gep, len).as_basic_value_enum())
+                }
+                _ => Ok(gep.as_basic_value_enum()),
+            }
+        }
+    }
+}
+
+// `len(s)`, the number of bytes of `s`, at the width the context expects (`i64` otherwise), and
+// `slice(s, start, end)`, the bytes from `start` up to `end`.
+fn generate_string_builtin<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    expect_arg_count(name, args, if name == "len" { 1 } else { 3 })?;
+
+    let string = match generate_expression_ir(context, builder, &args[0], variables, module, None, options)? {
+        BasicValueEnum::StructValue(string) if is_str_type(string.get_type().into()) => string,
+        other => return Err(invalid(
+            "builtin operand",
+            tr!("builtin `{0}` expects a `str`, found `{1}`", name, llvm_type_name(other.get_type())),
+        )),
+    };
+
+    if name == "len" {
+        let (_, len) = str_parts(builder, string);
+        let len = match expected_type {
+            Some(BasicTypeEnum::IntType(int_ty)) => cast_int(builder, len, int_ty, false),
+            _ => len,
+        };
+        return Ok(len.as_basic_value_enum());
+    }
+
+    let i64_type = context.i64_type();
+    let mut bound = |arg: &Expression| -> Result<IntValue<'ctx>, CodegenError> {
+        let value = generate_expression_ir(context, builder, arg, variables, module, Some(i64_type.into()), options)?;
+        Ok(cast_int(builder, expect_int(value, name)?, i64_type, true))
+    };
+    let start = bound(&args[1])?;
+    let end = bound(&args[2])?;
+    Ok(generate_str_slice(context, builder, module, string, start, end).as_basic_value_enum())
+}
+
+fn generate_int_operands<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    signed: bool,
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<(IntValue<'ctx>, IntValue<'ctx>), CodegenError> {
+    let expected_int = match expected_type {
+        Some(BasicTypeEnum::IntType(int_ty)) => Some(int_ty),
+        _ => None,
+    };
+
+    // Literals take their width from the other operand, so evaluate the non-literal side first.
+    let first = if matches!(args[0], Expression::Literal(_)) && !matches!(args[1], Expression::Literal(_)) { 1 } else { 0 };
+    let first_val = generate_expression_ir(context, builder, &args[first], variables, module, expected_int.map(|t| t.into()), options)?;
+    let first_val = expect_int(first_val, name)?;
+    let int_ty = expected_int.unwrap_or(first_val.get_type());
+    let second_val = generate_expression_ir(context, builder, &args[1 - first], variables, module, Some(int_ty.into()), options)?;
+    let second_val = expect_int(second_val, name)?;
+
+    let first_val = cast_int(builder, first_val, int_ty, signed);
+    let second_val = cast_int(builder, second_val, int_ty, signed);
+
+    if first == 0 {
+        Ok((first_val, second_val))
+    } else {
+        Ok((second_val, first_val))
+    }
+}
+
+fn generate_overflow_op<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    op: &str,
+    signed: bool,
+    lhs: IntValue<'ctx>,
+    rhs: IntValue<'ctx>,
+) -> (IntValue<'ctx>, IntValue<'ctx>) {
+    let intrinsic_name = format!("llvm.{}{}.with.overflow", if signed { "s" } else { "u" }, op);
+    let pair = call_int_intrinsic(builder, module, &intrinsic_name, lhs, rhs).into_struct_value();
+    let value = builder.build_extract_value(pair, 0, &value_name("ov_value")).unwrap().into_int_value();
+    let overflow = builder.build_extract_value(pair, 1, &value_name("ov_flag")).unwrap().into_int_value();
+    (value, overflow)
+}
+
+fn call_int_intrinsic<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    intrinsic_name: &str,
+    lhs: IntValue<'ctx>,
+    rhs: IntValue<'ctx>,
+) -> BasicValueEnum<'ctx> {
+    let intrinsic = Intrinsic::find(intrinsic_name)
+        .unwrap_or_else(|| panic!("LLVM intrinsic '{}' not found", intrinsic_name));
+    let function = intrinsic
+        .get_declaration(module, &[lhs.get_type().into()])
+        .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic '{}'", intrinsic_name));
+
+    builder
+        .build_call(function, &[lhs.into(), rhs.into()], &value_name("intrinsic_call"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+}
+
+fn expect_arg_count(name: &str, args: &[Expression], count: usize) -> Result<(), CodegenError> {
+    if args.len() != count {
+        return Err(invalid(
+            "builtin arity",
+            tr!("builtin `{0}` takes {1} argument(s), found {2}", name, count, args.len()),
+        ));
+    }
+    Ok(())
+}
+
+fn expect_int<'ctx>(value: BasicValueEnum<'ctx>, name: &str) -> Result<IntValue<'ctx>, CodegenError> {
+    match value {
+        BasicValueEnum::IntValue(v) => Ok(v),
+        other => Err(invalid(
+            "builtin operand",
+            tr!("builtin `{0}` expects integer arguments, found `{1}`", name, llvm_type_name(other.get_type())),
+        )),
+    }
+}
+
+fn not_a_byte_array(name: &str, found: BasicTypeEnum) -> CodegenError {
+    invalid("builtin operand", tr!("builtin `{0}` expects a `u8` array, found `{1}`", name, llvm_type_name(found)))
+}
+
+fn cast_int<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+    int_ty: IntType<'ctx>,
+    signed: bool,
+) -> IntValue<'ctx> {
+    if value.get_type().get_bit_width() == int_ty.get_bit_width() {
+        value
+    } else {
+        builder.build_int_cast_sign_flag(value, int_ty, signed, &value_name("builtin_cast")).unwrap()
+    }
+}
+
+// `value` converted to `expected_type` when that is an integer type, extended by `signed`.
+fn fit_expected<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    signed: bool,
+) -> IntValue<'ctx> {
+    match expected_type {
+        Some(BasicTypeEnum::IntType(int_ty)) => cast_int(builder, value, int_ty, signed),
+        _ => value,
+    }
+}
+
+fn signed_min(int_ty: IntType) -> IntValue {
+    let bits = int_ty.get_bit_width() as u64;
+    int_ty.const_int(1, false).const_shl(int_ty.const_int(bits - 1, false))
+}
+
+fn signed_max(int_ty: IntType) -> IntValue {
+    signed_min(int_ty).const_not()
+}
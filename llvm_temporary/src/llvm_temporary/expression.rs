@@ -1,10 +1,18 @@
 use std::collections::HashMap;
 use inkwell::context::Context;
-use inkwell::{FloatPredicate, IntPredicate};
-use inkwell::types::{AnyTypeEnum, BasicType, BasicTypeEnum};
-use inkwell::values::{BasicValue, BasicValueEnum};
-use parser::ast::{AssignOperator, Expression, Literal, Operator};
-use crate::llvm_temporary::llvm_codegen::{generate_address_ir, VariableInfo};
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate};
+use inkwell::types::{AnyType, BasicType, BasicTypeEnum, FloatType, IntType};
+use inkwell::values::{BasicValue, BasicValueEnum, CallableValue, FloatValue, IntValue, PointerValue, StructValue};
+use parser::ast::{AssignOperator, Expression, Literal, Mutability, Operator, WaveType};
+use parser::typeck::widen;
+use error::tr;
+use crate::llvm_temporary::branch::{truth_value, ValueBranch};
+use crate::llvm_temporary::builtin::generate_builtin_call;
+use crate::llvm_temporary::heap::{generate_alloc, generate_dealloc};
+use crate::llvm_temporary::print::generate_flush;
+use crate::llvm_temporary::statement::{coerce_expression, coerce_value, generate_while_ir, LoopValues};
+use crate::llvm_temporary::string::{generate_str_compare, generate_str_concat, generate_str_literal, is_str_type, str_parts};
+use crate::llvm_temporary::llvm_codegen::{asm_callee, generate_float_to_int, call_target, describe_expression, generate_address_ir, generate_bounds_check, generate_division_check, generate_overflow_check, generate_pointer_checks, float_literal, function_address, function_llvm_type, generate_string_literal, immutable_assignment, int_literal, invalid, is_unsuffixed_literal, literal_signedness, llvm_type_name, enum_variant, resolve_wave_type, struct_declaration, struct_field, undefined_function, undefined_variable, unsupported_construct, usize_index, value_name, variant_payload_type, wave_type_to_llvm_type, CodegenError, CodegenOptions, VariableInfo};
 
 pub fn generate_expression_ir<'ctx>(
     context: &'ctx Context,
@@ -13,69 +21,92 @@ pub fn generate_expression_ir<'ctx>(
     variables: &mut HashMap<String, VariableInfo<'ctx>>,
     module: &'ctx inkwell::module::Module<'ctx>,
     expected_type: Option<BasicTypeEnum<'ctx>>,
-) -> BasicValueEnum<'ctx> {
-    match expr {
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    let value = match expr {
         Expression::Literal(lit) => match lit {
             Literal::Number(v) => {
                 match expected_type {
                     Some(BasicTypeEnum::IntType(int_ty)) => {
-                        int_ty.const_int(*v as u64, false).as_basic_value_enum()
+                        int_literal(*v, int_ty, None)?.as_basic_value_enum()
                     }
-                    None => {
-                        context.i64_type().const_int(*v as u64, false).as_basic_value_enum()
+                    Some(BasicTypeEnum::FloatType(float_ty)) => {
+                        float_literal(*v as f64, float_ty)?.as_basic_value_enum()
                     }
-                    _ => panic!("Expected integer type for numeric literal, got {:?}", expected_type),
+                    // Unsuffixed and uncoerced, an integer literal is an `i32`, as type checking has it.
+                    None => int_literal(*v, context.i32_type(), Some(true))?.as_basic_value_enum(),
+                    Some(other) => return Err(invalid(
+                        "mismatched literal",
+                        tr!("expected `{0}`, found an integer literal", llvm_type_name(other)),
+                    )),
                 }
             }
             Literal::Float(value) => {
                 match expected_type {
-                    Some(BasicTypeEnum::FloatType(float_ty)) => float_ty.const_float(*value).as_basic_value_enum(),
-                    Some(BasicTypeEnum::IntType(int_ty)) => builder.build_float_to_signed_int(context.f32_type().const_float(*value), int_ty, "f32_to_int").unwrap().as_basic_value_enum(),
+                    Some(BasicTypeEnum::FloatType(float_ty)) => float_literal(*value, float_ty)?.as_basic_value_enum(),
+                    Some(BasicTypeEnum::IntType(int_ty)) => {
+                        generate_float_to_int(context, builder, module, context.f32_type().const_float(*value), int_ty, true, options).as_basic_value_enum()
+                    }
                     None => context.f32_type().const_float(*value).as_basic_value_enum(),
-                    _ => panic!("Unsupported expected_type for float"),
+                    Some(other) => return Err(invalid(
+                        "mismatched literal",
+                        tr!("expected `{0}`, found a float literal", llvm_type_name(other)),
+                    )),
                 }
             }
-            Literal::String(value) => unsafe {
-                let bytes = value.as_bytes();
-                let mut null_terminated = bytes.to_vec();
-                null_terminated.push(0);
-
+            Literal::Bool(value) => {
+                let int_ty = match expected_type {
+                    Some(BasicTypeEnum::IntType(int_ty)) => int_ty,
+                    _ => context.bool_type(),
+                };
+                int_ty.const_int(*value as u64, false).as_basic_value_enum()
+            }
+            Literal::Char(value) => {
+                let int_ty = match expected_type {
+                    Some(BasicTypeEnum::IntType(int_ty)) => int_ty,
+                    _ => context.i8_type(),
+                };
+                int_ty.const_int(*value as u64, false).as_basic_value_enum()
+            }
+            // Where a pointer is expected, such as a `ptr<i8>` handed to a syscall, a literal is the
+            // address of its first byte.
+            Literal::String(value) => {
                 let global_name = format!("str_lit_{}", value.replace(" ", "_"));
-                let str_type = context.i8_type().array_type(null_terminated.len() as u32);
-                let global = module.add_global(str_type, None, &global_name);
-                global.set_initializer(&context.const_string(&null_terminated, false));
-                global.set_constant(true);
-
-                let zero = context.i32_type().const_zero();
-                let indices = [zero, zero];
-                let gep = builder.build_gep(global.as_pointer_value(), &indices, "str_gep").unwrap();
-
-                gep.as_basic_value_enum()
+                match expected_type {
+                    Some(BasicTypeEnum::PointerType(_)) => {
+                        generate_string_literal(context, builder, module, value, &global_name).as_basic_value_enum()
+                    }
+                    _ => generate_str_literal(context, builder, module, value, &global_name).as_basic_value_enum(),
+                }
             }
-            _ => unimplemented!("Unsupported literal type"),
         },
 
         Expression::Variable(var_name) => {
             if let Some(var_info) = variables.get(var_name) {
-                builder.build_load(var_info.ptr, var_name).unwrap()
-            } else if module.get_function(var_name).is_some() {
-                panic!("Error: '{}' is a function name, not a variable", var_name);
+                builder.build_load(var_info.ptr, &value_name(var_name)).unwrap()
+            } else if let Some((address, _)) = function_address(var_name, expected_type, module)? {
+                address.as_basic_value_enum()
             } else {
-                panic!("variable '{}' not found in current scope", var_name);
+                return Err(undefined_variable(var_name, variables.keys()));
             }
         }
 
         Expression::Deref(inner_expr) => {
             match &**inner_expr {
                 Expression::Variable(var_name) => {
-                    let ptr_to_value = variables.get(var_name).unwrap().ptr;
-                    let actual_ptr = builder.build_load(ptr_to_value, "deref_target").unwrap().into_pointer_value();
-                    builder.build_load(actual_ptr, "deref_load").unwrap().as_basic_value_enum()
+                    let ptr_to_value = variables.get(var_name)
+                        .ok_or_else(|| undefined_variable(var_name, variables.keys()))?
+                        .ptr;
+                    let target = builder.build_load(ptr_to_value, &value_name("deref_target")).unwrap();
+                    let actual_ptr = pointer_operand(builder, target);
+                    generate_pointer_checks(context, builder, module, actual_ptr, options);
+                    builder.build_load(actual_ptr, &value_name("deref_load")).unwrap().as_basic_value_enum()
                 }
                 _ => {
-                    let ptr_val = generate_expression_ir(context, builder, inner_expr, variables, module, None);
-                    let ptr = ptr_val.into_pointer_value();
-                    builder.build_load(ptr, "deref_load").unwrap().as_basic_value_enum()
+                    let ptr_val = generate_expression_ir(context, builder, inner_expr, variables, module, None, options)?;
+                    let ptr = pointer_operand(builder, ptr_val);
+                    generate_pointer_checks(context, builder, module, ptr, options);
+                    builder.build_load(ptr, &value_name("deref_load")).unwrap().as_basic_value_enum()
                 }
             }
         }
@@ -88,7 +119,7 @@ pub fn generate_expression_ir<'ctx>(
                         let elem_type = array_type.get_element_type();
 
                         let array_type = elem_type.array_type(elements.len() as u32);
-                        let tmp_alloca = builder.build_alloca(array_type, "tmp_array").unwrap();
+                        let tmp_alloca = builder.build_alloca(array_type, &value_name("tmp_array")).unwrap();
 
                         for (i, expr) in elements.iter().enumerate() {
                             let val = generate_expression_ir(
@@ -98,7 +129,8 @@ pub fn generate_expression_ir<'ctx>(
                                 variables,
                                 module,
                                 Some(elem_type),
-                            );
+                                options,
+                            )?;
                             let gep = builder.build_in_bounds_gep(
                                 tmp_alloca,
                                 &[
@@ -110,69 +142,75 @@ pub fn generate_expression_ir<'ctx>(
                             builder.build_store(gep, val).unwrap();
                         }
 
-                        let alloca = builder.build_alloca(tmp_alloca.get_type(), "tmp_array_ptr").unwrap();
+                        let alloca = builder.build_alloca(tmp_alloca.get_type(), &value_name("tmp_array_ptr")).unwrap();
                         builder.build_store(alloca, tmp_alloca).unwrap();
                         alloca.as_basic_value_enum()
                     }
 
                     Expression::Variable(var_name) => {
                         let ptr = variables.get(var_name)
-                            .unwrap_or_else(|| panic!("Variable {} not found", var_name));
-                        let alloca = builder.build_alloca(ptr.ptr.get_type(), "tmp_var_ptr").unwrap();
-                        builder.build_store(alloca, ptr.ptr).unwrap();
-                        alloca.as_basic_value_enum()
+                            .ok_or_else(|| undefined_variable(var_name, variables.keys()))?;
+                        ptr.ptr.as_basic_value_enum()
                     }
 
-                    _ => panic!("& operator must be used on variable name or array literal"),
+                    other => return Err(unsupported_construct(
+                        &tr!("taking the address of {0}", tr!(describe_expression(other))),
+                        "`&` only applies to a variable or an array literal; store the value in a variable first",
+                    )),
                 }
             } else {
-                panic!("Expected pointer type for AddressOf");
+                return Err(invalid("address without pointer type", tr!("`&` is only allowed where a pointer is expected")));
             }
         }
 
         Expression::FunctionCall { name, args } => {
-            let function = module
-                .get_function(name)
-                .unwrap_or_else(|| panic!("Function '{}' not found", name));
-
-            let function_type = function.get_type();
-            let param_types: Vec<BasicTypeEnum> = function_type
-                .get_param_types()
-                .iter()
-                .map(|t| t.clone().into())
-                .collect();
-
-            let mut compiled_args = vec![];
-            for (i, arg) in args.iter().enumerate() {
-                let expected = param_types.get(i).copied();
-                let val = generate_expression_ir(context, builder, arg, variables, module, expected);
-                compiled_args.push(val.into());
-            }
+            let value = generate_call_ir(context, builder, name, args, variables, module, expected_type, options)?
+                .ok_or_else(|| invalid("void call used as value", tr!("`{0}` does not return a value", name)))?;
 
-            let call_site = builder.build_call(function, &compiled_args, "calltmp").unwrap();
-            println!("call_site: {:?}", call_site);
-            if let Some(ret_val) = call_site.try_as_basic_value().left() {
-                println!("return value type: {:?}", ret_val.get_type());
-                ret_val
-            } else {
-                panic!("Function '{}' did not return a value", name);
+            // Like an arithmetic result, a returned number takes the type its context expects.
+            match (value, expected_type) {
+                (BasicValueEnum::IntValue(_) | BasicValueEnum::FloatValue(_), Some(ty @ (BasicTypeEnum::IntType(_) | BasicTypeEnum::FloatType(_)))) => {
+                    coerce_value(context, builder, module, value, ty, options)?
+                }
+                _ => value,
             }
         }
 
         Expression::AssignOperation { target, operator, value } => {
-            let ptr = generate_address_ir(context, builder, target, variables, module);
+            if let Some(name) = assigned_variable(target, variables) {
+                if let Some(var_info) = variables.get(name) {
+                    if matches!(var_info.mutability, Mutability::Let) {
+                        return Err(immutable_assignment(name, var_info.span));
+                    }
+                }
+            }
 
-            let current_val = builder.build_load(ptr, "load_current").unwrap();
+            let ptr = generate_address_ir(context, builder, target, variables, module, options)?;
 
-            let new_val = generate_expression_ir(context, builder, value, variables, module, Some(current_val.get_type()));
+            let current_val = builder.build_load(ptr, &value_name("load_current")).unwrap();
+            let element_type = current_val.get_type();
 
-            let (current_val, new_val) = match (current_val, new_val) {
-                (BasicValueEnum::FloatValue(lhs), BasicValueEnum::IntValue(rhs)) => {
-                    let rhs_casted = builder.build_signed_int_to_float(rhs, lhs.get_type(), "int_to_float").unwrap();
-                    (BasicValueEnum::FloatValue(lhs), BasicValueEnum::FloatValue(rhs_casted))
+            let declared = match &**target {
+                Expression::Variable(name) => variables.get(name).map(|info| info.ty.clone()),
+                _ => None,
+            };
+            let new_val = match (&**value, current_val.get_type()) {
+                (Expression::Literal(Literal::Number(literal)), BasicTypeEnum::IntType(int_ty)) => {
+                    int_literal(*literal, int_ty, declared.as_ref().and_then(literal_signedness))?.as_basic_value_enum()
                 }
+                (_, current_type) => generate_expression_ir(context, builder, value, variables, module, Some(current_type), options)?,
+            };
+
+            // The value takes the target's type, unless a float meets an integer target.
+            let new_val = match (current_val, new_val) {
+                (BasicValueEnum::IntValue(_), BasicValueEnum::IntValue(_)) | (BasicValueEnum::FloatValue(_), BasicValueEnum::IntValue(_) | BasicValueEnum::FloatValue(_)) => {
+                    coerce_expression(context, builder, module, value, new_val, current_val.get_type(), variables, options)?
+                }
+                _ => new_val,
+            };
+            let (current_val, new_val) = match (current_val, new_val) {
                 (BasicValueEnum::IntValue(lhs), BasicValueEnum::FloatValue(rhs)) => {
-                    let lhs_casted = builder.build_signed_int_to_float(lhs, rhs.get_type(), "int_to_float").unwrap();
+                    let lhs_casted = builder.build_signed_int_to_float(lhs, rhs.get_type(), &value_name("int_to_float")).unwrap();
                     (BasicValueEnum::FloatValue(lhs_casted), BasicValueEnum::FloatValue(rhs))
                 }
                 other => other,
@@ -180,44 +218,58 @@ pub fn generate_expression_ir<'ctx>(
 
             let result = match (current_val, new_val) {
                 (BasicValueEnum::IntValue(lhs), BasicValueEnum::IntValue(rhs)) => {
+                    let unsigned = is_unsigned_integer(target, variables);
+                    if options.check_ub && matches!(operator, AssignOperator::DivAssign | AssignOperator::RemAssign) {
+                        generate_division_check(context, builder, module, lhs, rhs, !unsigned);
+                    }
+                    let checked = options.check_ub && !is_unsigned_operand(target, variables);
                     match operator {
                         AssignOperator::Assign => rhs.as_basic_value_enum(),
-                        AssignOperator::AddAssign => builder.build_int_add(lhs, rhs, "add_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::SubAssign => builder.build_int_sub(lhs, rhs, "sub_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::MulAssign => builder.build_int_mul(lhs, rhs, "mul_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::DivAssign => builder.build_int_signed_div(lhs, rhs, "div_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::RemAssign => builder.build_int_signed_rem(lhs, rhs, "rem_assign").unwrap().as_basic_value_enum(),
+                        AssignOperator::AddAssign if checked => generate_overflow_check(context, builder, module, "+", lhs, rhs).as_basic_value_enum(),
+                        AssignOperator::SubAssign if checked => generate_overflow_check(context, builder, module, "-", lhs, rhs).as_basic_value_enum(),
+                        AssignOperator::MulAssign if checked => generate_overflow_check(context, builder, module, "*", lhs, rhs).as_basic_value_enum(),
+                        AssignOperator::AddAssign => builder.build_int_add(lhs, rhs, &value_name("add_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::SubAssign => builder.build_int_sub(lhs, rhs, &value_name("sub_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::MulAssign => builder.build_int_mul(lhs, rhs, &value_name("mul_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::DivAssign if unsigned => builder.build_int_unsigned_div(lhs, rhs, &value_name("div_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::RemAssign if unsigned => builder.build_int_unsigned_rem(lhs, rhs, &value_name("rem_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::DivAssign => builder.build_int_signed_div(lhs, rhs, &value_name("div_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::RemAssign => builder.build_int_signed_rem(lhs, rhs, &value_name("rem_assign")).unwrap().as_basic_value_enum(),
                     }
                 }
                 (BasicValueEnum::FloatValue(lhs), BasicValueEnum::FloatValue(rhs)) => {
                     match operator {
                         AssignOperator::Assign => rhs.as_basic_value_enum(),
-                        AssignOperator::AddAssign => builder.build_float_add(lhs, rhs, "add_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::SubAssign => builder.build_float_sub(lhs, rhs, "sub_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::MulAssign => builder.build_float_mul(lhs, rhs, "mul_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::DivAssign => builder.build_float_div(lhs, rhs, "div_assign").unwrap().as_basic_value_enum(),
-                        AssignOperator::RemAssign => builder.build_float_rem(lhs, rhs, "rem_assign").unwrap().as_basic_value_enum(),
+                        AssignOperator::AddAssign => builder.build_float_add(lhs, rhs, &value_name("add_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::SubAssign => builder.build_float_sub(lhs, rhs, &value_name("sub_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::MulAssign => builder.build_float_mul(lhs, rhs, &value_name("mul_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::DivAssign => builder.build_float_div(lhs, rhs, &value_name("div_assign")).unwrap().as_basic_value_enum(),
+                        AssignOperator::RemAssign => builder.build_float_rem(lhs, rhs, &value_name("rem_assign")).unwrap().as_basic_value_enum(),
                     }
                 }
-                _ => panic!("Type mismatch or unsupported type in AssignOperation"),
-            };
-
-            let element_type = match ptr.get_type().get_element_type() {
-                AnyTypeEnum::IntType(t) => BasicTypeEnum::IntType(t),
-                AnyTypeEnum::FloatType(t) => BasicTypeEnum::FloatType(t),
-                AnyTypeEnum::PointerType(t) => BasicTypeEnum::PointerType(t),
-                AnyTypeEnum::ArrayType(t) => BasicTypeEnum::ArrayType(t),
-                AnyTypeEnum::StructType(t) => BasicTypeEnum::StructType(t),
-                AnyTypeEnum::VectorType(t) => BasicTypeEnum::VectorType(t),
-                _ => panic!("Unsupported LLVM element type"),
+                // Pointers, function addresses among them, and structs are only replaced.
+                (BasicValueEnum::PointerValue(lhs), BasicValueEnum::PointerValue(rhs))
+                    if matches!(operator, AssignOperator::Assign) && lhs.get_type() == rhs.get_type() =>
+                {
+                    rhs.as_basic_value_enum()
+                }
+                (BasicValueEnum::StructValue(lhs), BasicValueEnum::StructValue(rhs))
+                    if matches!(operator, AssignOperator::Assign) && lhs.get_type() == rhs.get_type() =>
+                {
+                    rhs.as_basic_value_enum()
+                }
+                (current, new) => return Err(invalid(
+                    "mismatched assignment",
+                    tr!("cannot assign `{0}` to `{1}`", llvm_type_name(new.get_type()), llvm_type_name(current.get_type())),
+                )),
             };
 
             let result_casted = match (result, element_type) {
                 (BasicValueEnum::FloatValue(val), BasicTypeEnum::IntType(int_ty)) => {
-                    builder.build_float_to_signed_int(val, int_ty, "float_to_int").unwrap().as_basic_value_enum()
+                    generate_float_to_int(context, builder, module, val, int_ty, true, options).as_basic_value_enum()
                 }
                 (BasicValueEnum::IntValue(val), BasicTypeEnum::FloatType(float_ty)) => {
-                    builder.build_signed_int_to_float(val, float_ty, "int_to_float").unwrap().as_basic_value_enum()
+                    builder.build_signed_int_to_float(val, float_ty, &value_name("int_to_float")).unwrap().as_basic_value_enum()
                 }
                 _ => result,
             };
@@ -226,33 +278,95 @@ pub fn generate_expression_ir<'ctx>(
             result
         }
 
-        Expression::Assignment { target, value } => {
-            let ptr = generate_address_ir(context, builder, target, variables, module); // → PointerValue
+        Expression::Assignment { target, value: value_expr } => {
+            let ptr = generate_address_ir(context, builder, target, variables, module, options)?; // → PointerValue
 
             let value = generate_expression_ir(
                 context,
                 builder,
-                value,
+                value_expr,
                 variables,
                 module,
-                Some(ptr.get_type().get_element_type().try_into().unwrap())
-            );
+                Some(ptr.get_type().get_element_type().try_into().unwrap()),
+                options
+            )?;
+            let value = coerce_expression(context, builder, module, value_expr, value, ptr.get_type().get_element_type().try_into().unwrap(), variables, options)?;
 
             let value = match value {
                 BasicValueEnum::IntValue(v) => v.as_basic_value_enum(),
                 BasicValueEnum::FloatValue(v) => v.as_basic_value_enum(),
                 BasicValueEnum::PointerValue(v) => v.as_basic_value_enum(),
-                _ => panic!("Unsupported assignment value"),
+                BasicValueEnum::StructValue(v) if is_str_type(v.get_type().into()) => v.as_basic_value_enum(),
+                _ => return Err(unsupported_construct("assigning a whole array", "assign the elements one by one")),
             };
 
             builder.build_store(ptr, value).unwrap();
             value
         }
 
+        // `a && b` only evaluates `b` when `a` is true, and `a || b` only when it is false.
+        Expression::BinaryExpression { left, operator: operator @ (Operator::LogicalAnd | Operator::LogicalOr), right } => {
+            let is_and = matches!(operator, Operator::LogicalAnd);
+            let left_val = generate_expression_ir(context, builder, left, variables, module, None, options)?;
+            let left_bool = truth_value(builder, left_val, "l_bool")
+                .ok_or_else(|| unsupported_operator(operator, "values that are not numbers"))?;
+
+            let mut right_bool = || -> Result<BasicValueEnum<'ctx>, CodegenError> {
+                let right_val = generate_expression_ir(context, builder, right, variables, module, None, options)?;
+                let right_bool = truth_value(builder, right_val, "r_bool")
+                    .ok_or_else(|| unsupported_operator(operator, "values that are not numbers"))?;
+                Ok(right_bool.as_basic_value_enum())
+            };
+            let decided = context.bool_type().const_int(!is_and as u64, false).as_basic_value_enum();
+
+            let mut branch = ValueBranch::begin(context, builder, left_bool, if is_and { "and" } else { "or" });
+            let result = if is_and {
+                let right_bool = right_bool()?;
+                branch.else_arm(builder, right_bool);
+                branch.merge(builder, decided)
+            } else {
+                branch.else_arm(builder, decided);
+                let right_bool = right_bool()?;
+                branch.merge(builder, right_bool)
+            };
+            let result = result.expect("both arms of `&&` and `||` reach the end of the branch");
+
+            match expected_type {
+                Some(target_ty @ BasicTypeEnum::IntType(_)) => coerce_value(context, builder, module, result, target_ty, options)?,
+                _ => result,
+            }
+        }
+
         Expression::BinaryExpression { left, operator, right } => {
-            println!("🔧 BinaryExpression triggered");
-            let left_val = generate_expression_ir(context, builder, left, variables, module, None);
-            let right_val = generate_expression_ir(context, builder, right, variables, module, None);
+            tracing::trace!(operator = ?operator, "lowering binary expression");
+            // An unsuffixed literal takes the type of the operand it is combined with, so `x < 10`
+            // compares at the width of `x`. Literal-only arithmetic follows the surrounding context.
+            let arithmetic = matches!(
+                operator,
+                Operator::Add | Operator::Subtract | Operator::Multiply | Operator::Divide | Operator::Remainder
+            );
+            let (left_val, right_val) = match (is_unsuffixed_literal(left), is_unsuffixed_literal(right)) {
+                (true, false) => {
+                    let right_val = generate_expression_ir(context, builder, right, variables, module, None, options)?;
+                    let left_type = literal_operand_type(left, right_val.get_type());
+                    let left_val = generate_expression_ir(context, builder, left, variables, module, left_type, options)?;
+                    (left_val, right_val)
+                }
+                (false, true) => {
+                    let left_val = generate_expression_ir(context, builder, left, variables, module, None, options)?;
+                    let right_type = literal_operand_type(right, left_val.get_type());
+                    let right_val = generate_expression_ir(context, builder, right, variables, module, right_type, options)?;
+                    (left_val, right_val)
+                }
+                (true, true) if arithmetic => (
+                    generate_expression_ir(context, builder, left, variables, module, expected_type, options)?,
+                    generate_expression_ir(context, builder, right, variables, module, expected_type, options)?,
+                ),
+                _ => (
+                    generate_expression_ir(context, builder, left, variables, module, None, options)?,
+                    generate_expression_ir(context, builder, right, variables, module, None, options)?,
+                ),
+            };
 
             // Branch after Type Examination
             match (left_val, right_val) {
@@ -260,38 +374,50 @@ pub fn generate_expression_ir<'ctx>(
                     let l_type = l.get_type();
                     let r_type = r.get_type();
 
-                    let (l_casted, r_casted) = if l_type != r_type {
-                        if l_type.get_bit_width() < r_type.get_bit_width() {
-                            let new_l = builder.build_int_z_extend(l, r_type, "zext_l").unwrap();
-                            (new_l, r)
-                        } else {
-                            let new_r = builder.build_int_z_extend(r, l_type, "zext_r").unwrap();
-                            (l, new_r)
-                        }
+                    // The operands meet in the type `widen` joins them to, each extended by its own sign.
+                    let (l_wave, r_wave) = (integer_operand_type(left, l, variables), integer_operand_type(right, r, variables));
+                    let joined = widen(&l_wave, &r_wave).expect("two integers always widen");
+                    let unsigned = !matches!(joined, WaveType::Int(_));
+                    let joined_type = if l_type.get_bit_width() >= r_type.get_bit_width() { l_type } else { r_type };
+                    let l_casted = widen_integer(builder, l, &l_wave, joined_type, unsigned);
+                    let r_casted = widen_integer(builder, r, &r_wave, joined_type, unsigned);
+
+                    let (gt, lt, ge, le) = if unsigned {
+                        (IntPredicate::UGT, IntPredicate::ULT, IntPredicate::UGE, IntPredicate::ULE)
                     } else {
-                        (l, r)
+                        (IntPredicate::SGT, IntPredicate::SLT, IntPredicate::SGE, IntPredicate::SLE)
                     };
 
+                    let signed = !unsigned && !is_unsigned_operand(left, variables) && !is_unsigned_operand(right, variables);
+                    if options.check_ub && matches!(operator, Operator::Divide | Operator::Remainder) {
+                        generate_division_check(context, builder, module, l_casted, r_casted, !unsigned);
+                    }
+
                     let mut result = match operator {
-                        Operator::Add => builder.build_int_add(l_casted, r_casted, "addtmp"),
-                        Operator::Subtract => builder.build_int_sub(l_casted, r_casted, "subtmp"),
-                        Operator::Multiply => builder.build_int_mul(l_casted, r_casted, "multmp"),
-                        Operator::Divide => builder.build_int_signed_div(l_casted, r_casted, "divtmp"),
-                        Operator::Remainder => builder.build_int_signed_rem(l_casted, r_casted, "modtmp"),
-                        Operator::Greater => builder.build_int_compare(IntPredicate::SGT, l_casted, r_casted, "cmptmp"),
-                        Operator::Less => builder.build_int_compare(IntPredicate::SLT, l_casted, r_casted, "cmptmp"),
-                        Operator::Equal => builder.build_int_compare(IntPredicate::EQ, l_casted, r_casted, "cmptmp"),
-                        Operator::NotEqual => builder.build_int_compare(IntPredicate::NE, l_casted, r_casted, "cmptmp"),
-                        Operator::GreaterEqual => builder.build_int_compare(IntPredicate::SGE, l_casted, r_casted, "cmptmp"),
-                        Operator::LessEqual => builder.build_int_compare(IntPredicate::SLE, l_casted, r_casted, "cmptmp"),
-                        _ => panic!("Unsupported binary operator"),
+                        Operator::Add | Operator::Subtract | Operator::Multiply if options.check_ub && signed => {
+                            Ok(generate_overflow_check(context, builder, module, operator_symbol(operator), l_casted, r_casted))
+                        }
+                        Operator::Add => builder.build_int_add(l_casted, r_casted, &value_name("addtmp")),
+                        Operator::Subtract => builder.build_int_sub(l_casted, r_casted, &value_name("subtmp")),
+                        Operator::Multiply => builder.build_int_mul(l_casted, r_casted, &value_name("multmp")),
+                        Operator::Divide if unsigned => builder.build_int_unsigned_div(l_casted, r_casted, &value_name("divtmp")),
+                        Operator::Divide => builder.build_int_signed_div(l_casted, r_casted, &value_name("divtmp")),
+                        Operator::Remainder if unsigned => builder.build_int_unsigned_rem(l_casted, r_casted, &value_name("modtmp")),
+                        Operator::Remainder => builder.build_int_signed_rem(l_casted, r_casted, &value_name("modtmp")),
+                        Operator::Greater => builder.build_int_compare(gt, l_casted, r_casted, &value_name("cmptmp")),
+                        Operator::Less => builder.build_int_compare(lt, l_casted, r_casted, &value_name("cmptmp")),
+                        Operator::Equal => builder.build_int_compare(IntPredicate::EQ, l_casted, r_casted, &value_name("cmptmp")),
+                        Operator::NotEqual => builder.build_int_compare(IntPredicate::NE, l_casted, r_casted, &value_name("cmptmp")),
+                        Operator::GreaterEqual => builder.build_int_compare(ge, l_casted, r_casted, &value_name("cmptmp")),
+                        Operator::LessEqual => builder.build_int_compare(le, l_casted, r_casted, &value_name("cmptmp")),
+                        other => return Err(unsupported_operator(other, "integers")),
                     }.unwrap();
 
                     if let Some(BasicTypeEnum::IntType(target_ty)) = expected_type {
                         let result_ty = result.get_type();
 
                         if result_ty != target_ty {
-                            result = builder.build_int_cast(result, target_ty, "cast_result").unwrap();
+                            result = builder.build_int_cast_sign_flag(result, target_ty, !unsigned, &value_name("cast_result")).unwrap();
                         }
                     }
 
@@ -299,99 +425,101 @@ pub fn generate_expression_ir<'ctx>(
                 }
 
                 (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) => {
+                    // Only `f32` and `f64` are lowered, so of two different floats the `f32` extends.
+                    let (l, r) = match (l.get_type(), r.get_type()) {
+                        (l_type, r_type) if l_type == r_type => (l, r),
+                        (l_type, r_type) if l_type == context.f32_type() => (builder.build_float_ext(l, r_type, &value_name("fpext_l")).unwrap(), r),
+                        (l_type, _) => (l, builder.build_float_ext(r, l_type, &value_name("fpext_r")).unwrap()),
+                    };
                     match operator {
-                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, l, r, "fcmpgt").unwrap().as_basic_value_enum(),
-                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, l, r, "fcmplt").unwrap().as_basic_value_enum(),
-                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, l, r, "fcmpeq").unwrap().as_basic_value_enum(),
-                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, l, r, "fcmpne").unwrap().as_basic_value_enum(),
-                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, l, r, "fcmpge").unwrap().as_basic_value_enum(),
-                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, l, r, "fcmple").unwrap().as_basic_value_enum(),
-                        Operator::Remainder => builder.build_float_rem(l, r, "modtmp").unwrap().as_basic_value_enum(),
-                        _ => panic!("Unsupported float operator"),
+                        Operator::Add => builder.build_float_add(l, r, &value_name("addtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Subtract => builder.build_float_sub(l, r, &value_name("subtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Multiply => builder.build_float_mul(l, r, &value_name("multmp")).unwrap().as_basic_value_enum(),
+                        Operator::Divide => builder.build_float_div(l, r, &value_name("divtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, l, r, &value_name("fcmpgt")).unwrap().as_basic_value_enum(),
+                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, l, r, &value_name("fcmplt")).unwrap().as_basic_value_enum(),
+                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, l, r, &value_name("fcmpeq")).unwrap().as_basic_value_enum(),
+                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, l, r, &value_name("fcmpne")).unwrap().as_basic_value_enum(),
+                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, l, r, &value_name("fcmpge")).unwrap().as_basic_value_enum(),
+                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, l, r, &value_name("fcmple")).unwrap().as_basic_value_enum(),
+                        Operator::Remainder => builder.build_float_rem(l, r, &value_name("modtmp")).unwrap().as_basic_value_enum(),
+                        other => return Err(unsupported_operator(other, "floats")),
                     }
                 }
 
                 (BasicValueEnum::IntValue(int_val), BasicValueEnum::FloatValue(float_val)) => {
-                    let casted = builder.build_signed_int_to_float(int_val, float_val.get_type(), "cast_lhs").unwrap();
+                    let casted = int_to_float(builder, int_val, is_unsigned_integer(left, variables), float_val.get_type(), "cast_lhs");
                     match operator {
-                        Operator::Add => builder.build_float_add(casted, float_val, "addtmp").unwrap().as_basic_value_enum(),
-                        Operator::Subtract => builder.build_float_sub(casted, float_val, "subtmp").unwrap().as_basic_value_enum(),
-                        Operator::Multiply => builder.build_float_mul(casted, float_val, "multmp").unwrap().as_basic_value_enum(),
-                        Operator::Divide => builder.build_float_div(casted, float_val, "divtmp").unwrap().as_basic_value_enum(),
-                        Operator::Remainder => builder.build_float_rem(casted, float_val, "modtmp").unwrap().as_basic_value_enum(),
-                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, casted, float_val, "fcmpgt").unwrap().as_basic_value_enum(),
-                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, casted, float_val, "fcmplt").unwrap().as_basic_value_enum(),
-                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, casted, float_val, "fcmpeq").unwrap().as_basic_value_enum(),
-                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, casted, float_val, "fcmpne").unwrap().as_basic_value_enum(),
-                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, casted, float_val, "fcmpge").unwrap().as_basic_value_enum(),
-                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, casted, float_val, "fcmple").unwrap().as_basic_value_enum(),
-                        _ => panic!("Unsupported mixed-type operator (int + float)"),
+                        Operator::Add => builder.build_float_add(casted, float_val, &value_name("addtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Subtract => builder.build_float_sub(casted, float_val, &value_name("subtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Multiply => builder.build_float_mul(casted, float_val, &value_name("multmp")).unwrap().as_basic_value_enum(),
+                        Operator::Divide => builder.build_float_div(casted, float_val, &value_name("divtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Remainder => builder.build_float_rem(casted, float_val, &value_name("modtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, casted, float_val, &value_name("fcmpgt")).unwrap().as_basic_value_enum(),
+                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, casted, float_val, &value_name("fcmplt")).unwrap().as_basic_value_enum(),
+                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, casted, float_val, &value_name("fcmpeq")).unwrap().as_basic_value_enum(),
+                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, casted, float_val, &value_name("fcmpne")).unwrap().as_basic_value_enum(),
+                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, casted, float_val, &value_name("fcmpge")).unwrap().as_basic_value_enum(),
+                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, casted, float_val, &value_name("fcmple")).unwrap().as_basic_value_enum(),
+                        other => return Err(unsupported_operator(other, "an integer and a float")),
                     }
                 }
 
                 (BasicValueEnum::FloatValue(float_val), BasicValueEnum::IntValue(int_val)) => {
-                    let casted = builder.build_signed_int_to_float(int_val, float_val.get_type(), "cast_rhs").unwrap();
+                    let casted = int_to_float(builder, int_val, is_unsigned_integer(right, variables), float_val.get_type(), "cast_rhs");
                     match operator {
-                        Operator::Add => builder.build_float_add(float_val, casted, "addtmp").unwrap().as_basic_value_enum(),
-                        Operator::Subtract => builder.build_float_sub(float_val, casted, "subtmp").unwrap().as_basic_value_enum(),
-                        Operator::Multiply => builder.build_float_mul(float_val, casted, "multmp").unwrap().as_basic_value_enum(),
-                        Operator::Divide => builder.build_float_div(float_val, casted, "divtmp").unwrap().as_basic_value_enum(),
-                        Operator::Remainder => builder.build_float_rem(float_val, casted, "modtmp").unwrap().as_basic_value_enum(),
-                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, float_val, casted, "fcmpgt").unwrap().as_basic_value_enum(),
-                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, float_val, casted, "fcmplt").unwrap().as_basic_value_enum(),
-                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, float_val, casted, "fcmpeq").unwrap().as_basic_value_enum(),
-                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, float_val, casted, "fcmpne").unwrap().as_basic_value_enum(),
-                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, float_val, casted, "fcmpge").unwrap().as_basic_value_enum(),
-                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, float_val, casted, "fcmple").unwrap().as_basic_value_enum(),
-                        _ => panic!("Unsupported mixed-type operator (float + int)"),
+                        Operator::Add => builder.build_float_add(float_val, casted, &value_name("addtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Subtract => builder.build_float_sub(float_val, casted, &value_name("subtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Multiply => builder.build_float_mul(float_val, casted, &value_name("multmp")).unwrap().as_basic_value_enum(),
+                        Operator::Divide => builder.build_float_div(float_val, casted, &value_name("divtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Remainder => builder.build_float_rem(float_val, casted, &value_name("modtmp")).unwrap().as_basic_value_enum(),
+                        Operator::Greater => builder.build_float_compare(FloatPredicate::OGT, float_val, casted, &value_name("fcmpgt")).unwrap().as_basic_value_enum(),
+                        Operator::Less => builder.build_float_compare(FloatPredicate::OLT, float_val, casted, &value_name("fcmplt")).unwrap().as_basic_value_enum(),
+                        Operator::Equal => builder.build_float_compare(FloatPredicate::OEQ, float_val, casted, &value_name("fcmpeq")).unwrap().as_basic_value_enum(),
+                        Operator::NotEqual => builder.build_float_compare(FloatPredicate::ONE, float_val, casted, &value_name("fcmpne")).unwrap().as_basic_value_enum(),
+                        Operator::GreaterEqual => builder.build_float_compare(FloatPredicate::OGE, float_val, casted, &value_name("fcmpge")).unwrap().as_basic_value_enum(),
+                        Operator::LessEqual => builder.build_float_compare(FloatPredicate::OLE, float_val, casted, &value_name("fcmple")).unwrap().as_basic_value_enum(),
+                        other => return Err(unsupported_operator(other, "a float and an integer")),
                     }
                 }
 
-                _ => panic!("Type mismatch in binary expression"),
-            }
-        }
-
-        Expression::IndexAccess { target, index } => unsafe {
-            let target_val = generate_expression_ir(context, builder, target, variables, module, None);
-
-            let index_val = generate_expression_ir(context, builder, index, variables, module, None);
-            let index_int = match index_val {
-                BasicValueEnum::IntValue(i) => i,
-                _ => panic!("Index must be an integer"),
-            };
-
-            let zero = context.i32_type().const_zero();
+                (BasicValueEnum::StructValue(l), BasicValueEnum::StructValue(r))
+                    if is_str_type(l.get_type().into()) && is_str_type(r.get_type().into()) && matches!(operator, Operator::Add) => {
+                    generate_str_concat(context, builder, module, l, r)?.as_basic_value_enum()
+                }
 
-            match target_val {
-                BasicValueEnum::PointerValue(ptr_val) => {
-                    let element_type = ptr_val.get_type().get_element_type();
+                (BasicValueEnum::StructValue(l), BasicValueEnum::StructValue(r))
+                    if is_str_type(l.get_type().into()) && is_str_type(r.get_type().into()) => {
+                    let mut result = generate_string_compare(context, builder, module, operator, l, r)?;
 
-                    if element_type.is_array_type() {
-                        let gep = builder.build_in_bounds_gep(
-                            ptr_val,
-                            &[zero, index_int],
-                            "array_index_gep",
-                        ).unwrap();
-                        builder.build_load(gep, "load_array_elem").unwrap().as_basic_value_enum()
+                    if let Some(BasicTypeEnum::IntType(target_ty)) = expected_type {
+                        if result.get_type() != target_ty {
+                            result = builder.build_int_cast(result, target_ty, &value_name("cast_result")).unwrap();
+                        }
                     }
 
-                    else {
-                        let gep = builder.build_in_bounds_gep(
-                            ptr_val,
-                            &[index_int],
-                            "ptr_index_gep",
-                        ).unwrap();
-                        builder.build_load(gep, "load_ptr_elem").unwrap().as_basic_value_enum()
-                    }
+                    result.as_basic_value_enum()
                 }
 
-                _ => panic!("Unsupported target in IndexAccess"),
+                (l, r) => return Err(invalid(
+                    "mismatched operands",
+                    tr!("mismatched operand types `{0}` and `{1}`", llvm_type_name(l.get_type()), llvm_type_name(r.get_type())),
+                )),
             }
         }
 
+        Expression::Grouped(inner) => {
+            generate_expression_ir(context, builder, inner, variables, module, expected_type, options)?
+        }
+
+        Expression::IndexAccess { target, index } => {
+            let element = generate_index_address(context, builder, target, index, variables, module, options)?;
+            builder.build_load(element, &value_name("load_elem")).unwrap()
+        }
+
         Expression::AsmBlock { instructions, inputs, outputs } => {
             use inkwell::InlineAsmDialect;
-            use inkwell::values::{BasicMetadataValueEnum, CallableValue};
+            use inkwell::values::BasicMetadataValueEnum;
             use std::collections::HashSet;
 
             let asm_code: String = instructions.join("\n");
@@ -404,24 +532,24 @@ pub fn generate_expression_ir<'ctx>(
 
             for (reg, var) in outputs {
                 if input_regs.contains(reg) {
-                    panic!("Register '{}' used in both input and output in inline asm", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is used as both an input and an output", reg)));
                 }
 
                 if !seen_regs.insert(reg.to_string()) {
-                    panic!("Register '{}' duplicated in outputs", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is listed more than once", reg)));
                 }
 
                 let info = variables
                     .get(var)
-                    .unwrap_or_else(|| panic!("Output variable '{}' not found", var));
-                let dummy_val = builder.build_load(info.ptr, var).unwrap().into();
+                    .ok_or_else(|| undefined_variable(var, variables.keys()))?;
+                let dummy_val = builder.build_load(info.ptr, &value_name(var)).unwrap().into();
                 operand_vals.push(dummy_val);
                 constraint_parts.push(format!("={{{}}}", reg));
             }
 
             for (reg, var) in inputs {
                 if !seen_regs.insert(reg.to_string()) {
-                    panic!("Register '{}' duplicated in inputs", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is listed more than once", reg)));
                 }
 
                 let val: BasicMetadataValueEnum = if let Ok(value) = var.parse::<i64>() {
@@ -429,8 +557,8 @@ pub fn generate_expression_ir<'ctx>(
                 } else {
                     let info = variables
                         .get(var)
-                        .unwrap_or_else(|| panic!("Input variable '{}' not found", var));
-                    builder.build_load(info.ptr, var).unwrap().into()
+                        .ok_or_else(|| undefined_variable(var, variables.keys()))?;
+                    builder.build_load(info.ptr, &value_name(var)).unwrap().into()
                 };
 
                 operand_vals.push(val);
@@ -455,16 +583,494 @@ pub fn generate_expression_ir<'ctx>(
                 false,
             );
 
-            let inline_asm_fn = CallableValue::try_from(inline_asm_ptr)
-                .expect("Failed to convert inline asm to CallableValue");
+            let inline_asm_fn = asm_callee(inline_asm_ptr)?;
 
             let call = builder
-                .build_call(inline_asm_fn, &operand_vals, "inline_asm_expr")
+                .build_call(inline_asm_fn, &operand_vals, &value_name("inline_asm_expr"))
                 .unwrap();
 
             call.try_as_basic_value().left().unwrap()
         }
 
-        _ => unimplemented!("Unsupported expression type"),
+        Expression::FieldAccess { .. } => {
+            let ptr = generate_address_ir(context, builder, expr, variables, module, options)?;
+            builder.build_load(ptr, &value_name("load_field")).unwrap()
+        }
+
+        Expression::Alloc { ty, count } => {
+            let element_type = wave_type_to_llvm_type(context, &resolve_wave_type(ty, variables, module)?)?;
+            let i64_type = context.i64_type();
+            let count = match count {
+                Some(count) => match generate_expression_ir(context, builder, count, variables, module, Some(i64_type.into()), options)? {
+                    BasicValueEnum::IntValue(count) if count.get_type() == i64_type => count,
+                    BasicValueEnum::IntValue(count) => builder.build_int_s_extend_or_bit_cast(count, i64_type, &value_name("alloc_count")).unwrap(),
+                    other => return Err(invalid(
+                        "alloc count",
+                        tr!("the count of `alloc` must be an integer, found `{0}`", llvm_type_name(other.get_type())),
+                    )),
+                },
+                None => i64_type.const_int(1, false),
+            };
+            generate_alloc(context, builder, module, element_type, count)?.as_basic_value_enum()
+        }
+
+        // An integer is truncated, or extended by the sign of `value`; `bool` is never negative.
+        // Between floats and integers the value rounds toward zero, as an implicit conversion does.
+        Expression::Cast { value, ty } => {
+            let target = resolve_wave_type(ty, variables, module)?;
+            let target_type = wave_type_to_llvm_type(context, &target)?;
+            let signed = !is_unsigned_integer(value, variables);
+            let target_signed = matches!(target, WaveType::Int(_));
+            let name = value_name("cast");
+            match (generate_expression_ir(context, builder, value, variables, module, None, options)?, target_type) {
+                (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(t)) => {
+                    let signed = signed && v.get_type().get_bit_width() > 1;
+                    builder.build_int_cast_sign_flag(v, t, signed, &name).unwrap().into()
+                }
+                (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) if signed && v.get_type().get_bit_width() > 1 => {
+                    builder.build_signed_int_to_float(v, t, &name).unwrap().into()
+                }
+                (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => builder.build_unsigned_int_to_float(v, t, &name).unwrap().into(),
+                (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
+                    generate_float_to_int(context, builder, module, v, t, target_signed, options).into()
+                }
+                (BasicValueEnum::FloatValue(v), BasicTypeEnum::FloatType(t)) => builder.build_float_cast(v, t, &name).unwrap().into(),
+                (BasicValueEnum::PointerValue(v), BasicTypeEnum::PointerType(t)) => builder.build_pointer_cast(v, t, &name).unwrap().into(),
+                (BasicValueEnum::PointerValue(v), BasicTypeEnum::IntType(t)) => builder.build_ptr_to_int(v, t, &name).unwrap().into(),
+                (BasicValueEnum::IntValue(v), BasicTypeEnum::PointerType(t)) => builder.build_int_to_ptr(v, t, &name).unwrap().into(),
+                (other, _) => return Err(invalid(
+                    "invalid cast",
+                    tr!("cannot cast `{0}` as `{1}`", llvm_type_name(other.get_type()), target),
+                )),
+            }
+        }
+
+        Expression::StructLiteral { name, fields } => {
+            let declaration = struct_declaration(name)?;
+            let struct_type = wave_type_to_llvm_type(context, &WaveType::Struct(name.clone()))?;
+            let struct_ptr = builder.build_alloca(struct_type, &value_name(name)).unwrap();
+
+            if let Some((unknown, _)) = fields.iter().find(|(given, _)| declaration.field(given).is_none()) {
+                struct_field(name, unknown)?;
+            }
+
+            for field in &declaration.fields {
+                let Some((_, value)) = fields.iter().find(|(given, _)| *given == field.name) else {
+                    return Err(invalid("missing struct field", tr!("missing field(s) {0} in this `{1}` literal", format!("`{}`", field.name), name)));
+                };
+                let (index, field_type) = struct_field(name, &field.name)?;
+                let field_type = wave_type_to_llvm_type(context, &field_type)?;
+                let value = generate_expression_ir(context, builder, value, variables, module, Some(field_type), options)?;
+                let value = coerce_value(context, builder, module, value, field_type, options)?;
+                let field_ptr = builder.build_struct_gep(struct_ptr, index, &value_name(&field.name)).unwrap();
+                builder.build_store(field_ptr, value).unwrap();
+            }
+
+            builder.build_load(struct_ptr, &value_name("struct_value")).unwrap()
+        }
+
+        Expression::EnumVariant { enum_name, variant, args } => {
+            let (tag, declared) = enum_variant(enum_name, variant)?;
+            if args.len() != declared.fields.len() {
+                return Err(invalid(
+                    "wrong number of fields",
+                    tr!("`{0}::{1}` has {2} field(s), found {3}", enum_name, variant, declared.fields.len(), args.len()),
+                ));
+            }
+            let enum_type = wave_type_to_llvm_type(context, &WaveType::Struct(enum_name.clone()))?;
+            let enum_ptr = builder.build_alloca(enum_type, &value_name(enum_name)).unwrap();
+            let tag_ptr = builder.build_struct_gep(enum_ptr, 0, &value_name("tag")).unwrap();
+            builder.build_store(tag_ptr, context.i32_type().const_int(tag as u64, false)).unwrap();
+
+            let payload_type = variant_payload_type(context, &declared)?;
+            let payload_ptr = builder.build_struct_gep(enum_ptr, 1, &value_name("payload")).unwrap();
+            let payload_ptr = builder.build_pointer_cast(payload_ptr, payload_type.ptr_type(AddressSpace::default()), &value_name(variant)).unwrap();
+            for (index, (arg, field_type)) in args.iter().zip(&declared.fields).enumerate() {
+                let field_type = wave_type_to_llvm_type(context, field_type)?;
+                let value = generate_expression_ir(context, builder, arg, variables, module, Some(field_type), options)?;
+                let value = coerce_value(context, builder, module, value, field_type, options)?;
+                let field_ptr = builder.build_struct_gep(payload_ptr, index as u32, &value_name(&format!("{}.{}", variant, index))).unwrap();
+                builder.build_store(field_ptr, value).unwrap();
+            }
+
+            builder.build_load(enum_ptr, &value_name("enum_value")).unwrap()
+        }
+
+        Expression::While { .. } | Expression::Loop { .. } => {
+            let (keyword, condition, body, label) = match expr {
+                Expression::While { condition, body, label } => ("while", Some(&**condition), body, label),
+                Expression::Loop { body, label } => ("loop", None, body, label),
+                _ => unreachable!(),
+            };
+
+            // The loop starts a nesting of its own: a `break` in it cannot leave an enclosing loop.
+            // Its string constants are named apart from the function's by LLVM.
+            let mut string_counter = 0;
+            let values = LoopValues { ty: expected_type, incoming: vec![] };
+            let values = generate_while_ir(
+                context, builder, module, &mut string_counter, condition, None, body, label.as_deref(), variables, &mut vec![], &mut vec![], options, Some(values),
+            )?
+            .unwrap();
+
+            let Some(ty) = values.ty else {
+                return Err(invalid("loop without a value", tr!("this `{0}` is used as a value but no `break` gives it one", keyword)));
+            };
+            let phi = builder.build_phi(ty, &value_name(&format!("{}.value", keyword))).unwrap();
+            for (value, block) in &values.incoming {
+                phi.add_incoming(&[(value, *block)]);
+            }
+            phi.as_basic_value()
+        }
+
+        // An array literal is lowered where it initializes or is assigned to an array.
+        Expression::ArrayLiteral(_) => return Err(unsupported_construct(
+            "an array literal outside an array declaration",
+            "declare an array variable with it and use the variable",
+        )),
+    };
+    Ok(value)
+}
+
+// The variable whose value an assignment to `target` changes: the struct holding a field or the
+// array holding an element, but not the struct behind a pointer, which the pointer variable does not own.
+fn assigned_variable<'e>(target: &'e Expression, variables: &HashMap<String, VariableInfo>) -> Option<&'e str> {
+    match target {
+        Expression::Variable(name) => Some(name),
+        Expression::FieldAccess { target, .. } => match assigned_variable(target, variables) {
+            Some(name) if variables.get(name).is_some_and(|info| matches!(info.ty, WaveType::Pointer(_))) => None,
+            other => other,
+        },
+        Expression::IndexAccess { target, .. } => {
+            assigned_variable(target, variables).filter(|name| variables.get(*name).is_some_and(|info| matches!(info.ty, WaveType::Array(..))))
+        }
+        _ => None,
+    }
+}
+
+/// The address of element `index` of `target`, an array, a pointer or a `str`. An array's index
+/// is checked against its length unless `options.no_bounds_check`; a pointer's is not.
+pub fn generate_index_address<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    target: &Expression,
+    index: &Expression,
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    options: &CodegenOptions,
+) -> Result<PointerValue<'ctx>, CodegenError> {
+    // A local array is indexed in place through its alloca instead of being loaded whole.
+    let target_val = match target {
+        Expression::Variable(name) if variables.get(name).is_some_and(|info| matches!(info.ty, WaveType::Array(..))) => {
+            variables[name].ptr.as_basic_value_enum()
+        }
+        _ => generate_expression_ir(context, builder, target, variables, module, None, options)?,
+    };
+
+    let usize_type = wave_type_to_llvm_type(context, &WaveType::Uint(WaveType::POINTER_SIZED))?;
+    let index_val = generate_expression_ir(context, builder, index, variables, module, Some(usize_type), options)?;
+    let index_int = match index_val {
+        BasicValueEnum::IntValue(i) => usize_index(context, builder, i, is_unsigned_operand(index, variables)),
+        other => return Err(invalid("non-integer index", tr!("an index must be an integer, found `{0}`", llvm_type_name(other.get_type())))),
+    };
+
+    let zero = usize_type.into_int_type().const_zero();
+
+    // A `str` is indexed through its bytes; like a pointer's, the index is not checked.
+    let target_val = match target_val {
+        BasicValueEnum::StructValue(value) if is_str_type(value.get_type().into()) => {
+            str_parts(builder, value).0.as_basic_value_enum()
+        }
+        other => other,
+    };
+
+    match target_val {
+        BasicValueEnum::PointerValue(ptr_val) => unsafe {
+            let element_type = ptr_val.get_type().get_element_type();
+
+            if element_type.is_array_type() {
+                if !options.no_bounds_check {
+                    generate_bounds_check(context, builder, module, index_int, element_type.into_array_type().len());
+                }
+                Ok(builder.build_in_bounds_gep(ptr_val, &[zero, index_int], "array_index_gep").unwrap())
+            } else {
+                Ok(builder.build_in_bounds_gep(ptr_val, &[index_int], "ptr_index_gep").unwrap())
+            }
+        },
+
+        BasicValueEnum::ArrayValue(_) => Err(unsupported_construct(
+            &tr!("indexing into {0} that yields an array", tr!(describe_expression(target))),
+            "store the array in a variable first and index the variable",
+        )),
+
+        _ => Err(unsupported_construct(
+            &tr!("indexing into {0}", tr!(describe_expression(target))),
+            "only pointers and arrays can be indexed; store the value in a pointer variable first",
+        )),
+    }
+}
+
+// `operator` has no lowering for operands of the kind `operands` names (an English catalog key).
+fn unsupported_operator(operator: &Operator, operands: &str) -> CodegenError {
+    unsupported_construct(
+        &tr!("the `{0}` operator on {1}", operator_symbol(operator), tr!(operands)),
+        "convert the operands to a type the operator applies to",
+    )
+}
+
+fn operator_symbol(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Add => "+",
+        Operator::Subtract => "-",
+        Operator::Multiply => "*",
+        Operator::Divide => "/",
+        Operator::Remainder => "%",
+        Operator::GreaterEqual => ">=",
+        Operator::LessEqual => "<=",
+        Operator::Greater => ">",
+        Operator::Less => "<",
+        Operator::Equal => "==",
+        Operator::NotEqual => "!=",
+        Operator::LogicalAnd => "&&",
+        Operator::BitwiseAnd => "&",
+        Operator::LogicalOr => "||",
+        Operator::BitwiseOr => "|",
+        Operator::Assign => "=",
+    }
+}
+
+/// Lowers a call to `name`, a builtin or a function of the program. `None` for a function
+/// without a return type, which can only be called as a statement.
+pub fn generate_call_ir<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    name: &str,
+    args: &[Expression],
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expected_type: Option<BasicTypeEnum<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<Option<BasicValueEnum<'ctx>>, CodegenError> {
+    // A variable of function type shadows the functions of the program.
+    if let Some(VariableInfo { ptr, ty: WaveType::Function(params, return_type), .. }) = variables.get(name) {
+        let (ptr, params, return_type) = (*ptr, params.clone(), return_type.clone());
+        if params.len() != args.len() {
+            return Err(invalid("indirect call arity", tr!("function `{0}` takes {1} argument(s), found {2}", name, params.len(), args.len())));
+        }
+        let function_type = function_llvm_type(context, &params, return_type.as_deref(), false)?;
+        let address = builder.build_load(ptr, &value_name(name)).unwrap().into_pointer_value();
+        let mut compiled_args = vec![];
+        for (param, arg) in params.iter().zip(args) {
+            let param_type = wave_type_to_llvm_type(context, param)?;
+            let val = generate_expression_ir(context, builder, arg, variables, module, Some(param_type), options)?;
+            compiled_args.push(coerce_expression(context, builder, module, arg, val, param_type, variables, options)?.into());
+        }
+        debug_assert_eq!(address.get_type().get_element_type(), function_type.as_any_type_enum());
+        let callee = CallableValue::try_from(address).unwrap();
+        let call_site = builder.build_call(callee, &compiled_args, &value_name("calltmp")).unwrap();
+        tracing::trace!(function = %name, call_site = ?call_site, "lowered indirect call");
+        return Ok(call_site.try_as_basic_value().left());
+    }
+
+    let function = call_target(name, args, variables, module)?;
+    if function.is_none() {
+        // `dealloc` and `flush` are the builtins without a value.
+        if name == "dealloc" {
+            let [pointer] = args else {
+                return Err(invalid("builtin arity", tr!("builtin `{0}` takes {1} argument(s), found {2}", name, 1, args.len())));
+            };
+            let pointer = generate_expression_ir(context, builder, pointer, variables, module, None, options)?;
+            generate_dealloc(context, builder, module, pointer)?;
+            return Ok(None);
+        }
+        if name == "flush" {
+            if !args.is_empty() {
+                return Err(invalid("builtin arity", tr!("builtin `{0}` takes {1} argument(s), found {2}", name, 0, args.len())));
+            }
+            generate_flush(context, builder, module);
+            return Ok(None);
+        }
+        if let Some(value) = generate_builtin_call(context, builder, name, args, variables, module, expected_type, options)? {
+            return Ok(Some(value));
+        }
+    }
+
+    let function = function.ok_or_else(|| undefined_function(name, module, options))?;
+
+    let function_type = function.get_type();
+    let param_types: Vec<BasicTypeEnum> = function_type
+        .get_param_types()
+        .iter()
+        .map(|t| t.clone().into())
+        .collect();
+
+    // Each argument is converted to its parameter's type; the extra arguments of a variadic
+    // `extern` such as `printf` are promoted as C does, a string literal to its bytes.
+    let mut compiled_args = vec![];
+    for (i, arg) in args.iter().enumerate() {
+        let expected = param_types.get(i).copied().or_else(|| {
+            let bytes = context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum();
+            matches!(arg, Expression::Literal(Literal::String(_))).then_some(bytes)
+        });
+        let val = generate_expression_ir(context, builder, arg, variables, module, expected, options)?;
+        let val = match param_types.get(i) {
+            Some(&param_type) => coerce_expression(context, builder, module, arg, val, param_type, variables, options)?,
+            None => promote_variadic_argument(context, builder, val, is_unsigned_operand(arg, variables)),
+        };
+        compiled_args.push(val.into());
+    }
+
+    let call_site = builder.build_call(function, &compiled_args, &value_name("calltmp")).unwrap();
+    tracing::trace!(function = %name, call_site = ?call_site, "lowered call");
+    Ok(call_site.try_as_basic_value().left())
+}
+
+// C's default argument promotions, for an argument passed to a `...`: a float widens to `f64`
+// and an integer narrower than `i32` to `i32`, with its sign unless `unsigned`.
+fn promote_variadic_argument<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    unsigned: bool,
+) -> BasicValueEnum<'ctx> {
+    match value {
+        BasicValueEnum::FloatValue(v) if v.get_type() != context.f64_type() => {
+            builder.build_float_ext(v, context.f64_type(), &value_name("vararg_f64")).unwrap().as_basic_value_enum()
+        }
+        BasicValueEnum::IntValue(v) if v.get_type().get_bit_width() < 32 => {
+            let signed = !unsigned && v.get_type().get_bit_width() > 1;
+            builder.build_int_cast_sign_flag(v, context.i32_type(), signed, &value_name("vararg_i32")).unwrap().as_basic_value_enum()
+        }
+        other => other,
+    }
+}
+
+// The type a literal operand adopts from the other operand: integers and floats only,
+// and never an integer type for a literal that contains a fraction.
+// The type of the integer operand `expr` lowered to `value`, for `widen`: the width of `value`,
+// signed unless `expr` is unsigned or a `bool`.
+fn integer_operand_type(expr: &Expression, value: IntValue, variables: &HashMap<String, VariableInfo>) -> WaveType {
+    let bits = value.get_type().get_bit_width() as u16;
+    if bits == 1 || is_unsigned_integer(expr, variables) {
+        WaveType::Uint(bits)
+    } else {
+        WaveType::Int(bits)
+    }
+}
+
+// `value`, an operand of type `ty`, extended to `to` by its sign. A signed operand that meets an
+// unsigned one at least as wide is zero-extended; type checking warns of it.
+fn widen_integer<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+    ty: &WaveType,
+    to: IntType<'ctx>,
+    unsigned: bool,
+) -> IntValue<'ctx> {
+    if value.get_type() == to {
+        return value;
+    }
+    let signed = matches!(ty, WaveType::Int(_));
+    builder.build_int_cast_sign_flag(value, to, signed && !unsigned, &value_name("widen")).unwrap()
+}
+
+fn int_to_float<'ctx>(
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+    unsigned: bool,
+    to: FloatType<'ctx>,
+    name: &str,
+) -> FloatValue<'ctx> {
+    if unsigned || value.get_type().get_bit_width() == 1 {
+        builder.build_unsigned_int_to_float(value, to, &value_name(name)).unwrap()
+    } else {
+        builder.build_signed_int_to_float(value, to, &value_name(name)).unwrap()
+    }
+}
+
+fn literal_operand_type<'ctx>(literal: &Expression, other: BasicTypeEnum<'ctx>) -> Option<BasicTypeEnum<'ctx>> {
+    match other {
+        BasicTypeEnum::IntType(_) if !has_float_literal(literal) => Some(other),
+        BasicTypeEnum::FloatType(_) => Some(other),
+        _ => None,
+    }
+}
+
+fn has_float_literal(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Float(_)) => true,
+        Expression::Grouped(inner) => has_float_literal(inner),
+        Expression::BinaryExpression { left, right, .. } => has_float_literal(left) || has_float_literal(right),
+        _ => false,
+    }
+}
+
+// Whether `expr` has a type `--check-ub` wraps around instead of checking for overflow.
+fn is_unsigned_operand(expr: &Expression, variables: &HashMap<String, VariableInfo>) -> bool {
+    match expr {
+        Expression::Variable(name) => variables.get(name).is_some_and(|info| matches!(info.ty, WaveType::Bool)) || is_unsigned_integer(expr, variables),
+        Expression::Grouped(inner) => is_unsigned_operand(inner, variables),
+        _ => is_unsigned_integer(expr, variables),
+    }
+}
+
+// Whether `expr` is a `u8` to `u64`, a `char` or a `byte`, which compare, divide and widen as
+// unsigned. Arithmetic has the type of its operands, so one unsigned operand makes it unsigned.
+pub fn is_unsigned_integer(expr: &Expression, variables: &HashMap<String, VariableInfo>) -> bool {
+    let variable_type = |expr: &Expression| match expr {
+        Expression::Variable(name) => variables.get(name).map(|info| &info.ty),
+        _ => None,
+    };
+    match expr {
+        Expression::Literal(Literal::Char(_)) => true,
+        Expression::Variable(_) => variable_type(expr).is_some_and(is_unsigned_type),
+        Expression::IndexAccess { target, .. } => {
+            matches!(variable_type(target), Some(WaveType::Array(element, _)) if is_unsigned_type(element))
+        }
+        Expression::Deref(inner) => matches!(variable_type(inner), Some(WaveType::Pointer(element)) if is_unsigned_type(element)),
+        Expression::Grouped(inner) => is_unsigned_integer(inner, variables),
+        Expression::Cast { ty, .. } => is_unsigned_type(ty),
+        Expression::AssignOperation { target, .. } => is_unsigned_integer(target, variables),
+        Expression::BinaryExpression { left, operator, right } => {
+            matches!(
+                operator,
+                Operator::Add | Operator::Subtract | Operator::Multiply | Operator::Divide | Operator::Remainder | Operator::BitwiseAnd | Operator::BitwiseOr
+            ) && (is_unsigned_integer(left, variables) || is_unsigned_integer(right, variables))
+        }
+        _ => false,
+    }
+}
+
+fn is_unsigned_type(ty: &WaveType) -> bool {
+    matches!(ty, WaveType::Uint(_) | WaveType::Char | WaveType::Byte)
+}
+
+// The pointer `deref` follows: the value itself, or the bytes of a `str`.
+fn pointer_operand<'ctx>(builder: &'ctx inkwell::builder::Builder<'ctx>, value: BasicValueEnum<'ctx>) -> PointerValue<'ctx> {
+    match value {
+        BasicValueEnum::StructValue(value) if is_str_type(value.get_type().into()) => str_parts(builder, value).0,
+        other => other.into_pointer_value(),
     }
-}
\ No newline at end of file
+}
+
+/// Compares two strings by content, so `s == "abc"` tests the characters, not the addresses.
+fn generate_string_compare<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    operator: &Operator,
+    left: StructValue<'ctx>,
+    right: StructValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodegenError> {
+    let predicate = match operator {
+        Operator::Equal => IntPredicate::EQ,
+        Operator::NotEqual => IntPredicate::NE,
+        Operator::Less => IntPredicate::SLT,
+        Operator::LessEqual => IntPredicate::SLE,
+        Operator::Greater => IntPredicate::SGT,
+        Operator::GreaterEqual => IntPredicate::SGE,
+        other => return Err(unsupported_operator(other, "strings")),
+    };
+
+    let ordering = generate_str_compare(context, builder, module, left, right);
+    Ok(builder.build_int_compare(predicate, ordering, context.i32_type().const_zero(), &value_name("strcmptmp")).unwrap())
+}
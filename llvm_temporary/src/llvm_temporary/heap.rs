@@ -0,0 +1,88 @@
+use inkwell::context::Context;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::types::{BasicType, BasicTypeEnum};
+use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use inkwell::AddressSpace;
+use crate::llvm_temporary::llvm_codegen::{
+    generate_runtime_trap, invalid, llvm_type_name, targets_wasm, unsupported_construct, value_name, CodegenError,
+};
+use error::tr;
+
+/// `malloc` from the C library, declared the first time the program needs it.
+pub fn malloc_function<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) -> FunctionValue<'ctx> {
+    module.get_function("malloc").unwrap_or_else(|| {
+        let bytes = context.i8_type().ptr_type(AddressSpace::default());
+        module.add_function("malloc", bytes.fn_type(&[context.i64_type().into()], false), None)
+    })
+}
+
+/// `alloc<T>(count)`: `malloc` of `count` times the ABI size of `element_type`, as a pointer to
+/// the first element. Aborts when the size does not fit in 64 bits or `malloc` finds no memory.
+pub fn generate_alloc<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    element_type: BasicTypeEnum<'ctx>,
+    count: IntValue<'ctx>,
+) -> Result<PointerValue<'ctx>, CodegenError> {
+    if targets_wasm(module) {
+        return Err(unsupported_construct("heap allocation on WebAssembly", "use a local variable or an array"));
+    }
+    let size = element_type
+        .size_of()
+        .ok_or_else(|| invalid("unsized allocation", tr!("cannot allocate a value of type `{0}`", llvm_type_name(element_type))))?;
+
+    // Compared unsigned, a negative count overflows too.
+    let multiply = Intrinsic::find("llvm.umul.with.overflow")
+        .and_then(|intrinsic| intrinsic.get_declaration(module, &[size.get_type().into()]))
+        .expect("Failed to declare LLVM intrinsic 'llvm.umul.with.overflow'");
+    let product = builder
+        .build_call(multiply, &[size.into(), count.into()], &value_name("alloc_size"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_struct_value();
+    let bytes = builder.build_extract_value(product, 0, &value_name("alloc_bytes")).unwrap().into_int_value();
+    let overflow = builder.build_extract_value(product, 1, &value_name("alloc_overflow")).unwrap().into_int_value();
+    generate_runtime_trap(context, builder, module, overflow, "allocation size overflow");
+
+    let memory = builder
+        .build_call(malloc_function(context, module), &[bytes.into()], &value_name("alloc"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+    let failed = builder.build_is_null(memory, &value_name("alloc_failed")).unwrap();
+    generate_runtime_trap(context, builder, module, failed, "out of memory");
+
+    Ok(builder
+        .build_pointer_cast(memory, element_type.ptr_type(AddressSpace::default()), &value_name("alloc_ptr"))
+        .unwrap())
+}
+
+/// `dealloc(p)`: gives memory from `alloc` back to `free`.
+pub fn generate_dealloc<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: BasicValueEnum<'ctx>,
+) -> Result<(), CodegenError> {
+    if targets_wasm(module) {
+        return Err(unsupported_construct("heap allocation on WebAssembly", "use a local variable or an array"));
+    }
+    let BasicValueEnum::PointerValue(pointer) = value else {
+        return Err(invalid(
+            "builtin operand",
+            tr!("builtin `{0}` expects a pointer, found `{1}`", "dealloc", llvm_type_name(value.get_type())),
+        ));
+    };
+    let bytes = context.i8_type().ptr_type(AddressSpace::default());
+    let free = module
+        .get_function("free")
+        .unwrap_or_else(|| module.add_function("free", context.void_type().fn_type(&[bytes.into()], false), None));
+    let memory = builder.build_pointer_cast(pointer, bytes, &value_name("dealloc_ptr")).unwrap();
+    builder.build_call(free, &[memory.into()], "").unwrap();
+    Ok(())
+}
@@ -1,31 +1,298 @@
+use std::fmt;
 use std::process::Command;
 use std::fs;
 use std::path::Path;
+use inkwell::context::Context;
+use inkwell::memory_buffer::MemoryBuffer;
+use inkwell::module::Module;
+use inkwell::passes::{PassManager, PassManagerBuilder};
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple};
+use inkwell::values::InstructionOpcode;
+use inkwell::OptimizationLevel;
 
-pub fn compile_ir_to_machine_code(ir: &str, file_stem: &str) -> String {
-    let target_dir = Path::new("target");
-    if !target_dir.exists() {
-        fs::create_dir_all(target_dir).expect("Unable to create target directory");
+/// The machine code is generated for: the host's unless `--target` names another.
+#[derive(Debug, Clone, Default)]
+pub struct TargetOptions {
+    /// LLVM target triple, e.g. `aarch64-unknown-linux-gnu` or `wasm32-unknown-unknown` (`--target=<triple>`).
+    pub triple: Option<String>,
+    /// CPU to select and schedule instructions for (`--target-cpu=<name>`); `native` is the host's.
+    pub cpu: Option<String>,
+    /// Features added to or removed from the CPU's, e.g. `+neon,-sve` (`--target-features=<list>`).
+    pub features: Option<String>,
+}
+
+impl TargetOptions {
+    /// Whether the output is a WebAssembly module rather than an executable.
+    pub fn is_wasm(&self) -> bool {
+        self.triple.as_deref().is_some_and(|triple| triple.starts_with("wasm"))
+    }
+
+    /// Whether the code is for another kind of machine than this one, so it cannot run here:
+    /// another architecture or operating system. The vendor, as in `x86_64-pc-linux-gnu` and
+    /// `x86_64-unknown-linux-gnu`, makes no difference.
+    pub fn is_cross(&self) -> bool {
+        let arch_and_os = |triple: &TargetTriple| {
+            let normalized = TargetMachine::normalize_triple(triple).as_str().to_string_lossy().into_owned();
+            let parts: Vec<&str> = normalized.split('-').collect();
+            (parts.first().map(|arch| arch.to_string()), parts.get(2).map(|os| os.to_string()))
+        };
+        self.triple.as_deref().is_some_and(|triple| {
+            arch_and_os(&TargetTriple::create(triple)) != arch_and_os(&TargetMachine::get_default_triple())
+        })
+    }
+}
+
+/// Why the backend produced no output.
+#[derive(Debug)]
+pub enum BackendError {
+    /// LLVM rejected the IR code generation produced, a bug of the compiler.
+    InvalidIr(String),
+    /// A file could not be created or written.
+    Io(String),
+    /// LLVM cannot generate code for the target, or linking failed.
+    Failed(String),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::InvalidIr(message) => write!(f, "the generated IR is invalid: {}", message),
+            BackendError::Io(message) | BackendError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Whether LLVM can generate code for `target`; the message says why not.
+pub fn check_target(target: &TargetOptions) -> Result<(), String> {
+    target_machine(target, OptimizationLevel::None).map(drop)
+}
+
+/// Compiles `ir` for `target` and links it into the executable `output`, with the object
+/// file written next to it. `linker` names the linker the C compiler driver uses (`lld`,
+/// `gold`, ...); the driver's default when `None`.
+pub fn compile_ir_to_machine_code(
+    ir: &str,
+    output: &Path,
+    opt_level: u8,
+    linker: Option<&str>,
+    target: &TargetOptions,
+) -> Result<(), BackendError> {
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| BackendError::Io(format!("cannot create '{}': {}", dir.display(), e)))?;
+    }
+
+    let object_path = output.with_extension("o");
+    emit_object(ir, &object_path, opt_level, target)?;
+    link_executable(&object_path, output, linker, target).map_err(BackendError::Failed)
+}
+
+/// Runs LLVM's standard module pipeline for `-O<opt_level>` over `ir`: among others mem2reg,
+/// function inlining and dead code elimination. At `-O0` the IR is returned unchanged.
+pub fn optimize_ir(ir: &str, opt_level: u8) -> Result<String, BackendError> {
+    let opt_level = optimization_level(opt_level);
+    if opt_level == OptimizationLevel::None {
+        return Ok(ir.to_string());
     }
+    let _span = tracing::debug_span!("optimize", ?opt_level).entered();
+
+    let context = Context::create();
+    let module = parse_ir(&context, ir).map_err(BackendError::InvalidIr)?;
+
+    let passes = PassManagerBuilder::create();
+    passes.set_optimization_level(opt_level);
+    // clang's thresholds: 225 up to -O2, more eager inlining at -O3.
+    passes.set_inliner_with_threshold(if opt_level == OptimizationLevel::Aggressive { 275 } else { 225 });
+    let module_passes = PassManager::create(());
+    passes.populate_module_pass_manager(&module_passes);
+    module_passes.run_on(&module);
+
+    Ok(module.print_to_string().to_string())
+}
 
-    let ir_path = "target/temp.ll";
-    fs::write(ir_path, ir).expect("Unable to write IR to file");
+/// Writes `ir` as an object file for `target`; `opt_level` only tunes instruction selection
+/// and scheduling, `optimize_ir` runs the IR passes.
+pub fn emit_object(ir: &str, output: &Path, opt_level: u8, target: &TargetOptions) -> Result<(), BackendError> {
+    let _span = tracing::debug_span!("emit_object", output = %output.display(), opt_level).entered();
 
-    let machine_code_path = format!("target/{}", file_stem);
+    let machine = target_machine(target, optimization_level(opt_level)).map_err(BackendError::Failed)?;
+    let context = Context::create();
+    let module = parse_ir(&context, ir).map_err(BackendError::InvalidIr)?;
 
-    let output = Command::new("clang")
-        .arg("-o")
-        .arg(&machine_code_path)
-        .arg(ir_path)
-        .arg("-lc")
-        .arg("-lm")
-        .output()
-        .expect("Failed to execute clang");
+    machine
+        .write_to_file(&module, FileType::Object, output)
+        .map_err(|e| BackendError::Io(format!("cannot write '{}': {}", output.display(), e.to_string_lossy())))
+}
 
-    if !output.status.success() {
-        eprintln!("clang failed: {}", String::from_utf8_lossy(&output.stderr));
-        return String::new();
+/// Links `object` with the C library into the executable `output`. The system C compiler
+/// driver runs the linker, so the startup files and library paths of the host are used; for
+/// another target it is `clang`, which needs that target's C library and linker installed.
+pub fn link_executable(object: &Path, output: &Path, linker: Option<&str>, target: &TargetOptions) -> Result<(), String> {
+    if target.is_wasm() {
+        return link_wasm_module(object, output, linker);
     }
+    let driver = if target.is_cross() { "clang" } else { "cc" };
+    let mut command = Command::new(driver);
+    if let (true, Some(triple)) = (target.is_cross(), &target.triple) {
+        command.arg(format!("--target={}", triple));
+    }
+    command.arg("-o").arg(output).arg(object).arg("-lc").arg("-lm");
+    if let Some(linker) = linker {
+        command.arg(format!("-fuse-ld={}", linker));
+    }
+    let _span = tracing::debug_span!("link", output = %output.display(), ?linker).entered();
+
+    let result = command.output().map_err(|e| format!("cannot run the linker `{}`: {}", driver, e))?;
+    if !result.status.success() {
+        return Err(format!("linking failed: {}", String::from_utf8_lossy(&result.stderr).trim_end()));
+    }
+    Ok(())
+}
+
+// A WebAssembly module has no C library or entry point: `main` is exported for the host to
+// call, and the `wave` imports the printing lowers to stay undefined for the host to provide.
+fn link_wasm_module(object: &Path, output: &Path, linker: Option<&str>) -> Result<(), String> {
+    let linker = linker.unwrap_or("wasm-ld");
+    let mut command = Command::new(linker);
+    command.args(["--no-entry", "--export=main", "--allow-undefined", "-o"]).arg(output).arg(object);
+    let _span = tracing::debug_span!("link", output = %output.display(), linker).entered();
+
+    let result = command.output().map_err(|e| format!("cannot run the linker `{}`: {}", linker, e))?;
+    if !result.status.success() {
+        return Err(format!("linking failed: {}", String::from_utf8_lossy(&result.stderr).trim_end()));
+    }
+    Ok(())
+}
+
+/// Stack frame of one function, as laid out for the target.
+pub struct FrameUsage {
+    pub function: String,
+    pub bytes: u64,
+    /// An alloca outside the entry block: the frame grows each time that block runs.
+    pub dynamic: bool,
+}
+
+fn optimization_level(opt_level: u8) -> OptimizationLevel {
+    match opt_level {
+        0 => OptimizationLevel::None,
+        1 => OptimizationLevel::Less,
+        2 => OptimizationLevel::Default,
+        _ => OptimizationLevel::Aggressive,
+    }
+}
+
+/// The code generator for `target`, or why LLVM has none, e.g. for a triple it does not know.
+// The C compiler driver links position-independent executables by default, so match that here.
+pub fn target_machine(target: &TargetOptions, opt_level: OptimizationLevel) -> Result<TargetMachine, String> {
+    let triple = match &target.triple {
+        Some(triple) => {
+            Target::initialize_all(&InitializationConfig::default());
+            TargetTriple::create(triple)
+        }
+        None => {
+            Target::initialize_native(&InitializationConfig::default())?;
+            TargetMachine::get_default_triple()
+        }
+    };
+    // LLVM picks the target's baseline for an empty CPU; not every target calls it `generic`.
+    let cpu = match target.cpu.as_deref() {
+        Some("native") => TargetMachine::get_host_cpu_name().to_string(),
+        Some(cpu) => cpu.to_string(),
+        None if target.triple.is_none() => "generic".to_string(),
+        None => String::new(),
+    };
+    let features = target.features.as_deref().unwrap_or("");
+
+    let describe = || triple.as_str().to_string_lossy().into_owned();
+    Target::from_triple(&triple)
+        .map_err(|e| format!("unknown target `{}`: {}", describe(), e.to_string_lossy()))?
+        .create_target_machine(&triple, &cpu, features, opt_level, RelocMode::PIC, CodeModel::Default)
+        .ok_or_else(|| format!("LLVM cannot generate code for `{}` on CPU `{}`", describe(), cpu))
+}
+
+fn parse_ir<'ctx>(context: &'ctx Context, ir: &str) -> Result<Module<'ctx>, String> {
+    let buffer = MemoryBuffer::create_from_memory_range_copy(ir.as_bytes(), "main");
+    context.create_module_from_ir(buffer).map_err(|e| e.to_string_lossy().into_owned())
+}
+
+/// Sums the allocas of every defined function in `ir`, padding each to its ABI alignment.
+pub fn frame_usage(ir: &str, target: &TargetOptions) -> Result<Vec<FrameUsage>, BackendError> {
+    let machine = target_machine(target, OptimizationLevel::None).map_err(BackendError::Failed)?;
+    let layout = machine.get_target_data();
+    let context = Context::create();
+    let module = parse_ir(&context, ir).map_err(BackendError::InvalidIr)?;
+
+    let mut frames = vec![];
+    for function in module.get_functions() {
+        let Some(entry) = function.get_first_basic_block() else {
+            continue;
+        };
+
+        let mut bytes: u64 = 0;
+        let mut dynamic = false;
+        for block in function.get_basic_blocks() {
+            let mut instruction = block.get_first_instruction();
+            while let Some(inst) = instruction {
+                if inst.get_opcode() == InstructionOpcode::Alloca {
+                    let ty = inst.get_allocated_type().unwrap();
+                    let align = layout.get_abi_alignment(&ty) as u64;
+                    bytes = bytes.div_ceil(align) * align + layout.get_abi_size(&ty);
+                    dynamic |= block != entry;
+                }
+                instruction = inst.get_next_instruction();
+            }
+        }
+
+        frames.push(FrameUsage {
+            function: function.get_name().to_string_lossy().into_owned(),
+            bytes,
+            dynamic,
+        });
+    }
+
+    Ok(frames)
+}
+
+/// Section and per-function sizes of the object file `ir` compiles to.
+pub struct SizeReport {
+    pub sections: Vec<(String, u64)>,
+    pub functions: Vec<(String, u64)>,
+}
+
+/// Emits `ir` as an object for `target` and reads the sizes back out of it.
+pub fn object_sizes(ir: &str, target: &TargetOptions) -> Result<SizeReport, BackendError> {
+    let machine = target_machine(target, OptimizationLevel::None).map_err(BackendError::Failed)?;
+    let context = Context::create();
+    let module = parse_ir(&context, ir).map_err(BackendError::InvalidIr)?;
+
+    let object = machine
+        .write_to_memory_buffer(&module, FileType::Object)
+        .map_err(|e| BackendError::Failed(e.to_string_lossy().into_owned()))?
+        .create_object_file()
+        .map_err(|_| BackendError::Failed("the emitted object file could not be read back".to_string()))?;
+
+    let sections = object
+        .get_sections()
+        .filter(|section| section.size() > 0)
+        .filter_map(|section| {
+            let name = section.get_name()?.to_string_lossy().into_owned();
+            (!name.is_empty()).then_some((name, section.size()))
+        })
+        .collect();
+
+    let defined: Vec<String> = module
+        .get_functions()
+        .filter(|function| function.get_first_basic_block().is_some())
+        .map(|function| function.get_name().to_string_lossy().into_owned())
+        .collect();
+    let functions = object
+        .get_symbols()
+        // The source-file symbol shares the module's name, `main`, but has no size.
+        .filter(|symbol| symbol.size() > 0)
+        .filter_map(|symbol| {
+            let name = symbol.get_name()?.to_string_lossy().into_owned();
+            defined.contains(&name).then_some((name, symbol.size()))
+        })
+        .collect();
 
-    machine_code_path
-}
\ No newline at end of file
+    Ok(SizeReport { sections, functions })
+}
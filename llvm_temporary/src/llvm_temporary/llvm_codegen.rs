@@ -1,189 +1,1466 @@
-use parser::ast::{ASTNode, FunctionNode, Expression, WaveType, Mutability, Value};
+use parser::overload::{select_overload, symbol_name, Overload};
+use parser::parse_format_string;
+use parser::typeck::widen;
+use parser::ast::{ASTNode, EnumNode, EnumVariant, FormatPart, FunctionNode, Expression, Literal, Operator, Span, StatementNode, StructNode, WaveType, Mutability, Value};
+use error::error::{WaveError, WaveErrorKind};
+use error::fixes::{similar_name, Suggestion};
+use error::tr;
 use inkwell::context::Context;
-use inkwell::values::{PointerValue, FunctionValue, BasicValue};
-use inkwell::{AddressSpace};
+use inkwell::debug_info::{AsDIScope, DICompileUnit, DIFlags, DIFlagsConstants, DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder};
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::{FlagBehavior, Linkage};
+use inkwell::targets::TargetData;
+use inkwell::values::{CallableValue, PointerValue, FunctionValue, BasicValue, BasicValueEnum, FloatValue, IntValue};
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate};
+use llvm_sys::core::LLVMSetCurrentDebugLocation2;
+use llvm_sys::debuginfo::LLVMDIBuilderCreateDebugLocation;
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use inkwell::types::{AnyType, AnyTypeEnum, BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FloatType, FunctionType, IntType, StructType};
 use lexer::token::TokenType;
-use crate::llvm_temporary::statement::generate_statement_ir;
+use crate::llvm_temporary::expression::{generate_expression_ir, generate_index_address};
+use crate::llvm_temporary::statement::{block_terminated, generate_statement_ir};
+use crate::llvm_temporary::bench::generate_bench_main;
+use crate::llvm_temporary::backtrace::{enable_backtraces, generate_backtrace_print, generate_frame_pop, generate_frame_push};
+use crate::llvm_temporary::builtin::BUILTIN_FUNCTIONS;
+use crate::llvm_temporary::print::{entry_alloca, generate_bool_text, generate_flush, generate_stdout_buffering, PrintArgument};
+use crate::llvm_temporary::llvm_backend::{target_machine, TargetOptions};
+use crate::llvm_temporary::string::{generate_str_literal, is_str_type, str_parts, str_type};
 
-pub unsafe fn generate_ir(ast_nodes: &[ASTNode]) -> String {
+#[derive(Debug, Clone, Default)]
+pub struct CodegenOptions {
+    /// Insert a runtime check before every pointer deref that traps on misaligned addresses.
+    pub check_alignment: bool,
+    /// Trap on every operation LLVM leaves undefined, and on signed overflow, each with its own
+    /// message: `--check-align` plus null derefs, division by zero and overflowing arithmetic
+    /// (`--check-ub`). Shift amounts and float-to-int conversions out of range also trap,
+    /// whatever `shift_amount` and `float_to_int` say.
+    pub check_ub: bool,
+    /// What `shift_left` and `shift_right` do with an amount of the width or more (`--shift-amount=mask|trap`).
+    pub shift_amount: ShiftAmount,
+    /// What converting a float that is NaN or out of the integer's range gives (`--float-to-int=saturate|trap`).
+    pub float_to_int: FloatToInt,
+    /// Leave out the runtime check of array indices against the array's length (`--no-bounds-check`).
+    pub no_bounds_check: bool,
+    /// Directory that `include_bytes`/`include_str` paths are resolved against.
+    pub source_dir: Option<PathBuf>,
+    /// Write a Makefile-style `.d` file listing every source the output depends on.
+    pub emit_dep_info: bool,
+    /// Write a `.su` file with each function's stack frame size.
+    pub emit_stack_usage: bool,
+    /// Write a `.size` file with the object's section and per-function sizes.
+    pub emit_size: bool,
+    /// Write a `.ll` file with the IR, each source line's instructions under a comment quoting it.
+    pub emit_llvm_ir: bool,
+    /// Write a `.callgraph.dot` Graphviz file with the program's call graph.
+    pub emit_callgraph: bool,
+    /// Write a `.cfg.dot` Graphviz file with the control-flow graph of every compiled function.
+    pub emit_cfg: bool,
+    /// Write a `.symbols.json` file indexing every definition of the program and its references.
+    pub emit_symbols: bool,
+    /// Write a `.layout` file with the size, alignment and field offsets of every struct.
+    pub emit_layout: bool,
+    /// Replace `main` with a harness that times every `#[bench]` function (`wave bench`).
+    pub bench: bool,
+    /// File that diagnostics point into.
+    pub source_file: Option<PathBuf>,
+    /// The file defining each function that comes from an import, for its diagnostics and debug info.
+    pub function_files: HashMap<String, PathBuf>,
+    /// Functions dropped as unreachable before codegen, still offered as spelling suggestions.
+    pub pruned_functions: Vec<String>,
+    /// How the warnings of each lint named with `--allow=`, `--warn=` or `--deny=` are reported:
+    /// `implicit-conversion`, `recursion` or a registered lint pass. The others warn.
+    pub lint_levels: HashMap<String, LintLevel>,
+    /// Optimization level of the executable, `-O0` to `-O3`; `wave bench` uses `-O2`.
+    pub opt_level: u8,
+    /// Linker used to produce the executable (`--linker=<name>`, e.g. `lld`); the system default when unset.
+    pub linker: Option<String>,
+    /// Machine to generate code for (`--target`, `--target-cpu`, `--target-features`); the host by default.
+    pub target: TargetOptions,
+    /// Emit DWARF debug info, a line location for every statement, so gdb and lldb can step
+    /// through the program, and keep the Wave call stack for runtime errors to print (`-g`).
+    pub debug_info: bool,
+    /// Directory prefixes debug info records under another name (`--remap-path-prefix=FROM=TO`),
+    /// so that it does not depend on where the program is built; the last that matches applies.
+    pub remap_path_prefix: Vec<(PathBuf, PathBuf)>,
+    /// The compiler debug info names as the producer, e.g. `wavec 0.1.2`; filled in by the driver,
+    /// whose version this crate does not know.
+    pub producer: String,
+    /// Whether foreign exceptions may unwind through Wave frames (`--panic=abort|unwind`).
+    pub panic: PanicStrategy,
+}
+
+/// What becomes of an exception of C++ or Rust that unwinds into a Wave frame. Wave itself
+/// never unwinds: a failed runtime check aborts under either strategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PanicStrategy {
+    /// Functions are `nounwind` and get no unwind tables; the unwinder stops at the first Wave
+    /// frame and the program terminates.
+    #[default]
+    Abort,
+    /// Every function gets an unwind table, so the unwinder can step through its frame.
+    /// Wave frames hold nothing to clean up (no destructors, no owned values), so they need no
+    /// landing pad and no personality routine, as C compiled with `-fexceptions`.
+    Unwind,
+}
+
+impl PanicStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abort" => Some(PanicStrategy::Abort),
+            "unwind" => Some(PanicStrategy::Unwind),
+            _ => None,
+        }
+    }
+}
+
+/// How a shift by the width of the value or more, which LLVM leaves undefined, is lowered.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ShiftAmount {
+    /// The amount is taken modulo the width, as x86 and AArch64 do for 32- and 64-bit shifts.
+    #[default]
+    Mask,
+    /// The program aborts with a runtime error.
+    Trap,
+}
+
+impl ShiftAmount {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mask" => Some(ShiftAmount::Mask),
+            "trap" => Some(ShiftAmount::Trap),
+            _ => None,
+        }
+    }
+}
+
+/// How a float is converted to an integer whose range does not hold it, which LLVM's
+/// `fptosi` leaves undefined.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FloatToInt {
+    /// The nearest value of the range, and 0 for NaN, as Rust's `as` does.
+    #[default]
+    Saturate,
+    /// The program aborts with a runtime error.
+    Trap,
+}
+
+impl FloatToInt {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "saturate" => Some(FloatToInt::Saturate),
+            "trap" => Some(FloatToInt::Trap),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LintLevel {
+    Allow,
+    #[default]
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn from_flag(flag: &str) -> Option<(Self, &str)> {
+        [("--allow=", LintLevel::Allow), ("--warn=", LintLevel::Warn), ("--deny=", LintLevel::Deny)]
+            .into_iter()
+            .find_map(|(prefix, level)| flag.strip_prefix(prefix).map(|lint| (level, lint)))
+    }
+}
+
+/// The type (a `WaveType::Function`) and symbol of each function that has a name.
+type Overloads = Vec<(WaveType, String)>;
+
+thread_local! {
+    static CURRENT_FUNCTION: RefCell<Option<String>> = const { RefCell::new(None) };
+    static CURRENT_NODE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static CURRENT_SPAN: Cell<Span> = const { Cell::new(Span { line: 0, column: 0 }) };
+    static SOURCE_FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    /// The structs of the program being lowered, by name.
+    static STRUCTS: RefCell<HashMap<String, StructNode>> = RefCell::new(HashMap::new());
+    /// The enums of the program being lowered, by name.
+    static ENUMS: RefCell<HashMap<String, EnumNode>> = RefCell::new(HashMap::new());
+    /// The functions of the program being lowered, by name; more than one for an overloaded name.
+    static FUNCTIONS: RefCell<HashMap<String, Overloads>> = RefCell::new(HashMap::new());
+    /// How often each value name was handed out in the function being lowered.
+    static VALUE_NAMES: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+    /// The width of a pointer on the target, and so of `isize` and `usize`.
+    static POINTER_BITS: Cell<u32> = const { Cell::new(64) };
+}
+
+/// Restores the previously active node when the node being lowered finishes.
+pub struct NodeGuard(Option<String>, Span);
+
+impl Drop for NodeGuard {
+    fn drop(&mut self) {
+        CURRENT_NODE.with(|node| *node.borrow_mut() = self.0.take());
+        CURRENT_SPAN.with(|span| span.set(self.1));
+    }
+}
+
+/// Records `description` as the node being lowered until the returned guard is dropped.
+/// A default `span` keeps the enclosing node's position.
+pub fn enter_node(description: String, span: Span) -> NodeGuard {
+    tracing::trace!(node = %description, line = span.line, column = span.column, "lowering");
+    let previous_span = CURRENT_SPAN.with(|current| {
+        let previous = current.get();
+        if span != Span::default() {
+            current.set(span);
+        }
+        previous
+    });
+    NodeGuard(CURRENT_NODE.with(|node| node.borrow_mut().replace(description)), previous_span)
+}
+
+/// Points the instructions built from here on at `span` in `function`'s source. Does nothing
+/// without debug info (`-g`), which is when `function` has no subprogram, or for a span
+/// without a position.
+pub fn set_debug_location(context: &Context, builder: &inkwell::builder::Builder, function: FunctionValue, span: Span) {
+    let Some(subprogram) = function.get_subprogram() else { return };
+    if span == Span::default() {
+        return;
+    }
+    // inkwell only hands out locations through the `DebugInfoBuilder`, which statement lowering
+    // has no handle on; LLVM itself needs nothing but the context.
+    unsafe {
+        let location = LLVMDIBuilderCreateDebugLocation(
+            context.raw(),
+            span.line as u32,
+            span.column as u32,
+            subprogram.as_mut_ptr(),
+            std::ptr::null_mut(),
+        );
+        #[allow(deprecated)]
+        LLVMSetCurrentDebugLocation2(builder.as_mut_ptr(), location);
+    }
+}
+
+/// A name for an IR value, unique within the function being lowered: `base` tagged with the
+/// source line it comes from, as in `sum.L12`, and a counter when that line produces several,
+/// as in `sum.L12.1`. Keeps the emitted IR readable where LLVM would append bare numbers.
+pub fn value_name(base: &str) -> String {
+    let line = CURRENT_SPAN.with(|span| span.get().line);
+    let name = if line == 0 { base.to_string() } else { format!("{}.L{}", base, line) };
+    VALUE_NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        let count = names.entry(name.clone()).or_insert(0);
+        *count += 1;
+        match *count {
+            1 => name,
+            n => format!("{}.{}", name, n - 1),
+        }
+    })
+}
+
+/// The function and node being lowered right now, for internal compiler error reports.
+pub fn compile_context() -> (Option<String>, Option<String>) {
+    (
+        CURRENT_FUNCTION.with(|f| f.borrow().clone()),
+        CURRENT_NODE.with(|n| n.borrow().clone()),
+    )
+}
+
+/// Why lowering a program to LLVM IR failed. Each variant carries the diagnostic to print,
+/// positioned at the statement being lowered when the error was found.
+#[derive(Debug)]
+pub enum CodegenError {
+    /// A variable or function that is not defined where it is used.
+    Undefined(Box<WaveError>),
+    /// A type the backend cannot lower, such as a float width other than 32 or 64.
+    UnsupportedType(Box<WaveError>),
+    /// A statement or expression form the backend does not lower.
+    UnsupportedConstruct(Box<WaveError>),
+    /// Any other error in the program: a bad assignment, an out-of-range literal, a denied lint.
+    Invalid(Box<WaveError>),
+}
+
+impl CodegenError {
+    pub fn diagnostic(&self) -> &WaveError {
+        match self {
+            CodegenError::Undefined(error)
+            | CodegenError::UnsupportedType(error)
+            | CodegenError::UnsupportedConstruct(error)
+            | CodegenError::Invalid(error) => error,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        let error = self.diagnostic();
+        Span::new(error.line, error.column)
+    }
+
+    pub fn into_diagnostic(self) -> WaveError {
+        match self {
+            CodegenError::Undefined(error)
+            | CodegenError::UnsupportedType(error)
+            | CodegenError::UnsupportedConstruct(error)
+            | CodegenError::Invalid(error) => *error,
+        }
+    }
+}
+
+/// The inline assembly `asm`, as a callee for `build_call`.
+pub fn asm_callee(asm: PointerValue) -> Result<CallableValue, CodegenError> {
+    CallableValue::try_from(asm).map_err(|_| invalid("inline asm", tr!("the `asm` block could not be lowered to a call")))
+}
+
+/// An error in the program at the statement being lowered; `kind` is a short English
+/// category, `message` the translated text.
+pub fn invalid(kind: &str, message: String) -> CodegenError {
+    CodegenError::Invalid(Box::new(diagnostic_here(WaveErrorKind::SemanticError(kind.to_string()), message)))
+}
+
+/// `ty` cannot be lowered; `help` says what to use instead.
+pub fn unsupported_type(ty: &WaveType, help: String) -> CodegenError {
+    let error = diagnostic_here(
+        WaveErrorKind::UnsupportedConstruct(ty.to_string()),
+        tr!("type `{0}` is not supported", ty),
+    )
+    .with_help(help);
+    CodegenError::UnsupportedType(Box::new(error))
+}
+
+/// A construct the backend does not lower, pointing at the statement being lowered.
+/// `construct` and `help` are English catalog keys (or already translated text).
+pub fn unsupported_construct(construct: &str, help: &str) -> CodegenError {
+    let node = CURRENT_NODE.with(|node| node.borrow().clone());
+
+    let mut error = diagnostic_here(
+        WaveErrorKind::UnsupportedConstruct(construct.to_string()),
+        tr!("{0} is not supported", tr!(construct)),
+    )
+    .with_help(tr!(help));
+
+    if let Some(node) = node {
+        error = error.with_label(tr!("in this {0}", tr!(&node)));
+    }
+
+    CodegenError::UnsupportedConstruct(Box::new(error))
+}
+
+/// `name` is not a variable in scope. When a name in `in_scope` is a likely typo match,
+/// the diagnostic carries a fix replacing it.
+pub fn undefined_variable<'a>(name: &str, in_scope: impl IntoIterator<Item = &'a String>) -> CodegenError {
+    let error = diagnostic_here(
+        WaveErrorKind::SemanticError("undefined variable".to_string()),
+        tr!("cannot find variable `{0}` in this scope", name),
+    );
+    let similar = similar_name(name, in_scope.into_iter().map(String::as_str))
+        .map(|similar| (tr!("a variable with a similar name exists: `{0}`", similar), similar));
+
+    CodegenError::Undefined(Box::new(suggest_similar(error, name, similar)))
+}
+
+/// No function or builtin is called `name`. Suggests the closest builtin or function of
+/// the program, including pruned ones.
+pub fn undefined_function(name: &str, module: &inkwell::module::Module, options: &CodegenOptions) -> CodegenError {
+    let error = diagnostic_here(
+        WaveErrorKind::SemanticError("undefined function".to_string()),
+        tr!("cannot find function `{0}` in this scope", name),
+    );
+    // Symbols with a `.` are internal or overloads, which go by the name they share.
+    let mut defined: Vec<String> = module
+        .get_functions()
+        .map(|function| function.get_name().to_string_lossy().into_owned())
+        .filter(|function| !function.contains('.'))
+        .collect();
+    FUNCTIONS.with(|functions| defined.extend(functions.borrow().keys().cloned()));
+    let candidates = defined
+        .iter()
+        .chain(&options.pruned_functions)
+        .map(String::as_str)
+        .chain(BUILTIN_FUNCTIONS.iter().copied());
+    let similar = similar_name(name, candidates)
+        .map(|similar| (tr!("a function with a similar name exists: `{0}`", similar), similar));
+
+    CodegenError::Undefined(Box::new(suggest_similar(error, name, similar)))
+}
+
+/// The function a call to `name` with `args` goes to: the one of that name, or the overload
+/// the types of `args` select as the type checker selects it. `None` when no function of the
+/// program is called `name`, as for a builtin.
+pub fn call_target<'ctx>(
+    name: &str,
+    args: &[Expression],
+    variables: &HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) -> Result<Option<FunctionValue<'ctx>>, CodegenError> {
+    let overloads = FUNCTIONS.with(|functions| functions.borrow().get(name).cloned()).unwrap_or_default();
+    let [_, _, ..] = overloads[..] else {
+        return Ok(module.get_function(name));
+    };
+    let arg_types: Vec<Option<WaveType>> = args.iter().map(|arg| infer_binding_type(arg, variables, module).ok()).collect();
+    let candidates: Vec<Vec<WaveType>> = overloads
+        .iter()
+        .map(|(function_type, _)| match function_type {
+            WaveType::Function(params, _) => params.clone(),
+            _ => vec![],
+        })
+        .collect();
+    match select_overload(&candidates, &arg_types) {
+        Overload::Selected(index) => Ok(module.get_function(&overloads[index].1)),
+        Overload::NoMatch | Overload::Ambiguous(_) => Err(invalid(
+            "overload resolution",
+            tr!("no single function `{0}` takes the arguments of this call", name),
+        )),
+    }
+}
+
+/// The address of function `name` used as a value, with its `fun(..) -> ..` type. Of an
+/// overloaded name, the function whose address has the `expected` type. `None` when no
+/// function of the program is called `name`.
+pub fn function_address<'ctx>(
+    name: &str,
+    expected: Option<BasicTypeEnum<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) -> Result<Option<(PointerValue<'ctx>, WaveType)>, CodegenError> {
+    let Some(overloads) = FUNCTIONS.with(|functions| functions.borrow().get(name).cloned()) else {
+        return Ok(None);
+    };
+    let address = |symbol: &str| module.get_function(symbol).map(|function| function.as_global_value().as_pointer_value());
+    let chosen = match overloads[..] {
+        [(ref function_type, ref symbol)] => address(symbol).map(|address| (address, function_type.clone())),
+        _ => overloads.iter().find_map(|(function_type, symbol)| {
+            address(symbol)
+                .filter(|address| Some(address.get_type().as_basic_type_enum()) == expected)
+                .map(|address| (address, function_type.clone()))
+        }),
+    };
+    match chosen {
+        Some(chosen) => Ok(Some(chosen)),
+        None => Err(invalid(
+            "overloaded function used as value",
+            tr!("`{0}` is overloaded, so its address needs a variable of function type to pick one", name),
+        )),
+    }
+}
+
+/// No struct of the program is called `name`.
+pub fn undefined_struct(name: &str) -> CodegenError {
+    let error = diagnostic_here(
+        WaveErrorKind::SemanticError("undefined struct".to_string()),
+        tr!("cannot find struct `{0}` in this scope", name),
+    );
+    let declared: Vec<String> = STRUCTS.with(|structs| structs.borrow().keys().cloned().collect());
+    let similar = similar_name(name, declared.iter().map(String::as_str))
+        .map(|similar| (tr!("a struct with a similar name exists: `{0}`", similar), similar));
+
+    CodegenError::Undefined(Box::new(suggest_similar(error, name, similar)))
+}
+
+/// The declaration of struct `name`.
+pub fn struct_declaration(name: &str) -> Result<StructNode, CodegenError> {
+    STRUCTS
+        .with(|structs| structs.borrow().get(name).cloned())
+        .ok_or_else(|| undefined_struct(name))
+}
+
+/// The index and type of `field` in struct `name`.
+pub fn struct_field(name: &str, field: &str) -> Result<(u32, WaveType), CodegenError> {
+    let declaration = struct_declaration(name)?;
+    match declaration.field(field) {
+        Some((index, field_type)) => Ok((index as u32, field_type.clone())),
+        None => {
+            let fields: Vec<String> = declaration.fields.iter().map(|field| format!("`{}`", field.name)).collect();
+            let error = diagnostic_here(
+                WaveErrorKind::SemanticError("unknown field".to_string()),
+                tr!("no field `{0}` on type `{1}`", field, name),
+            )
+            .with_note(tr!("`{0}` has the fields {1}", name, fields.join(", ")));
+            Err(CodegenError::Invalid(Box::new(error)))
+        }
+    }
+}
+
+/// No enum of the program is called `name`.
+pub fn undefined_enum(name: &str) -> CodegenError {
+    let error = diagnostic_here(
+        WaveErrorKind::SemanticError("undefined enum".to_string()),
+        tr!("cannot find enum `{0}` in this scope", name),
+    );
+    let declared: Vec<String> = ENUMS.with(|enums| enums.borrow().keys().cloned().collect());
+    let similar = similar_name(name, declared.iter().map(String::as_str))
+        .map(|similar| (tr!("an enum with a similar name exists: `{0}`", similar), similar));
+
+    CodegenError::Undefined(Box::new(suggest_similar(error, name, similar)))
+}
+
+/// Whether `name` is an enum of the program rather than a struct.
+pub fn is_enum(name: &str) -> bool {
+    ENUMS.with(|enums| enums.borrow().contains_key(name))
+}
+
+/// The tag and declaration of `variant` of enum `enum_name`.
+pub fn enum_variant(enum_name: &str, variant: &str) -> Result<(u32, EnumVariant), CodegenError> {
+    let declaration = ENUMS
+        .with(|enums| enums.borrow().get(enum_name).cloned())
+        .ok_or_else(|| undefined_enum(enum_name))?;
+    match declaration.variant(variant) {
+        Some((tag, declared)) => Ok((tag as u32, declared.clone())),
+        None => {
+            let variants: Vec<String> = declaration.variants.iter().map(|variant| format!("`{}`", variant.name)).collect();
+            let error = diagnostic_here(
+                WaveErrorKind::SemanticError("unknown variant".to_string()),
+                tr!("no variant `{0}` on enum `{1}`", variant, enum_name),
+            )
+            .with_note(tr!("`{0}` has the variants {1}", enum_name, variants.join(", ")));
+            Err(CodegenError::Invalid(Box::new(error)))
+        }
+    }
+}
+
+/// The fields of `variant` laid out as they are in the payload of its enum.
+pub fn variant_payload_type<'ctx>(context: &'ctx Context, variant: &EnumVariant) -> Result<StructType<'ctx>, CodegenError> {
+    let field_types = variant.fields.iter()
+        .map(|field| wave_type_to_llvm_type(context, field))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(context.struct_type(&field_types, false))
+}
+
+// Whether a value of type `ty` holds a `name` inside it, directly or through other structs
+// and enums.
+fn contains_struct(
+    ty: &WaveType,
+    name: &str,
+    structs: &HashMap<String, StructNode>,
+    enums: &HashMap<String, EnumNode>,
+    seen: &mut Vec<String>,
+) -> bool {
+    match ty {
+        WaveType::Struct(inner) if inner == name => true,
+        WaveType::Struct(inner) if !seen.contains(inner) => {
+            seen.push(inner.clone());
+            let struct_fields = structs.get(inner).into_iter().flat_map(|declaration| declaration.fields.iter().map(|field| &field.field_type));
+            let variant_fields = enums.get(inner).into_iter().flat_map(|declaration| declaration.variants.iter().flat_map(|variant| &variant.fields));
+            struct_fields.chain(variant_fields).any(|field| contains_struct(field, name, structs, enums, seen))
+        }
+        WaveType::Array(element, _) => contains_struct(element, name, structs, enums, seen),
+        _ => false,
+    }
+}
+
+// Points `error` at `name` on its source line and attaches `similar` as a replacing fix,
+// or as plain help when the name cannot be located.
+fn suggest_similar(mut error: WaveError, name: &str, similar: Option<(String, &str)>) -> WaveError {
+    error = error.with_label(tr!("not found in this scope"));
+
+    let column = error.source.as_deref().and_then(|line| find_identifier(line, name, error.column));
+    if let Some(column) = column {
+        error.column = column;
+    }
+
+    match (similar, column) {
+        (Some((message, similar)), Some(column)) => {
+            let suggestion = Suggestion::new(message, error.line, column, name.chars().count(), similar);
+            error.with_suggestion(suggestion)
+        }
+        (Some((message, _)), None) => error.with_help(message),
+        (None, _) => error,
+    }
+}
+
+/// Lowers the integer literal `value` at `int_ty`, failing when it does not fit. `signed`
+/// is the signedness of the declared type, when known; otherwise either range is accepted.
+pub fn int_literal<'ctx>(value: i64, int_ty: IntType<'ctx>, signed: Option<bool>) -> Result<IntValue<'ctx>, CodegenError> {
+    let bits = int_ty.get_bit_width();
+    if bits < 64 {
+        let (min, max) = match signed {
+            Some(true) => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+            Some(false) => (0, (1i128 << bits) - 1),
+            None => (-(1i128 << (bits - 1)), (1i128 << bits) - 1),
+        };
+        if !(min..=max).contains(&(value as i128)) {
+            let type_name = match signed {
+                Some(true) => format!("i{}", bits),
+                Some(false) => format!("u{}", bits),
+                None => tr!("a {0}-bit integer", bits),
+            };
+            let error = diagnostic_here(
+                WaveErrorKind::SemanticError("literal out of range".to_string()),
+                tr!("literal `{0}` does not fit in {1}", value, type_name),
+            )
+            .with_label(tr!("out of range"))
+            .with_note(tr!("the range of {0} is {1}..={2}", type_name, min, max));
+            return Err(CodegenError::Invalid(Box::new(error)));
+        }
+    }
+    Ok(int_ty.const_int(value as u64, false))
+}
+
+/// Lowers the float literal `value` at `float_ty`, failing when it overflows an `f32`.
+pub fn float_literal<'ctx>(value: f64, float_ty: FloatType<'ctx>) -> Result<FloatValue<'ctx>, CodegenError> {
+    if float_ty == float_ty.get_context().f32_type() && value.is_finite() && value.abs() > f32::MAX as f64 {
+        let error = diagnostic_here(
+            WaveErrorKind::SemanticError("literal out of range".to_string()),
+            tr!("literal `{0}` does not fit in {1}", value, "f32"),
+        )
+        .with_label(tr!("out of range"))
+        .with_help(tr!("declare the value as `f64`"));
+        return Err(CodegenError::Invalid(Box::new(error)));
+    }
+    Ok(float_ty.const_float(value))
+}
+
+/// Signedness of `ty` for literal range checks; `None` for non-integer types.
+pub fn literal_signedness(ty: &WaveType) -> Option<bool> {
+    match ty {
+        WaveType::Int(_) => Some(true),
+        WaveType::Uint(_) | WaveType::Byte | WaveType::Char | WaveType::Bool => Some(false),
+        _ => None,
+    }
+}
+
+/// How a lowered type is spelled in diagnostics, in Wave syntax where there is one.
+pub fn llvm_type_name(ty: BasicTypeEnum) -> String {
+    match ty {
+        BasicTypeEnum::IntType(int_ty) if int_ty.get_bit_width() == 1 => "bool".to_string(),
+        BasicTypeEnum::IntType(int_ty) => format!("i{}", int_ty.get_bit_width()),
+        BasicTypeEnum::FloatType(float_ty) => format!("f{}", if mantissa_bits(float_ty) == 24 { 32 } else { 64 }),
+        other if is_str_type(other) => "str".to_string(),
+        other => other.print_to_string().to_string(),
+    }
+}
+
+fn mantissa_bits(float_ty: FloatType) -> u32 {
+    if float_ty == float_ty.get_context().f32_type() { 24 } else { 53 }
+}
+
+/// `name`, declared with plain `let` at `declared_at`, is assigned to. Offers `let mut`
+/// as a fix when the declaration can be located.
+pub fn immutable_assignment(name: &str, declared_at: Span) -> CodegenError {
+    let mut error = diagnostic_here(
+        WaveErrorKind::ImmutableAssignment(name.to_string()),
+        tr!("cannot assign to immutable variable `{0}`", name),
+    )
+    .with_label(tr!("cannot assign"));
+
+    let file = error.file.clone();
+    let declaration = std::fs::read_to_string(&file)
+        .ok()
+        .and_then(|code| code.lines().nth(declared_at.line.wrapping_sub(1)).map(str::to_string));
+    let declared_with_let = declaration.as_deref().is_some_and(|line| {
+        line.chars().skip(declared_at.column.saturating_sub(1)).collect::<String>().starts_with("let ")
+    });
+    let counted_by_for = declared_at != Span::default()
+        && declaration.as_deref().is_some_and(|line| line.contains(&format!("for {} in ", name)));
+
+    error = if declared_at != Span::default() && declared_with_let {
+        error
+            .with_note(tr!("`{0}` is declared at {1}", name, format!("{}:{}:{}", file, declared_at.line, declared_at.column)))
+            .with_suggestion(Suggestion::new(
+                tr!("make `{0}` mutable", name),
+                declared_at.line,
+                declared_at.column,
+                3,
+                "let mut",
+            ))
+    } else if counted_by_for {
+        error.with_help(tr!("the `for` loop counts with `{0}`; copy it into a `var` to change it", name))
+    } else {
+        error.with_help(tr!("parameters cannot be assigned to; copy `{0}` into a `var` first", name))
+    };
+
+    CodegenError::Invalid(Box::new(error))
+}
+
+// A diagnostic at the statement being lowered, quoting its source line.
+fn diagnostic_here(kind: WaveErrorKind, message: String) -> WaveError {
+    let span = CURRENT_SPAN.with(|span| span.get());
+    let file = SOURCE_FILE.with(|file| file.borrow().clone());
+
+    let mut error = WaveError::new(
+        kind,
+        message,
+        file.as_ref().map(|f| f.display().to_string()).unwrap_or_else(|| "<unknown>".to_string()),
+        span.line,
+        span.column,
+    );
+
+    let source_line = file
+        .and_then(|f| std::fs::read_to_string(f).ok())
+        .and_then(|code| code.lines().nth(span.line.wrapping_sub(1)).map(str::to_string));
+    if let Some(line) = source_line {
+        error = error.with_source(line);
+    }
+
+    error
+}
+
+// 1-based column of the first whole-word `name` in `line` at or after `from_column`.
+fn find_identifier(line: &str, name: &str, from_column: usize) -> Option<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    let needle: Vec<char> = name.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+
+    (from_column.saturating_sub(1)..chars.len().saturating_sub(needle.len() - 1)).find(|&start| {
+        chars[start..start + needle.len()] == needle[..]
+            && (start == 0 || !is_word(&chars[start - 1]))
+            && chars.get(start + needle.len()).is_none_or(|c| !is_word(c))
+    })
+    .map(|start| start + 1)
+}
+
+// Declares the program's structs and enums in `context`, with the bodies they have on the target
+// `layout` describes, and records their declarations for the lowering to look fields up in.
+fn declare_types(context: &Context, ast_nodes: &[ASTNode], layout: &TargetData) -> Result<(), CodegenError> {
+    POINTER_BITS.with(|bits| bits.set(layout.get_pointer_byte_size(None) * 8));
+
+    // Struct and enum types come first: signatures and bodies name them, and a field may point
+    // to any of them, its own included, so every name exists before any body is set.
+    let structs: HashMap<String, StructNode> = ast_nodes
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::Struct(declaration) => Some((declaration.name.clone(), declaration.clone())),
+            _ => None,
+        })
+        .collect();
+    let enums: HashMap<String, EnumNode> = ast_nodes
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::Enum(declaration) => Some((declaration.name.clone(), declaration.clone())),
+            _ => None,
+        })
+        .collect();
+    for name in structs.keys().chain(enums.keys()) {
+        context.opaque_struct_type(name);
+    }
+    for ast in ast_nodes {
+        if let ASTNode::Struct(declaration) = ast {
+            let _node = enter_node(tr!("struct `{0}`", declaration.name), declaration.span);
+            let field_types = declaration.fields.iter()
+                .map(|field| {
+                    if contains_struct(&field.field_type, &declaration.name, &structs, &enums, &mut vec![]) {
+                        return Err(unsupported_type(
+                            &field.field_type,
+                            tr!("a struct cannot contain itself; use `ptr<{0}>` for field `{1}`", declaration.name, field.name),
+                        ));
+                    }
+                    wave_type_to_llvm_type(context, &field.field_type)
+                })
+                .collect::<Result<Vec<_>, _>>()
+                ?;
+            context.get_struct_type(&declaration.name).unwrap().set_body(&field_types, false);
+        }
+    }
+    // An enum is its variant's tag followed by room for the largest payload; the fields are
+    // read through a pointer cast to the variant's own layout. A payload can only be measured
+    // once the enums it holds have bodies, so the enums are laid out as theirs become known.
+    let mut pending: Vec<&EnumNode> = ast_nodes
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::Enum(declaration) => Some(declaration),
+            _ => None,
+        })
+        .collect();
+    while !pending.is_empty() {
+        let mut laid_out = false;
+        for declaration in std::mem::take(&mut pending) {
+            let _node = enter_node(tr!("enum `{0}`", declaration.name), declaration.span);
+            let mut payloads = vec![];
+            for variant in &declaration.variants {
+                if let Some(field) = variant.fields.iter().find(|field| contains_struct(field, &declaration.name, &structs, &enums, &mut vec![])) {
+                    return Err(unsupported_type(
+                        field,
+                        tr!("an enum cannot contain itself; use `ptr<{0}>` in variant `{1}`", declaration.name, variant.name),
+                    ));
+                }
+                payloads.push(variant_payload_type(context, variant)?);
+            }
+            if !payloads.iter().all(|payload| payload.is_sized()) {
+                pending.push(declaration);
+                continue;
+            }
+            let payload_words = payloads.iter().map(|payload| layout.get_abi_size(payload).div_ceil(8)).max().unwrap_or(0);
+            let body = [context.i32_type().into(), context.i64_type().array_type(payload_words as u32).into()];
+            context.get_struct_type(&declaration.name).unwrap().set_body(&body, false);
+            laid_out = true;
+        }
+        // Without self-containment every round lays out at least the innermost enum.
+        assert!(laid_out || pending.is_empty(), "enum payloads contain each other");
+    }
+    STRUCTS.with(|registry| *registry.borrow_mut() = structs);
+    ENUMS.with(|registry| *registry.borrow_mut() = enums);
+    Ok(())
+}
+
+/// Where the fields of a struct lie on the target: its size and alignment, and the offset and
+/// size of each field, in bytes.
+pub struct StructLayout {
+    pub name: String,
+    pub size: u64,
+    pub align: u32,
+    pub fields: Vec<FieldLayout>,
+}
+
+pub struct FieldLayout {
+    pub name: String,
+    pub ty: WaveType,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// The layout of every struct of `ast_nodes` on `target`, in source order. The lowering lays
+/// fields out as C does: in order, each at the first offset its alignment allows.
+pub fn struct_layouts(ast_nodes: &[ASTNode], target: &TargetOptions) -> Result<Vec<StructLayout>, CodegenError> {
+    let machine = target_machine(target, inkwell::OptimizationLevel::None).map_err(|message| invalid("target", message))?;
+    let layout = machine.get_target_data();
+    let context = Context::create();
+    declare_types(&context, ast_nodes, &layout)?;
+
+    let layouts = ast_nodes
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::Struct(declaration) => Some(declaration),
+            _ => None,
+        })
+        .map(|declaration| {
+            let struct_type = context.get_struct_type(&declaration.name).unwrap();
+            let fields = declaration
+                .fields
+                .iter()
+                .zip(struct_type.get_field_types())
+                .enumerate()
+                .map(|(index, (field, field_type))| FieldLayout {
+                    name: field.name.clone(),
+                    ty: field.field_type.clone(),
+                    offset: layout.offset_of_element(&struct_type, index as u32).unwrap(),
+                    size: layout.get_abi_size(&field_type),
+                })
+                .collect();
+            StructLayout {
+                name: declaration.name.clone(),
+                size: layout.get_abi_size(&struct_type),
+                align: layout.get_abi_alignment(&struct_type),
+                fields,
+            }
+        })
+        .collect();
+    Ok(layouts)
+}
+
+/// Lowers `ast_nodes` to the textual IR of one LLVM module. A function that fails to lower does
+/// not stop the others, so the errors are those of every function, in source order; an error in
+/// a struct, an enum or a signature stops at once.
+pub unsafe fn generate_ir(ast_nodes: &[ASTNode], options: &CodegenOptions) -> Result<String, Vec<CodegenError>> {
     let context = Context::create();
 
     let ir = {
-        let module = Box::leak(Box::new(context.create_module("main")));
+        // The whole program, imports included, is one module named after its main file.
+        let module_name = options.source_file.as_deref().and_then(Path::file_stem).map(|stem| stem.to_string_lossy().into_owned());
+        let module = Box::leak(Box::new(context.create_module(module_name.as_deref().unwrap_or("main"))));
         let builder = Box::leak(Box::new(context.create_builder()));
-        let mut functions: HashMap<String, FunctionValue> = HashMap::new();
+        // Ordered by source position: declarations, bodies and the printed module all follow it.
+        let mut functions: IndexMap<String, (FunctionValue, &FunctionNode)> = IndexMap::new();
+        SOURCE_FILE.with(|file| *file.borrow_mut() = options.source_file.clone());
+
+        // Sizes and alignments, the enum layouts below among them, are the target's.
+        let machine = target_machine(&options.target, inkwell::OptimizationLevel::None)
+            .map_err(|message| vec![invalid("target", message)])?;
+        let layout = machine.get_target_data();
+        module.set_triple(&machine.get_triple());
+        module.set_data_layout(&layout.get_data_layout());
+        declare_types(&context, ast_nodes, &layout).map_err(|error| vec![error])?;
 
+        let mut registry: HashMap<String, Overloads> = HashMap::new();
         for ast in ast_nodes {
-            if let ASTNode::Function(FunctionNode { name, parameters, return_type, .. }) = ast {
-                let param_types: Vec<BasicMetadataTypeEnum> = parameters.iter()
-                    .map(|p| wave_type_to_llvm_type(&context, &p.param_type).into())
-                    .collect();
-
-                let fn_type = match return_type {
-                    Some(wave_ret_ty) => {
-                        let llvm_ret_type = wave_type_to_llvm_type(&context, wave_ret_ty);
-                        match llvm_ret_type {
-                            BasicTypeEnum::IntType(int_ty) => int_ty.fn_type(&param_types, false),
-                            BasicTypeEnum::FloatType(float_ty) => float_ty.fn_type(&param_types, false),
-                            BasicTypeEnum::PointerType(ptr_ty) => ptr_ty.fn_type(&param_types, false),
-                            _ => panic!("Unsupported return type"),
-                        }
-                    }
-                    None => context.void_type().fn_type(&param_types, false),
-                };
+            let (ASTNode::Function(function_node @ FunctionNode { name, parameters, return_type, .. })
+            | ASTNode::Extern(function_node @ FunctionNode { name, parameters, return_type, .. })) = ast
+            else {
+                continue;
+            };
+            let _node = enter_node(tr!("signature of `{0}`", name), function_node.span);
+            let param_types: Vec<WaveType> = parameters.iter().map(|p| p.param_type.clone()).collect();
+            // The C runtime takes what `main` returns as the exit status, so one declared without
+            // a return type returns 0.
+            let return_type = match (ast, return_type) {
+                (ASTNode::Function(_), None) if name == "main" => Some(&WaveType::Int(32)),
+                _ => return_type.as_ref(),
+            };
+            let fn_type = function_llvm_type(&context, &param_types, return_type, function_node.variadic)
+                .map_err(|error| vec![error])?;
 
-                let function = module.add_function(name, fn_type, None);
-                functions.insert(name.clone(), function);
+            // An extern is only declared, under its own name; the runtime's calls into libc
+            // find the declaration too.
+            if matches!(ast, ASTNode::Extern(_)) {
+                registry.entry(name.clone()).or_default().push((function_node.function_type(), name.clone()));
+                module.add_function(name, fn_type, None);
+                continue;
             }
+            let symbol = symbol_name(function_node, ast_nodes);
+            registry.entry(name.clone()).or_default().push((function_node.function_type(), symbol.clone()));
+            let function = module.add_function(&symbol, fn_type, None);
+            functions.insert(symbol, (function, function_node));
         }
+        FUNCTIONS.with(|functions| *functions.borrow_mut() = registry);
 
-        for ast in ast_nodes {
-            if let ASTNode::Function(FunctionNode { name, parameters, return_type, body }) = ast {
-                let function = *functions.get(name).unwrap();
-
-                let entry_block = context.append_basic_block(function, "entry");
-                builder.position_at_end(entry_block);
-
-                let mut variables: HashMap<String, VariableInfo> = HashMap::new();
-                let mut string_counter = 0;
-                let mut loop_exit_stack = vec![];
-                let mut loop_continue_stack = vec![];
-
-                for (i, param) in parameters.iter().enumerate() {
-                    let llvm_type = wave_type_to_llvm_type(&context, &param.param_type);
-                    let alloca = builder.build_alloca(llvm_type, &param.name).unwrap();
-
-                    let init_value = if let Some(initial) = &param.initial_value {
-                        match (initial, llvm_type) {
-                            (Value::Int(v), BasicTypeEnum::IntType(int_ty)) => {
-                                Some(int_ty.const_int(*v as u64, false).as_basic_value_enum())
-                            }
-                            (Value::Float(f), BasicTypeEnum::FloatType(float_ty)) => {
-                                Some(float_ty.const_float(*f).as_basic_value_enum())
-                            }
-                            (Value::Text(s), BasicTypeEnum::PointerType(ptr_ty)) => unsafe {
-                                let mut bytes = s.as_bytes().to_vec();
-                                bytes.push(0);
-                                let const_str = context.const_string(&bytes, false);
-                                let global = module.add_global(
-                                    context.i8_type().array_type(bytes.len() as u32),
-                                    None,
-                                    &format!("param_str_{}", param.name),
-                                );
-                                global.set_initializer(&const_str);
-                                global.set_constant(true);
-                                let zero = context.i32_type().const_zero();
-                                let gep = builder.build_gep(global.as_pointer_value(), &[zero, zero], "gep").unwrap();
-                                Some(gep.as_basic_value_enum())
-                            }
-                            _ => None,
-                        }
-                    } else {
-                        Some(function.get_nth_param(i as u32).unwrap())
-                    };
+        // `-g`: one compile unit for the program and a subprogram per function; each statement
+        // then points its instructions at its line under that subprogram.
+        let debug_info = options.debug_info.then(|| create_debug_info(&context, module, options));
+        if options.debug_info && !targets_wasm(module) {
+            enable_backtraces(&context, module);
+        }
+
+        let mut errors = vec![];
+        for (symbol, &(function, function_node)) in &functions {
+            let name = &function_node.name;
+            CURRENT_FUNCTION.with(|f| *f.borrow_mut() = Some(name.clone()));
+            CURRENT_SPAN.with(|span| span.set(function_node.span));
+            let source_file = options.function_files.get(name).or(options.source_file.as_ref());
+            SOURCE_FILE.with(|file| *file.borrow_mut() = source_file.cloned());
+            VALUE_NAMES.with(|names| names.borrow_mut().clear());
+            let _span = tracing::debug_span!("codegen_function", function = %name).entered();
 
-                    if let Some(init_val) = init_value {
-                        builder.build_store(alloca, init_val).unwrap();
+            if let Some((dibuilder, compile_unit)) = &debug_info {
+                let file = match options.function_files.get(name) {
+                    Some(path) => {
+                        let (file_name, directory) = debug_file_name(path, &options.remap_path_prefix);
+                        dibuilder.create_file(&file_name, &directory)
                     }
+                    None => compile_unit.get_file(),
+                };
+                let line = function_node.span.line as u32;
+                let signature = dibuilder.create_subroutine_type(file, None, &[], DIFlags::ZERO);
+                let subprogram = dibuilder.create_function(
+                    compile_unit.as_debug_info_scope(), name, (symbol != name).then_some(symbol.as_str()), file, line, signature,
+                    false, true, line, DIFlags::ZERO, options.opt_level > 0,
+                );
+                function.set_subprogram(subprogram);
+            }
 
-                    variables.insert(
-                        param.name.clone(),
-                        VariableInfo {
-                            ptr: alloca,
-                            mutability: Mutability::Let,
-                        },
-                    );
-                }
+            let entry_block = context.append_basic_block(function, "entry");
+            builder.position_at_end(entry_block);
+            set_debug_location(&context, builder, function, function_node.span);
+            generate_frame_push(&context, builder, module, name, function_node.span.line);
+            if name == "main" {
+                generate_stdout_buffering(&context, builder, module);
+            }
 
-                let is_void_fn = return_type.is_none();
-                let did_return = false;
-
-                for stmt in body {
-                    match stmt {
-                        ASTNode::Variable(_) | ASTNode::Statement(_) => {
-                            generate_statement_ir(
-                                &context,
-                                &builder,
-                                &module,
-                                &mut string_counter,
-                                stmt,
-                                &mut variables,
-                                &mut loop_exit_stack,
-                                &mut loop_continue_stack,
-                                function,
-                            );
-                        }
-                        _ => panic!("Unsupported ASTNode in function body"),
-                    }
-                }
+            if let Err(error) = generate_function_body(&context, builder, module, function, function_node, options) {
+                errors.push(error);
+            }
+        }
 
-                if !did_return && is_void_fn {
-                    let _ = builder.build_return(None);
-                }
+        CURRENT_FUNCTION.with(|f| *f.borrow_mut() = None);
+        SOURCE_FILE.with(|file| *file.borrow_mut() = options.source_file.clone());
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        // The bench harness has no subprogram, so its instructions must not point into one.
+        builder.unset_current_debug_location();
+        if let Some((dibuilder, _)) = &debug_info {
+            dibuilder.finalize();
+        }
+
+        for (global, is_hook) in [("llvm.global_ctors", FunctionNode::is_constructor as fn(&FunctionNode) -> bool), ("llvm.global_dtors", FunctionNode::is_destructor)] {
+            let hooks: Vec<FunctionValue> = functions.values()
+                .filter(|(_, function_node)| is_hook(function_node))
+                .map(|&(function, _)| function)
+                .collect();
+            if !hooks.is_empty() {
+                generate_global_hooks(&context, module, global, &hooks);
             }
         }
 
+        if options.bench {
+            let benches: Vec<FunctionValue> = functions.values()
+                .filter(|(_, function_node)| function_node.is_bench())
+                .map(|&(function, _)| function)
+                .collect();
+            generate_bench_main(&context, builder, module, &benches);
+        }
+
+        // The runtime's helpers and the bench harness are Wave frames too.
+        let unwind_attribute = match options.panic {
+            PanicStrategy::Abort => "nounwind",
+            PanicStrategy::Unwind => "uwtable",
+        };
+        let unwind_attribute = context.create_enum_attribute(Attribute::get_named_enum_kind_id(unwind_attribute), 0);
+        for function in module.get_functions().filter(|function| function.get_first_basic_block().is_some()) {
+            function.add_attribute(AttributeLoc::Function, unwind_attribute);
+        }
+
         module.print_to_string().to_string()
     };
-    ir
-}
-
-pub fn wave_format_to_c(format: &str, arg_types: &[BasicTypeEnum]) -> String {
-    let mut result = String::new();
-    let mut chars = format.chars().peekable();
-    let mut arg_index = 0;
-
-    while let Some(c) = chars.next() {
-        if c == '{' {
-            if let Some('}') = chars.peek() {
-                chars.next(); // consume '}'
-
-                if let Some(arg_type) = arg_types.get(arg_index) {
-                    let fmt = match arg_type {
-                        BasicTypeEnum::FloatType(_) => "%f",
-                        BasicTypeEnum::IntType(_) => "%d",
-                        BasicTypeEnum::PointerType(ptr_ty) => {
-                            if ptr_ty.get_element_type().is_int_type() && ptr_ty.get_element_type().into_int_type().get_bit_width() == 8 {
-                                "%s"
-                            } else {
-                                "%ld"
-                            }
-                        },
-                        _ => "%d", // fallback
-                    };
-                    result.push_str(fmt);
-                    arg_index += 1;
-                    continue;
+    Ok(ir)
+}
+
+// The parameters and statements of `function`, whose entry block the builder is at.
+unsafe fn generate_function_body<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    function: FunctionValue<'ctx>,
+    function_node: &FunctionNode,
+    options: &CodegenOptions,
+) -> Result<(), CodegenError> {
+    let FunctionNode { parameters, return_type, body, .. } = function_node;
+    let mut variables: HashMap<String, VariableInfo> = HashMap::new();
+    let mut string_counter = 0;
+    let mut loop_exit_stack = vec![];
+    let mut loop_continue_stack = vec![];
+
+    for (i, param) in parameters.iter().enumerate() {
+        let llvm_type = wave_type_to_llvm_type(context, &param.param_type)?;
+        let alloca = builder.build_alloca(llvm_type, &value_name(&param.name)).unwrap();
+
+        let init_value = if let Some(initial) = &param.initial_value {
+            match (initial, llvm_type) {
+                (Value::Int(v), BasicTypeEnum::IntType(int_ty)) => {
+                    Some(int_ty.const_int(*v as u64, false).as_basic_value_enum())
+                }
+                (Value::Bool(b), BasicTypeEnum::IntType(int_ty)) => {
+                    Some(int_ty.const_int(*b as u64, false).as_basic_value_enum())
                 }
+                (Value::Float(f), BasicTypeEnum::FloatType(float_ty)) => {
+                    Some(float_ty.const_float(*f).as_basic_value_enum())
+                }
+                (Value::Text(s), ty) if is_str_type(ty) => {
+                    let name = format!("param_str_{}", param.name);
+                    Some(generate_str_literal(context, builder, module, s, &name).as_basic_value_enum())
+                }
+                _ => None,
             }
+        } else {
+            Some(function.get_nth_param(i as u32).unwrap())
+        };
+
+        if let Some(init_val) = init_value {
+            builder.build_store(alloca, init_val).unwrap();
         }
-        result.push(c);
+
+        variables.insert(
+            param.name.clone(),
+            VariableInfo {
+                ptr: alloca,
+                mutability: Mutability::Let,
+                ty: param.param_type.clone(),
+                span: Span::default(),
+            },
+        );
     }
 
-    result
+    for stmt in body {
+        match stmt {
+            ASTNode::Variable(_) | ASTNode::Statement(..) => {
+                generate_statement_ir(
+                    context,
+                    builder,
+                    module,
+                    &mut string_counter,
+                    stmt,
+                    &mut variables,
+                    &mut loop_exit_stack,
+                    &mut loop_continue_stack,
+                    function,
+                    options,
+                )?;
+            }
+            _ => return Err(unsupported_construct("an item nested in a function body", "move it to the top level of the file")),
+        }
+    }
+
+    // The semantic pass rejects functions with a return type whose end is reachable,
+    // so an open block here is the end of a void function or dead code.
+    if !block_terminated(builder) {
+        if return_type.is_none() {
+            generate_frame_pop(context, builder, module);
+            build_void_return(builder, function);
+        } else {
+            let _ = builder.build_unreachable();
+        }
+    }
+    Ok(())
+}
+
+/// Returns from `function`, declared without a return type: with nothing, or with exit status 0
+/// from a `main`, which returns an `i32` to the C runtime.
+pub fn build_void_return(builder: &inkwell::builder::Builder, function: FunctionValue) {
+    match function.get_type().get_return_type() {
+        Some(BasicTypeEnum::IntType(status)) => builder.build_return(Some(&status.const_zero())).unwrap(),
+        _ => builder.build_return(None).unwrap(),
+    };
+}
+
+// `global` (`llvm.global_ctors` or `llvm.global_dtors`) listing `functions`, which the startup or
+// exit code then calls in order. Every entry has the default priority and no associated data.
+fn generate_global_hooks<'ctx>(
+    context: &'ctx Context,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    global: &str,
+    functions: &[FunctionValue<'ctx>],
+) {
+    let priority = context.i32_type();
+    let function_ptr = context.void_type().fn_type(&[], false).ptr_type(AddressSpace::default());
+    let data_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let entry = context.struct_type(&[priority.into(), function_ptr.into(), data_ptr.into()], false);
+    let entries: Vec<_> = functions
+        .iter()
+        .map(|function| {
+            entry.const_named_struct(&[
+                priority.const_int(65535, false).into(),
+                function.as_global_value().as_pointer_value().into(),
+                data_ptr.const_null().into(),
+            ])
+        })
+        .collect();
+    let array = entry.const_array(&entries);
+    let hooks = module.add_global(array.get_type(), None, global);
+    hooks.set_linkage(Linkage::Appending);
+    hooks.set_initializer(&array);
+}
+
+// The name and directory DWARF records for the source `path`, its directory remapped.
+fn debug_file_name(path: &Path, remap_path_prefix: &[(PathBuf, PathBuf)]) -> (String, String) {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let remapped = remap_path_prefix.iter().rev().find_map(|(from, to)| {
+        let from = std::fs::canonicalize(from).unwrap_or_else(|_| from.clone());
+        let rest = directory.strip_prefix(&from).ok()?;
+        Some(if rest.as_os_str().is_empty() { to.clone() } else { to.join(rest) })
+    });
+    let directory = remapped.as_deref().unwrap_or(directory).display().to_string();
+    (file_name, directory)
 }
 
-pub fn wave_type_to_llvm_type<'ctx>(context: &'ctx Context, wave_type: &WaveType) -> BasicTypeEnum<'ctx> {
-    match wave_type {
+// The compile unit `-g` hangs every function's debug info on, for the main source file.
+fn create_debug_info<'ctx>(
+    context: &'ctx Context,
+    module: &inkwell::module::Module<'ctx>,
+    options: &CodegenOptions,
+) -> (DebugInfoBuilder<'ctx>, DICompileUnit<'ctx>) {
+    let debug_metadata_version = context.i32_type().const_int(3, false);
+    module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, debug_metadata_version);
+
+    let source = options.source_file.clone().unwrap_or_else(|| PathBuf::from("main.wave"));
+    let (file_name, directory) = debug_file_name(&source, &options.remap_path_prefix);
+    module.create_debug_info_builder(
+        true,
+        DWARFSourceLanguage::C,
+        &file_name,
+        &directory,
+        &options.producer,
+        options.opt_level > 0,
+        "",
+        0,
+        "",
+        DWARFEmissionKind::Full,
+        0,
+        false,
+        false,
+        "",
+        "",
+    )
+}
+
+pub fn wave_type_to_llvm_type<'ctx>(context: &'ctx Context, wave_type: &WaveType) -> Result<BasicTypeEnum<'ctx>, CodegenError> {
+    let llvm_type = match wave_type {
+        WaveType::Int(WaveType::POINTER_SIZED) | WaveType::Uint(WaveType::POINTER_SIZED) => {
+            context.custom_width_int_type(POINTER_BITS.with(Cell::get)).as_basic_type_enum()
+        }
         WaveType::Int(bits) => context.custom_width_int_type(*bits as u32).as_basic_type_enum(),
         WaveType::Uint(bits) => context.custom_width_int_type(*bits as u32).as_basic_type_enum(),
         WaveType::Float(bits) => match bits {
             32 => context.f32_type().as_basic_type_enum(),
             64 => context.f64_type().as_basic_type_enum(),
-            _ => panic!("Unsupported float bit width: {}", bits),
+            _ => return Err(unsupported_type(wave_type, tr!("only `f32` and `f64` are supported"))),
         },
         WaveType::Bool => context.bool_type().as_basic_type_enum(),
         WaveType::Char => context.i8_type().as_basic_type_enum(), // assuming 1-byte char
         WaveType::Byte => context.i8_type().as_basic_type_enum(),
-        WaveType::String => context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum(),
-        WaveType::Pointer(inner) => wave_type_to_llvm_type(context, inner).ptr_type(AddressSpace::default()).as_basic_type_enum(),
+        WaveType::String => str_type(context).as_basic_type_enum(),
+        WaveType::Pointer(inner) => wave_type_to_llvm_type(context, inner)?.ptr_type(AddressSpace::default()).as_basic_type_enum(),
         WaveType::Array(inner, size) => {
-            let inner_type = wave_type_to_llvm_type(context, inner);
+            let inner_type = wave_type_to_llvm_type(context, inner)?;
             inner_type.array_type(*size).as_basic_type_enum()
         }
+        WaveType::Struct(name) => context
+            .get_struct_type(name)
+            .ok_or_else(|| undefined_struct(name))?
+            .as_basic_type_enum(),
+        WaveType::Function(params, return_type) => function_llvm_type(context, params, return_type.as_deref(), false)?
+            .ptr_type(AddressSpace::default())
+            .as_basic_type_enum(),
+        WaveType::TypeOf(_) => panic!("typeof(..) must be resolved before lowering"),
+        WaveType::Inferred => panic!("an inferred `let` type must be resolved before lowering"),
+    };
+    Ok(llvm_type)
+}
+
+/// The LLVM type of a function taking `params`, and any arguments more when `variadic`, and
+/// returning `return_type`.
+pub fn function_llvm_type<'ctx>(
+    context: &'ctx Context,
+    params: &[WaveType],
+    return_type: Option<&WaveType>,
+    variadic: bool,
+) -> Result<FunctionType<'ctx>, CodegenError> {
+    let param_types = params
+        .iter()
+        .map(|param| wave_type_to_llvm_type(context, param).map(BasicMetadataTypeEnum::from))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fn_type = match return_type {
+        Some(wave_ret_ty) => {
+            let llvm_ret_type = wave_type_to_llvm_type(context, wave_ret_ty)?;
+            match llvm_ret_type {
+                BasicTypeEnum::IntType(int_ty) => int_ty.fn_type(&param_types, variadic),
+                BasicTypeEnum::FloatType(float_ty) => float_ty.fn_type(&param_types, variadic),
+                BasicTypeEnum::PointerType(ptr_ty) => ptr_ty.fn_type(&param_types, variadic),
+                // Returned by value as an LLVM aggregate; `extern` functions cannot return one.
+                BasicTypeEnum::StructType(struct_ty) => struct_ty.fn_type(&param_types, variadic),
+                _ => return Err(unsupported_type(
+                    wave_ret_ty,
+                    tr!("functions cannot return `{0}` by value; return a pointer to it instead", wave_ret_ty),
+                )),
+            }
+        }
+        None => context.void_type().fn_type(&param_types, variadic),
+    };
+    Ok(fn_type)
+}
+
+pub fn resolve_wave_type<'ctx>(
+    wave_type: &WaveType,
+    variables: &HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) -> Result<WaveType, CodegenError> {
+    let resolved = match wave_type {
+        WaveType::TypeOf(expr) => infer_wave_type(expr, variables, module)?,
+        WaveType::Pointer(inner) => WaveType::Pointer(Box::new(resolve_wave_type(inner, variables, module)?)),
+        WaveType::Array(inner, size) => WaveType::Array(Box::new(resolve_wave_type(inner, variables, module)?), *size),
+        WaveType::Function(params, return_type) => WaveType::Function(
+            params.iter().map(|param| resolve_wave_type(param, variables, module)).collect::<Result<_, _>>()?,
+            match return_type {
+                Some(return_type) => Some(Box::new(resolve_wave_type(return_type, variables, module)?)),
+                None => None,
+            },
+        ),
+        other => other.clone(),
+    };
+    Ok(resolved)
+}
+
+/// The type of an unannotated `let` initialized with `expr`. An unsuffixed literal defaults
+/// to i32 or f64, unless it is combined with an operand whose type is known.
+pub fn infer_binding_type<'ctx>(
+    expr: &Expression,
+    variables: &HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) -> Result<WaveType, CodegenError> {
+    let inferred = match expr {
+        Expression::Literal(Literal::Number(_)) => WaveType::Int(32),
+        Expression::Literal(Literal::Float(_)) => WaveType::Float(64),
+        Expression::Grouped(inner) => infer_binding_type(inner, variables, module)?,
+        Expression::While { body, label, .. } => loop_type("while", body, label, |value| infer_binding_type(value, variables, module))?,
+        Expression::Loop { body, label } => loop_type("loop", body, label, |value| infer_binding_type(value, variables, module))?,
+        Expression::ArrayLiteral(elements) if !elements.is_empty() => {
+            WaveType::Array(Box::new(infer_binding_type(&elements[0], variables, module)?), elements.len() as u32)
+        }
+        Expression::BinaryExpression {
+            left,
+            operator: Operator::Add | Operator::Subtract | Operator::Multiply | Operator::Divide | Operator::Remainder,
+            right,
+        } => {
+            let left_type = infer_binding_type(left, variables, module)?;
+            let right_type = infer_binding_type(right, variables, module)?;
+            // Mixed int/float arithmetic is lowered in floating point.
+            match (&left_type, &right_type) {
+                (WaveType::Float(_), WaveType::Float(_)) | (WaveType::Int(_) | WaveType::Uint(_), WaveType::Int(_) | WaveType::Uint(_))
+                    if is_unsuffixed_literal(left) => right_type,
+                (WaveType::Int(_) | WaveType::Uint(_), WaveType::Float(_)) => right_type,
+                _ => left_type,
+            }
+        }
+        _ => infer_wave_type(expr, variables, module)?,
+    };
+    Ok(inferred)
+}
+
+pub fn is_unsuffixed_literal(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::Number(_) | Literal::Float(_)) => true,
+        Expression::Grouped(inner) => is_unsuffixed_literal(inner),
+        Expression::BinaryExpression { left, right, .. } => is_unsuffixed_literal(left) && is_unsuffixed_literal(right),
+        _ => false,
+    }
+}
+
+pub fn infer_wave_type<'ctx>(
+    expr: &Expression,
+    variables: &HashMap<String, VariableInfo<'ctx>>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) -> Result<WaveType, CodegenError> {
+    let inferred = match expr {
+        Expression::Literal(Literal::Number(_)) => WaveType::Int(32),
+        Expression::Literal(Literal::Float(_)) => WaveType::Float(32),
+        Expression::Literal(Literal::String(_)) => WaveType::String,
+        Expression::Literal(Literal::Bool(_)) => WaveType::Bool,
+        Expression::Literal(Literal::Char(_)) => WaveType::Char,
+        Expression::Variable(name) => match variables.get(name) {
+            Some(info) => info.ty.clone(),
+            None => match function_address(name, None, module)? {
+                Some((_, function_type)) => function_type,
+                None => return Err(undefined_variable(name, variables.keys())),
+            },
+        },
+        Expression::Grouped(inner) => infer_wave_type(inner, variables, module)?,
+        Expression::AddressOf(inner) => WaveType::Pointer(Box::new(infer_wave_type(inner, variables, module)?)),
+        Expression::Deref(inner) => match infer_wave_type(inner, variables, module)? {
+            WaveType::Pointer(pointee) => *pointee,
+            other => return Err(invalid("deref of non-pointer", tr!("cannot `deref` a value of type `{0}`", other))),
+        },
+        Expression::IndexAccess { target, .. } => match infer_wave_type(target, variables, module)? {
+            WaveType::Array(element, _) => *element,
+            WaveType::Pointer(pointee) => match *pointee {
+                WaveType::Array(element, _) => *element,
+                other => other,
+            },
+            other => return Err(invalid("index of non-array", tr!("cannot index into a value of type `{0}`", other))),
+        },
+        Expression::ArrayLiteral(elements) => {
+            let element = match elements.first() {
+                Some(first) => infer_wave_type(first, variables, module)?,
+                None => return Err(invalid("empty array literal", tr!("cannot infer the type of an empty array literal"))),
+            };
+            WaveType::Array(Box::new(element), elements.len() as u32)
+        }
+        Expression::BinaryExpression { left, operator, right } => match operator {
+            Operator::Greater | Operator::Less | Operator::GreaterEqual | Operator::LessEqual
+            | Operator::Equal | Operator::NotEqual | Operator::LogicalAnd | Operator::LogicalOr => WaveType::Bool,
+            _ if is_unsuffixed_literal(left) => infer_wave_type(right, variables, module)?,
+            _ if is_unsuffixed_literal(right) => infer_wave_type(left, variables, module)?,
+            _ => {
+                let left = infer_wave_type(left, variables, module)?;
+                match infer_wave_type(right, variables, module) {
+                    Ok(right) => widen(&left, &right).unwrap_or(left),
+                    Err(_) => left,
+                }
+            }
+        },
+        Expression::AssignOperation { target, .. } | Expression::Assignment { target, .. } => {
+            infer_wave_type(target, variables, module)?
+        }
+        Expression::FunctionCall { name, .. } if variables.get(name).is_some_and(|info| matches!(info.ty, WaveType::Function(..))) => {
+            match &variables[name].ty {
+                WaveType::Function(_, Some(return_type)) => (**return_type).clone(),
+                _ => {
+                    let error = diagnostic_here(
+                        WaveErrorKind::SemanticError("uninferable call".to_string()),
+                        tr!("cannot infer the type of a call to `{0}`", name),
+                    )
+                    .with_help(tr!("write the type of the binding explicitly"));
+                    return Err(CodegenError::Invalid(Box::new(error)));
+                }
+            }
+        }
+        Expression::FunctionCall { name, args } => {
+            let return_type = call_target(name, args, variables, module)?.map(|function| function.get_type().get_return_type());
+            match return_type {
+                Some(Some(BasicTypeEnum::IntType(int_ty))) if int_ty.get_bit_width() == 1 => WaveType::Bool,
+                Some(Some(BasicTypeEnum::IntType(int_ty))) => WaveType::Int(int_ty.get_bit_width() as u16),
+                Some(Some(BasicTypeEnum::FloatType(float_ty))) if float_ty == module.get_context().f64_type() => WaveType::Float(64),
+                Some(Some(BasicTypeEnum::FloatType(_))) => WaveType::Float(32),
+                Some(Some(ty)) if is_str_type(ty) => WaveType::String,
+                _ => {
+                    let error = diagnostic_here(
+                        WaveErrorKind::SemanticError("uninferable call".to_string()),
+                        tr!("cannot infer the type of a call to `{0}`", name),
+                    )
+                    .with_help(tr!("write the type of the binding explicitly"));
+                    return Err(CodegenError::Invalid(Box::new(error)));
+                }
+            }
+        }
+        Expression::AsmBlock { .. } => WaveType::Int(64),
+        Expression::While { body, label, .. } => loop_type("while", body, label, |value| infer_wave_type(value, variables, module))?,
+        Expression::Loop { body, label } => loop_type("loop", body, label, |value| infer_wave_type(value, variables, module))?,
+        Expression::StructLiteral { name, .. } => WaveType::Struct(name.clone()),
+        Expression::EnumVariant { enum_name, .. } => WaveType::Struct(enum_name.clone()),
+        Expression::Alloc { ty, .. } => WaveType::Pointer(Box::new(resolve_wave_type(ty, variables, module)?)),
+        Expression::Cast { ty, .. } => resolve_wave_type(ty, variables, module)?,
+        Expression::FieldAccess { target, field } => match infer_wave_type(target, variables, module)? {
+            WaveType::Struct(name) => struct_field(&name, field)?.1,
+            WaveType::Pointer(pointee) if matches!(*pointee, WaveType::Struct(_)) => {
+                let WaveType::Struct(name) = *pointee else { unreachable!() };
+                struct_field(&name, field)?.1
+            }
+            other => return Err(invalid("field of non-struct", tr!("no field `{0}` on type `{1}`", field, other))),
+        },
+    };
+    Ok(inferred)
+}
+
+// The type of a `keyword` loop used as a value, from the first of its `break` values that
+// `infer` can type before the loop runs: one naming a variable declared in the loop cannot be.
+fn loop_type(
+    keyword: &str,
+    body: &[ASTNode],
+    label: &Option<String>,
+    infer: impl Fn(&Expression) -> Result<WaveType, CodegenError>,
+) -> Result<WaveType, CodegenError> {
+    let mut values = vec![];
+    collect_break_values(body, label.as_deref(), false, &mut values);
+    if values.is_empty() {
+        return Err(invalid("loop without a value", tr!("this `{0}` is used as a value but no `break` gives it one", keyword)));
+    }
+    values.into_iter().find_map(|value| infer(value).ok()).ok_or_else(|| {
+        let error = diagnostic_here(
+            WaveErrorKind::SemanticError("uninferable loop".to_string()),
+            tr!("cannot infer the type of this `{0}` from its `break` values", keyword),
+        )
+        .with_help(tr!("write the type of the binding explicitly"));
+        CodegenError::Invalid(Box::new(error))
+    })
+}
+
+// The values of the `break`s in `nodes` that leave the loop labeled `label`; within a `nested`
+// loop only those naming the label do.
+fn collect_break_values<'a>(nodes: &'a [ASTNode], label: Option<&str>, nested: bool, values: &mut Vec<&'a Expression>) {
+    for node in nodes {
+        match node {
+            ASTNode::Statement(StatementNode::Break { label: target, value: Some(value) }, _) => {
+                let leaves = match target {
+                    Some(target) => label == Some(target.as_str()),
+                    None => !nested,
+                };
+                if leaves {
+                    values.push(value);
+                }
+            }
+            ASTNode::Statement(StatementNode::If { body, else_if_blocks, else_block, .. }, _) => {
+                collect_break_values(body, label, nested, values);
+                if let Some(blocks) = else_if_blocks {
+                    collect_break_values(blocks, label, nested, values);
+                }
+                if let Some(block) = else_block {
+                    collect_break_values(block, label, nested, values);
+                }
+            }
+            ASTNode::Statement(StatementNode::Match { arms, .. }, _) => {
+                for arm in arms {
+                    collect_break_values(&arm.body, label, nested, values);
+                }
+            }
+            // A nested loop with the same label hides this one.
+            ASTNode::Statement(
+                StatementNode::While { body, label: inner, .. }
+                | StatementNode::For { body, label: inner, .. }
+                | StatementNode::ForRange { body, label: inner, .. }
+                | StatementNode::Loop { body, label: inner, .. },
+                _,
+            ) if label.is_some() && inner.as_deref() != label => {
+                collect_break_values(body, label, true, values);
+            }
+            _ => {}
+        }
     }
 }
 
@@ -193,63 +1470,529 @@ pub fn generate_address_ir<'ctx>(
     expr: &Expression,
     variables: &mut HashMap<String, VariableInfo<'ctx>>,
     module: &'ctx inkwell::module::Module<'ctx>,
-) -> PointerValue<'ctx> {
+    options: &CodegenOptions,
+) -> Result<PointerValue<'ctx>, CodegenError> {
     match expr {
         Expression::Variable(name) => {
             let var_info = variables.get(name)
-                .unwrap_or_else(|| panic!("Variable {} not found", name));
+                .ok_or_else(|| undefined_variable(name, variables.keys()))?;
 
-            var_info.ptr
+            Ok(var_info.ptr)
         }
 
         Expression::Deref(inner_expr) => {
             match &**inner_expr {
                 Expression::Variable(var_name) => {
                     let ptr_to_ptr = variables.get(var_name)
-                        .unwrap_or_else(|| panic!("Variable {} not found", var_name))
+                        .ok_or_else(|| undefined_variable(var_name, variables.keys()))?
                         .ptr;
 
-                    let actual_ptr = builder.build_load(ptr_to_ptr, "deref_target").unwrap();
-                    actual_ptr.into_pointer_value()
+                    let actual_ptr = builder.build_load(ptr_to_ptr, &value_name("deref_target")).unwrap().into_pointer_value();
+                    generate_pointer_checks(context, builder, module, actual_ptr, options);
+                    Ok(actual_ptr)
                 }
-                _ => panic!("Nested deref not supported"),
+                other => Err(unsupported_construct(
+                    &tr!("assigning through `deref` of {0}", tr!(describe_expression(other))),
+                    "load the pointer into a variable first, then assign through `deref` of that variable",
+                )),
             }
         }
 
-        _ => panic!("Cannot take address of this expression"),
+        Expression::IndexAccess { target, index } => {
+            generate_index_address(context, builder, target, index, variables, module, options)
+        }
+
+        Expression::FieldAccess { target, field } => {
+            let base = match &**target {
+                Expression::Variable(_) | Expression::Deref(_) | Expression::FieldAccess { .. } | Expression::IndexAccess { .. } => {
+                    let address = generate_address_ir(context, builder, target, variables, module, options)?;
+                    // A pointer to a struct is followed to the struct it points to.
+                    match address.get_type().get_element_type() {
+                        AnyTypeEnum::PointerType(pointer) if pointer.get_element_type().is_struct_type() => {
+                            builder.build_load(address, &value_name("struct_ptr")).unwrap().into_pointer_value()
+                        }
+                        _ => address,
+                    }
+                }
+                other => match generate_expression_ir(context, builder, other, variables, module, None, options)? {
+                    BasicValueEnum::PointerValue(pointer) if pointer.get_type().get_element_type().is_struct_type() => pointer,
+                    // A struct returned by value is read through a copy.
+                    BasicValueEnum::StructValue(value) if !is_str_type(value.get_type().into()) => {
+                        let copy = entry_alloca(context, builder, value.get_type().into(), "struct_value");
+                        builder.build_store(copy, value).unwrap();
+                        copy
+                    }
+                    _ => return Err(unsupported_construct(
+                        &tr!("accessing a field of {0}", tr!(describe_expression(other))),
+                        "store the struct in a variable first, then access the field of that variable",
+                    )),
+                },
+            };
+
+            let AnyTypeEnum::StructType(struct_type) = base.get_type().get_element_type() else {
+                let target_type = infer_wave_type(target, variables, module)?;
+                return Err(invalid("field of non-struct", tr!("no field `{0}` on type `{1}`", field, target_type)));
+            };
+            let name = struct_type.get_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let (index, _) = struct_field(&name, field)?;
+            Ok(builder.build_struct_gep(base, index, field).unwrap())
+        }
+
+        other => Err(unsupported_construct(
+            &tr!("assigning to {0}", tr!(describe_expression(other))),
+            "only a variable or `deref <variable>` can be assigned to; store the value in a variable first",
+        )),
     }
 }
 
+/// Emits `value` as a private null-terminated constant named `name` and returns an `i8*` to its first byte.
+pub fn generate_string_literal<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: &str,
+    name: &str,
+) -> PointerValue<'ctx> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+
+    let global = module.add_global(context.i8_type().array_type(bytes.len() as u32), None, name);
+    global.set_initializer(&context.const_string(&bytes, false));
+    global.set_linkage(Linkage::Private);
+    global.set_constant(true);
+
+    let zero = context.i32_type().const_zero();
+    unsafe { builder.build_gep(global.as_pointer_value(), &[zero, zero], &value_name("str_gep")).unwrap() }
+}
+
+/// Short English name of an expression's form, for diagnostics.
+pub fn describe_expression(expr: &Expression) -> &'static str {
+    match expr {
+        Expression::FunctionCall { .. } => "a function call",
+        Expression::Literal(_) => "a literal",
+        Expression::Variable(_) => "a variable",
+        Expression::Deref(_) => "a `deref` expression",
+        Expression::AddressOf(_) => "an `&` expression",
+        Expression::BinaryExpression { .. } => "a binary expression",
+        Expression::IndexAccess { .. } => "an index expression",
+        Expression::ArrayLiteral(_) => "an array literal",
+        Expression::AssignOperation { .. } | Expression::Assignment { .. } => "an assignment",
+        Expression::AsmBlock { .. } => "an asm block",
+        Expression::While { .. } => "a `while` loop",
+        Expression::Loop { .. } => "a `loop`",
+        Expression::StructLiteral { .. } => "a struct literal",
+        Expression::EnumVariant { .. } => "an enum variant",
+        Expression::FieldAccess { .. } => "a field access",
+        Expression::Alloc { .. } => "an `alloc` expression",
+        Expression::Cast { .. } => "an `as` cast",
+        Expression::Grouped(inner) => describe_expression(inner),
+    }
+}
+
+fn natural_alignment(context: &Context, ty: AnyTypeEnum) -> u64 {
+    match ty {
+        AnyTypeEnum::IntType(int_ty) => (int_ty.get_bit_width() as u64).div_ceil(8).next_power_of_two().min(16),
+        AnyTypeEnum::FloatType(float_ty) if float_ty == context.f32_type() => 4,
+        AnyTypeEnum::FloatType(float_ty) if float_ty == context.f64_type() => 8,
+        AnyTypeEnum::FloatType(_) => 16,
+        AnyTypeEnum::PointerType(_) => 8,
+        AnyTypeEnum::ArrayType(arr_ty) => natural_alignment(context, arr_ty.get_element_type().as_any_type_enum()),
+        _ => 1,
+    }
+}
+
+fn generate_alignment_check<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    ptr: PointerValue<'ctx>,
+) {
+    let align = natural_alignment(context, ptr.get_type().get_element_type());
+    if align <= 1 {
+        return;
+    }
+
+    let i64_type = context.i64_type();
+    let addr = builder.build_ptr_to_int(ptr, i64_type, &value_name("ptr_addr")).unwrap();
+    let low_bits = builder.build_and(addr, i64_type.const_int(align - 1, false), &value_name("align_bits")).unwrap();
+    let misaligned = builder.build_int_compare(IntPredicate::NE, low_bits, i64_type.const_zero(), &value_name("misaligned")).unwrap();
+
+    generate_runtime_trap(
+        context,
+        builder,
+        module,
+        misaligned,
+        &format!("misaligned pointer dereference (expected {}-byte alignment)", align),
+    );
+}
+
+/// The checks `options` asks for before `ptr` is dereferenced: that it is not null under
+/// `--check-ub`, and aligned under `--check-align` or `--check-ub`.
+pub fn generate_pointer_checks<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    ptr: PointerValue<'ctx>,
+    options: &CodegenOptions,
+) {
+    if options.check_ub {
+        let null = builder.build_is_null(ptr, &value_name("is_null")).unwrap();
+        generate_runtime_trap(context, builder, module, null, "null pointer dereference");
+    }
+    if options.check_alignment || options.check_ub {
+        generate_alignment_check(context, builder, module, ptr);
+    }
+}
+
+/// `--check-ub`: `lhs` `operator` `rhs` (`+`, `-` or `*`) of signed integers, trapping when the
+/// result does not fit their type.
+pub fn generate_overflow_check<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    operator: &str,
+    lhs: IntValue<'ctx>,
+    rhs: IntValue<'ctx>,
+) -> IntValue<'ctx> {
+    let intrinsic_name = match operator {
+        "+" => "llvm.sadd.with.overflow",
+        "-" => "llvm.ssub.with.overflow",
+        _ => "llvm.smul.with.overflow",
+    };
+    let intrinsic = Intrinsic::find(intrinsic_name)
+        .and_then(|intrinsic| intrinsic.get_declaration(module, &[lhs.get_type().into()]))
+        .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic '{}'", intrinsic_name));
+    let checked = builder
+        .build_call(intrinsic, &[lhs.into(), rhs.into()], &value_name("checked"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_struct_value();
+    let result = builder.build_extract_value(checked, 0, &value_name("checked_result")).unwrap().into_int_value();
+    let overflow = builder.build_extract_value(checked, 1, &value_name("overflow")).unwrap().into_int_value();
+    generate_runtime_trap(context, builder, module, overflow, &format!("signed integer overflow in `{}`", operator));
+    result
+}
+
+/// `--check-ub`: traps before `lhs / rhs` or `lhs % rhs` when `rhs` is zero, or for signed
+/// integers, when the quotient of the minimum value and -1 does not fit.
+pub fn generate_division_check<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    lhs: IntValue<'ctx>,
+    rhs: IntValue<'ctx>,
+    signed: bool,
+) {
+    let int_type = rhs.get_type();
+    let zero = builder.build_int_compare(IntPredicate::EQ, rhs, int_type.const_zero(), &value_name("div_by_zero")).unwrap();
+    generate_runtime_trap(context, builder, module, zero, "attempt to divide by zero");
+    if signed && int_type.get_bit_width() > 1 {
+        let sign_bit = int_type.const_int(int_type.get_bit_width() as u64 - 1, false);
+        let min = int_type.const_int(1, false).const_shl(sign_bit);
+        let is_min = builder.build_int_compare(IntPredicate::EQ, lhs, min, &value_name("is_min")).unwrap();
+        let minus_one = builder.build_int_compare(IntPredicate::EQ, rhs, int_type.const_all_ones(), &value_name("is_minus_one")).unwrap();
+        let overflow = builder.build_and(is_min, minus_one, &value_name("div_overflow")).unwrap();
+        generate_runtime_trap(context, builder, module, overflow, "signed division overflow (the minimum value divided by -1)");
+    }
+}
+
+/// `value` converted to the integer type `int_type`, signed or not, rounding toward zero. A
+/// value that is NaN or out of range saturates, or traps under `--float-to-int=trap` or `--check-ub`.
+pub fn generate_float_to_int<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: FloatValue<'ctx>,
+    int_type: IntType<'ctx>,
+    signed: bool,
+    options: &CodegenOptions,
+) -> IntValue<'ctx> {
+    if options.float_to_int == FloatToInt::Trap || options.check_ub {
+        // The bounds, -2^(n-1) and 2^(n-1) or 2^n, are powers of two, so exact as floats. An
+        // unsigned value rounds to 0 from anything above -1.
+        let float_type = value.get_type();
+        let bits = int_type.get_bit_width() as i32;
+        let (min, max, predicate) = if signed {
+            (-2f64.powi(bits - 1), 2f64.powi(bits - 1), FloatPredicate::OGE)
+        } else {
+            (-1.0, 2f64.powi(bits), FloatPredicate::OGT)
+        };
+        let above_min = builder
+            .build_float_compare(predicate, value, float_type.const_float(min), &value_name("above_min"))
+            .unwrap();
+        let below_max = builder
+            .build_float_compare(FloatPredicate::OLT, value, float_type.const_float(max), &value_name("below_max"))
+            .unwrap();
+        let in_range = builder.build_and(above_min, below_max, &value_name("in_range")).unwrap();
+        let out_of_range = builder.build_not(in_range, &value_name("out_of_range")).unwrap();
+        let message = format!("float value is NaN or out of the range of {}{}", if signed { "i" } else { "u" }, bits);
+        generate_runtime_trap(context, builder, module, out_of_range, &message);
+        return if signed {
+            builder.build_float_to_signed_int(value, int_type, &value_name("float_to_int")).unwrap()
+        } else {
+            builder.build_float_to_unsigned_int(value, int_type, &value_name("float_to_int")).unwrap()
+        };
+    }
+
+    let name = if signed { "llvm.fptosi.sat" } else { "llvm.fptoui.sat" };
+    let intrinsic = Intrinsic::find(name)
+        .and_then(|intrinsic| intrinsic.get_declaration(module, &[int_type.into(), value.get_type().into()]))
+        .unwrap_or_else(|| panic!("Failed to declare LLVM intrinsic '{}'", name));
+    builder
+        .build_call(intrinsic, &[value.into()], &value_name("float_to_int"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value()
+}
+
+/// `index` as a `usize`, the width of the offsets of `getelementptr`: an unsigned index (or a
+/// `bool`) is extended with zeros and a signed one with its sign, so that a negative index stays
+/// negative. A wider index is left as it is; `getelementptr` truncates it.
+pub fn usize_index<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    index: IntValue<'ctx>,
+    unsigned: bool,
+) -> IntValue<'ctx> {
+    let usize_type = context.custom_width_int_type(POINTER_BITS.with(Cell::get));
+    if index.get_type().get_bit_width() >= usize_type.get_bit_width() {
+        return index;
+    }
+    let signed = !unsigned && index.get_type().get_bit_width() > 1;
+    builder.build_int_cast_sign_flag(index, usize_type, signed, &value_name("index")).unwrap()
+}
+
+/// Traps unless `index`, a `usize` from `usize_index`, is below `len`, the length of the array
+/// it indexes. Compared unsigned, a negative index is out of bounds too.
+pub fn generate_bounds_check<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    index: IntValue<'ctx>,
+    len: u32,
+) {
+    let bound = index.get_type().const_int(len as u64, false);
+    let out_of_bounds = builder.build_int_compare(IntPredicate::UGE, index, bound, &value_name("out_of_bounds")).unwrap();
+
+    generate_runtime_trap(
+        context,
+        builder,
+        module,
+        out_of_bounds,
+        &format!("index out of bounds (the length is {})", len),
+    );
+}
+
+/// Whether `module` is compiled for WebAssembly, where there is no C library: printing goes
+/// through functions the host provides and runtime errors trap.
+pub fn targets_wasm(module: &inkwell::module::Module) -> bool {
+    module.get_triple().as_str().to_bytes().starts_with(b"wasm")
+}
+
+// A function the WebAssembly host provides, imported from its `wave` module. The symbol is
+// prefixed so that a Wave function of the same name cannot take its place.
+fn host_function<'ctx>(
+    context: &'ctx Context,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    name: &str,
+    fn_type: inkwell::types::FunctionType<'ctx>,
+) -> FunctionValue<'ctx> {
+    let symbol = format!("wave.{}", name);
+    module.get_function(&symbol).unwrap_or_else(|| {
+        let function = module.add_function(&symbol, fn_type, None);
+        function.add_attribute(AttributeLoc::Function, context.create_string_attribute("wasm-import-module", "wave"));
+        function.add_attribute(AttributeLoc::Function, context.create_string_attribute("wasm-import-name", name));
+        function
+    })
+}
+
+// The host's `print_str(bytes, len)`, which prints `len` bytes from the module's memory.
+fn print_str_function<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) -> FunctionValue<'ctx> {
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let fn_type = context.void_type().fn_type(&[i8_ptr.into(), context.i32_type().into()], false);
+    host_function(context, module, "print_str", fn_type)
+}
+
+/// `print` and `println` on WebAssembly. The text around the placeholders and strings go to the
+/// host's `print_str` with `bool`s as `true` or `false`, integers and pointers to `print_i64` and
+/// floats to `print_f64`; the host prints each value plainly, so a placeholder's spec is not applied.
+/// The text is stored in globals named after `name`.
+pub fn generate_host_print<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    format: &str,
+    args: &[PrintArgument<'ctx>],
+    name: &str,
+) -> Result<(), CodegenError> {
+    let void = context.void_type();
+    let print_str = print_str_function(context, module);
+    let print_i64 = host_function(context, module, "print_i64", void.fn_type(&[context.i64_type().into()], false));
+    let print_f64 = host_function(context, module, "print_f64", void.fn_type(&[context.f64_type().into()], false));
+
+    let mut pieces = 0;
+    let mut flush = |text: &mut String| {
+        if !text.is_empty() {
+            let piece = generate_string_literal(context, builder, module, text, &format!("{}_{}", name, pieces));
+            let len = context.i32_type().const_int(text.len() as u64, false);
+            builder.build_call(print_str, &[piece.into(), len.into()], "").unwrap();
+            pieces += 1;
+            text.clear();
+        }
+    };
+
+    // Without arguments, `format` is the text of a `print` that had no placeholders.
+    let parts = match args {
+        [] => vec![FormatPart::Literal(format.to_string())],
+        _ => parse_format_string(format).unwrap_or_default(),
+    };
+    let mut text = String::new();
+    let mut args = args.iter();
+    for part in parts {
+        let arg = match part {
+            FormatPart::Literal(literal) => {
+                text.push_str(&literal);
+                continue;
+            }
+            FormatPart::Placeholder(..) => match args.next() {
+                Some(arg) => arg,
+                None => continue,
+            },
+        };
+        flush(&mut text);
+        match arg.value {
+            BasicValueEnum::IntValue(value) if value.get_type().get_bit_width() == 1 => {
+                let (text, len) = generate_bool_text(context, builder, value);
+                builder.build_call(print_str, &[text.into(), len.into()], "").unwrap();
+            }
+            BasicValueEnum::IntValue(value) => {
+                let signed = !arg.unsigned;
+                let wide = builder.build_int_cast_sign_flag(value, context.i64_type(), signed, &value_name("print_int")).unwrap();
+                builder.build_call(print_i64, &[wide.into()], "").unwrap();
+            }
+            BasicValueEnum::FloatValue(value) => {
+                let wide = builder.build_float_cast(value, context.f64_type(), &value_name("print_float")).unwrap();
+                builder.build_call(print_f64, &[wide.into()], "").unwrap();
+            }
+            BasicValueEnum::StructValue(value) if is_str_type(value.get_type().into()) => {
+                let (bytes, len) = str_parts(builder, value);
+                let len = builder.build_int_truncate(len, context.i32_type(), &value_name("print_len")).unwrap();
+                builder.build_call(print_str, &[bytes.into(), len.into()], "").unwrap();
+            }
+            BasicValueEnum::PointerValue(value) => {
+                let address = builder.build_ptr_to_int(value, context.i64_type(), &value_name("print_ptr")).unwrap();
+                builder.build_call(print_i64, &[address.into()], "").unwrap();
+            }
+            _ => return Err(unsupported_construct("printing this value on WebAssembly", "print its fields one at a time")),
+        }
+    }
+    flush(&mut text);
+    Ok(())
+}
+
+/// Branches to a block that reports `message` on stderr, with the call stack under `-g`, and
+/// aborts when `condition` is true; on WebAssembly the host prints the message and the module traps.
+/// The builder is left positioned in the continuation block.
+pub fn generate_runtime_trap<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    condition: IntValue<'ctx>,
+    message: &str,
+) {
+    let function = builder.get_insert_block().unwrap().get_parent().unwrap();
+    let trap_block = context.append_basic_block(function, "rt.trap");
+    let cont_block = context.append_basic_block(function, "rt.cont");
+
+    builder.build_conditional_branch(condition, trap_block, cont_block).unwrap();
+    builder.position_at_end(trap_block);
+
+    let i8_ptr_type = context.i8_type().ptr_type(AddressSpace::default());
+
+    if targets_wasm(module) {
+        let message = format!("runtime error: {}\n", message);
+        let text = builder.build_global_string_ptr(&message, "rt_msg").unwrap();
+        let len = context.i32_type().const_int(message.len() as u64, false);
+        builder.build_call(print_str_function(context, module), &[text.as_pointer_value().into(), len.into()], "").unwrap();
+        let trap = Intrinsic::find("llvm.trap")
+            .and_then(|intrinsic| intrinsic.get_declaration(module, &[]))
+            .expect("Failed to declare LLVM intrinsic 'llvm.trap'");
+        builder.build_call(trap, &[], "").unwrap();
+        builder.build_unreachable().unwrap();
+        builder.position_at_end(cont_block);
+        return;
+    }
+
+    let dprintf_func = match module.get_function("dprintf") {
+        Some(f) => f,
+        None => module.add_function(
+            "dprintf",
+            context.i32_type().fn_type(&[context.i32_type().into(), i8_ptr_type.into()], true),
+            None,
+        ),
+    };
+    let abort_func = match module.get_function("abort") {
+        Some(f) => f,
+        None => module.add_function("abort", context.void_type().fn_type(&[], false), None),
+    };
+
+    let text = builder
+        .build_global_string_ptr(&format!("runtime error: {}\n", message), "rt_msg")
+        .unwrap();
+
+    generate_flush(context, builder, module);
+    builder
+        .build_call(
+            dprintf_func,
+            &[context.i32_type().const_int(2, false).into(), text.as_pointer_value().into()],
+            "",
+        )
+        .unwrap();
+    generate_backtrace_print(context, builder, module, dprintf_func);
+    builder.build_call(abort_func, &[], "").unwrap();
+    builder.build_unreachable().unwrap();
+
+    builder.position_at_end(cont_block);
+}
+
 #[derive(Clone)]
 pub struct VariableInfo<'ctx> {
     pub ptr: PointerValue<'ctx>,
     pub mutability: Mutability,
+    pub ty: WaveType,
+    /// Where the variable is declared; the default span for parameters.
+    pub span: Span,
 }
 
-pub fn get_llvm_type<'a>(context: &'a Context, ty: &TokenType) -> BasicTypeEnum<'a> {
-    match ty {
+pub fn get_llvm_type<'a>(context: &'a Context, ty: &TokenType) -> Result<BasicTypeEnum<'a>, CodegenError> {
+    let llvm_type = match ty {
         TokenType::TypeInt(bits) => context.custom_width_int_type(*bits as u32).as_basic_type_enum(),
         TokenType::TypeUint(bits) => context.custom_width_int_type(*bits as u32).as_basic_type_enum(),
         TokenType::TypeFloat(bits) => match bits {
             32 => context.f32_type().as_basic_type_enum(),
             64 => context.f64_type().as_basic_type_enum(),
             128 => context.f128_type().as_basic_type_enum(),
-            _ => panic!("Unsupported float size: {}", bits),
+            _ => return Err(invalid("unsupported float size", tr!("type `{0}` is not supported", format!("f{}", bits)))),
         },
         TokenType::TypeBool => context.bool_type().as_basic_type_enum(),
         TokenType::TypeChar => context.i8_type().as_basic_type_enum(),
         TokenType::TypeByte => context.i8_type().as_basic_type_enum(),
         TokenType::TypePointer(inner_type) => {
-            let inner_llvm_type = get_llvm_type(context, &*inner_type); // Box 역참조
+            let inner_llvm_type = get_llvm_type(context, &*inner_type)?; // Box 역참조
             inner_llvm_type.ptr_type(AddressSpace::default()).as_basic_type_enum()
         }
         TokenType::TypeArray(inner_type, size) => {
-            let inner_llvm_type = get_llvm_type(context, &*inner_type); // Box 역참조
+            let inner_llvm_type = get_llvm_type(context, &*inner_type)?; // Box 역참조
             inner_llvm_type.array_type(*size as u32).as_basic_type_enum()
         }
-        TokenType::TypeString => context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum(),
-        _ => panic!("Unsupported type: {:?}", ty),
-    }
+        TokenType::TypeString => str_type(context).as_basic_type_enum(),
+        _ => return Err(invalid("unsupported type", tr!("type `{0}` is not supported", format!("{:?}", ty)))),
+    };
+    Ok(llvm_type)
 }
 
 pub unsafe fn create_alloc<'a>(
@@ -258,6 +2001,6 @@ pub unsafe fn create_alloc<'a>(
     function: FunctionValue<'a>,
     name: &'a str,
 ) -> PointerValue<'a> {
-    let alloca = builder.build_alloca(context.i32_type(), name).unwrap();
+    let alloca = builder.build_alloca(context.i32_type(), &value_name(name)).unwrap();
     alloca
 }
\ No newline at end of file
@@ -1,4 +1,11 @@
 pub mod llvm_codegen;
 pub mod llvm_backend;
 mod expression;
-mod statement;
\ No newline at end of file
+mod statement;
+mod branch;
+mod builtin;
+mod bench;
+mod string;
+mod print;
+mod heap;
+mod backtrace;
\ No newline at end of file
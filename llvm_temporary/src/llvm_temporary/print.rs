@@ -0,0 +1,458 @@
+use inkwell::context::Context;
+use inkwell::types::{BasicType, BasicTypeEnum};
+use inkwell::values::{ArrayValue, BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use inkwell::{AddressSpace, IntPredicate};
+use parser::ast::{FormatPart, FormatSpec, WaveType};
+use parser::parse_format_string;
+use crate::llvm_temporary::llvm_codegen::{generate_string_literal, targets_wasm, value_name};
+use crate::llvm_temporary::string::{is_str_type, str_parts};
+
+/// `printf` from the C library, declared the first time the program prints.
+pub fn printf_function<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) -> FunctionValue<'ctx> {
+    module.get_function("printf").unwrap_or_else(|| {
+        let printf_type = context.i32_type().fn_type(&[context.i8_type().ptr_type(AddressSpace::default()).into()], true);
+        module.add_function("printf", printf_type, None)
+    })
+}
+
+/// At the entry of `main`: has the C library buffer stdout in blocks rather than lines when it
+/// is not a terminal, so that many small prints into a pipe or file take few writes. A terminal
+/// keeps line buffering, so progress lines and prompts show at once. What is buffered is written
+/// by `flush()`, before a failed runtime check reports itself, and when the program exits. Does
+/// nothing on WebAssembly, whose host prints at once, or on Windows, whose C library has no
+/// `stdout` variable to name.
+pub fn generate_stdout_buffering<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) {
+    let triple = module.get_triple();
+    let triple = triple.as_str().to_string_lossy();
+    let stream_name = match &*triple {
+        _ if targets_wasm(module) || triple.contains("windows") => return,
+        triple if triple.contains("apple") => "__stdoutp",
+        _ => "stdout",
+    };
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let stream = module.get_global(stream_name).unwrap_or_else(|| module.add_global(i8_ptr, None, stream_name));
+    let stream = builder.build_load(stream.as_pointer_value(), &value_name("stdout")).unwrap();
+    let setvbuf = module.get_function("setvbuf").unwrap_or_else(|| {
+        let i32_type = context.i32_type();
+        let setvbuf_type = i32_type.fn_type(&[i8_ptr.into(), i8_ptr.into(), i32_type.into(), context.i64_type().into()], false);
+        module.add_function("setvbuf", setvbuf_type, None)
+    });
+    let i32_type = context.i32_type();
+    let isatty = module
+        .get_function("isatty")
+        .unwrap_or_else(|| module.add_function("isatty", i32_type.fn_type(&[i32_type.into()], false), None));
+    let terminal = builder
+        .build_call(isatty, &[i32_type.const_int(1, false).into()], &value_name("isatty"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let terminal = builder.build_int_compare(IntPredicate::NE, terminal, i32_type.const_zero(), &value_name("terminal")).unwrap();
+    // `_IOFBF` is 0 and `_IOLBF` 1 in the C libraries Wave targets.
+    let mode = builder.build_int_z_extend(terminal, i32_type, &value_name("buffer_mode")).unwrap();
+    let args = [stream.into(), i8_ptr.const_null().into(), mode.into(), context.i64_type().const_int(1 << 16, false).into()];
+    builder.build_call(setvbuf, &args, "").unwrap();
+}
+
+/// `flush()`: writes out what the C library has buffered for stdout, and for every other stream.
+pub fn generate_flush<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+) {
+    if targets_wasm(module) {
+        return;
+    }
+    let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+    let fflush = module
+        .get_function("fflush")
+        .unwrap_or_else(|| module.add_function("fflush", context.i32_type().fn_type(&[i8_ptr.into()], false), None));
+    builder.build_call(fflush, &[i8_ptr.const_null().into()], "").unwrap();
+}
+
+/// A value `print` is given, and whether it is unsigned, which its LLVM type does not say.
+#[derive(Clone, Copy)]
+pub struct PrintArgument<'ctx> {
+    pub value: BasicValueEnum<'ctx>,
+    pub unsigned: bool,
+}
+
+/// Whether a value of type `ty`, or each element of one, prints as unsigned.
+pub fn prints_unsigned(ty: &WaveType) -> bool {
+    match ty {
+        WaveType::Array(element, _) => prints_unsigned(element),
+        WaveType::Uint(_) | WaveType::Char | WaveType::Byte | WaveType::Bool => true,
+        _ => false,
+    }
+}
+
+/// `true` or `false` for a `bool`, as a C string and its length.
+pub fn generate_bool_text<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+) -> (PointerValue<'ctx>, IntValue<'ctx>) {
+    let true_text = builder.build_global_string_ptr("true", "print_true").unwrap().as_pointer_value();
+    let false_text = builder.build_global_string_ptr("false", "print_false").unwrap().as_pointer_value();
+    let text = builder.build_select(value, true_text, false_text, &value_name("bool_text")).unwrap().into_pointer_value();
+    let i32_type = context.i32_type();
+    let len = builder
+        .build_select(value, i32_type.const_int(4, false), i32_type.const_int(5, false), &value_name("bool_len"))
+        .unwrap()
+        .into_int_value();
+    (text, len)
+}
+
+/// `print`/`println` with `format` and its arguments, one for each placeholder.
+/// Scalars and strings go to a single `printf`; with an array, a binary `{:b}` or a centered
+/// `{:^8}` among them, the text between the placeholders is printed piece by piece, each array by
+/// a loop, as `[1, 2, 3]`, each binary number by its digits and each centered value between its
+/// padding. `global_name` names the format's constant, and prefixes those of the pieces.
+pub fn generate_print_format<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    format: &str,
+    args: &[PrintArgument<'ctx>],
+    global_name: &str,
+) {
+    let printf = printf_function(context, module);
+    let parts = parse_format_string(format).unwrap_or_default();
+    let specs: Vec<&FormatSpec> = parts
+        .iter()
+        .filter_map(|part| match part {
+            FormatPart::Placeholder(_, spec) => Some(spec),
+            FormatPart::Literal(_) => None,
+        })
+        .collect();
+    let piecewise = args.iter().any(|arg| arg.value.is_array_value())
+        || specs.iter().any(|spec| spec.radix == Some('b') || is_centered(spec));
+    let mut args = args.iter().zip(specs);
+
+    if !piecewise {
+        let mut c_format = String::new();
+        let mut printf_args = vec![];
+        for part in &parts {
+            match part {
+                FormatPart::Literal(text) => c_format.push_str(&text.replace('%', "%%")),
+                FormatPart::Placeholder(..) => {
+                    let Some((arg, spec)) = args.next() else { continue };
+                    c_format.push_str(&printf_conversion(spec, arg.value.get_type(), arg.unsigned));
+                    push_printf_arguments(context, builder, *arg, spec, &mut printf_args);
+                }
+            }
+        }
+        printf_args.insert(0, generate_string_literal(context, builder, module, &c_format, global_name).into());
+        builder.build_call(printf, &printf_args, &value_name("printf_call")).unwrap();
+        return;
+    }
+
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            FormatPart::Literal(text) => {
+                let text = text.replace('%', "%%");
+                let text = generate_string_literal(context, builder, module, &text, &format!("{}.{}", global_name, i));
+                builder.build_call(printf, &[text.into()], &value_name("printf_call")).unwrap();
+            }
+            FormatPart::Placeholder(..) => {
+                if let Some((arg, spec)) = args.next() {
+                    generate_value_print(context, builder, module, printf, *arg, spec);
+                }
+            }
+        }
+    }
+}
+
+// One value as a placeholder with `spec` prints it.
+fn generate_value_print<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    printf: FunctionValue<'ctx>,
+    arg: PrintArgument<'ctx>,
+    spec: &FormatSpec,
+) {
+    let mut arg = arg;
+    let binary_spec;
+    let mut spec = spec;
+    match arg.value {
+        BasicValueEnum::ArrayValue(array) => return generate_array_print(context, builder, module, printf, array, arg.unsigned, spec),
+        // The digits then print as a string, in the width and alignment of the spec.
+        BasicValueEnum::IntValue(int) if spec.radix == Some('b') => {
+            arg = PrintArgument { value: generate_binary_digits(context, builder, int, spec).into(), unsigned: false };
+            binary_spec = FormatSpec { zero_pad: false, radix: None, ..spec.clone() };
+            spec = &binary_spec;
+        }
+        _ => {}
+    }
+
+    let c_format = printf_conversion(spec, arg.value.get_type(), arg.unsigned);
+    let mut printf_args = vec![builder.build_global_string_ptr(&c_format, "print_spec").unwrap().as_pointer_value().into()];
+    push_printf_arguments(context, builder, arg, spec, &mut printf_args);
+    let (Some(width), true) = (spec.width, is_centered(spec)) else {
+        builder.build_call(printf, &printf_args, &value_name("printf_call")).unwrap();
+        return;
+    };
+
+    // Centered: `snprintf` measures the value, then half the padding goes before it and the
+    // rest, which may be a space more, after.
+    let i32_type = context.i32_type();
+    let mut measure_args = vec![context.i8_type().ptr_type(AddressSpace::default()).const_null().into(), context.i64_type().const_zero().into()];
+    measure_args.extend(printf_args.iter().copied());
+    let len = builder
+        .build_call(snprintf_function(context, module), &measure_args, &value_name("print_len"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    let width = i32_type.const_int(width as u64, false);
+    let shorter = builder.build_int_compare(IntPredicate::SLT, len, width, &value_name("is_shorter")).unwrap();
+    let padding = builder.build_int_sub(width, len, &value_name("padding")).unwrap();
+    let padding = builder.build_select(shorter, padding, i32_type.const_zero(), &value_name("padding")).unwrap().into_int_value();
+    let before = builder.build_right_shift(padding, i32_type.const_int(1, false), false, &value_name("padding_before")).unwrap();
+    let after = builder.build_int_sub(padding, before, &value_name("padding_after")).unwrap();
+
+    let pad_format = builder.build_global_string_ptr("%*s", "print_pad").unwrap().as_pointer_value();
+    let nothing = builder.build_global_string_ptr("", "print_none").unwrap().as_pointer_value();
+    builder.build_call(printf, &[pad_format.into(), before.into(), nothing.into()], &value_name("printf_call")).unwrap();
+    builder.build_call(printf, &printf_args, &value_name("printf_call")).unwrap();
+    builder.build_call(printf, &[pad_format.into(), after.into(), nothing.into()], &value_name("printf_call")).unwrap();
+}
+
+fn is_centered(spec: &FormatSpec) -> bool {
+    spec.align == Some('^') && spec.width.is_some()
+}
+
+// `snprintf` from the C library, which with no buffer gives the length a `printf` would print.
+fn snprintf_function<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) -> FunctionValue<'ctx> {
+    module.get_function("snprintf").unwrap_or_else(|| {
+        let i8_ptr = context.i8_type().ptr_type(AddressSpace::default());
+        let snprintf_type = context.i32_type().fn_type(&[i8_ptr.into(), context.i64_type().into(), i8_ptr.into()], true);
+        module.add_function("snprintf", snprintf_type, None)
+    })
+}
+
+// `[a, b, c]`, each element printed with `spec`, by a loop over a copy of the array in the
+// function's frame.
+fn generate_array_print<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    printf: FunctionValue<'ctx>,
+    array: ArrayValue<'ctx>,
+    unsigned: bool,
+    spec: &FormatSpec,
+) {
+    let print_text = |text: &str, name: &str| {
+        let text = builder.build_global_string_ptr(text, name).unwrap();
+        builder.build_call(printf, &[text.as_pointer_value().into()], &value_name("printf_call")).unwrap();
+    };
+    let array_type = array.get_type();
+    print_text("[", "print_open");
+    if !array_type.is_empty() {
+        let slot = entry_alloca(context, builder, array_type.as_basic_type_enum(), "print_array");
+        builder.build_store(slot, array).unwrap();
+
+        let function = builder.get_insert_block().unwrap().get_parent().unwrap();
+        let preheader = builder.get_insert_block().unwrap();
+        let body = context.append_basic_block(function, "print_element");
+        let done = context.append_basic_block(function, "print_done");
+        builder.build_unconditional_branch(body).unwrap();
+
+        builder.position_at_end(body);
+        let i64_type = context.i64_type();
+        let index = builder.build_phi(i64_type, &value_name("print_index")).unwrap();
+        index.add_incoming(&[(&i64_type.const_zero(), preheader)]);
+        let index_value = index.as_basic_value().into_int_value();
+
+        let first = builder.build_int_compare(IntPredicate::EQ, index_value, i64_type.const_zero(), &value_name("is_first")).unwrap();
+        let none = builder.build_global_string_ptr("", "print_none").unwrap().as_pointer_value();
+        let comma = builder.build_global_string_ptr(", ", "print_comma").unwrap().as_pointer_value();
+        let separator = builder.build_select(first, none, comma, &value_name("separator")).unwrap();
+        builder.build_call(printf, &[separator.into()], &value_name("printf_call")).unwrap();
+
+        // SAFETY: the index runs below the array's length.
+        let element_ptr = unsafe {
+            builder.build_in_bounds_gep(slot, &[i64_type.const_zero(), index_value], &value_name("print_element")).unwrap()
+        };
+        let element = builder.build_load(element_ptr, &value_name("element")).unwrap();
+        generate_value_print(context, builder, module, printf, PrintArgument { value: element, unsigned }, spec);
+
+        // The element's own printing may have left the block for others, as a nested array's does.
+        let latch = builder.get_insert_block().unwrap();
+        let next = builder.build_int_add(index_value, i64_type.const_int(1, false), &value_name("next_index")).unwrap();
+        index.add_incoming(&[(&next, latch)]);
+        let more = builder
+            .build_int_compare(IntPredicate::ULT, next, i64_type.const_int(array_type.len() as u64, false), &value_name("more"))
+            .unwrap();
+        builder.build_conditional_branch(more, body, done).unwrap();
+        builder.position_at_end(done);
+    }
+    print_text("]", "print_close");
+}
+
+// `{:b}`: the digits of the value as unsigned, written backwards from the end of a buffer, as a
+// C string. A zero-padded width is met by writing more digits.
+fn generate_binary_digits<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    value: IntValue<'ctx>,
+    spec: &FormatSpec,
+) -> PointerValue<'ctx> {
+    let i64_type = context.i64_type();
+    let i8_type = context.i8_type();
+    let buffer = entry_alloca(context, builder, i8_type.array_type(65).as_basic_type_enum(), "print_digits");
+    let digit_at = |index: IntValue<'ctx>| {
+        // SAFETY: at most 64 digits go before the NUL at 64.
+        unsafe { builder.build_in_bounds_gep(buffer, &[i64_type.const_zero(), index], &value_name("digit_ptr")).unwrap() }
+    };
+    builder.build_store(digit_at(i64_type.const_int(64, false)), i8_type.const_zero()).unwrap();
+    let value = builder.build_int_cast_sign_flag(value, i64_type, false, &value_name("print_bits")).unwrap();
+    let min_digits = if spec.zero_pad { spec.width.unwrap_or(1).clamp(1, 64) } else { 1 };
+
+    let function = builder.get_insert_block().unwrap().get_parent().unwrap();
+    let preheader = builder.get_insert_block().unwrap();
+    let body = context.append_basic_block(function, "print_digit");
+    let done = context.append_basic_block(function, "print_digits_done");
+    builder.build_unconditional_branch(body).unwrap();
+
+    builder.position_at_end(body);
+    let rest = builder.build_phi(i64_type, &value_name("rest")).unwrap();
+    let end = builder.build_phi(i64_type, &value_name("digits_end")).unwrap();
+    rest.add_incoming(&[(&value, preheader)]);
+    end.add_incoming(&[(&i64_type.const_int(64, false), preheader)]);
+    let rest_value = rest.as_basic_value().into_int_value();
+    let position = builder
+        .build_int_sub(end.as_basic_value().into_int_value(), i64_type.const_int(1, false), &value_name("position"))
+        .unwrap();
+    let bit = builder.build_and(rest_value, i64_type.const_int(1, false), &value_name("bit")).unwrap();
+    let bit = builder.build_int_truncate(bit, i8_type, &value_name("bit")).unwrap();
+    let digit = builder.build_int_add(bit, i8_type.const_int(b'0' as u64, false), &value_name("digit")).unwrap();
+    builder.build_store(digit_at(position), digit).unwrap();
+    let next = builder.build_right_shift(rest_value, i64_type.const_int(1, false), false, &value_name("rest")).unwrap();
+    rest.add_incoming(&[(&next, body)]);
+    end.add_incoming(&[(&position, body)]);
+
+    let nonzero = builder.build_int_compare(IntPredicate::NE, next, i64_type.const_zero(), &value_name("more_bits")).unwrap();
+    let written = builder.build_int_sub(i64_type.const_int(64, false), position, &value_name("written")).unwrap();
+    let short = builder
+        .build_int_compare(IntPredicate::ULT, written, i64_type.const_int(min_digits as u64, false), &value_name("short"))
+        .unwrap();
+    let more = builder.build_or(nonzero, short, &value_name("more")).unwrap();
+    builder.build_conditional_branch(more, body, done).unwrap();
+
+    builder.position_at_end(done);
+    digit_at(position)
+}
+
+// A slot in the entry block, so that printing in a loop does not grow the stack.
+pub fn entry_alloca<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    slot_type: BasicTypeEnum<'ctx>,
+    name: &str,
+) -> PointerValue<'ctx> {
+    let function = builder.get_insert_block().unwrap().get_parent().unwrap();
+    let entry = function.get_first_basic_block().unwrap();
+    let entry_builder = context.create_builder();
+    match entry.get_first_instruction() {
+        Some(first) => entry_builder.position_before(&first),
+        None => entry_builder.position_at_end(entry),
+    }
+    entry_builder.build_alloca(slot_type, &value_name(name)).unwrap()
+}
+
+// The `printf` conversion of a value of type `ty` under `spec`. An integer goes as an `int` or,
+// when wider, a `long long`, and in a radix always as unsigned; a `bool` goes as its word. A
+// centered value is printed without a width, between padding of its own.
+fn printf_conversion(spec: &FormatSpec, ty: BasicTypeEnum, unsigned: bool) -> String {
+    let mut conversion = String::from("%");
+    if spec.align == Some('<') {
+        conversion.push('-');
+    }
+    let is_bool = matches!(ty, BasicTypeEnum::IntType(int_type) if int_type.get_bit_width() == 1);
+    if spec.zero_pad && !is_bool {
+        conversion.push('0');
+    }
+    if let Some(width) = spec.width.filter(|_| !is_centered(spec)) {
+        conversion.push_str(&width.to_string());
+    }
+    let precision = spec.precision.map(|precision| format!(".{}", precision)).unwrap_or_default();
+    match ty {
+        BasicTypeEnum::FloatType(_) => conversion.push_str(&format!("{}f", precision)),
+        _ if is_bool => conversion.push('s'),
+        BasicTypeEnum::IntType(int_type) => {
+            if int_type.get_bit_width() > 32 {
+                conversion.push_str("ll");
+            }
+            conversion.push(spec.radix.unwrap_or(if unsigned { 'u' } else { 'd' }));
+        }
+        // The length, then the bytes: they need not end in a NUL.
+        ty if is_str_type(ty) => conversion.push_str(".*s"),
+        BasicTypeEnum::PointerType(ptr_ty) => {
+            if ptr_ty.get_element_type().is_int_type() && ptr_ty.get_element_type().into_int_type().get_bit_width() == 8 {
+                conversion.push_str(&format!("{}s", precision));
+            } else {
+                conversion.push_str("ld");
+            }
+        }
+        _ => conversion.push('d'), // fallback
+    }
+    conversion
+}
+
+// The `printf` arguments of a value for the conversion `printf_conversion` gives it: an `f32`
+// widens to a `double` and an integer to the `int` or `long long` it is printed as.
+fn push_printf_arguments<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    arg: PrintArgument<'ctx>,
+    spec: &FormatSpec,
+    printf_args: &mut Vec<BasicMetadataValueEnum<'ctx>>,
+) {
+    let value = match arg.value {
+        // `%.*s` takes the length as an `int` before the bytes; a precision shortens it.
+        BasicValueEnum::StructValue(string) if is_str_type(string.get_type().into()) => {
+            let (bytes, mut len) = str_parts(builder, string);
+            if let Some(precision) = spec.precision {
+                let precision = len.get_type().const_int(precision as u64, false);
+                let longer = builder.build_int_compare(IntPredicate::UGT, len, precision, &value_name("is_longer")).unwrap();
+                len = builder.build_select(longer, precision, len, &value_name("print_len")).unwrap().into_int_value();
+            }
+            let len = builder.build_int_truncate(len, context.i32_type(), &value_name("print_len")).unwrap();
+            printf_args.push(len.into());
+            bytes.as_basic_value_enum()
+        }
+        BasicValueEnum::IntValue(int) if int.get_type().get_bit_width() == 1 => {
+            generate_bool_text(context, builder, int).0.as_basic_value_enum()
+        }
+        BasicValueEnum::IntValue(int) => {
+            let wide = if int.get_type().get_bit_width() > 32 { context.i64_type() } else { context.i32_type() };
+            let signed = !arg.unsigned && spec.radix.is_none();
+            builder.build_int_cast_sign_flag(int, wide, signed, &value_name("print_int")).unwrap().as_basic_value_enum()
+        }
+        BasicValueEnum::PointerValue(ptr_val) => {
+            let element_ty = ptr_val.get_type().get_element_type();
+            if element_ty.is_int_type() && element_ty.into_int_type().get_bit_width() == 8 {
+                ptr_val.as_basic_value_enum()
+            } else {
+                builder
+                    .build_ptr_to_int(ptr_val, context.i64_type(), &value_name("ptr_as_int"))
+                    .unwrap()
+                    .as_basic_value_enum()
+            }
+        }
+        BasicValueEnum::FloatValue(fv) => builder
+            .build_float_ext(fv, context.f64_type(), &value_name("cast_to_double"))
+            .unwrap()
+            .as_basic_value_enum(),
+        value => value,
+    };
+    printf_args.push(value.into());
+}
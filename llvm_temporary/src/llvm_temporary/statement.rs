@@ -2,12 +2,15 @@ use std::collections::HashMap;
 use inkwell::{AddressSpace, FloatPredicate};
 use inkwell::basic_block::BasicBlock;
 use inkwell::context::Context;
-use inkwell::module::Linkage;
 use inkwell::types::{AnyTypeEnum, BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue};
-use parser::ast::{ASTNode, Expression, Literal, Mutability, StatementNode, VariableNode, WaveType};
-use crate::llvm_temporary::expression::generate_expression_ir;
-use crate::llvm_temporary::llvm_codegen::{generate_address_ir, wave_format_to_c, wave_type_to_llvm_type, VariableInfo};
+use parser::ast::{ASTNode, AssignOperator, Expression, Literal, Mutability, Operator, Pattern, Span, StatementNode, VariableNode, WaveType};
+use error::tr;
+use crate::llvm_temporary::expression::{generate_call_ir, generate_expression_ir, is_unsigned_integer};
+use crate::llvm_temporary::string::{generate_str_literal, is_str_type};
+use crate::llvm_temporary::backtrace::{generate_frame_line, generate_frame_pop};
+use crate::llvm_temporary::print::{generate_print_format, printf_function, prints_unsigned, PrintArgument};
+use crate::llvm_temporary::llvm_codegen::{asm_callee, build_void_return, generate_float_to_int, compile_context, describe_expression, enum_variant, is_enum, generate_address_ir, enter_node, generate_pointer_checks, float_literal, function_address, generate_host_print, generate_string_literal, infer_binding_type, int_literal, invalid, literal_signedness, resolve_wave_type, immutable_assignment, set_debug_location, targets_wasm, undefined_variable, unsupported_construct, value_name, variant_payload_type, wave_type_to_llvm_type, CodegenError, CodegenOptions, VariableInfo};
 
 pub fn generate_statement_ir<'ctx>(
     context: &'ctx Context,
@@ -16,33 +19,59 @@ pub fn generate_statement_ir<'ctx>(
     string_counter: &mut usize,
     stmt: &ASTNode,
     variables: &mut HashMap<String, VariableInfo<'ctx>>,
-    loop_exit_stack: &mut Vec<BasicBlock<'ctx>>,
+    loop_exit_stack: &mut Vec<LoopExit<'ctx>>,
     loop_continue_stack: &mut Vec<BasicBlock<'ctx>>,
     current_function: FunctionValue<'ctx>,
-) {
+    options: &CodegenOptions,
+) -> Result<(), CodegenError> {
+    let _node = enter_node(describe_node(stmt), node_span(stmt));
+    set_debug_location(context, builder, current_function, node_span(stmt));
+
+    // Code after a `return`, `break` or `continue` is never run, but it is still lowered so
+    // its mistakes are reported; it goes in a block of its own that nothing branches to.
+    if block_terminated(builder) {
+        let dead = context.append_basic_block(current_function, "dead");
+        builder.position_at_end(dead);
+    }
+    generate_frame_line(context, builder, module, node_span(stmt).line);
+
     match stmt {
         ASTNode::Variable(VariableNode {
                               name,
                               type_name,
                               initial_value,
-                              mutability
+                              mutability,
+                              attributes,
+                              span,
                           }) => unsafe {
-            let llvm_type = wave_type_to_llvm_type(&context, &type_name);
-            let alloca = builder.build_alloca(llvm_type, &name).unwrap();
+            let type_name = &match (type_name, initial_value) {
+                (WaveType::Inferred, Some(init)) => infer_binding_type(init, variables, module)?,
+                _ => resolve_wave_type(type_name, variables, module)?,
+            };
+            let llvm_type = wave_type_to_llvm_type(&context, &type_name)?;
+            let alloca = builder.build_alloca(llvm_type, &value_name(&name)).unwrap();
+
+            let align = attributes
+                .iter()
+                .find(|attr| attr.name == "align")
+                .and_then(|attr| attr.args.first())
+                .and_then(|n| n.parse::<u32>().ok());
+            if let Some(align) = align {
+                alloca.as_instruction().unwrap().set_alignment(align).unwrap();
+            }
 
             if let (WaveType::Array(element_type, size), Some(Expression::ArrayLiteral(values))) = (&type_name, &initial_value) {
                 if values.len() != *size as usize {
-                    panic!(
-                        "❌ Array length mismatch: expected {}, got {}",
-                        size,
-                        values.len()
-                    );
+                    return Err(invalid(
+                        "array length mismatch",
+                        tr!("expected an array of {0} elements, found {1}", size, values.len()),
+                    ));
                 }
 
-                let llvm_element_type = wave_type_to_llvm_type(context, element_type);
+                let llvm_element_type = wave_type_to_llvm_type(context, element_type)?;
 
                 for (i, value_expr) in values.iter().enumerate() {
-                    let value = generate_expression_ir(context, builder, value_expr, variables, module, Some(llvm_element_type));
+                    let value = generate_expression_ir(context, builder, value_expr, variables, module, Some(llvm_element_type), options)?;
 
                     let gep = builder.build_in_bounds_gep(
                         alloca,
@@ -61,10 +90,12 @@ pub fn generate_statement_ir<'ctx>(
                     VariableInfo {
                         ptr: alloca,
                         mutability: mutability.clone(),
+                        ty: type_name.clone(),
+                        span: *span,
                     },
                 );
 
-                return;
+                return Ok(());
             }
 
             variables.insert(
@@ -72,17 +103,31 @@ pub fn generate_statement_ir<'ctx>(
                 VariableInfo {
                     ptr: alloca,
                     mutability: mutability.clone(),
+                    ty: type_name.clone(),
+                    span: *span,
                 },
             );
 
             if let Some(init) = initial_value {
                 match (init, llvm_type) {
                     (Expression::Literal(Literal::Number(value)), BasicTypeEnum::IntType(int_type)) => {
+                        let init_value = int_literal(*value, int_type, literal_signedness(type_name))?;
+                        let _ = builder.build_store(alloca, init_value);
+                    }
+                    (Expression::Literal(Literal::Bool(value)), BasicTypeEnum::IntType(int_type)) => {
                         let init_value = int_type.const_int(*value as u64, false);
                         let _ = builder.build_store(alloca, init_value);
                     }
+                    (Expression::Literal(Literal::Char(value)), BasicTypeEnum::IntType(int_type)) => {
+                        let init_value = int_type.const_int(*value as u64, false);
+                        let _ = builder.build_store(alloca, init_value);
+                    }
+                    (Expression::Literal(Literal::Number(value)), BasicTypeEnum::FloatType(float_type)) => {
+                        let init_value = float_literal(*value as f64, float_type)?;
+                        builder.build_store(alloca, init_value).unwrap();
+                    }
                     (Expression::Literal(Literal::Float(value)), BasicTypeEnum::FloatType(float_type)) => {
-                        let init_value = float_type.const_float(*value);
+                        let init_value = float_literal(*value, float_type)?;
                         builder.build_store(alloca, init_value).unwrap();
                     }
                     (Expression::Literal(Literal::Float(value)), _) => {
@@ -90,40 +135,32 @@ pub fn generate_statement_ir<'ctx>(
 
                         let casted_value = match llvm_type {
                             BasicTypeEnum::IntType(int_ty) => {
-                                builder.build_float_to_signed_int(float_value, int_ty, "float_to_int").unwrap().as_basic_value_enum()
+                                generate_float_to_int(context, builder, module, float_value, int_ty, true, options).as_basic_value_enum()
                             }
                             BasicTypeEnum::FloatType(_) => float_value.as_basic_value_enum(),
-                            _ => panic!("Unsupported type for float literal initialization"),
+                            _ => return Err(unsupported_construct(
+                                &tr!("initializing a `{0}` variable with {1}", type_name, tr!(describe_expression(init))),
+                                "compute the value in a separate statement, or declare the variable with a matching type",
+                            )),
                         };
 
                         builder.build_store(alloca, casted_value).unwrap();
                     }
-                    (Expression::Literal(Literal::String(value)), BasicTypeEnum::PointerType(_)) => unsafe {
+                    (Expression::Literal(Literal::String(value)), BasicTypeEnum::PointerType(_)) => {
                         let string_name = format!("str_init_{}", name);
-                        let mut bytes = value.as_bytes().to_vec();
-                        bytes.push(0); // null-terminated
-
-                        let const_str = context.const_string(&bytes, false);
-                        let global = module.add_global(
-                            context.i8_type().array_type(bytes.len() as u32),
-                            None,
-                            &string_name,
-                        );
-                        global.set_initializer(&const_str);
-                        global.set_linkage(Linkage::Private);
-                        global.set_constant(true);
-
-                        let zero = context.i32_type().const_zero();
-                        let indices = [zero, zero];
-                        let gep = builder.build_gep(global.as_pointer_value(), &indices, "str_gep").unwrap();
-
+                        let gep = generate_string_literal(context, builder, module, value, &string_name);
                         let _ = builder.build_store(alloca, gep);
                     }
+                    (Expression::Literal(Literal::String(value)), ty) if is_str_type(ty) => {
+                        let string_name = format!("str_init_{}", name);
+                        let string = generate_str_literal(context, builder, module, value, &string_name);
+                        builder.build_store(alloca, string).unwrap();
+                    }
                     (Expression::AddressOf(inner_expr), BasicTypeEnum::PointerType(_)) => {
                         match &**inner_expr {
                             Expression::Variable(var_name) => {
                                 let ptr = variables.get(var_name)
-                                    .unwrap_or_else(|| panic!("Variable {} not found", var_name));
+                                    .ok_or_else(|| undefined_variable(var_name, variables.keys()))?;
                                 builder.build_store(alloca, ptr.ptr).unwrap();
                             }
                             Expression::ArrayLiteral(elements) => {
@@ -131,14 +168,17 @@ pub fn generate_statement_ir<'ctx>(
                                     BasicTypeEnum::PointerType(ptr_ty) => {
                                         match ptr_ty.get_element_type() {
                                             AnyTypeEnum::ArrayType(arr_ty) => arr_ty.get_element_type(),
-                                            _ => panic!("Expected pointer to array type"),
+                                            _ => return Err(invalid(
+                                                "address of array literal",
+                                                tr!("`&` of an array literal needs a pointer-to-array type, found `{0}`", type_name),
+                                            )),
                                         }
                                     }
-                                    _ => panic!("Expected pointer-to-array type for array literal"),
+                                    _ => unreachable!("matched on a pointer type"),
                                 };
 
                                 let array_type = elem_type.array_type(elements.len() as u32);
-                                let tmp_alloca = builder.build_alloca(array_type, "tmp_array").unwrap();
+                                let tmp_alloca = builder.build_alloca(array_type, &value_name("tmp_array")).unwrap();
 
                                 for (i, expr) in elements.iter().enumerate() {
                                     let val = generate_expression_ir(
@@ -148,7 +188,8 @@ pub fn generate_statement_ir<'ctx>(
                                         variables,
                                         module,
                                         Some(elem_type),
-                                    );
+                                        options,
+                                    )?;
                                     let gep = builder.build_in_bounds_gep(
                                         tmp_alloca,
                                         &[
@@ -162,77 +203,56 @@ pub fn generate_statement_ir<'ctx>(
 
                                 builder.build_store(alloca, tmp_alloca).unwrap();
                             }
-                            _ => panic!("& operator must be used on variable name or array literal"),
+                            other => return Err(unsupported_construct(
+                                &tr!("taking the address of {0}", tr!(describe_expression(other))),
+                                "`&` only applies to a variable or an array literal; store the value in a variable first",
+                            )),
                         }
                     }
                     (Expression::Deref(inner_expr), BasicTypeEnum::IntType(int_type)) => {
                         let target_ptr = match &**inner_expr {
                             Expression::Variable(var_name) => {
-                                let ptr_to_value = variables.get(var_name).unwrap().ptr;
-                                builder.build_load(ptr_to_value, "load_ptr").unwrap().into_pointer_value()
+                                let ptr_to_value = variables.get(var_name)
+                                    .ok_or_else(|| undefined_variable(var_name, variables.keys()))?
+                                    .ptr;
+                                builder.build_load(ptr_to_value, &value_name("load_ptr")).unwrap().into_pointer_value()
                             }
-                            _ => panic!("Invalid deref in variable init"),
+                            other => return Err(unsupported_construct(
+                                &tr!("`deref` of {0} in an initializer", tr!(describe_expression(other))),
+                                "bind the pointer to a variable first, then initialize with `deref` of that variable",
+                            )),
                         };
 
-                        let val = builder.build_load(target_ptr, "deref_value").unwrap();
+                        generate_pointer_checks(context, builder, module, target_ptr, options);
+
+                        let val = builder.build_load(target_ptr, &value_name("deref_value")).unwrap();
+                        let val = coerce_expression(context, builder, module, init, val, llvm_type, variables, options)?;
                         let _ = builder.build_store(alloca, val);
                     }
-                    (Expression::IndexAccess { target, index }, _) => {
-                        let val = generate_expression_ir(context, builder, init, variables, module, Some(llvm_type));
+                    (Expression::IndexAccess { .. } | Expression::FunctionCall { .. } | Expression::EnumVariant { .. } | Expression::While { .. } | Expression::Loop { .. } | Expression::Alloc { .. } | Expression::Cast { .. } | Expression::BinaryExpression { .. } | Expression::Grouped(_) | Expression::FieldAccess { .. } | Expression::StructLiteral { .. }, _) => {
+                        let val = generate_expression_ir(context, builder, init, variables, module, Some(llvm_type), options)?;
+                        let val = coerce_expression(context, builder, module, init, val, llvm_type, variables, options)?;
                         builder.build_store(alloca, val).unwrap();
                     }
-                    (Expression::FunctionCall { .. }, _) => {
-                        let val = generate_expression_ir(context, builder, init, variables, module, Some(llvm_type));
-                        builder.build_store(alloca, val).unwrap();
-                    }
-                    (Expression::BinaryExpression { .. }, _) => {
-                        let val = generate_expression_ir(context, builder, init, variables, module, Some(llvm_type));
-
-                        let casted_val = match (val, llvm_type) {
-                            (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
-                                builder.build_float_to_signed_int(v, t, "float_to_int").unwrap().as_basic_value_enum()
-                            }
-                            (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => {
-                                builder.build_signed_int_to_float(v, t, "int_to_float").unwrap().as_basic_value_enum()
-                            }
-                            _ => val,
-                        };
-
-                        builder.build_store(alloca, casted_val).unwrap();
+                    (Expression::Variable(var_name), _) if !variables.contains_key(var_name) => {
+                        let (address, _) = function_address(var_name, Some(llvm_type), module)?
+                            .ok_or_else(|| undefined_variable(var_name, variables.keys()))?;
+                        builder.build_store(alloca, address).unwrap();
                     }
                     (Expression::Variable(var_name), _) => {
-                        let source_var = variables.get(var_name)
-                            .unwrap_or_else(|| panic!("Variable {} not found", var_name));
+                        let source_var = &variables[var_name];
 
                         let loaded_value = builder
-                            .build_load(source_var.ptr, &format!("load_{}", var_name))
+                            .build_load(source_var.ptr, &value_name(&format!("load_{}", var_name)))
                             .unwrap();
 
-                        let loaded_type = loaded_value.get_type();
-
-                        let casted_value = match (loaded_type, llvm_type) {
-                            (BasicTypeEnum::IntType(_), BasicTypeEnum::FloatType(float_ty)) => {
-                                builder.build_signed_int_to_float(
-                                    loaded_value.into_int_value(),
-                                    float_ty,
-                                    "int_to_float",
-                                ).unwrap().as_basic_value_enum()
-                            }
-                            (BasicTypeEnum::FloatType(_), BasicTypeEnum::IntType(int_ty)) => {
-                                builder.build_float_to_signed_int(
-                                    loaded_value.into_float_value(),
-                                    int_ty,
-                                    "float_to_int",
-                                ).unwrap().as_basic_value_enum()
-                            }
-                            _ => loaded_value,
-                        };
+                        let casted_value = coerce_expression(context, builder, module, init, loaded_value, llvm_type, variables, options)?;
 
                         builder.build_store(alloca, casted_value).unwrap();
                     }
                     (Expression::AsmBlock { instructions, inputs, outputs }, BasicTypeEnum::IntType(int_type)) => {
                         use inkwell::InlineAsmDialect;
-                        use inkwell::values::{BasicMetadataValueEnum, CallableValue};
+                        use inkwell::values::BasicMetadataValueEnum;
 
                         let asm_code: String = instructions.join("\n");
                         let mut operand_vals: Vec<BasicMetadataValueEnum> = vec![];
@@ -242,9 +262,9 @@ pub fn generate_statement_ir<'ctx>(
                             let val = if let Ok(num) = var.parse::<i64>() {
                                 context.i64_type().const_int(num as u64, false).into()
                             } else if let Some(info) = variables.get(var) {
-                                builder.build_load(info.ptr, var).unwrap().into()
+                                builder.build_load(info.ptr, &value_name(var)).unwrap().into()
                             } else {
-                                panic!("Input variable '{}' not found", var);
+                                return Err(undefined_variable(var, variables.keys()));
                             };
 
                             operand_vals.push(val);
@@ -273,141 +293,64 @@ pub fn generate_statement_ir<'ctx>(
                             false, // can_throw
                         );
 
-                        let inline_asm_fn = CallableValue::try_from(inline_asm_ptr)
-                            .expect("Failed to cast inline asm to CallableValue");
+                        let inline_asm_fn = asm_callee(inline_asm_ptr)?;
 
                         let call = builder
-                            .build_call(inline_asm_fn, &operand_vals, "inline_asm")
+                            .build_call(inline_asm_fn, &operand_vals, &value_name("inline_asm"))
                             .unwrap();
 
                         if expects_return {
                             let result = call.try_as_basic_value().left()
-                                .expect("Expected return value from inline asm but got none");
+                                .ok_or_else(|| invalid("inline asm", tr!("the `asm` block returned no value for `{0}`", name)))?;
 
                             builder.build_store(alloca, result).unwrap();
                         }
                     }
-                    _ => {
-                        panic!("Unsupported type/value combination for initialization: {:?}", init);
-                    }
+                    (init, _) => return Err(unsupported_construct(
+                        &tr!("initializing a `{0}` variable with {1}", type_name, tr!(describe_expression(init))),
+                        "compute the value in a separate statement, or declare the variable with a matching type",
+                    )),
                 }
             }
         }
-        ASTNode::Statement(StatementNode::Println(message)) |
-        ASTNode::Statement(StatementNode::Print(message)) => {
-            let global_name = format!("str_{}", *string_counter);
+        ASTNode::Statement(StatementNode::Println(message), _) |
+        ASTNode::Statement(StatementNode::Print(message), _) => {
+            let global_name = format!("str_{}_{}", current_function.get_name().to_str().unwrap(), *string_counter);
             *string_counter += 1;
-
-            let mut bytes = message.as_bytes().to_vec();
-            bytes.push(0);
-            let const_str = context.const_string(&bytes, false);
-
-            let global = module.add_global(
-                context.i8_type().array_type(bytes.len() as u32),
-                None,
-                &global_name,
-            );
-            global.set_initializer(&const_str);
-            global.set_linkage(Linkage::Private);
-            global.set_constant(true);
-
-            let printf_type = context.i32_type().fn_type(
-                &[context.i8_type().ptr_type(AddressSpace::default()).into()],
-                true,
-            );
-            let printf_func = match module.get_function("printf") {
-                Some(f) => f,
-                None => module.add_function("printf", printf_type, None),
-            };
-
-            let zero = context.i32_type().const_zero();
-            let indices = [zero, zero];
-            let gep = unsafe {
-                builder.build_gep(global.as_pointer_value(), &indices, "gep").unwrap()
-            };
-
-            let _ = builder.build_call(printf_func, &[gep.into()], "printf_call");
-        }
-        ASTNode::Statement(StatementNode::PrintlnFormat { format, args }) |
-        ASTNode::Statement(StatementNode::PrintFormat { format, args }) => {
-            let mut arg_types = vec![];
-            for arg in args {
-                let val = generate_expression_ir(context, builder, arg, variables, module, None);
-                arg_types.push(val.get_type());
+            if targets_wasm(module) {
+                return generate_host_print(context, builder, module, message, &[], &global_name);
             }
-            let c_format_string = wave_format_to_c(&format, &arg_types);
 
-            let global_name = format!("str_{}", *string_counter);
+            // The text is printf's format, so a `%` in it is written `%%`.
+            let gep = generate_string_literal(context, builder, module, &message.replace('%', "%%"), &global_name);
+            let _ = builder.build_call(printf_function(context, module), &[gep.into()], &value_name("printf_call"));
+        }
+        ASTNode::Statement(StatementNode::PrintlnFormat { format, args }, _) |
+        ASTNode::Statement(StatementNode::PrintFormat { format, args }, _) => {
+            let global_name = format!("str_{}_{}", current_function.get_name().to_str().unwrap(), *string_counter);
             *string_counter += 1;
-
-            let mut bytes = c_format_string.as_bytes().to_vec();
-            bytes.push(0);
-            let const_str = context.const_string(&bytes, false);
-
-            let global = module.add_global(
-                context.i8_type().array_type(bytes.len() as u32),
-                None,
-                &global_name,
-            );
-            global.set_initializer(&const_str);
-            global.set_linkage(Linkage::Private);
-            global.set_constant(true);
-
-            let printf_type = context.i32_type().fn_type(
-                &[context.i8_type().ptr_type(AddressSpace::default()).into()],
-                true,
-            );
-            let printf_func = match module.get_function("printf") {
-                Some(func) => func,
-                None => module.add_function("printf", printf_type, None),
-            };
-
-            let zero = context.i32_type().const_zero();
-            let indices = [zero, zero];
-            let gep = unsafe {
-                builder.build_gep(global.as_pointer_value(), &indices, "gep").unwrap()
-            };
-
-            let mut printf_args = vec![gep.into()];
-            for arg in args {
-                let value = generate_expression_ir(context, builder, arg, variables, module, None);
-
-                let casted_value = match value {
-                    BasicValueEnum::PointerValue(ptr_val) => {
-                        let element_ty = ptr_val.get_type().get_element_type();
-                        if element_ty.is_int_type() && element_ty.into_int_type().get_bit_width() == 8 {
-                            ptr_val.as_basic_value_enum()
-                        } else {
-                            builder
-                                .build_ptr_to_int(ptr_val, context.i64_type(), "ptr_as_int")
-                                .unwrap()
-                                .as_basic_value_enum()
-                        }
-                    }
-                    BasicValueEnum::FloatValue(fv) => {
-                        let double_ty = context.f64_type();
-                        builder
-                            .build_float_ext(fv, double_ty, "cast_to_double")
-                            .unwrap()
-                            .as_basic_value_enum()
-                    }
-                    _ => value,
-                };
-
-                printf_args.push(casted_value.into());
+            let args = args
+                .iter()
+                .map(|arg| {
+                    let value = generate_expression_ir(context, builder, arg, variables, module, None, options)?;
+                    let unsigned = infer_binding_type(arg, variables, module).is_ok_and(|ty| prints_unsigned(&ty));
+                    Ok(PrintArgument { value, unsigned })
+                })
+                .collect::<Result<Vec<_>, CodegenError>>()?;
+            if targets_wasm(module) {
+                return generate_host_print(context, builder, module, format, &args, &global_name);
             }
-
-            let _ = builder.build_call(printf_func, &printf_args, "printf_call");
+            generate_print_format(context, builder, module, format, &args, &global_name);
         }
         ASTNode::Statement(StatementNode::If {
                                condition,
                                body,
                                else_if_blocks,
                                else_block,
-                           }) => {
+                           }, _) => {
             let current_fn = builder.get_insert_block().unwrap().get_parent().unwrap();
 
-            let cond_value = generate_expression_ir(context, builder, condition, variables, module, None);
+            let cond_value = generate_expression_ir(context, builder, condition, variables, module, None, options)?;
 
             let then_block = context.append_basic_block(current_fn, "then");
             let else_block_bb = context.append_basic_block(current_fn, "else");
@@ -418,12 +361,16 @@ pub fn generate_statement_ir<'ctx>(
             let then_has_terminator;
             let else_has_terminator;
 
+            // Each branch is its own scope: its declarations are dropped and shadowed names restored.
+            let outer_scope = variables.clone();
+
             // then
             builder.position_at_end(then_block);
             for stmt in body {
-                generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function);
+                generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function, options)?;
             }
-            then_has_terminator = then_block.get_terminator().is_some();
+            *variables = outer_scope.clone();
+            then_has_terminator = block_terminated(builder);
             if !then_has_terminator {
                 let _ = builder.build_unconditional_branch(merge_block);
             }
@@ -433,71 +380,164 @@ pub fn generate_statement_ir<'ctx>(
 
             if let Some(else_ifs) = else_if_blocks {
                 for else_if in else_ifs.iter() {
-                    generate_statement_ir(context, builder, module, string_counter, else_if, variables, loop_exit_stack, loop_continue_stack, current_function);
+                    generate_statement_ir(context, builder, module, string_counter, else_if, variables, loop_exit_stack, loop_continue_stack, current_function, options)?;
                 }
             }
 
             if let Some(else_body) = else_block {
                 for stmt in else_body.iter() {
-                    generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function);
+                    generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function, options)?;
                 }
             }
-            else_has_terminator = else_block_bb.get_terminator().is_some();
+            *variables = outer_scope;
+            else_has_terminator = block_terminated(builder);
             if !else_has_terminator {
                 let _ = builder.build_unconditional_branch(merge_block);
             }
 
-            if !then_has_terminator || !else_has_terminator {
-                builder.position_at_end(merge_block);
-            }
+            // When both branches leave, `merge` has no predecessors; later code still needs a block,
+            // and the enclosing statement or function end terminates it.
+            builder.position_at_end(merge_block);
         }
-        ASTNode::Statement(StatementNode::While { condition, body }) => {
+        ASTNode::Statement(StatementNode::Match { value, arms }, _) => {
             let current_fn = builder.get_insert_block().unwrap().get_parent().unwrap();
+            let matched = generate_expression_ir(context, builder, value, variables, module, None, options)?;
+
+            // An enum is matched on its tag, and its fields are read from a copy of the value.
+            let (tag, matched_ptr) = match matched {
+                BasicValueEnum::StructValue(enum_value)
+                    if enum_value.get_type().get_name().and_then(|name| name.to_str().ok()).is_some_and(is_enum) =>
+                {
+                    let matched_ptr = builder.build_alloca(enum_value.get_type(), &value_name("matched")).unwrap();
+                    builder.build_store(matched_ptr, enum_value).unwrap();
+                    let tag_ptr = builder.build_struct_gep(matched_ptr, 0, &value_name("tag")).unwrap();
+                    (Some(builder.build_load(tag_ptr, &value_name("tag")).unwrap().into_int_value()), Some(matched_ptr))
+                }
+                BasicValueEnum::IntValue(int_value) => (Some(int_value), None),
+                _ => (None, None),
+            };
 
-            let cond_block = context.append_basic_block(current_fn, "while.cond");
-            let body_block = context.append_basic_block(current_fn, "while.body");
-            let merge_block = context.append_basic_block(current_fn, "while.end");
-
-            loop_exit_stack.push(merge_block);
-            loop_continue_stack.push(cond_block);
-
-            let _ = builder.build_unconditional_branch(cond_block);
-            builder.position_at_end(cond_block);
-
-            let cond_val = generate_expression_ir(context, builder, condition, variables, module, None);
+            let merge_block = context.append_basic_block(current_fn, "match.end");
+            let arm_blocks: Vec<BasicBlock> = arms.iter().map(|_| context.append_basic_block(current_fn, "match.arm")).collect();
+            let mut cases = vec![];
+            for (arm, block) in arms.iter().zip(&arm_blocks) {
+                let case = match (&arm.pattern, tag) {
+                    (Pattern::Variant { enum_name, variant, .. }, Some(tag)) => {
+                        tag.get_type().const_int(enum_variant(enum_name, variant)?.0 as u64, false)
+                    }
+                    (Pattern::Number(number), Some(tag)) => tag.get_type().const_int(*number as u64, true),
+                    _ => continue,
+                };
+                cases.push((case, *block));
+            }
 
-            let cond_bool = match cond_val {
-                BasicValueEnum::IntValue(val) => {
-                    let zero = val.get_type().const_zero();
-                    builder
-                        .build_int_compare(inkwell::IntPredicate::NE, val, zero, "while_cond")
-                        .unwrap()
+            // Unmatched values go to the `_` arm. Type checking makes an enum match without one
+            // list every variant, so its tag cannot miss; an integer that no arm lists skips the match.
+            let switch_block = builder.get_insert_block().unwrap();
+            let wildcard = arms.iter().position(|arm| matches!(arm.pattern, Pattern::Wildcard));
+            let default_block = match wildcard {
+                Some(index) => arm_blocks[index],
+                None if matched_ptr.is_some() => {
+                    let unmatched = context.append_basic_block(current_fn, "match.unmatched");
+                    builder.position_at_end(unmatched);
+                    builder.build_unreachable().unwrap();
+                    unmatched
                 }
-                BasicValueEnum::FloatValue(val) => {
-                    let zero = val.get_type().const_float(0.0);
-                    builder
-                        .build_float_compare(FloatPredicate::ONE, val, zero, "while_cond")
-                        .unwrap()
-                }
-                _ => panic!("Unsupported condition type in while loop"),
+                None => merge_block,
+            };
+            builder.position_at_end(switch_block);
+            match tag {
+                Some(tag) => builder.build_switch(tag, default_block, &cases).unwrap(),
+                None => builder.build_unconditional_branch(default_block).unwrap(),
             };
 
-            let _ = builder.build_conditional_branch(cond_bool, body_block, merge_block);
-
-            builder.position_at_end(body_block);
-            for stmt in body.iter() {
-                generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function);
+            for (arm, block) in arms.iter().zip(arm_blocks) {
+                builder.position_at_end(block);
+                // Each arm is its own scope; the fields it binds are read-only views into the copy.
+                let outer_scope = variables.clone();
+                if let (Pattern::Variant { enum_name, variant, bindings }, Some(matched_ptr)) = (&arm.pattern, matched_ptr) {
+                    let (_, declared) = enum_variant(enum_name, variant)?;
+                    let payload_type = variant_payload_type(context, &declared)?;
+                    let payload_ptr = builder.build_struct_gep(matched_ptr, 1, &value_name("payload")).unwrap();
+                    let payload_ptr = builder.build_pointer_cast(payload_ptr, payload_type.ptr_type(AddressSpace::default()), &value_name(variant)).unwrap();
+                    for (index, (binding, field_type)) in bindings.iter().zip(&declared.fields).enumerate() {
+                        if binding == "_" {
+                            continue;
+                        }
+                        let field_ptr = builder.build_struct_gep(payload_ptr, index as u32, &value_name(binding)).unwrap();
+                        variables.insert(
+                            binding.clone(),
+                            VariableInfo { ptr: field_ptr, mutability: Mutability::Let, ty: field_type.clone(), span: node_span(stmt) },
+                        );
+                    }
+                }
+                for stmt in &arm.body {
+                    generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_function, options)?;
+                }
+                *variables = outer_scope;
+                if !block_terminated(builder) {
+                    let _ = builder.build_unconditional_branch(merge_block);
+                }
             }
-            let _ = builder.build_unconditional_branch(cond_block);
-
-            loop_exit_stack.pop();
-            loop_continue_stack.pop();
 
             builder.position_at_end(merge_block);
         }
-        ASTNode::Statement(StatementNode::AsmBlock { instructions, inputs, outputs }) => {
+        ASTNode::Statement(StatementNode::While { condition, body, label }, _) => {
+            generate_while_ir(context, builder, module, string_counter, Some(condition), None, body, label.as_deref(), variables, loop_exit_stack, loop_continue_stack, options, None)?;
+        }
+        ASTNode::Statement(StatementNode::Loop { body, label }, _) => {
+            generate_while_ir(context, builder, module, string_counter, None, None, body, label.as_deref(), variables, loop_exit_stack, loop_continue_stack, options, None)?;
+        }
+        ASTNode::Statement(StatementNode::For { initialization, condition, increment, body, label }, _) => {
+            generate_expression_ir(context, builder, initialization, variables, module, None, options)?;
+            generate_while_ir(context, builder, module, string_counter, Some(condition), Some(increment), body, label.as_deref(), variables, loop_exit_stack, loop_continue_stack, options, None)?;
+        }
+        // Lowered as `for (; i.next < i.end; i.next += 1)`: `i.next` is a mutable view of the
+        // counter the body sees as immutable `i`, and `i.end` holds the end bound evaluated once.
+        // Neither name can be written in Wave.
+        ASTNode::Statement(StatementNode::ForRange { variable, start, end, body, label }, span) => {
+            // An unsuffixed literal bound takes the type of the other one, as in type checking.
+            let typed = if matches!(start, Expression::Literal(Literal::Number(_))) { end } else { start };
+            let counter_type = infer_binding_type(typed, variables, module)?;
+            let llvm_type = wave_type_to_llvm_type(context, &counter_type)?;
+
+            let start_value = generate_expression_ir(context, builder, start, variables, module, Some(llvm_type), options)?;
+            let start_value = coerce_value(context, builder, module, start_value, llvm_type, options)?;
+            let end_value = generate_expression_ir(context, builder, end, variables, module, Some(llvm_type), options)?;
+            let end_value = coerce_value(context, builder, module, end_value, llvm_type, options)?;
+
+            let counter = builder.build_alloca(llvm_type, &value_name(variable)).unwrap();
+            builder.build_store(counter, start_value).unwrap();
+            let bound = builder.build_alloca(llvm_type, &value_name(&format!("{}.end", variable))).unwrap();
+            builder.build_store(bound, end_value).unwrap();
+
+            let outer_scope = variables.clone();
+            let next = format!("{}.next", variable);
+            let end_name = format!("{}.end", variable);
+            for (name, ptr, mutability) in [
+                (variable.clone(), counter, Mutability::Let),
+                (next.clone(), counter, Mutability::Var),
+                (end_name.clone(), bound, Mutability::Let),
+            ] {
+                variables.insert(name, VariableInfo { ptr, mutability, ty: counter_type.clone(), span: *span });
+            }
+
+            let condition = Expression::BinaryExpression {
+                left: Box::new(Expression::Variable(next.clone())),
+                operator: Operator::Less,
+                right: Box::new(Expression::Variable(end_name)),
+            };
+            let increment = Expression::AssignOperation {
+                target: Box::new(Expression::Variable(next)),
+                operator: AssignOperator::AddAssign,
+                value: Box::new(Expression::Literal(Literal::Number(1))),
+            };
+            generate_while_ir(context, builder, module, string_counter, Some(&condition), Some(&increment), body, label.as_deref(), variables, loop_exit_stack, loop_continue_stack, options, None)?;
+            *variables = outer_scope;
+        }
+        ASTNode::Statement(StatementNode::AsmBlock { instructions, inputs, outputs }, _) => {
             use inkwell::InlineAsmDialect;
-            use inkwell::values::{BasicMetadataValueEnum, CallableValue};
+            use inkwell::values::BasicMetadataValueEnum;
             use std::collections::HashSet;
 
             let asm_code: String = instructions.join("\n");
@@ -509,24 +549,24 @@ pub fn generate_statement_ir<'ctx>(
 
             for (reg, var) in outputs {
                 if input_regs.contains(reg) {
-                    panic!("Register '{}' used in both input and output in inline asm", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is used as both an input and an output", reg)));
                 }
 
                 if !seen_regs.insert(reg.to_string()) {
-                    panic!("Register '{}' duplicated in outputs", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is listed more than once", reg)));
                 }
 
                 let info = variables
                     .get(var)
-                    .unwrap_or_else(|| panic!("Output variable '{}' not found", var));
-                let dummy_val = builder.build_load(info.ptr, var).unwrap().into();
+                    .ok_or_else(|| undefined_variable(var, variables.keys()))?;
+                let dummy_val = builder.build_load(info.ptr, &value_name(var)).unwrap().into();
                 operand_vals.push(dummy_val);
                 constraint_parts.push(format!("={{{}}}", reg)); // e.g., ={rax}
             }
 
             for (reg, var) in inputs {
                 if !seen_regs.insert(reg.to_string()) {
-                    panic!("Register '{}' duplicated in inputs", reg);
+                    return Err(invalid("asm register conflict", tr!("register `{0}` is listed more than once", reg)));
                 }
 
                 let val: BasicMetadataValueEnum = if let Ok(value) = var.parse::<i64>() {
@@ -534,8 +574,8 @@ pub fn generate_statement_ir<'ctx>(
                 } else {
                     let info = variables
                         .get(var)
-                        .unwrap_or_else(|| panic!("Input variable '{}' not found", var));
-                    builder.build_load(info.ptr, var).unwrap().into()
+                        .ok_or_else(|| undefined_variable(var, variables.keys()))?;
+                    builder.build_load(info.ptr, &value_name(var)).unwrap().into()
                 };
 
                 operand_vals.push(val);
@@ -556,11 +596,10 @@ pub fn generate_statement_ir<'ctx>(
                 false,
             );
 
-            let inline_asm_fn = CallableValue::try_from(inline_asm_ptr)
-                .expect("Failed to convert inline asm to CallableValue");
+            let inline_asm_fn = asm_callee(inline_asm_ptr)?;
 
             let call = builder
-                .build_call(inline_asm_fn, &operand_vals, "inline_asm")
+                .build_call(inline_asm_fn, &operand_vals, &value_name("inline_asm"))
                 .unwrap();
 
             if let Some((_, out_var)) = outputs.first() {
@@ -569,92 +608,99 @@ pub fn generate_statement_ir<'ctx>(
                 builder.build_store(ret_ptr, ret_val).unwrap();
             }
         }
-        ASTNode::Statement(StatementNode::Expression(expr)) => {
-            let _ = generate_expression_ir(context, builder, expr, variables, module, None);
+        ASTNode::Statement(StatementNode::Expression(Expression::FunctionCall { name, args }), _) => {
+            generate_call_ir(context, builder, name, args, variables, module, None, options)?;
         }
-        ASTNode::Statement(StatementNode::Assign { variable, value }) => {
+        ASTNode::Statement(StatementNode::Expression(expr), _) => {
+            let _ = generate_expression_ir(context, builder, expr, variables, module, None, options)?;
+        }
+        ASTNode::Statement(StatementNode::Assign { variable, value }, _) => {
             if variable == "deref" {
                 if let Expression::BinaryExpression { left, operator: _, right } = value {
-                    if let Expression::Deref(inner_expr) = &**left {
-                        let target_ptr = generate_address_ir(context, builder, inner_expr, variables, module);
-                        let val = generate_expression_ir(context, builder, right, variables, module, None);
+                    if let Expression::Deref(_) = &**left {
+                        // The address of `deref p` is the value of `p`, not the variable holding it.
+                        let target_ptr = generate_address_ir(context, builder, left, variables, module, options)?;
+                        let pointee = BasicTypeEnum::try_from(target_ptr.get_type().get_element_type()).ok();
+                        let val = generate_expression_ir(context, builder, right, variables, module, pointee, options)?;
+                        let val = match pointee {
+                            Some(pointee) => coerce_value(context, builder, module, val, pointee, options)?,
+                            None => val,
+                        };
                         builder.build_store(target_ptr, val).unwrap();
                     }
                 }
-                return;
+                return Ok(());
             }
 
             let var_info = variables.get(variable)
-                .unwrap_or_else(|| panic!("Variable {} not declared", variable));
+                .ok_or_else(|| undefined_variable(variable, variables.keys()))?;
 
             if matches!(var_info.mutability, Mutability::Let) {
-                panic!("Cannot assign to immutable variable '{}'", variable);
+                return Err(immutable_assignment(variable, var_info.span));
             }
 
-            let element_type = var_info.ptr.get_type().get_element_type();
-
-            let expected_type = match element_type {
-                AnyTypeEnum::IntType(t) => t.as_basic_type_enum(),
-                AnyTypeEnum::FloatType(t) => t.as_basic_type_enum(),
-                AnyTypeEnum::PointerType(t) => t.as_basic_type_enum(),
-                AnyTypeEnum::ArrayType(t) => t.as_basic_type_enum(),
-                AnyTypeEnum::StructType(t) => t.as_basic_type_enum(),
-                AnyTypeEnum::VectorType(t) => t.as_basic_type_enum(),
-                _ => panic!("Unsupported LLVM type in assignment"),
-            };
-
-            let val = generate_expression_ir(context, builder, value, variables, module, Some(expected_type));
+            // Copied out, as generating the value needs `variables` mutably.
+            let (ptr, ty) = (var_info.ptr, var_info.ty.clone());
+            let expected_type = BasicTypeEnum::try_from(ptr.get_type().get_element_type())
+                .map_err(|_| invalid("unsupported assignment", tr!("`{0}` of type `{1}` cannot be assigned to", variable, ty)))?;
 
-            if let Some(var_info) = variables.get(variable) {
-                if matches!(var_info.mutability, Mutability::Let) {
-                    panic!("Cannot assign to immutable variable '{}'", variable);
+            let val = match (value, expected_type) {
+                (Expression::Literal(Literal::Number(literal)), BasicTypeEnum::IntType(int_type)) => {
+                    int_literal(*literal, int_type, literal_signedness(&ty))?.as_basic_value_enum()
                 }
+                _ => generate_expression_ir(context, builder, value, variables, module, Some(expected_type), options)?,
+            };
 
-                let element_type = match var_info.ptr.get_type().get_element_type() {
-                    AnyTypeEnum::IntType(t) => BasicTypeEnum::IntType(t),
-                    AnyTypeEnum::FloatType(t) => BasicTypeEnum::FloatType(t),
-                    AnyTypeEnum::PointerType(t) => BasicTypeEnum::PointerType(t),
-                    AnyTypeEnum::ArrayType(t) => BasicTypeEnum::ArrayType(t),
-                    AnyTypeEnum::StructType(t) => BasicTypeEnum::StructType(t),
-                    AnyTypeEnum::VectorType(t) => BasicTypeEnum::VectorType(t),
-                    _ => panic!("Unsupported LLVM type in assignment"),
-                };
-
-                let casted_val = match (val, element_type) {
-                    (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
-                        builder.build_float_to_signed_int(v, t, "float_to_int").unwrap().as_basic_value_enum()
-                    }
-                    (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => {
-                        builder.build_signed_int_to_float(v, t, "int_to_float").unwrap().as_basic_value_enum()
-                    }
-                    _ => val,
-                };
-                builder.build_store(var_info.ptr, casted_val).unwrap();
-            } else {
-                panic!("Variable {} not declared", variable);
-            }
+            let casted_val = coerce_expression(context, builder, module, value, val, expected_type, variables, options)?;
+            builder.build_store(ptr, casted_val).unwrap();
         }
-        ASTNode::Statement(StatementNode::Break) => {
-            if let Some(target_block) = loop_exit_stack.last() {
-                let _ = builder.build_unconditional_branch(*target_block);
-            } else {
-                panic!("break used outside of loop!");
+        ASTNode::Statement(StatementNode::Break { label, value }, _) => {
+            let Some(index) = target_loop(loop_exit_stack, label.as_deref()) else {
+                return Err(match label {
+                    Some(label) => undeclared_label(label),
+                    None => invalid("break outside loop", tr!("`break` outside of a loop")),
+                });
+            };
+            let exit = &mut loop_exit_stack[index];
+            match (value, &mut exit.values) {
+                (Some(value), Some(values)) => {
+                    let value = generate_expression_ir(context, builder, value, variables, module, values.ty, options)?;
+                    let value = match values.ty {
+                        Some(ty) => coerce_value(context, builder, module, value, ty, options)?,
+                        None => value,
+                    };
+                    values.ty = Some(value.get_type());
+                    values.incoming.push((value, builder.get_insert_block().unwrap()));
+                }
+                (None, None) => {}
+                (Some(_), None) => {
+                    return Err(invalid("break value outside a value loop", tr!("`break` with a value outside a loop used as a value")));
+                }
+                (None, Some(_)) => {
+                    return Err(invalid("break without a value", tr!("`break` without a value in a loop used as a value")));
+                }
             }
+            let _ = builder.build_unconditional_branch(exit.block);
         }
-        ASTNode::Statement(StatementNode::Continue) => {
-            if let Some(target_block) = loop_continue_stack.last() {
-                let _ = builder.build_unconditional_branch(*target_block);
-                builder.build_unreachable().unwrap();
-            } else {
-                panic!("continue used outside of loop!");
-            }
+        ASTNode::Statement(StatementNode::Continue { label }, _) => {
+            // Both loop stacks hold an entry for each enclosing loop.
+            let Some(index) = target_loop(loop_exit_stack, label.as_deref()) else {
+                return Err(match label {
+                    Some(label) => undeclared_label(label),
+                    None => invalid("continue outside loop", tr!("`continue` outside of a loop")),
+                });
+            };
+            let _ = builder.build_unconditional_branch(loop_continue_stack[index]);
         }
-        ASTNode::Statement(StatementNode::Return(expr_opt)) => {
+        ASTNode::Statement(StatementNode::Return(expr_opt), _) => {
             if let Some(expr) = expr_opt {
-                let ret_type = current_function.get_type().get_return_type()
-                    .expect("Function should have a return type");
-                let expected_type = ret_type.try_into()
-                    .expect("Failed to convert return type to BasicTypeEnum");
+                let Some(ret_type) = current_function.get_type().get_return_type() else {
+                    return Err(invalid(
+                        "return value in void function",
+                        tr!("`{0}` has no return type, so `return` cannot carry a value", compile_context().0.unwrap_or_default()),
+                    ));
+                };
+                let expected_type = ret_type;
 
                 let value = generate_expression_ir(
                     context,
@@ -663,33 +709,252 @@ pub fn generate_statement_ir<'ctx>(
                     variables,
                     module,
                     Some(expected_type),
-                );
-
-                let value = match value {
-                    BasicValueEnum::PointerValue(ptr) => {
-                        builder.build_load(ptr, "load_ret").unwrap().as_basic_value_enum()
-                    },
-                    other => other,
-                };
-
-                let casted_value = match (value, expected_type) {
-                    (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
-                        builder.build_float_to_signed_int(v, t, "float_to_int").unwrap().as_basic_value_enum()
-                    }
-                    (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => {
-                        builder.build_signed_int_to_float(v, t, "int_to_float").unwrap().as_basic_value_enum()
-                    }
-                    _ => value,
-                };
+                    options,
+                )?;
 
+                let casted_value = coerce_value(context, builder, module, value, expected_type, options)?;
+                generate_frame_pop(context, builder, module);
                 let _ = builder.build_return(Some(&casted_value));
             } else {
-                let _ = builder.build_return(None);
+                generate_frame_pop(context, builder, module);
+                build_void_return(builder, current_function);
             }
         }
-        ASTNode::Statement(StatementNode::Expression(expr)) => {
-            generate_expression_ir(context, builder, expr, variables, module, None);
+        ASTNode::Statement(StatementNode::Expression(expr), _) => {
+            generate_expression_ir(context, builder, expr, variables, module, None, options)?;
         }
         _ => {}
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
+/// Whether the block being built already ends in a `ret`, `br` or `unreachable`, after which
+/// LLVM accepts no further instructions.
+pub fn block_terminated(builder: &inkwell::builder::Builder) -> bool {
+    builder.get_insert_block().is_some_and(|block| block.get_terminator().is_some())
+}
+
+/// Where a `break` jumps: the block after its loop. A loop used as a value also collects the
+/// values its `break`s pass out.
+pub struct LoopExit<'ctx> {
+    pub block: BasicBlock<'ctx>,
+    pub values: Option<LoopValues<'ctx>>,
+    pub label: Option<String>,
+}
+
+pub struct LoopValues<'ctx> {
+    /// The type of the loop's value: the one its context expects, or that of the first `break`.
+    pub ty: Option<BasicTypeEnum<'ctx>>,
+    /// Each value with the block its `break` leaves from, for the phi that joins them.
+    pub incoming: Vec<(BasicValueEnum<'ctx>, BasicBlock<'ctx>)>,
+}
+
+/// Lowers `while condition { body }`, a `loop` without a condition, or with `increment` the
+/// loop of a `for`, which runs it after the body and on `continue` before testing the condition
+/// again. A `label` lets a `break` or `continue` in a nested loop name this one. With `values`,
+/// the loop is used as a value; the values its `break`s pass out are returned, and the builder
+/// is left at the start of the block after the loop for the caller to join them.
+pub fn generate_while_ir<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    string_counter: &mut usize,
+    condition: Option<&Expression>,
+    increment: Option<&Expression>,
+    body: &[ASTNode],
+    label: Option<&str>,
+    variables: &mut HashMap<String, VariableInfo<'ctx>>,
+    loop_exit_stack: &mut Vec<LoopExit<'ctx>>,
+    loop_continue_stack: &mut Vec<BasicBlock<'ctx>>,
+    options: &CodegenOptions,
+    values: Option<LoopValues<'ctx>>,
+) -> Result<Option<LoopValues<'ctx>>, CodegenError> {
+    let current_fn = builder.get_insert_block().unwrap().get_parent().unwrap();
+
+    let kind = match (condition, increment) {
+        (None, _) => "loop",
+        (_, Some(_)) => "for",
+        _ => "while",
+    };
+    // A `loop` tests nothing: every iteration starts straight at the body.
+    let cond_block = condition.map(|_| context.append_basic_block(current_fn, &format!("{}.cond", kind)));
+    let body_block = context.append_basic_block(current_fn, &format!("{}.body", kind));
+    let header_block = cond_block.unwrap_or(body_block);
+    // The latch ends every iteration, whether the body finishes or `continue`s.
+    let latch_block = match increment {
+        Some(_) => context.append_basic_block(current_fn, "for.step"),
+        None => header_block,
+    };
+    let merge_block = context.append_basic_block(current_fn, &format!("{}.end", kind));
+
+    loop_exit_stack.push(LoopExit { block: merge_block, values, label: label.map(str::to_string) });
+    loop_continue_stack.push(latch_block);
+
+    let _ = builder.build_unconditional_branch(header_block);
+    if let (Some(condition), Some(cond_block)) = (condition, cond_block) {
+        builder.position_at_end(cond_block);
+        let cond_val = generate_expression_ir(context, builder, condition, variables, module, None, options)?;
+
+        let cond_bool = match cond_val {
+            BasicValueEnum::IntValue(val) => {
+                let zero = val.get_type().const_zero();
+                builder
+                    .build_int_compare(inkwell::IntPredicate::NE, val, zero, &value_name("while_cond"))
+                    .unwrap()
+            }
+            BasicValueEnum::FloatValue(val) => {
+                let zero = val.get_type().const_float(0.0);
+                builder
+                    .build_float_compare(FloatPredicate::ONE, val, zero, &value_name("while_cond"))
+                    .unwrap()
+            }
+            _ => return Err(unsupported_construct(
+                "a `while` condition that is not a number",
+                "compare the value explicitly, e.g. `while (x != 0)`",
+            )),
+        };
+
+        let _ = builder.build_conditional_branch(cond_bool, body_block, merge_block);
+    }
+
+    builder.position_at_end(body_block);
+    let outer_scope = variables.clone();
+    for stmt in body.iter() {
+        generate_statement_ir(context, builder, module, string_counter, stmt, variables, loop_exit_stack, loop_continue_stack, current_fn, options)?;
+    }
+    *variables = outer_scope;
+    if !block_terminated(builder) {
+        let _ = builder.build_unconditional_branch(latch_block);
+    }
+
+    if let Some(increment) = increment {
+        builder.position_at_end(latch_block);
+        generate_expression_ir(context, builder, increment, variables, module, None, options)?;
+        let _ = builder.build_unconditional_branch(header_block);
+    }
+
+    let mut values = loop_exit_stack.pop().unwrap().values;
+    loop_continue_stack.pop();
+
+    // A `while` can also end when its condition fails; as a value that only happens for a
+    // condition other than `while true`, which type checking rejects.
+    if let (Some(LoopValues { ty: Some(ty), incoming }), Some(cond_block)) = (&mut values, cond_block) {
+        incoming.push((ty.const_zero(), cond_block));
+    }
+
+    builder.position_at_end(merge_block);
+    Ok(values)
+}
+
+// The index in the loop stacks of the loop a `break` or `continue` leaves: the innermost one,
+// or the innermost with `label`.
+fn target_loop(loop_exit_stack: &[LoopExit], label: Option<&str>) -> Option<usize> {
+    match label {
+        Some(label) => loop_exit_stack.iter().rposition(|exit| exit.label.as_deref() == Some(label)),
+        None => loop_exit_stack.len().checked_sub(1),
+    }
+}
+
+fn undeclared_label(label: &str) -> CodegenError {
+    invalid("undeclared label", tr!("use of undeclared label `'{0}`", label))
+}
+
+// `value` converted to `expected_type` where the two are numbers of different types; a pointer
+// to a value of that type (e.g. a string) is loaded.
+pub fn coerce_value<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    expected_type: BasicTypeEnum<'ctx>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    coerce(context, builder, module, value, false, expected_type, options)
+}
+
+// `value`, the result of `expr`, converted like `coerce_value`, except that an unsigned integer
+// is zero-extended and converts to a float as an unsigned number.
+pub fn coerce_expression<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    expr: &Expression,
+    value: BasicValueEnum<'ctx>,
+    expected_type: BasicTypeEnum<'ctx>,
+    variables: &HashMap<String, VariableInfo<'ctx>>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    let unsigned = is_unsigned_integer(expr, variables);
+    coerce(context, builder, module, value, unsigned, expected_type, options)
+}
+
+// The conversion of `coerce_value`, with integers extended and converted to floats as
+// `unsigned` says.
+fn coerce<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    unsigned: bool,
+    expected_type: BasicTypeEnum<'ctx>,
+    options: &CodegenOptions,
+) -> Result<BasicValueEnum<'ctx>, CodegenError> {
+    let value = match value {
+        BasicValueEnum::PointerValue(ptr) if ptr.get_type().as_basic_type_enum() != expected_type => {
+            builder.build_load(ptr, &value_name("load_ret")).unwrap().as_basic_value_enum()
+        },
+        other => other,
+    };
+
+    let casted_value = match (value, expected_type) {
+        (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) => {
+            generate_float_to_int(context, builder, module, v, t, true, options).as_basic_value_enum()
+        }
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(t)) => {
+            if unsigned || v.get_type().get_bit_width() == 1 {
+                builder.build_unsigned_int_to_float(v, t, &value_name("int_to_float")).unwrap().as_basic_value_enum()
+            } else {
+                builder.build_signed_int_to_float(v, t, &value_name("int_to_float")).unwrap().as_basic_value_enum()
+            }
+        }
+        // Integers widen with their sign, except a `bool`, which is 0 or 1.
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(t)) if v.get_type() != t => {
+            let signed = !unsigned && v.get_type().get_bit_width() > 1;
+            builder.build_int_cast_sign_flag(v, t, signed, &value_name("int_cast")).unwrap().as_basic_value_enum()
+        }
+        (BasicValueEnum::FloatValue(v), BasicTypeEnum::FloatType(t)) if v.get_type() != t => {
+            builder.build_float_cast(v, t, &value_name("float_cast")).unwrap().as_basic_value_enum()
+        }
+        _ => value,
+    };
+    Ok(casted_value)
+}
+
+fn node_span(stmt: &ASTNode) -> Span {
+    match stmt {
+        ASTNode::Variable(VariableNode { span, .. }) | ASTNode::Statement(_, span) => *span,
+        _ => Span::default(),
+    }
+}
+
+fn describe_node(stmt: &ASTNode) -> String {
+    match stmt {
+        ASTNode::Variable(VariableNode { name, .. }) => tr!("declaration of `{0}`", name),
+        ASTNode::Statement(StatementNode::Print(_) | StatementNode::PrintFormat { .. }, _) => "print statement".to_string(),
+        ASTNode::Statement(StatementNode::Println(_) | StatementNode::PrintlnFormat { .. }, _) => "println statement".to_string(),
+        ASTNode::Statement(StatementNode::If { .. }, _) => "if statement".to_string(),
+        ASTNode::Statement(StatementNode::While { .. }, _) => "while loop".to_string(),
+        ASTNode::Statement(StatementNode::For { .. } | StatementNode::ForRange { .. }, _) => "for loop".to_string(),
+        ASTNode::Statement(StatementNode::Loop { .. }, _) => "loop".to_string(),
+        ASTNode::Statement(StatementNode::Match { .. }, _) => "match statement".to_string(),
+        ASTNode::Statement(StatementNode::Assign { variable, .. }, _) => tr!("assignment to `{0}`", variable),
+        ASTNode::Statement(StatementNode::AsmBlock { .. }, _) => "asm block".to_string(),
+        ASTNode::Statement(StatementNode::Return(_), _) => "return statement".to_string(),
+        ASTNode::Statement(StatementNode::Break { .. }, _) => "break statement".to_string(),
+        ASTNode::Statement(StatementNode::Continue { .. }, _) => "continue statement".to_string(),
+        ASTNode::Statement(StatementNode::Expression(Expression::FunctionCall { name, .. }), _) => tr!("call to `{0}`", name),
+        ASTNode::Statement(StatementNode::Expression(_), _) => "expression statement".to_string(),
+        ASTNode::Statement(other, _) => format!("{:?}", other),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,191 @@
+use inkwell::context::Context;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicTypeEnum, StructType};
+use inkwell::values::{BasicValue, FunctionValue, IntValue, PointerValue, StructValue};
+use inkwell::{AddressSpace, IntPredicate};
+use crate::llvm_temporary::heap::malloc_function;
+use crate::llvm_temporary::llvm_codegen::{
+    generate_runtime_trap, generate_string_literal, targets_wasm, unsupported_construct, value_name, CodegenError,
+};
+
+/// The LLVM type of `str`: the address of the first byte and the number of bytes, `{ i8*, i64 }`.
+/// The bytes need not end in a NUL; a literal's do, so it can still be handed to C.
+pub fn str_type<'ctx>(context: &'ctx Context) -> StructType<'ctx> {
+    context.get_struct_type("str").unwrap_or_else(|| {
+        let str_type = context.opaque_struct_type("str");
+        let bytes = context.i8_type().ptr_type(AddressSpace::default());
+        str_type.set_body(&[bytes.into(), context.i64_type().into()], false);
+        str_type
+    })
+}
+
+/// Whether `ty` is `str`. No struct of the program can take the name, `str` being a keyword.
+pub fn is_str_type(ty: BasicTypeEnum) -> bool {
+    matches!(ty, BasicTypeEnum::StructType(struct_type) if struct_type.get_name().is_some_and(|name| name.to_bytes() == b"str"))
+}
+
+/// A `str` of `len` bytes starting at `bytes`.
+pub fn build_str<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    bytes: PointerValue<'ctx>,
+    len: IntValue<'ctx>,
+) -> StructValue<'ctx> {
+    let value = builder.build_insert_value(str_type(context).get_undef(), bytes, 0, &value_name("str_bytes")).unwrap();
+    builder.build_insert_value(value, len, 1, &value_name("str")).unwrap().into_struct_value()
+}
+
+/// The address of the first byte of `value` and the number of bytes.
+pub fn str_parts<'ctx>(builder: &'ctx inkwell::builder::Builder<'ctx>, value: StructValue<'ctx>) -> (PointerValue<'ctx>, IntValue<'ctx>) {
+    let bytes = builder.build_extract_value(value, 0, &value_name("bytes")).unwrap().into_pointer_value();
+    let len = builder.build_extract_value(value, 1, &value_name("len")).unwrap().into_int_value();
+    (bytes, len)
+}
+
+/// A `str` of the constant `value`, stored as a global named `name`.
+pub fn generate_str_literal<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: &str,
+    name: &str,
+) -> StructValue<'ctx> {
+    let bytes = generate_string_literal(context, builder, module, value, name);
+    build_str(context, builder, bytes, context.i64_type().const_int(value.len() as u64, false))
+}
+
+/// `left + right`: the bytes of both in a new buffer from `malloc`, followed by a NUL. The
+/// buffer is never freed. WebAssembly has no allocator to take it from.
+pub fn generate_str_concat<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    left: StructValue<'ctx>,
+    right: StructValue<'ctx>,
+) -> Result<StructValue<'ctx>, CodegenError> {
+    if targets_wasm(module) {
+        return Err(unsupported_construct("concatenating strings on WebAssembly", "print the strings one after the other"));
+    }
+    let i64_type = context.i64_type();
+    let malloc = malloc_function(context, module);
+
+    let (left_bytes, left_len) = str_parts(builder, left);
+    let (right_bytes, right_len) = str_parts(builder, right);
+    let len = builder.build_int_add(left_len, right_len, &value_name("concat_len")).unwrap();
+    let size = builder.build_int_add(len, i64_type.const_int(1, false), &value_name("concat_size")).unwrap();
+    let bytes = builder
+        .build_call(malloc, &[size.into()], &value_name("concat"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    builder.build_memcpy(bytes, 1, left_bytes, 1, left_len).unwrap();
+    let tail = unsafe { builder.build_in_bounds_gep(bytes, &[left_len], &value_name("concat_tail")).unwrap() };
+    builder.build_memcpy(tail, 1, right_bytes, 1, right_len).unwrap();
+    let end = unsafe { builder.build_in_bounds_gep(bytes, &[len], &value_name("concat_end")).unwrap() };
+    builder.build_store(end, context.i8_type().const_zero()).unwrap();
+
+    Ok(build_str(context, builder, bytes, len))
+}
+
+/// `i32` below, at or above zero as `left` sorts before, with or after `right`: byte by byte,
+/// then the shorter first.
+pub fn generate_str_compare<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    left: StructValue<'ctx>,
+    right: StructValue<'ctx>,
+) -> IntValue<'ctx> {
+    builder
+        .build_call(str_compare_function(context, module), &[left.into(), right.into()], &value_name("str_compare"))
+        .unwrap()
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value()
+}
+
+/// `slice(value, start, end)`: the bytes from `start` up to `end`, sharing `value`'s. Aborts
+/// unless `start <= end <= len`.
+pub fn generate_str_slice<'ctx>(
+    context: &'ctx Context,
+    builder: &'ctx inkwell::builder::Builder<'ctx>,
+    module: &'ctx inkwell::module::Module<'ctx>,
+    value: StructValue<'ctx>,
+    start: IntValue<'ctx>,
+    end: IntValue<'ctx>,
+) -> StructValue<'ctx> {
+    let (bytes, len) = str_parts(builder, value);
+    // Compared unsigned, a negative bound is past the end too.
+    let reversed = builder.build_int_compare(IntPredicate::UGT, start, end, &value_name("slice_reversed")).unwrap();
+    let past_end = builder.build_int_compare(IntPredicate::UGT, end, len, &value_name("slice_past_end")).unwrap();
+    let out_of_range = builder.build_or(reversed, past_end, &value_name("slice_out_of_range")).unwrap();
+    generate_runtime_trap(context, builder, module, out_of_range, "string slice out of range");
+
+    let bytes = unsafe { builder.build_in_bounds_gep(bytes, &[start], &value_name("slice_bytes")).unwrap() };
+    let len = builder.build_int_sub(end, start, &value_name("slice_len")).unwrap();
+    build_str(context, builder, bytes, len)
+}
+
+// `wave.str_compare`, defined in the module the first time a comparison needs it. It loops over
+// the bytes itself rather than calling `memcmp`, so it needs no C library.
+fn str_compare_function<'ctx>(context: &'ctx Context, module: &'ctx inkwell::module::Module<'ctx>) -> FunctionValue<'ctx> {
+    if let Some(function) = module.get_function("wave.str_compare") {
+        return function;
+    }
+    let (i32_type, i64_type) = (context.i32_type(), context.i64_type());
+    let fn_type = i32_type.fn_type(&[str_type(context).into(), str_type(context).into()], false);
+    let function = module.add_function("wave.str_compare", fn_type, Some(Linkage::Private));
+
+    // A builder of its own keeps the caller's position and debug location.
+    let builder = context.create_builder();
+    let entry = context.append_basic_block(function, "entry");
+    let head = context.append_basic_block(function, "head");
+    let body = context.append_basic_block(function, "body");
+    let differ = context.append_basic_block(function, "differ");
+    let end = context.append_basic_block(function, "end");
+
+    builder.position_at_end(entry);
+    let left = function.get_nth_param(0).unwrap().into_struct_value();
+    let right = function.get_nth_param(1).unwrap().into_struct_value();
+    let left_bytes = builder.build_extract_value(left, 0, "left_bytes").unwrap().into_pointer_value();
+    let left_len = builder.build_extract_value(left, 1, "left_len").unwrap().into_int_value();
+    let right_bytes = builder.build_extract_value(right, 0, "right_bytes").unwrap().into_pointer_value();
+    let right_len = builder.build_extract_value(right, 1, "right_len").unwrap().into_int_value();
+    let left_shorter = builder.build_int_compare(IntPredicate::ULT, left_len, right_len, "left_shorter").unwrap();
+    let shorter = builder.build_select(left_shorter, left_len, right_len, "shorter").unwrap().into_int_value();
+    builder.build_unconditional_branch(head).unwrap();
+
+    builder.position_at_end(head);
+    let index = builder.build_phi(i64_type, "index").unwrap();
+    let index_value = index.as_basic_value().into_int_value();
+    let done = builder.build_int_compare(IntPredicate::EQ, index_value, shorter, "done").unwrap();
+    builder.build_conditional_branch(done, end, body).unwrap();
+
+    builder.position_at_end(body);
+    let left_byte_ptr = unsafe { builder.build_in_bounds_gep(left_bytes, &[index_value], "left_byte_ptr").unwrap() };
+    let right_byte_ptr = unsafe { builder.build_in_bounds_gep(right_bytes, &[index_value], "right_byte_ptr").unwrap() };
+    let left_byte = builder.build_load(left_byte_ptr, "left_byte").unwrap().into_int_value();
+    let right_byte = builder.build_load(right_byte_ptr, "right_byte").unwrap().into_int_value();
+    let next = builder.build_int_add(index_value, i64_type.const_int(1, false), "next").unwrap();
+    let same = builder.build_int_compare(IntPredicate::EQ, left_byte, right_byte, "same").unwrap();
+    builder.build_conditional_branch(same, head, differ).unwrap();
+    index.add_incoming(&[(&i64_type.const_zero(), entry), (&next, body)]);
+
+    let (below, above) = (i32_type.const_all_ones(), i32_type.const_int(1, false));
+    builder.position_at_end(differ);
+    let byte_below = builder.build_int_compare(IntPredicate::ULT, left_byte, right_byte, "byte_below").unwrap();
+    let ordering = builder.build_select(byte_below, below, above, "ordering").unwrap();
+    builder.build_return(Some(&ordering)).unwrap();
+
+    builder.position_at_end(end);
+    let left_longer = builder.build_int_compare(IntPredicate::UGT, left_len, right_len, "left_longer").unwrap();
+    let by_length = builder.build_select(left_longer, above, i32_type.const_zero(), "by_length").unwrap().into_int_value();
+    let ordering = builder.build_select(left_shorter, below, by_length, "ordering").unwrap();
+    builder.build_return(Some(&ordering.as_basic_value_enum())).unwrap();
+
+    function
+}
@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use lexer::Lexer;
+#[cfg(feature = "llvm")]
+use llvm_temporary::llvm_temporary::llvm_backend::{optimize_ir, BackendError};
+#[cfg(feature = "llvm")]
+use llvm_temporary::llvm_temporary::llvm_codegen::{generate_ir, CodegenOptions, LintLevel};
+use ::parser::*;
+use ::parser::ast::{ASTNode, StatementNode};
+#[cfg(feature = "llvm")]
+use ::parser::ast::{Expression, Literal};
+use ::parser::import::resolve_imports;
+use ::parser::lint::run_lint_passes;
+use ::parser::macros::expand_macros;
+use ::parser::callgraph::CallGraph;
+use ::parser::overload::symbol_name;
+use ::parser::const_eval::fold_constants;
+use ::parser::typeck::TypeChecker;
+use ::parser::validate::{confusable_identifier_warnings, control_flow_diagnostics, lexical_errors, recursion_warning, unused_function_warning, validate_benches, validate_constructors, validate_definitions, validate_main};
+#[cfg(feature = "llvm")]
+use ::parser::visit::walk_expressions;
+use error::error::{Severity, WaveError, WaveErrorKind};
+use error::tr;
+use tracing::debug_span;
+
+/// A compiled program: its IR and what the `--emit` outputs are made from.
+#[cfg(feature = "llvm")]
+pub struct Artifacts {
+    /// LLVM IR of the program, after the passes of `options.opt_level`.
+    pub ir: String,
+    /// Warnings, in the order they were found.
+    pub warnings: Vec<WaveError>,
+    /// The program as compiled: imports merged, macros expanded, functions nothing calls left out.
+    pub ast: Vec<ASTNode>,
+    /// Every source file with the nodes it declares, the main file first.
+    pub units: Vec<(PathBuf, Vec<ASTNode>)>,
+    /// Calls between the functions of the program, before unreachable ones were left out.
+    pub call_graph: CallGraph,
+    /// Functions compilation starts from: `main`, or the `#[bench]` functions under `options.bench`,
+    /// and the `#[ctor]` and `#[dtor]` functions.
+    pub roots: Vec<String>,
+    /// Every file the output depends on: the sources and the files `include_bytes`/`include_str` read.
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// A program that passed every check that comes before code generation.
+pub struct Checked {
+    /// Warnings, in the order they were found.
+    pub warnings: Vec<WaveError>,
+    /// The program to compile: imports merged, macros expanded, functions nothing calls left out.
+    pub ast: Vec<ASTNode>,
+    /// Every source file with the nodes it declares, the main file first.
+    pub units: Vec<(PathBuf, Vec<ASTNode>)>,
+    /// Calls between the functions of the program, before unreachable ones were left out.
+    pub call_graph: CallGraph,
+    /// Functions compilation starts from: `main`, or the `#[bench]` functions when benchmarking,
+    /// and the `#[ctor]` and `#[dtor]` functions.
+    pub roots: Vec<String>,
+    /// The functions left out of `ast`.
+    pub pruned_functions: Vec<String>,
+}
+
+/// Why a program did not compile.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    /// The errors, and the warnings found before them, in the order they were found.
+    pub diagnostics: Vec<WaveError>,
+    /// What stopped compilation when it was not a diagnostic, e.g. an import that was not found.
+    pub summary: Option<String>,
+    /// Whether what stopped it was a bug of the compiler rather than of the program: LLVM
+    /// rejected the IR it generated.
+    pub internal: bool,
+}
+
+impl Diagnostics {
+    fn errors(diagnostics: Vec<WaveError>) -> Self {
+        Diagnostics { diagnostics, summary: None, internal: false }
+    }
+
+    fn stop(diagnostics: Vec<WaveError>, summary: impl Into<String>) -> Self {
+        Diagnostics { diagnostics, summary: Some(summary.into()), internal: false }
+    }
+}
+
+/// Checks the program `source` as far as compilation goes before code generation: lexing,
+/// parsing, imports, macros and the semantic passes. Diagnostics point into `file_path`, and
+/// imports are found in `base_dir`. `bench` checks the `#[bench]` functions instead of `main`.
+/// Needs no LLVM, so it also runs where the backend is left out, as in the playground.
+pub fn check_source(source: &str, file_path: &Path, base_dir: &Path, bench: bool) -> Result<Checked, Diagnostics> {
+    let mut diagnostics = vec![];
+
+    let mut lexer = Lexer::new(source);
+    let tokens = debug_span!("lex").in_scope(|| lexer.tokenize());
+
+    let errors = lexical_errors(&tokens, file_path, source);
+    if !errors.is_empty() {
+        let summary = format!("{} lexical error(s) in '{}'", errors.len(), file_path.display());
+        return Err(Diagnostics::stop(errors, summary));
+    }
+    diagnostics.extend(confusable_identifier_warnings(&tokens, file_path, source));
+
+    let mut ast = match debug_span!("parse").in_scope(|| parse_program(&tokens)) {
+        Ok(ast) => ast,
+        Err(error) => {
+            diagnostics.push(
+                WaveError::new(WaveErrorKind::SyntaxError(error.message.clone()), error.message, file_path.display().to_string(), error.line, error.column)
+                    .with_source(source.lines().nth(error.line.saturating_sub(1)).unwrap_or(""))
+                    .with_label(tr!("parsing stopped here")),
+            );
+            return Err(Diagnostics::stop(diagnostics, "Failed to parse Wave code"));
+        }
+    };
+
+    let imports = debug_span!("imports").entered();
+    let imported = match resolve_imports(file_path, &ast, base_dir) {
+        Ok(imported) => imported,
+        Err(error) => {
+            diagnostics.push(*error);
+            return Err(Diagnostics::errors(diagnostics));
+        }
+    };
+    let mut units = vec![(file_path.to_path_buf(), ast.clone())];
+    units.extend(imported);
+
+    // The files are joined into one program, the imported declarations first.
+    let is_import = |node: &ASTNode| matches!(node, ASTNode::Statement(StatementNode::Import(_), _));
+    let extended_ast: Vec<ASTNode> = units[1..]
+        .iter()
+        .chain(&units[..1])
+        .flat_map(|(_, nodes)| nodes.iter().filter(|node| !is_import(node)).cloned())
+        .collect();
+
+    drop(imports);
+
+    let semantic = debug_span!("semantic").entered();
+    if let Err(error) = validate_definitions(&units).and_then(|()| validate_constructors(&units)) {
+        diagnostics.push(*error);
+        return Err(Diagnostics::errors(diagnostics));
+    }
+
+    ast = match expand_macros(extended_ast) {
+        Ok(expanded) => expanded,
+        Err(message) => return Err(Diagnostics::stop(diagnostics, format!("Macro expansion failed: {}", message))),
+    };
+
+    // `wave bench` swaps the program's own `main` for a generated harness that calls the benchmarks
+    let root_names = if bench {
+        match validate_benches(&units[0].1, file_path) {
+            Ok(benches) => {
+                ast.retain(|node| !matches!(node, ASTNode::Function(function) if function.name == "main"));
+                benches
+            }
+            Err(error) => {
+                diagnostics.push(*error);
+                return Err(Diagnostics::errors(diagnostics));
+            }
+        }
+    } else {
+        if let Err(error) = validate_main(&ast, file_path, source) {
+            diagnostics.push(*error);
+            return Err(Diagnostics::errors(diagnostics));
+        }
+        vec!["main".to_string()]
+    };
+    // The startup and exit code calls the `#[ctor]` and `#[dtor]` functions, of any file. The
+    // call graph has a node per overload, so the roots are the symbols of these functions.
+    let roots: Vec<String> = ast
+        .iter()
+        .filter_map(|node| match node {
+            ASTNode::Function(function)
+                if root_names.contains(&function.name) || function.is_constructor() || function.is_destructor() =>
+            {
+                Some(symbol_name(function, &ast))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Only functions reachable from main are compiled; unused ones in the main file get a warning,
+    // unused library functions from imports are dropped silently.
+    let call_graph = CallGraph::build(&ast);
+    let reachable: HashSet<String> = roots.iter().flat_map(|root| call_graph.reachable_from(root)).collect();
+    if !bench {
+        for node in &units[0].1 {
+            if let ASTNode::Function(function) = node {
+                if !reachable.contains(&symbol_name(function, &ast)) && !function.is_bench() {
+                    diagnostics.push(unused_function_warning(function, file_path));
+                }
+            }
+        }
+    }
+    // Flow, type, lint and recursion checks cover every function parsed, compiled or not,
+    // reported against the file defining it.
+    let mut defined_in: HashMap<&str, &Path> = HashMap::new();
+    for (path, nodes) in &units {
+        for node in nodes {
+            if let ASTNode::Function(function) | ASTNode::Extern(function) = node {
+                defined_in.entry(function.name.as_str()).or_insert(path.as_path());
+            }
+        }
+    }
+    let type_checker = TypeChecker::new(&ast);
+    for node in &ast {
+        if let ASTNode::Extern(function) = node {
+            let file = defined_in.get(function.name.as_str()).copied().unwrap_or(file_path);
+            diagnostics.extend(type_checker.check_extern(function, file));
+        }
+        if let ASTNode::Function(function) = node {
+            let file = defined_in.get(function.name.as_str()).copied().unwrap_or(file_path);
+            diagnostics.extend(control_flow_diagnostics(function, file));
+            diagnostics.extend(type_checker.check_function(function, file));
+            diagnostics.extend(recursion_warning(function, &ast, &call_graph, file));
+            let file_source = if file == file_path { Some(source.to_string()) } else { std::fs::read_to_string(file).ok() };
+            diagnostics.extend(run_lint_passes(function, &ast, file, file_source.as_deref()));
+        }
+    }
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+        return Err(Diagnostics::errors(diagnostics));
+    }
+
+    let symbols: Vec<Option<String>> = ast
+        .iter()
+        .map(|node| match node {
+            ASTNode::Function(function) => Some(symbol_name(function, &ast)),
+            _ => None,
+        })
+        .collect();
+    let is_pruned = |symbol: &Option<String>| symbol.as_ref().is_some_and(|symbol| !reachable.contains(symbol));
+    let pruned_functions = ast
+        .iter()
+        .zip(&symbols)
+        .filter_map(|(node, symbol)| match node {
+            ASTNode::Function(function) if is_pruned(symbol) => Some(function.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut kept = symbols.iter().map(|symbol| !is_pruned(symbol));
+    ast.retain(|_| kept.next().unwrap_or(true));
+    // An overload whose siblings are all pruned is compiled under its plain name.
+    let renamed: HashMap<String, String> = ast
+        .iter()
+        .zip(symbols.iter().filter(|symbol| !is_pruned(symbol)))
+        .filter_map(|(node, symbol)| match (node, symbol) {
+            (ASTNode::Function(function), Some(before)) => {
+                let after = symbol_name(function, &ast);
+                (*before != after).then(|| (before.clone(), after))
+            }
+            _ => None,
+        })
+        .collect();
+    let call_graph = call_graph.renamed(&renamed);
+    tracing::debug!(functions = reachable.len(), "reachable functions");
+    drop(semantic);
+
+    // After the checks, so their diagnostics still see the expressions as written.
+    fold_constants(&mut ast);
+
+    Ok(Checked { warnings: diagnostics, ast, units, call_graph, roots, pruned_functions })
+}
+
+/// Compiles the program `source` to LLVM IR without touching the terminal or the process, for
+/// tools that embed the compiler. Diagnostics point into `options.source_file`, and imports and
+/// includes are found next to it (or in `options.source_dir`, or the working directory).
+/// Running the backend on the IR is up to the caller, as in `wave build`.
+#[cfg(feature = "llvm")]
+pub fn compile_source(source: &str, options: &CodegenOptions) -> Result<Artifacts, Diagnostics> {
+    let file_path = options.source_file.clone().unwrap_or_else(|| PathBuf::from("main.wave"));
+    let file_path = file_path.as_path();
+
+    let base_dir = options.source_dir.clone().unwrap_or_else(|| {
+        file_path.canonicalize()
+            .ok()
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| Path::new(".").to_path_buf())
+    });
+    let Checked { warnings: mut diagnostics, ast, units, call_graph, roots, pruned_functions } =
+        check_source(source, file_path, &base_dir, options.bench)?;
+
+    // `--allow` drops the warnings of a lint and `--deny` makes them errors.
+    let level = |diagnostic: &WaveError| {
+        diagnostic.kind.lint_name().and_then(|lint| options.lint_levels.get(lint)).copied().unwrap_or_default()
+    };
+    diagnostics.retain(|diagnostic| level(diagnostic) != LintLevel::Allow);
+    let mut denied = false;
+    for diagnostic in diagnostics.iter_mut().filter(|diagnostic| level(diagnostic) == LintLevel::Deny) {
+        diagnostic.severity = Severity::Error;
+        denied = true;
+    }
+    if denied {
+        return Err(Diagnostics::errors(diagnostics));
+    }
+
+    let mut function_files = HashMap::new();
+    for (path, nodes) in &units {
+        for node in nodes {
+            if let ASTNode::Function(function) = node {
+                function_files.entry(function.name.clone()).or_insert_with(|| path.clone());
+            }
+        }
+    }
+    function_files.retain(|_, path| path != file_path);
+
+    let options = &CodegenOptions {
+        source_dir: Some(base_dir.clone()),
+        source_file: Some(file_path.to_path_buf()),
+        function_files,
+        pruned_functions,
+        producer: format!("wavec {}", env!("CARGO_PKG_VERSION")),
+        ..options.clone()
+    };
+    // SAFETY: `generate_ir` is unsafe for the GEPs it builds, whose indices come from the
+    // declarations the checks above accepted.
+    let ir = match debug_span!("codegen").in_scope(|| unsafe { generate_ir(&ast, options) }) {
+        Ok(ir) => ir,
+        Err(errors) => {
+            diagnostics.extend(errors.into_iter().map(|error| error.into_diagnostic()));
+            return Err(Diagnostics::errors(diagnostics));
+        }
+    };
+    let ir = match optimize_ir(&ir, options.opt_level) {
+        Ok(ir) => ir,
+        Err(error) => {
+            let internal = matches!(error, BackendError::InvalidIr(_));
+            let summary = Some(format!("Failed to generate machine code: {}", error));
+            return Err(Diagnostics { diagnostics, summary, internal });
+        }
+    };
+
+    let mut dependencies = vec![file_path.canonicalize().unwrap_or_else(|_| file_path.to_path_buf())];
+    dependencies.extend(units[1..].iter().map(|(path, _)| path.clone()));
+    walk_expressions(&ast, &mut |expr| {
+        if let Expression::FunctionCall { name, args } = expr {
+            if let (true, Some(Expression::Literal(Literal::String(path)))) =
+                (name == "include_bytes" || name == "include_str", args.first())
+            {
+                dependencies.push(base_dir.join(path));
+            }
+        }
+    });
+
+    Ok(Artifacts { ir, warnings: diagnostics, ast, units, call_graph, roots, dependencies })
+}
@@ -0,0 +1,289 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use lexer::Lexer;
+use ::parser::ast::{ASTNode, EnumNode, FunctionNode, StructNode};
+use ::parser::import::resolve_imports;
+use ::parser::parse;
+
+/// What `wave doc` writes: one page per module and an index of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "markdown" | "md" => Some(DocFormat::Markdown),
+            "html" => Some(DocFormat::Html),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+}
+
+// A top-level item with the `///` comment above it.
+struct Item<'a> {
+    node: &'a ASTNode,
+    doc: Option<String>,
+}
+
+/// Documents `file_path` and the files it imports, each a module named after the file, into
+/// `out_dir`. Every struct, enum and function is listed with its signature and the `///` lines
+/// right above it; `//!` lines at the top of a file document the module. Wave has no
+/// visibility modifiers, so every top-level item is public. Returns the files written.
+pub fn document(file_path: &Path, format: DocFormat, out_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let source = fs::read_to_string(file_path).map_err(|e| format!("Failed to read '{}': {}", file_path.display(), e))?;
+    let tokens = Lexer::new(&source).tokenize();
+    let ast = parse(&tokens).ok_or_else(|| format!("Failed to parse '{}'", file_path.display()))?;
+
+    let base_dir = file_path
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."));
+    let imported = resolve_imports(file_path, &ast, &base_dir).map_err(|error| error.message)?;
+    let mut modules = vec![(file_path.to_path_buf(), source, ast)];
+    for (found, nodes) in imported {
+        let source = fs::read_to_string(&found).unwrap_or_default();
+        modules.push((found, source, nodes));
+    }
+
+    fs::create_dir_all(out_dir).map_err(|e| format!("Failed to create '{}': {}", out_dir.display(), e))?;
+    let mut written = vec![];
+    let mut index = vec![];
+    for (path, source, nodes) in &modules {
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let items: Vec<Item> = nodes
+            .iter()
+            .filter_map(|node| {
+                let line = match node {
+                    ASTNode::Function(function) | ASTNode::Extern(function) => function.span.line,
+                    ASTNode::Struct(struct_node) => struct_node.span.line,
+                    ASTNode::Enum(enum_node) => enum_node.span.line,
+                    _ => return None,
+                };
+                Some(Item { node, doc: item_doc(source, line) })
+            })
+            .collect();
+        let module_doc = module_doc(source);
+        let page = match format {
+            DocFormat::Markdown => markdown_page(&name, module_doc.as_deref(), &items),
+            DocFormat::Html => html_page(&name, module_doc.as_deref(), &items),
+        };
+        written.push(write(out_dir, &name, format, &page)?);
+        index.push((name, module_doc));
+    }
+
+    let page = match format {
+        DocFormat::Markdown => markdown_index(&index),
+        DocFormat::Html => html_index(&index),
+    };
+    written.push(write(out_dir, "index", format, &page)?);
+    Ok(written)
+}
+
+fn write(out_dir: &Path, name: &str, format: DocFormat, page: &str) -> Result<PathBuf, String> {
+    let path = out_dir.join(name).with_extension(format.extension());
+    fs::write(&path, page).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    Ok(path)
+}
+
+// The `///` lines right above `line`, where the item starts; its attributes may come between.
+fn item_doc(source: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = source.lines().take(line.saturating_sub(1)).collect();
+    let mut doc = vec![];
+    for text in lines.iter().rev().map(|text| text.trim()) {
+        if text.starts_with("#[") && doc.is_empty() {
+            continue;
+        }
+        let Some(comment) = text.strip_prefix("///") else {
+            break;
+        };
+        doc.push(comment.strip_prefix(' ').unwrap_or(comment));
+    }
+    doc.reverse();
+    (!doc.is_empty()).then(|| doc.join("\n"))
+}
+
+// The `//!` lines the file starts with.
+fn module_doc(source: &str) -> Option<String> {
+    let doc: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .map_while(|text| text.strip_prefix("//!"))
+        .map(|comment| comment.strip_prefix(' ').unwrap_or(comment))
+        .collect();
+    (!doc.is_empty()).then(|| doc.join("\n"))
+}
+
+fn name(node: &ASTNode) -> &str {
+    match node {
+        ASTNode::Function(function) | ASTNode::Extern(function) => &function.name,
+        ASTNode::Struct(struct_node) => &struct_node.name,
+        ASTNode::Enum(enum_node) => &enum_node.name,
+        _ => "",
+    }
+}
+
+fn signature(node: &ASTNode) -> String {
+    match node {
+        ASTNode::Function(function) => function_signature(function),
+        ASTNode::Extern(function) => format!("extern \"C\" {};", function_signature(function)),
+        ASTNode::Struct(struct_node) => struct_signature(struct_node),
+        ASTNode::Enum(enum_node) => enum_signature(enum_node),
+        _ => String::new(),
+    }
+}
+
+fn function_signature(function: &FunctionNode) -> String {
+    let mut params: Vec<String> = function
+        .parameters
+        .iter()
+        .map(|param| format!("{}: {}", param.name, param.param_type))
+        .collect();
+    if function.variadic {
+        params.push("...".to_string());
+    }
+    let mut signature = function
+        .attributes
+        .iter()
+        .map(|attribute| match attribute.args.as_slice() {
+            [] => format!("#[{}]\n", attribute.name),
+            args => format!("#[{}({})]\n", attribute.name, args.join(", ")),
+        })
+        .collect::<String>();
+    signature.push_str(&format!("fun {}({})", function.name, params.join("; ")));
+    if let Some(return_type) = &function.return_type {
+        signature.push_str(&format!(" -> {}", return_type));
+    }
+    signature
+}
+
+fn struct_signature(struct_node: &StructNode) -> String {
+    let mut signature = format!("struct {} {{\n", struct_node.name);
+    for field in &struct_node.fields {
+        signature.push_str(&format!("    {}: {};\n", field.name, field.field_type));
+    }
+    signature.push('}');
+    signature
+}
+
+fn enum_signature(enum_node: &EnumNode) -> String {
+    let mut signature = format!("enum {} {{\n", enum_node.name);
+    for variant in &enum_node.variants {
+        if variant.fields.is_empty() {
+            signature.push_str(&format!("    {},\n", variant.name));
+        } else {
+            let fields: Vec<String> = variant.fields.iter().map(ToString::to_string).collect();
+            signature.push_str(&format!("    {}({}),\n", variant.name, fields.join(", ")));
+        }
+    }
+    signature.push('}');
+    signature
+}
+
+// Items under a heading per kind, in declaration order.
+fn sections<'a>(items: &'a [Item<'a>]) -> Vec<(&'static str, Vec<&'a Item<'a>>)> {
+    ["Structs", "Enums", "Functions"]
+        .into_iter()
+        .map(|heading| (heading, items.iter().filter(|item| section(item.node) == heading).collect::<Vec<_>>()))
+        .filter(|(_, items)| !items.is_empty())
+        .collect()
+}
+
+fn section(node: &ASTNode) -> &'static str {
+    match node {
+        ASTNode::Struct(_) => "Structs",
+        ASTNode::Enum(_) => "Enums",
+        _ => "Functions",
+    }
+}
+
+fn markdown_page(module: &str, module_doc: Option<&str>, items: &[Item]) -> String {
+    let mut page = format!("# Module `{}`\n", module);
+    if let Some(doc) = module_doc {
+        page.push_str(&format!("\n{}\n", doc));
+    }
+    for (heading, items) in sections(items) {
+        page.push_str(&format!("\n## {}\n", heading));
+        for item in items {
+            page.push_str(&format!("\n### `{}`\n\n```wave\n{}\n```\n", name(item.node), signature(item.node)));
+            if let Some(doc) = &item.doc {
+                page.push_str(&format!("\n{}\n", doc));
+            }
+        }
+    }
+    page
+}
+
+fn markdown_index(modules: &[(String, Option<String>)]) -> String {
+    let mut page = String::from("# Modules\n\n");
+    for (name, doc) in modules {
+        match doc.as_deref().and_then(|doc| doc.lines().next()) {
+            Some(summary) => page.push_str(&format!("- [`{0}`]({0}.md): {1}\n", name, summary)),
+            None => page.push_str(&format!("- [`{0}`]({0}.md)\n", name)),
+        }
+    }
+    page
+}
+
+fn html_page(module: &str, module_doc: Option<&str>, items: &[Item]) -> String {
+    let mut body = format!("<h1>Module <code>{}</code></h1>\n", escape(module));
+    if let Some(doc) = module_doc {
+        body.push_str(&html_paragraphs(doc));
+    }
+    for (heading, items) in sections(items) {
+        body.push_str(&format!("<h2>{}</h2>\n", heading));
+        for item in items {
+            let name = escape(name(item.node));
+            body.push_str(&format!("<h3 id=\"{0}\"><code>{0}</code></h3>\n", name));
+            body.push_str(&format!("<pre><code>{}</code></pre>\n", escape(&signature(item.node))));
+            if let Some(doc) = &item.doc {
+                body.push_str(&html_paragraphs(doc));
+            }
+        }
+    }
+    html_document(module, &body)
+}
+
+fn html_index(modules: &[(String, Option<String>)]) -> String {
+    let mut body = String::from("<h1>Modules</h1>\n<ul>\n");
+    for (name, doc) in modules {
+        let name = escape(name);
+        body.push_str(&format!("<li><a href=\"{0}.html\"><code>{0}</code></a>", name));
+        if let Some(summary) = doc.as_deref().and_then(|doc| doc.lines().next()) {
+            body.push_str(&format!(": {}", escape(summary)));
+        }
+        body.push_str("</li>\n");
+    }
+    body.push_str("</ul>\n");
+    html_document("Modules", &body)
+}
+
+fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+// Paragraphs are separated by blank lines, as in Markdown.
+fn html_paragraphs(doc: &str) -> String {
+    doc.split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph.trim())))
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
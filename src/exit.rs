@@ -0,0 +1,18 @@
+//! Exit codes of `wavec`, so that a script wrapping it can tell why it stopped. The numbers
+//! are those of BSD's `sysexits.h`, except for errors in the program, which exit with 1 as
+//! C compilers do. Diagnostics go to stderr whatever the code; stdout only carries what a
+//! command outputs, such as the output of the program `wave run` ran. When that program fails,
+//! `wave run` exits with its status instead, as a shell reports it: the program's exit code, or
+//! 128 plus the number of the signal that stopped it.
+
+/// The program has errors, or its executable could not be linked; the diagnostics say which.
+pub const ERROR_EXIT_CODE: i32 = 1;
+
+/// The command line is wrong: an unknown command or option, or a missing argument.
+pub const USAGE_EXIT_CODE: i32 = 64;
+
+/// The compiler itself failed: it panicked, or generated IR that LLVM rejects.
+pub const ICE_EXIT_CODE: i32 = 70;
+
+/// A file could not be read or written, or the built program could not be started.
+pub const IO_EXIT_CODE: i32 = 74;
@@ -0,0 +1,52 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::panic::{self, PanicHookInfo};
+use std::process;
+use colorex::Colorize;
+use llvm_temporary::llvm_temporary::llvm_codegen::compile_context;
+use crate::exit::ICE_EXIT_CODE;
+use crate::version::version;
+
+/// Replaces the default panic output with an internal compiler error report.
+/// Errors in the program are returned as values; a panic is always a compiler bug.
+pub fn install_ice_hook() {
+    panic::set_hook(Box::new(|info| {
+        report_ice(info);
+        process::exit(ICE_EXIT_CODE);
+    }));
+}
+
+fn report_ice(info: &PanicHookInfo) {
+    let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+
+    eprintln!("{} {}", "error: internal compiler error:".color("255,71,71"), message);
+
+    if let Some(location) = info.location() {
+        eprintln!("  --> {}:{}:{}", location.file(), location.line(), location.column());
+    }
+
+    let (function, node) = compile_context();
+    match (function, node) {
+        (Some(function), Some(node)) => eprintln!("note: while compiling {} in function `{}`", node, function),
+        (Some(function), None) => eprintln!("note: while compiling function `{}`", function),
+        (None, Some(node)) => eprintln!("note: while compiling {}", node),
+        (None, None) => {}
+    }
+
+    eprintln!();
+    eprintln!("note: the compiler unexpectedly panicked. this is a bug.");
+    eprintln!("note: please file a report at {}/issues", env!("CARGO_PKG_REPOSITORY"));
+    eprintln!("note: wavec {}", version());
+
+    let backtrace = Backtrace::capture();
+    if backtrace.status() == BacktraceStatus::Captured {
+        eprintln!("\nstack backtrace:\n{}", backtrace);
+    } else {
+        eprintln!("note: run with `RUST_BACKTRACE=1` to include a backtrace");
+    }
+}
@@ -1,11 +1,31 @@
+pub mod compile;
+pub mod doc;
+pub mod exit;
+#[cfg(feature = "llvm")]
+pub mod ice;
+pub mod logging;
+#[cfg(feature = "llvm")]
 pub mod runner;
 pub mod version;
 
+#[cfg(feature = "llvm")]
 use std::path::Path;
 use colorex::Colorize;
 
-pub unsafe fn compile_and_run(path: &Path) {
-    runner::run_wave_file(path);
+pub use compile::{check_source, Checked, Diagnostics};
+#[cfg(feature = "llvm")]
+pub use compile::{compile_source, Artifacts};
+#[cfg(feature = "llvm")]
+pub use llvm_temporary::llvm_temporary::llvm_codegen::{CodegenOptions, FloatToInt, LintLevel, PanicStrategy, ShiftAmount};
+
+#[cfg(feature = "llvm")]
+pub unsafe fn compile_and_run(path: &Path, options: &CodegenOptions) {
+    runner::run_wave_file(path, options);
+}
+
+#[cfg(feature = "llvm")]
+pub unsafe fn compile_to_executable(path: &Path, options: &CodegenOptions, output: &Path) {
+    runner::build_wave_file(path, options, output);
 }
 
 pub fn version_wave() {
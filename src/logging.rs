@@ -0,0 +1,26 @@
+use std::io::IsTerminal;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding a per-module filter, e.g. `WAVE_LOG=parser=trace,llvm_temporary=debug`.
+/// It takes precedence over `-v`/`-vv`.
+pub const LOG_ENV: &str = "WAVE_LOG";
+
+/// Sends compiler logs to stderr. `verbosity` counts `-v` flags: 0 shows warnings only,
+/// 1 adds per-stage timings and debug events, 2 and more traces every lowered node.
+pub fn init_logging(verbosity: usize) {
+    let default = match verbosity {
+        0 => "warn",
+        1 => "debug",
+        _ => "trace",
+    };
+
+    let filter = EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_| EnvFilter::new(default));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+}
@@ -1,10 +1,54 @@
 use std::{env, process};
+use std::path::{Path, PathBuf};
 use colorex::Colorize;
-use wavec::compile_and_run;
+use wavec::{compile_and_run, compile_to_executable};
+use wavec::doc::{document, DocFormat};
+use wavec::exit::{ERROR_EXIT_CODE, USAGE_EXIT_CODE};
+use wavec::ice::install_ice_hook;
+use wavec::logging::init_logging;
 use wavec::version_wave;
+use wavec::{CodegenOptions, FloatToInt, LintLevel, PanicStrategy, ShiftAmount};
+use error::fixes::set_apply_fixes;
+use parser::lint::is_known_lint;
+use error::i18n::{set_locale, Locale};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    install_ice_hook();
+
+    let mut args: Vec<String> = env::args().collect();
+
+    // -v / -vv may appear anywhere on the command line
+    let verbosity: usize = args.iter()
+        .skip(1)
+        .map(|arg| match arg.as_str() {
+            "-v" => 1,
+            "-vv" => 2,
+            _ => 0,
+        })
+        .sum();
+    args.retain(|arg| arg != "-v" && arg != "-vv");
+    init_logging(verbosity);
+
+    // --locale=<tag> picks the diagnostic language; otherwise WAVE_LOCALE, LC_ALL, LC_MESSAGES, LANG
+    let locale = match args.iter().find_map(|arg| arg.strip_prefix("--locale=")) {
+        Some(tag) => match Locale::from_tag(tag) {
+            Some(locale) => locale,
+            None => {
+                eprintln!("{} {} (available: en, ko)",
+                          "Unknown locale:".color("255,71,71"),
+                          tag);
+                process::exit(USAGE_EXIT_CODE);
+            }
+        },
+        None => Locale::from_env(),
+    };
+    set_locale(locale);
+    args.retain(|arg| !arg.starts_with("--locale="));
+
+    if args.iter().any(|arg| arg == "--apply-fixes") {
+        set_apply_fixes(true);
+        args.retain(|arg| arg != "--apply-fixes");
+    }
 
     if args.len() < 2 {
         eprintln!("{} {}",
@@ -18,10 +62,118 @@ fn main() {
                   "run <file>".color("38,139,235"),
                   "Execute the specified Wave file");
 
+        eprintln!("  {}  {}",
+                  "build <file>".color("38,139,235"),
+                  "Compile the file to a native executable (-o <path>; default: the file name)");
+
+        eprintln!("  {}  {}",
+                  "bench <file>".color("38,139,235"),
+                  "Time the #[bench] functions in the file at -O2");
+
+        eprintln!("  {}    {}",
+                  "doc <file>".color("38,139,235"),
+                  "Write Markdown docs from /// comments to target/doc (--format=html, -o <dir>)");
+
+        eprintln!("  {}   {}",
+                  "--check-align".color("38,139,235"),
+                  "Trap on misaligned pointer dereferences (with run or build)");
+
+        eprintln!("  {}      {}",
+                  "--check-ub".color("38,139,235"),
+                  "Trap on null or misaligned derefs, division by zero and signed overflow (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--no-bounds-check".color("38,139,235"),
+                  "Skip the check of array indices against the length (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--emit=dep-info".color("38,139,235"),
+                  "Write a Makefile-style .d file next to the binary (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--emit=stack-usage".color("38,139,235"),
+                  "Write per-function stack frame sizes to a .su file (with run or build)");
+
+        eprintln!("  {}       {}",
+                  "--emit=size".color("38,139,235"),
+                  "Write section and per-function code sizes to a .size file (with run or build)");
+
+        eprintln!("  {}    {}",
+                  "--emit=llvm-ir".color("38,139,235"),
+                  "Write the IR to a .ll file, annotated with the source lines (with run or build)");
+
+        eprintln!("  {}  {}",
+                  "--emit=callgraph".color("38,139,235"),
+                  "Write the call graph to a Graphviz .callgraph.dot file (with run or build)");
+
+        eprintln!("  {}        {}",
+                  "--emit=cfg".color("38,139,235"),
+                  "Write each function's control-flow graph to a Graphviz .cfg.dot file (with run or build)");
+
+        eprintln!("  {}    {}",
+                  "--emit=symbols".color("38,139,235"),
+                  "Write definitions and references with positions to a .symbols.json file (with run or build)");
+
+        eprintln!("  {}     {}",
+                  "--emit=layout".color("38,139,235"),
+                  "Write the size, alignment and field offsets of every struct to a .layout file (with run or build)");
+
+        eprintln!("  {}       {}",
+                  "--emit=wasm".color("38,139,235"),
+                  "Build a WebAssembly module for wasm32-unknown-unknown (with build)");
+
+        eprintln!("  {}        {}",
+                  "--deny=<lint>".color("38,139,235"),
+                  "Make the warnings of a lint, e.g. implicit-conversion or recursion, errors; --allow= silences them (with run or build)");
+
+        eprintln!("  {}   {}",
+                  "-O0 ... -O3".color("38,139,235"),
+                  "Optimization level: inlining, mem2reg, dead code elimination, ... (default -O0)");
+
+        eprintln!("  {}            {}",
+                  "-g".color("38,139,235"),
+                  "Emit debug info for gdb and lldb, and a call stack on runtime errors (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--remap-path-prefix=FROM=TO".color("38,139,235"),
+                  "Record source directories under FROM as under TO in debug info (with -g)");
+
+        eprintln!("  {} {}",
+                  "--panic=<abort|unwind>".color("38,139,235"),
+                  "Let C++/Rust exceptions unwind through Wave frames, or abort (default) (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--shift-amount=<mask|trap>".color("38,139,235"),
+                  "Take shift amounts modulo the width (default), or trap when too large (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--float-to-int=<saturate|trap>".color("38,139,235"),
+                  "Clamp floats converted to integers, NaN to 0 (default), or trap when out of range (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--linker=<name>".color("38,139,235"),
+                  "Link with another linker, e.g. lld (with run or build)");
+
+        eprintln!("  {} {}",
+                  "--target=<triple>".color("38,139,235"),
+                  "Cross-compile, e.g. for aarch64-unknown-linux-gnu; --target-cpu=, --target-features= tune it (with build)");
+
+        eprintln!("  {}   {}",
+                  "--apply-fixes".color("38,139,235"),
+                  "Rewrite the source with the fixes suggested by diagnostics");
+
+        eprintln!("  {} {}",
+                  "--locale=<en|ko>".color("38,139,235"),
+                  "Language of diagnostics (default: from WAVE_LOCALE or LANG)");
+
+        eprintln!("  {}      {}",
+                  "-v, -vv".color("38,139,235"),
+                  "Log compiler stages to stderr (per-module filter: WAVE_LOG)");
+
         eprintln!("  {}     {}",
                   "--version".color("38,139,235"),
                   "Show the CLI version");
-        process::exit(1);
+        process::exit(USAGE_EXIT_CODE);
     }
 
     match args[1].as_str() {
@@ -34,11 +186,101 @@ fn main() {
                 eprintln!("{} {}",
                           "Usage:".color("255,71,71"),
                           "wave run <file>");
-                process::exit(1);
+                process::exit(USAGE_EXIT_CODE);
             }
 
             let file_path = &args[2];
-            compile_and_run(file_path.as_ref());
+            let options = codegen_options(&args[3..]);
+
+            compile_and_run(file_path.as_ref(), &options);
+        }
+        "build" => unsafe {
+            if args.len() < 3 {
+                eprintln!("{} {}",
+                          "Usage:".color("255,71,71"),
+                          "wave build <file> [-o <path>]");
+                process::exit(USAGE_EXIT_CODE);
+            }
+
+            let file_path = Path::new(&args[2]);
+            let mut flags = args[3..].to_vec();
+            let wasm = flags.iter().any(|flag| flag == "--emit=wasm");
+            let output = match flags.iter().position(|flag| flag == "-o") {
+                Some(index) if index + 1 < flags.len() => {
+                    let output = flags.remove(index + 1);
+                    flags.remove(index);
+                    PathBuf::from(output)
+                }
+                Some(_) => {
+                    eprintln!("{} {}",
+                              "Missing path after".color("255,71,71"),
+                              "-o");
+                    process::exit(USAGE_EXIT_CODE);
+                }
+                // Like `cc`, the executable lands in the current directory, named after the source
+                None if wasm => Path::new(file_path.file_stem().unwrap_or(file_path.as_os_str())).with_extension("wasm"),
+                None => PathBuf::from(file_path.file_stem().unwrap_or(file_path.as_os_str())),
+            };
+
+            let options = codegen_options(&flags);
+            compile_to_executable(file_path, &options, &output);
+        }
+        "bench" => unsafe {
+            if args.len() < 3 {
+                eprintln!("{} {}",
+                          "Usage:".color("255,71,71"),
+                          "wave bench <file>");
+                process::exit(USAGE_EXIT_CODE);
+            }
+
+            let options = CodegenOptions {
+                bench: true,
+                opt_level: 2,
+                ..CodegenOptions::default()
+            };
+            compile_and_run(args[2].as_ref(), &options);
+        }
+        "doc" => {
+            if args.len() < 3 {
+                eprintln!("{} {}",
+                          "Usage:".color("255,71,71"),
+                          "wave doc <file> [--format=markdown|html] [-o <dir>]");
+                process::exit(USAGE_EXIT_CODE);
+            }
+
+            let flags = &args[3..];
+            let format = match flags.iter().find_map(|flag| flag.strip_prefix("--format=")) {
+                Some(name) => DocFormat::from_name(name).unwrap_or_else(|| {
+                    eprintln!("{} {} (available: markdown, html)",
+                              "Unknown doc format:".color("255,71,71"),
+                              name);
+                    process::exit(USAGE_EXIT_CODE);
+                }),
+                None => DocFormat::Markdown,
+            };
+            let out_dir = match flags.iter().position(|flag| flag == "-o") {
+                Some(index) => match flags.get(index + 1) {
+                    Some(dir) => PathBuf::from(dir),
+                    None => {
+                        eprintln!("{} {}",
+                                  "Missing path after".color("255,71,71"),
+                                  "-o");
+                        process::exit(USAGE_EXIT_CODE);
+                    }
+                },
+                None => Path::new("target").join("doc"),
+            };
+
+            match document(Path::new(&args[2]), format, &out_dir) {
+                Ok(written) => eprintln!("{} {} files in {}",
+                                         "Documented".color("2,161,47"),
+                                         written.len(),
+                                         out_dir.display()),
+                Err(message) => {
+                    eprintln!("❌ {}", message);
+                    process::exit(ERROR_EXIT_CODE);
+                }
+            }
         }
         "help" => {
             println!("{}", "Options:".color("145,161,2"));
@@ -46,10 +288,134 @@ fn main() {
                      "run <file>".color("38,139,235"),
                      "Run the Wave code.");
 
+            println!("      {}     {}\n",
+                     "build <file>".color("38,139,235"),
+                     "Compile to a native executable; -o <path> names it.");
+
+            println!("      {}     {}\n",
+                     "bench <file>".color("38,139,235"),
+                     "Time the #[bench] functions at -O2.");
+
+            println!("      {}       {}\n",
+                     "doc <file>".color("38,139,235"),
+                     "Document the file and its imports, one page per module; --format=html|markdown, -o <dir>.");
+
+            println!("      {}    {}\n",
+                     "--check-align".color("38,139,235"),
+                     "Trap on misaligned pointer dereferences.");
+
+            println!("      {}       {}\n",
+                     "--check-ub".color("38,139,235"),
+                     "Trap on every source of undefined behavior, each with its own message: null and misaligned derefs, division by zero or of the minimum by -1, and signed overflow.");
+
+            println!("      {} {}\n",
+                     "--no-bounds-check".color("38,139,235"),
+                     "Skip the check of array indices against the array's length.");
+
+            println!("      {}  {}\n",
+                     "--emit=dep-info".color("38,139,235"),
+                     "Write a Makefile-style .d file next to the binary.");
+
+            println!("      {}  {}\n",
+                     "--emit=stack-usage".color("38,139,235"),
+                     "Write per-function stack frame sizes to a .su file.");
+
+            println!("      {}         {}\n",
+                     "--emit=size".color("38,139,235"),
+                     "Write section and per-function code sizes to a .size file.");
+
+            println!("      {}      {}\n",
+                     "--emit=llvm-ir".color("38,139,235"),
+                     "Write the IR to a .ll file, with `; line N: <source>` above each line's instructions.");
+
+            println!("      {}    {}\n",
+                     "--emit=callgraph".color("38,139,235"),
+                     "Write the call graph to a .callgraph.dot file; functions main never reaches are dashed.");
+
+            println!("      {}          {}\n",
+                     "--emit=cfg".color("38,139,235"),
+                     "Write every function's control-flow graph to a .cfg.dot file, one cluster per function.");
+
+            println!("      {}      {}\n",
+                     "--emit=symbols".color("38,139,235"),
+                     "Write a .symbols.json index of functions, parameters and variables with every reference, for editors.");
+
+            println!("      {}       {}\n",
+                     "--emit=layout".color("38,139,235"),
+                     "Write a .layout file with the size and alignment of every struct on the target, and the offset and size of each field.");
+
+            println!("      {}         {}\n",
+                     "--emit=wasm".color("38,139,235"),
+                     "Build a .wasm module instead of an executable; println calls the host's wave.print_* imports.");
+
+            println!("      {}       {}\n",
+                     "--deny=<lint>".color("38,139,235"),
+                     "Make the lint's warnings errors: implicit-conversion, recursion or a registered lint pass; --allow=<lint> silences them.");
+
+            println!("      {}      {}\n",
+                     "-O0 ... -O3".color("38,139,235"),
+                     "Optimize the executable: -O1 and up inline, promote locals to registers and drop dead code.");
+
+            println!("      {}               {}\n",
+                     "-g".color("38,139,235"),
+                     "Emit DWARF debug info so gdb and lldb can step through the source; a failed runtime check also prints the Wave call stack.");
+
+            println!("      {} {}\n",
+                     "--remap-path-prefix=FROM=TO".color("38,139,235"),
+                     "Write a source directory under FROM as under TO in the debug info, e.g. FROM the checkout and TO `.`, so the build does not depend on where it happens.");
+
+            println!("      {} {}\n",
+                     "--panic=<abort|unwind>".color("38,139,235"),
+                     "unwind gives every function an unwind table so foreign exceptions pass through; abort marks them nounwind.");
+
+            println!("      {} {}\n",
+                     "--shift-amount=<mask|trap>".color("38,139,235"),
+                     "What shift_left and shift_right do with an amount of the width or more: mask it like the hardware, or trap. --check-ub traps.");
+
+            println!("      {} {}\n",
+                     "--float-to-int=<saturate|trap>".color("38,139,235"),
+                     "What converting a NaN or out-of-range float to an integer gives: the nearest value, 0 for NaN, or a trap. --check-ub traps.");
+
+            println!("      {}  {}\n",
+                     "--linker=<name>".color("38,139,235"),
+                     "Link with another linker, e.g. lld (passed to cc as -fuse-ld).");
+
+            println!("      {} {}\n",
+                     "--target=<triple>".color("38,139,235"),
+                     "Generate code for another machine, e.g. aarch64-unknown-linux-gnu; links with clang.");
+
+            println!("      {} {}\n",
+                     "--target-cpu=<name>".color("38,139,235"),
+                     "CPU to generate code for, `native` for this one (default: generic).");
+
+            println!("      {} {}\n",
+                     "--target-features=<list>".color("38,139,235"),
+                     "CPU features to add or remove, e.g. +avx2,-sse4.1.");
+
+            println!("      {}    {}\n",
+                     "--apply-fixes".color("38,139,235"),
+                     "Rewrite the source with the fixes suggested by diagnostics.");
+
+            println!("      {} {}\n",
+                     "--locale=<en|ko>".color("38,139,235"),
+                     "Language of diagnostics; defaults to WAVE_LOCALE, then LC_ALL/LC_MESSAGES/LANG.");
+
+            println!("      {}          {}\n",
+                     "-v, -vv".color("38,139,235"),
+                     "Log compiler stages to stderr; WAVE_LOG=<module>=<level> filters per module.");
+
             println!("{}", "Commands:".color("145,161,2"));
             println!("      {}    {}\n",
                      "-V, --version".color("38,139,235"),
                      "Verified the version of the Wave compiler.");
+
+            println!("{}", "Exit status:".color("145,161,2"));
+            println!("      {} {}\n",
+                     "0, 1, 64, 70, 74".color("38,139,235"),
+                     "Success, errors in the program, a wrong command line, a compiler bug, a file that could not be read or written. Diagnostics always go to stderr.");
+            println!("      {}         {}\n",
+                     "wave run".color("38,139,235"),
+                     "Exits with the status of the program when it fails: its exit code, or 128 plus the signal that stopped it, e.g. 134 for a failed runtime check.");
             return;
         }
         _ => {
@@ -58,7 +424,97 @@ fn main() {
                       args[1]);
             eprintln!("{}",
                       "Use 'wave --version' or 'wave run <file>'".color("145,161,2"));
-            process::exit(1);
+            process::exit(USAGE_EXIT_CODE);
+        }
+    }
+}
+
+// Flags shared by `run` and `build`; an unknown one ends the process.
+fn codegen_options(flags: &[String]) -> CodegenOptions {
+    let mut options = CodegenOptions::default();
+    for flag in flags {
+        match flag.as_str() {
+            "--check-align" => options.check_alignment = true,
+            "--check-ub" => options.check_ub = true,
+            "--no-bounds-check" => options.no_bounds_check = true,
+            "--emit=dep-info" => options.emit_dep_info = true,
+            "--emit=stack-usage" => options.emit_stack_usage = true,
+            "--emit=size" => options.emit_size = true,
+            "--emit=llvm-ir" => options.emit_llvm_ir = true,
+            "--emit=callgraph" => options.emit_callgraph = true,
+            "--emit=cfg" => options.emit_cfg = true,
+            "--emit=symbols" => options.emit_symbols = true,
+            "--emit=layout" => options.emit_layout = true,
+            "--emit=wasm" => options.target.triple = Some("wasm32-unknown-unknown".to_string()),
+            "-O0" | "-O1" | "-O2" | "-O3" => options.opt_level = flag[2..].parse().unwrap(),
+            "-g" => options.debug_info = true,
+            _ if flag.starts_with("--remap-path-prefix=") => match flag["--remap-path-prefix=".len()..].split_once('=') {
+                Some((from, to)) => options.remap_path_prefix.push((PathBuf::from(from), PathBuf::from(to))),
+                None => {
+                    eprintln!("{} {}",
+                              "Invalid path remapping:".color("255,71,71"),
+                              flag);
+                    eprintln!("{}",
+                              "Use --remap-path-prefix=FROM=TO".color("145,161,2"));
+                    process::exit(USAGE_EXIT_CODE);
+                }
+            },
+            _ if flag.starts_with("--panic=") => match PanicStrategy::from_name(&flag["--panic=".len()..]) {
+                Some(panic) => options.panic = panic,
+                None => {
+                    eprintln!("{} {}",
+                              "Unknown panic strategy:".color("255,71,71"),
+                              flag);
+                    eprintln!("{}",
+                              "Use --panic=abort or --panic=unwind".color("145,161,2"));
+                    process::exit(USAGE_EXIT_CODE);
+                }
+            },
+            _ if flag.starts_with("--shift-amount=") => match ShiftAmount::from_name(&flag["--shift-amount=".len()..]) {
+                Some(shift_amount) => options.shift_amount = shift_amount,
+                None => {
+                    eprintln!("{} {}",
+                              "Unknown shift amount handling:".color("255,71,71"),
+                              flag);
+                    eprintln!("{}",
+                              "Use --shift-amount=mask or --shift-amount=trap".color("145,161,2"));
+                    process::exit(USAGE_EXIT_CODE);
+                }
+            },
+            _ if flag.starts_with("--float-to-int=") => match FloatToInt::from_name(&flag["--float-to-int=".len()..]) {
+                Some(float_to_int) => options.float_to_int = float_to_int,
+                None => {
+                    eprintln!("{} {}",
+                              "Unknown float-to-int conversion:".color("255,71,71"),
+                              flag);
+                    eprintln!("{}",
+                              "Use --float-to-int=saturate or --float-to-int=trap".color("145,161,2"));
+                    process::exit(USAGE_EXIT_CODE);
+                }
+            },
+            _ if flag.starts_with("--linker=") => {
+                options.linker = flag.strip_prefix("--linker=").map(str::to_string);
+            }
+            _ if flag.starts_with("--target=") => {
+                options.target.triple = flag.strip_prefix("--target=").map(str::to_string);
+            }
+            _ if flag.starts_with("--target-cpu=") => {
+                options.target.cpu = flag.strip_prefix("--target-cpu=").map(str::to_string);
+            }
+            _ if flag.starts_with("--target-features=") => {
+                options.target.features = flag.strip_prefix("--target-features=").map(str::to_string);
+            }
+            _ if LintLevel::from_flag(flag).is_some_and(|(_, lint)| lint == "implicit-conversion" || is_known_lint(lint)) => {
+                let (level, lint) = LintLevel::from_flag(flag).unwrap();
+                options.lint_levels.insert(lint.to_string(), level);
+            }
+            _ => {
+                eprintln!("{} {}",
+                          "Unknown option:".color("255,71,71"),
+                          flag);
+                process::exit(USAGE_EXIT_CODE);
+            }
         }
     }
+    options
 }
@@ -1,64 +1,358 @@
 use std::{fs, process, process::Command};
 use std::collections::HashSet;
-use std::path::Path;
-use lexer::Lexer;
+use std::path::{Path, PathBuf};
 use llvm_temporary::llvm_temporary::llvm_backend::*;
 use llvm_temporary::llvm_temporary::llvm_codegen::*;
-use ::parser::*;
-use ::parser::ast::{ASTNode, StatementNode};
-use ::parser::import::local_import;
-
-pub(crate) unsafe fn run_wave_file(file_path: &Path) {
-    let code = fs::read_to_string(file_path).expect("Failed to read file");
-
-    let mut lexer = Lexer::new(&code);
-    let tokens = lexer.tokenize();
-
-    let mut ast = parse(&tokens).expect("Failed to parse Wave code");
-
-    let file_path = Path::new(file_path);
-    let base_dir = file_path.canonicalize()
-        .ok()
-        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-        .unwrap_or_else(|| Path::new(".").to_path_buf());
-
-    let mut already_imported = HashSet::new();
-    let mut extended_ast = vec![];
-
-    for node in &ast {
-        if let ASTNode::Statement(StatementNode::Import(path)) = node {
-            if !path.starts_with("std::") {
-                if let Some(mut imported_nodes) = local_import(&path, &mut already_imported, &base_dir) {
-                    extended_ast.append(&mut imported_nodes);
-                } else {
-                    eprintln!("❌ Failed to import '{}'", path);
-                    process::exit(1);
-                }
+use ::parser::ast::ASTNode;
+use ::parser::callgraph::CallGraph;
+use ::parser::cfg::Cfg;
+use ::parser::overload::symbol_name;
+use ::parser::symbols::SymbolIndex;
+use error::error::WaveError;
+use error::fixes::{apply_fixes_enabled, apply_suggestions};
+use tracing::debug_span;
+use crate::compile::{compile_source, Artifacts, Diagnostics};
+use crate::exit::{ERROR_EXIT_CODE, ICE_EXIT_CODE, IO_EXIT_CODE, USAGE_EXIT_CODE};
+
+/// Builds `file_path` into an executable under `target/` and runs it, then exits with the
+/// program's status when it fails, so `wave run` fails the way the program does.
+pub(crate) unsafe fn run_wave_file(file_path: &Path, options: &CodegenOptions) {
+    if options.target.is_cross() {
+        eprintln!("❌ A program built for `{}` cannot run on this machine; use `wave build` instead",
+                  options.target.triple.as_deref().unwrap_or_default());
+        process::exit(USAGE_EXIT_CODE);
+    }
+    let file_stem = file_path.file_stem().unwrap().to_str().unwrap();
+    let machine_code_path = Path::new("target").join(file_stem);
+    build_wave_file(file_path, options, &machine_code_path);
+
+    let output = match debug_span!("execute").in_scope(|| Command::new(&machine_code_path).output()) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("❌ Failed to execute '{}': {}", machine_code_path.display(), e);
+            process::exit(IO_EXIT_CODE);
+        }
+    };
+    tracing::debug!(status = %output.status, "program exited");
+
+    // println!("Generated LLVM IR:\n{}", ir);
+    println!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        process::exit(program_exit_code(output.status));
+    }
+}
+
+// The status a shell reports for a program: its exit code, or 128 plus the signal that killed it,
+// such as 134 for the `SIGABRT` of a failed runtime check.
+fn program_exit_code(status: process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(ERROR_EXIT_CODE)
+}
+
+// Each compiler stage runs in its own span; `-v` prints how long each one took.
+pub(crate) unsafe fn build_wave_file(file_path: &Path, options: &CodegenOptions, output_path: &Path) {
+    let _compile = tracing::info_span!("compile", file = %file_path.display()).entered();
+    if let Err(message) = check_target(&options.target) {
+        eprintln!("❌ {}", message);
+        process::exit(USAGE_EXIT_CODE);
+    }
+
+    let code = match fs::read_to_string(file_path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("❌ Failed to read '{}': {}", file_path.display(), e);
+            process::exit(IO_EXIT_CODE);
+        }
+    };
+
+    let options = &CodegenOptions { source_file: Some(file_path.to_path_buf()), ..options.clone() };
+    let Artifacts { ir, warnings, ast, units, call_graph, roots, dependencies } = match compile_source(&code, options) {
+        Ok(artifacts) => artifacts,
+        Err(Diagnostics { diagnostics, summary, internal }) => {
+            report(&diagnostics);
+            if let Some(summary) = summary {
+                eprintln!("❌ {}", summary);
             }
-        } else {
-            extended_ast.push(node.clone());
+            process::exit(if internal { ICE_EXIT_CODE } else { ERROR_EXIT_CODE });
         }
+    };
+    report(&warnings);
+    let machine_code_path = output_path.display().to_string();
+
+    // Before linking, so that a cross build without a linker for its target still has it.
+    if options.emit_layout {
+        write_layout(&machine_code_path, &ast, &options.target);
+    }
+
+    let linked = debug_span!("backend").in_scope(|| {
+        compile_ir_to_machine_code(&ir, output_path, options.opt_level, options.linker.as_deref(), &options.target)
+    });
+    if let Err(error) = linked {
+        exit_backend_failure("generate machine code", error);
+    }
+
+    if options.emit_dep_info {
+        write_dep_info(&machine_code_path, &dependencies);
     }
 
-    ast = extended_ast;
+    if options.emit_stack_usage {
+        write_stack_usage(&machine_code_path, &ir, &ast, &call_graph, &units, &options.target);
+    }
 
-    // println!("{}\n", code);
-    // println!("AST:\n{:#?}", ast);
+    if options.emit_size {
+        write_size_report(&machine_code_path, &ir, &options.target);
+    }
 
-    let ir = generate_ir(&ast);
-    let path = Path::new(file_path);
-    let file_stem = path.file_stem().unwrap().to_str().unwrap();
-    let machine_code_path = compile_ir_to_machine_code(&ir, file_stem);
+    if options.emit_llvm_ir {
+        write_annotated_ir(&machine_code_path, &ir, &ast, &units);
+    }
 
-    if machine_code_path.is_empty() {
-        eprintln!("Failed to generate machine code");
-        return;
+    if options.emit_callgraph {
+        write_dot(&format!("{}.callgraph.dot", machine_code_path), &call_graph.to_dot(&roots));
     }
 
-    let output = Command::new(machine_code_path)
-        .output()
-        .expect("Failed to execute machine code");
+    if options.emit_cfg {
+        write_cfg(&machine_code_path, &ast, &units);
+    }
 
-    // println!("Generated LLVM IR:\n{}", ir);
-    println!("{}", String::from_utf8_lossy(&output.stdout));
+    if options.emit_symbols {
+        write_symbols(&machine_code_path, &units);
+    }
+}
+
+// Under `--apply-fixes` the suggestions are written into the sources once everything is printed.
+fn report(diagnostics: &[WaveError]) {
+    for diagnostic in diagnostics {
+        diagnostic.display();
+    }
+    if apply_fixes_enabled() {
+        apply_suggestions(&diagnostics.iter().collect::<Vec<_>>());
+    }
+}
+
+// Makefile syntax: `output: deps...`, plus an empty rule per dependency so deleted files don't break make
+fn write_dep_info(output_path: &str, dependencies: &[PathBuf]) {
+    let mut seen = HashSet::new();
+    let deps: Vec<String> = dependencies
+        .iter()
+        .map(|p| p.display().to_string().replace(' ', "\\ "))
+        .filter(|p| seen.insert(p.clone()))
+        .collect();
+
+    let mut contents = format!("{}: {}\n", output_path, deps.join(" "));
+    for dep in &deps {
+        contents.push_str(&format!("\n{}:\n", dep));
+    }
+
+    let dep_path = format!("{}.d", output_path);
+    if let Err(e) = fs::write(&dep_path, contents) {
+        eprintln!("❌ Failed to write dep-info '{}': {}", dep_path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+// `what` is what failed, e.g. "generate machine code"; the exit code tells a compiler bug apart.
+fn exit_backend_failure(what: &str, error: BackendError) -> ! {
+    eprintln!("❌ Failed to {}: {}", what, error);
+    process::exit(match error {
+        BackendError::InvalidIr(_) => ICE_EXIT_CODE,
+        BackendError::Io(_) => IO_EXIT_CODE,
+        BackendError::Failed(_) => ERROR_EXIT_CODE,
+    });
+}
+
+// GCC `-fstack-usage` layout: `file:line:column:function<TAB>bytes<TAB>qualifiers`.
+// `dynamic` marks frames that grow inside loops; `recursive` means the total depth is unbounded.
+fn write_stack_usage(
+    output_path: &str,
+    ir: &str,
+    ast: &[ASTNode],
+    call_graph: &CallGraph,
+    units: &[(PathBuf, Vec<ASTNode>)],
+    target: &TargetOptions,
+) {
+    let mut contents = String::new();
+    let frames = frame_usage(ir, target).unwrap_or_else(|error| exit_backend_failure("measure stack usage", error));
+    for frame in frames {
+        let Some(function) = ast.iter().find_map(|node| match node {
+            ASTNode::Function(function) if symbol_name(function, ast) == frame.function => Some(function),
+            _ => None,
+        }) else {
+            continue;
+        };
+
+        let file = units
+            .iter()
+            .find(|(_, nodes)| nodes.iter().any(|node| matches!(node, ASTNode::Function(f) if symbol_name(f, ast) == frame.function)))
+            .map(|(path, _)| path.display().to_string())
+            .unwrap_or_default();
+
+        let mut qualifiers = vec![if frame.dynamic { "dynamic" } else { "static" }];
+        if call_graph.is_recursive(&frame.function) {
+            qualifiers.push("recursive");
+        }
+
+        contents.push_str(&format!(
+            "{}:{}:{}:{}\t{}\t{}\n",
+            file, function.span.line, function.span.column, function.name, frame.bytes, qualifiers.join(","),
+        ));
+    }
+
+    let su_path = format!("{}.su", output_path);
+    if let Err(e) = fs::write(&su_path, contents) {
+        eprintln!("❌ Failed to write stack usage '{}': {}", su_path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+// The IR the executable was built from, with `; line N: <source>` above the instructions each
+// source line produced. The line is the `.L<line>` codegen puts in value names; instructions
+// without a name, such as stores and branches, go with the line above them.
+fn write_annotated_ir(output_path: &str, ir: &str, ast: &[ASTNode], units: &[(PathBuf, Vec<ASTNode>)]) {
+    let sources: Vec<Vec<String>> = units
+        .iter()
+        .map(|(path, _)| fs::read_to_string(path).unwrap_or_default().lines().map(str::to_string).collect())
+        .collect();
+
+    let mut contents = String::new();
+    let mut source: &[String] = &[];
+    let mut last_line = None;
+    for instruction in ir.lines() {
+        if let Some(signature) = instruction.strip_prefix("define ") {
+            let name = signature.split_once('@').and_then(|(_, rest)| rest.split_once('(')).map(|(name, _)| name);
+            source = units
+                .iter()
+                .position(|(_, nodes)| nodes.iter().any(|node| matches!(node, ASTNode::Function(f) if Some(symbol_name(f, ast).as_str()) == name)))
+                .map_or(&[], |unit| &sources[unit]);
+            last_line = None;
+        } else if let Some(line) = value_line(instruction).filter(|&line| last_line != Some(line)) {
+            if let Some(text) = source.get(line - 1) {
+                let indent = &instruction[..instruction.len() - instruction.trim_start().len()];
+                contents.push_str(&format!("{}; line {}: {}\n", indent, line, text.trim()));
+            }
+            last_line = Some(line);
+        }
+        contents.push_str(instruction);
+        contents.push('\n');
+    }
+
+    let ll_path = format!("{}.ll", output_path);
+    if let Err(e) = fs::write(&ll_path, contents) {
+        eprintln!("❌ Failed to write IR '{}': {}", ll_path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+// One cluster per compiled function, in source order, its blocks quoting the file defining it.
+// Clusters are named by symbol: Graphviz merges clusters of the same name, as of two overloads.
+fn write_cfg(output_path: &str, ast: &[ASTNode], units: &[(PathBuf, Vec<ASTNode>)]) {
+    let mut dot = String::from("digraph cfg {\n  node [fontname=monospace];\n");
+    for node in ast {
+        let ASTNode::Function(function) = node else {
+            continue;
+        };
+        let symbol = symbol_name(function, ast);
+        let source = units
+            .iter()
+            .find(|(_, nodes)| nodes.iter().any(|node| matches!(node, ASTNode::Function(f) if symbol_name(f, ast) == symbol)))
+            .and_then(|(path, _)| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        dot.push_str(&Cfg::build(function).to_dot(&symbol, &source));
+    }
+    dot.push_str("}\n");
+    write_dot(&format!("{}.cfg.dot", output_path), &dot);
+}
+
+// Indexed from the sources as written, so positions match what the editor shows.
+fn write_symbols(output_path: &str, units: &[(PathBuf, Vec<ASTNode>)]) {
+    let files: Vec<(PathBuf, String)> = units
+        .iter()
+        .map(|(path, _)| (path.clone(), fs::read_to_string(path).unwrap_or_default()))
+        .collect();
+    let path = format!("{}.symbols.json", output_path);
+    if let Err(e) = fs::write(&path, SymbolIndex::build(&files).to_json()) {
+        eprintln!("❌ Failed to write symbol index '{}': {}", path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+// Each struct in Wave syntax, with its size and alignment and each field's offset and size in
+// comments, and the padding the alignment of the next field or of the struct leaves.
+fn write_layout(output_path: &str, ast: &[ASTNode], target: &TargetOptions) {
+    let layouts = match struct_layouts(ast, target) {
+        Ok(layouts) => layouts,
+        Err(error) => {
+            error.into_diagnostic().display();
+            process::exit(ERROR_EXIT_CODE);
+        }
+    };
+
+    let mut contents = String::new();
+    for layout in &layouts {
+        let mut lines = vec![(format!("struct {} {{", layout.name), format!("size {}, align {}", layout.size, layout.align))];
+        for (i, field) in layout.fields.iter().enumerate() {
+            lines.push((format!("    {}: {};", field.name, field.ty), format!("offset {}, size {}", field.offset, field.size)));
+            let next = layout.fields.get(i + 1).map_or(layout.size, |next| next.offset);
+            let padding = next - (field.offset + field.size);
+            if padding > 0 {
+                lines.push((String::new(), format!("{} byte(s) of padding", padding)));
+            }
+        }
+        let width = lines.iter().map(|(code, _)| code.len()).max().unwrap_or(0);
+        for (code, comment) in lines {
+            contents.push_str(&format!("{:<width$}  // {}\n", code, comment, width = width));
+        }
+        contents.push_str("}\n\n");
+    }
+
+    let path = format!("{}.layout", output_path);
+    if let Err(e) = fs::write(&path, contents.trim_end().to_string() + "\n") {
+        eprintln!("❌ Failed to write struct layout '{}': {}", path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+fn write_dot(path: &str, dot: &str) {
+    if let Err(e) = fs::write(path, dot) {
+        eprintln!("❌ Failed to write graph '{}': {}", path, e);
+        process::exit(IO_EXIT_CODE);
+    }
+}
+
+// `12` for `%sum.L12 = ...` and `%sum.L12.1 = ...`; the last `.L` part wins, since the
+// variable's own name may look like one.
+fn value_line(instruction: &str) -> Option<usize> {
+    let (name, _) = instruction.trim_start().strip_prefix('%')?.split_once(" = ")?;
+    name.split('.').rev().find_map(|part| part.strip_prefix('L')?.parse().ok()).filter(|&line| line > 0)
+}
+
+// Two tables in `size -A` style, sorted largest first so regressions show up at the top of a diff
+fn write_size_report(output_path: &str, ir: &str, target: &TargetOptions) {
+    let mut report = object_sizes(ir, target).unwrap_or_else(|error| exit_backend_failure("measure object sizes", error));
+    report.sections.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    report.functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut contents = String::new();
+    for (title, rows) in [("section", &report.sections), ("function", &report.functions)] {
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(title.len());
+        contents.push_str(&format!("{:<width$}  size\n", title, width = width));
+        for (name, size) in rows {
+            contents.push_str(&format!("{:<width$}  {}\n", name, size, width = width));
+        }
+        let total: u64 = rows.iter().map(|(_, size)| size).sum();
+        contents.push_str(&format!("{:<width$}  {}\n\n", "Total", total, width = width));
+    }
+
+    let size_path = format!("{}.size", output_path);
+    if let Err(e) = fs::write(&size_path, contents.trim_end().to_string() + "\n") {
+        eprintln!("❌ Failed to write size report '{}': {}", size_path, e);
+        process::exit(IO_EXIT_CODE);
+    }
 }
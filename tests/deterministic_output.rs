@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use llvm_temporary::llvm_temporary::llvm_backend::{emit_object, TargetOptions};
+use wavec::{compile_source, CodegenOptions};
+
+// Programs with several functions, prints in each, structs and overloads: what names globals
+// and symbols, and what a HashMap could order.
+const PROGRAMS: &[&str] = &["test/test70.wave", "test/test83.wave", "test/test85.wave", "test/test105.wave"];
+
+fn ir_of(path: &Path) -> String {
+    ir_with(path, &CodegenOptions::default())
+}
+
+fn ir_with(path: &Path, options: &CodegenOptions) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    let options = CodegenOptions { source_file: Some(path.to_path_buf()), ..options.clone() };
+    match compile_source(&source, &options) {
+        Ok(artifacts) => artifacts.ir,
+        Err(_) => panic!("{} compiles", path.display()),
+    }
+}
+
+fn object_of(ir: &str, name: &str) -> Vec<u8> {
+    let path = std::env::temp_dir().join(format!("wave-deterministic-{}-{}.o", std::process::id(), name));
+    emit_object(ir, &path, 0, &TargetOptions::default()).unwrap();
+    let object = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    object
+}
+
+#[test]
+fn same_source_gives_same_ir_and_object() {
+    for program in PROGRAMS {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(program);
+        let (first, second) = (ir_of(&path), ir_of(&path));
+        assert_eq!(first, second, "the IR of {} differs between two compilations", program);
+
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        let objects = (object_of(&first, &format!("{}-a", stem)), object_of(&second, &format!("{}-b", stem)));
+        assert!(objects.0 == objects.1, "the object of {} differs between two compilations", program);
+    }
+}
+
+// A `-g` build records the source directory; remapped, two checkouts give the same output.
+#[test]
+fn remapped_debug_info_is_the_same_in_any_directory() {
+    let program = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test/test70.wave");
+    let builds: Vec<(String, Vec<u8>)> = ["a", "b"]
+        .iter()
+        .map(|checkout| {
+            let dir = std::env::temp_dir().join(format!("wave-remap-{}-{}", std::process::id(), checkout));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("test70.wave");
+            fs::copy(&program, &path).unwrap();
+
+            let options = CodegenOptions {
+                debug_info: true,
+                remap_path_prefix: vec![(dir.clone(), PathBuf::from("/wave"))],
+                ..Default::default()
+            };
+            let ir = ir_with(&path, &options);
+            assert!(!ir.contains(&*dir.to_string_lossy()), "the IR of -g names the build directory");
+            assert!(ir.contains(&format!("producer: \"wavec {}\"", env!("CARGO_PKG_VERSION"))), "-g names another compiler");
+            let object = object_of(&ir, &format!("remap-{}", checkout));
+            let _ = fs::remove_dir_all(&dir);
+            (ir, object)
+        })
+        .collect();
+
+    assert_eq!(builds[0].0, builds[1].0, "the IR of -g differs between two directories");
+    assert!(builds[0].1 == builds[1].1, "the object of -g differs between two directories");
+}
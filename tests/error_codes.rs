@@ -0,0 +1,22 @@
+use wavec::{compile_source, CodegenOptions};
+
+// Each check that once shared E0006 reports its own code.
+const CASES: &[(&str, &str)] = &[
+    ("E0008", "fun value() -> i32 {\n}\n\nfun main() {\n    println(\"{}\", value());\n}\n"),
+    ("E0009", "fun main() {\n    let count: i32 = 1;\n    count = 2;\n}\n"),
+    ("E0010", "import(\"nowhere\");\n\nfun main() {\n}\n"),
+    ("E0011", "fun main() -> f64 {\n    return 1.0;\n}\n"),
+    ("E0011", "fun helper() {\n}\n"),
+    ("E0012", "fun main() {\n    var count: i32;\n    println(\"{}\", count);\n}\n"),
+];
+
+#[test]
+fn each_check_has_its_own_code() {
+    for (code, source) in CASES {
+        let Err(diagnostics) = compile_source(source, &CodegenOptions::default()) else {
+            panic!("expected {} from:\n{}", code, source);
+        };
+        let codes: Vec<&str> = diagnostics.diagnostics.iter().map(|d| d.kind.code()).collect();
+        assert!(codes.contains(code), "expected {} from:\n{}\ngot {:?}", code, source, codes);
+    }
+}
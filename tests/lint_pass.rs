@@ -0,0 +1,61 @@
+use std::sync::Once;
+use error::error::{Severity, WaveErrorKind};
+use parser::ast::{Expression, Literal};
+use parser::lint::{register_lint_pass, LintContext, LintPass};
+use wavec::{compile_source, CodegenOptions, LintLevel};
+
+// Warns of every `42`, as a crate outside the compiler would add a lint.
+struct MagicNumber;
+
+impl LintPass for MagicNumber {
+    fn name(&self) -> &'static str {
+        "magic_number"
+    }
+
+    fn check_expression(&self, cx: &mut LintContext, expr: &Expression) {
+        if let Expression::Literal(Literal::Number(42)) = expr {
+            let warning = cx.warning("magic number `42`").with_label("name this constant");
+            cx.report(warning);
+        }
+    }
+}
+
+const SOURCE: &str = "fun main() {\n    var answer: i32 = 42;\n    println(\"{}\", answer);\n}\n";
+
+fn options(level: Option<LintLevel>) -> CodegenOptions {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| register_lint_pass(Box::new(MagicNumber)));
+
+    let mut options = CodegenOptions::default();
+    if let Some(level) = level {
+        options.lint_levels.insert("magic_number".to_string(), level);
+    }
+    options
+}
+
+fn is_magic_number(kind: &WaveErrorKind) -> bool {
+    matches!(kind, WaveErrorKind::Lint(lint) if lint == "magic_number")
+}
+
+#[test]
+fn registered_pass_warns() {
+    let artifacts = compile_source(SOURCE, &options(None)).unwrap_or_else(|_| panic!("the program compiles"));
+    let warning = artifacts.warnings.iter().find(|w| is_magic_number(&w.kind)).expect("the pass reports `42`");
+    assert_eq!(warning.severity, Severity::Warning);
+    assert_eq!(warning.line, 2);
+}
+
+#[test]
+fn allow_silences_registered_pass() {
+    let artifacts = compile_source(SOURCE, &options(Some(LintLevel::Allow))).unwrap_or_else(|_| panic!("the program compiles"));
+    assert!(!artifacts.warnings.iter().any(|w| is_magic_number(&w.kind)));
+}
+
+#[test]
+fn deny_makes_registered_pass_an_error() {
+    let Err(diagnostics) = compile_source(SOURCE, &options(Some(LintLevel::Deny))) else {
+        panic!("`--deny=magic_number` stops compilation");
+    };
+    let error = diagnostics.diagnostics.iter().find(|d| is_magic_number(&d.kind)).expect("the pass reports `42`");
+    assert_eq!(error.severity, Severity::Error);
+}
@@ -0,0 +1,46 @@
+use wavec::{compile_source, CodegenOptions, PanicStrategy};
+
+const SOURCE: &str = "fun square(x: i32) -> i32 {\n    return x * x;\n}\n\nfun main() {\n    println(\"{}\", square(3));\n}\n";
+
+// The attributes of every function the IR defines, as written in its attribute groups.
+fn defined_function_attributes(panic: PanicStrategy) -> Vec<(String, String)> {
+    let options = CodegenOptions { panic, ..Default::default() };
+    let ir = match compile_source(SOURCE, &options) {
+        Ok(artifacts) => artifacts.ir,
+        Err(_) => panic!("the program compiles"),
+    };
+    let group = |id: &str| {
+        ir.lines()
+            .find_map(|line| line.strip_prefix(&format!("attributes {} = ", id)))
+            .unwrap_or_default()
+            .to_string()
+    };
+    ir.lines()
+        .filter_map(|line| line.strip_prefix("define "))
+        .map(|signature| {
+            let name = signature.split_once('@').and_then(|(_, rest)| rest.split_once('(')).map_or("", |(name, _)| name);
+            let attributes: Vec<String> = signature.split_whitespace().filter(|word| word.starts_with('#')).map(group).collect();
+            (name.to_string(), attributes.join(" "))
+        })
+        .collect()
+}
+
+#[test]
+fn abort_marks_every_function_nounwind() {
+    let functions = defined_function_attributes(PanicStrategy::Abort);
+    assert!(functions.iter().any(|(name, _)| name == "square"));
+    for (name, attributes) in functions {
+        assert!(attributes.contains("nounwind"), "`{}` is not nounwind: {}", name, attributes);
+        assert!(!attributes.contains("uwtable"), "`{}` has an unwind table: {}", name, attributes);
+    }
+}
+
+#[test]
+fn unwind_gives_every_function_an_unwind_table() {
+    let functions = defined_function_attributes(PanicStrategy::Unwind);
+    assert!(functions.iter().any(|(name, _)| name == "square"));
+    for (name, attributes) in functions {
+        assert!(attributes.contains("uwtable"), "`{}` has no unwind table: {}", name, attributes);
+        assert!(!attributes.contains("nounwind"), "`{}` is nounwind: {}", name, attributes);
+    }
+}